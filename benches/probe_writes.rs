@@ -0,0 +1,105 @@
+//! How much batching probe writes saves, as the watcher's probe buffer does
+//! with `--batch-size`: writes the same probes in transactions of different
+//! sizes to a database on disk with the pragmas of the storage profiles.
+//!
+//! Run with `cargo bench --bench probe_writes`.
+
+use rusqlite::{params, Connection};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const PROBES: usize = 2_000;
+
+/// Transaction sizes to compare: one probe per transaction as without the
+/// buffer, the default profile's 20 and the `sd-card` profile's 500.
+const BATCH_SIZES: [usize; 4] = [1, 20, 100, 500];
+
+/// A storage profile's name and its `synchronous` setting; both write ahead.
+struct Profile {
+    name: &'static str,
+    synchronous: &'static str,
+}
+
+const PROFILES: [Profile; 2] = [
+    Profile { name: "default", synchronous: "FULL" },
+    Profile { name: "sd-card", synchronous: "NORMAL" },
+];
+
+fn database(profile: &Profile) -> (Connection, PathBuf) {
+    let path = std::env::temp_dir().join(format!("webgone-bench-{}-{}.db", profile.name, std::process::id()));
+    remove(&path);
+    let conn = Connection::open(&path).expect("failed to create the benchmark database");
+    // journal_mode returns the resulting mode as a row
+    conn.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(())).expect("failed to set the journal mode");
+    conn.execute_batch(&format!("PRAGMA synchronous = {}", profile.synchronous)).expect("failed to set synchronous");
+    conn.execute_batch(
+        "CREATE TABLE probes (
+            id INTEGER PRIMARY KEY, time TEXT NOT NULL, target TEXT NOT NULL, success INTEGER NOT NULL,
+            latency_ms REAL, error TEXT, weight INTEGER NOT NULL DEFAULT 1
+        );
+        CREATE INDEX idx_probes_time ON probes(time);",
+    )
+    .expect("failed to create the probes table");
+    (conn, path)
+}
+
+fn remove(path: &Path) {
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+    }
+}
+
+/// Writes `PROBES` probes in transactions of `batch_size` like
+/// `ProbeBuffer::flush`, returning how long it took.
+fn write(conn: &Connection, batch_size: usize) -> Duration {
+    let started = Instant::now();
+    for batch in 0..PROBES.div_ceil(batch_size) {
+        let tx = conn.unchecked_transaction().expect("failed to begin");
+        {
+            let mut stmt = tx
+                .prepare_cached(
+                    "INSERT INTO probes (time, target, success, latency_ms, error, weight) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                )
+                .expect("failed to prepare the insert");
+            for index in batch * batch_size..((batch + 1) * batch_size).min(PROBES) {
+                let success = index % 10 != 0;
+                stmt.execute(params![
+                    format!("2026-01-01T00:{:02}:{:02}.{:03}+00:00", index / 6000 % 60, index / 100 % 60, index % 1000),
+                    "1.1.1.1:53",
+                    success,
+                    success.then_some(12.5),
+                    (!success).then_some("Connection timed out"),
+                    1,
+                ])
+                .expect("failed to insert a probe");
+            }
+        }
+        tx.commit().expect("failed to commit");
+    }
+    started.elapsed()
+}
+
+fn main() {
+    println!("{} probes per run\n", PROBES);
+    println!("{:<10} {:>6} {:>12} {:>14} {:>10}", "profile", "batch", "total", "per probe", "speedup");
+    for profile in &PROFILES {
+        let (conn, path) = database(profile);
+        // Warms up the file and the statement cache
+        write(&conn, 100);
+        let mut unbatched = None;
+        for batch_size in BATCH_SIZES {
+            let elapsed = write(&conn, batch_size);
+            let unbatched = *unbatched.get_or_insert(elapsed);
+            println!(
+                "{:<10} {:>6} {:>10.1}ms {:>12.1}µs {:>9.1}x",
+                profile.name,
+                batch_size,
+                elapsed.as_secs_f64() * 1000.0,
+                elapsed.as_secs_f64() * 1_000_000.0 / PROBES as f64,
+                unbatched.as_secs_f64() / elapsed.as_secs_f64(),
+            );
+        }
+        drop(conn);
+        remove(&path);
+    }
+}
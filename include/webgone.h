@@ -0,0 +1,56 @@
+/*
+ * C interface to webgone's outage state machine, built with
+ *   cargo rustc --release --lib --features ffi --crate-type staticlib
+ * Feed it the results of your own connectivity checks; it calls back when
+ * enough consecutive checks confirm the connection was lost or restored.
+ * Times are milliseconds since the Unix epoch.
+ */
+#ifndef WEBGONE_H
+#define WEBGONE_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define WEBGONE_EVENT_LOST 1
+#define WEBGONE_EVENT_RESTORED 2
+
+typedef struct WebgoneTracker WebgoneTracker;
+
+typedef struct {
+    /* WEBGONE_EVENT_LOST or WEBGONE_EVENT_RESTORED */
+    int32_t kind;
+    /* When the first failed check of the run that confirmed the outage started */
+    int64_t outage_start_ms;
+    /* Lost: when the result of that first failed check came in.
+     * Restored: when the first successful check of the confirming run started. */
+    int64_t at_ms;
+    /* Restored: length of the outage; 0 for lost events */
+    int64_t duration_ms;
+} WebgoneEvent;
+
+typedef void (*WebgoneEventCallback)(void *user_data, const WebgoneEvent *event);
+
+/* Declares the connection lost after fail_threshold consecutive failed checks
+ * and restored after recover_threshold successful ones (0 counts as 1).
+ * callback may be NULL. Free the tracker with webgone_tracker_free. */
+WebgoneTracker *webgone_tracker_new(uint32_t fail_threshold, uint32_t recover_threshold,
+                                    WebgoneEventCallback callback, void *user_data);
+
+/* Feeds the result of a check that ran from started_ms to finished_ms.
+ * Returns 0, or -1 if tracker is NULL or a time is out of range. */
+int32_t webgone_tracker_feed(WebgoneTracker *tracker, int64_t started_ms, int64_t finished_ms, bool success);
+
+/* Whether the tracker currently considers the connection up. */
+bool webgone_tracker_is_connected(const WebgoneTracker *tracker);
+
+void webgone_tracker_free(WebgoneTracker *tracker);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,176 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::fmt;
+
+use crate::error::WebgoneError;
+
+/// Metadata key holding the start of the outage the watcher is currently in.
+const CURRENT_OUTAGE_KEY: &str = "current_outage_start";
+
+/// Which outage to acknowledge.
+#[derive(Clone, Copy)]
+pub enum AckTarget {
+    Outage(i64),
+    /// The outage a running watcher is in right now
+    Current,
+}
+
+impl std::str::FromStr for AckTarget {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "current" => Ok(AckTarget::Current),
+            id => id
+                .parse()
+                .map(AckTarget::Outage)
+                .map_err(|_| format!("expected an outage id or `current`, got {}", id)),
+        }
+    }
+}
+
+/// Someone confirming they know about an outage.
+pub struct Ack {
+    pub by: String,
+    pub at: DateTime<Local>,
+    pub comment: Option<String>,
+}
+
+impl fmt::Display for Ack {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.by, self.at.format("%Y-%m-%d %H:%M"))?;
+        if let Some(comment) = &self.comment {
+            write!(f, ": {}", comment)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn init_table(conn: &Connection) -> Result<()> {
+    // Acks of the ongoing outage have no outage id until the watcher logs it
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS outage_acks (
+            id INTEGER PRIMARY KEY,
+            outage_id INTEGER UNIQUE REFERENCES outages(id),
+            outage_start TEXT NOT NULL,
+            acked_by TEXT NOT NULL,
+            acked_at TEXT NOT NULL,
+            comment TEXT
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Records that the watcher is in an outage since `start`, or that it ended.
+pub fn set_current(conn: &Connection, start: Option<DateTime<Local>>) -> Result<()> {
+    match start {
+        Some(start) => conn.execute(
+            "INSERT INTO metadata (key, value) VALUES (?1, ?2)
+            ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+            params![CURRENT_OUTAGE_KEY, start.to_rfc3339()],
+        )?,
+        None => conn.execute("DELETE FROM metadata WHERE key = ?", [CURRENT_OUTAGE_KEY])?,
+    };
+    Ok(())
+}
+
+/// Start of the outage the watcher is in right now, if any.
+pub fn current(conn: &Connection) -> Result<Option<DateTime<Local>>> {
+    let start: Option<String> = conn
+        .query_row("SELECT value FROM metadata WHERE key = ?", [CURRENT_OUTAGE_KEY], |row| row.get(0))
+        .optional()?;
+    Ok(start
+        .map(|start| DateTime::parse_from_rfc3339(&start))
+        .transpose()?
+        .map(|start| start.with_timezone(&Local)))
+}
+
+/// The user running webgone, as the default for who acknowledged an outage.
+pub fn default_user() -> String {
+    ["USER", "USERNAME"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|value| !value.is_empty()))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Acknowledges an outage, replacing an earlier acknowledgment of it.
+/// Returns the start time of the acknowledged outage.
+pub fn acknowledge(conn: &Connection, target: AckTarget, by: &str, comment: Option<&str>) -> Result<DateTime<Local>> {
+    let (outage_id, start): (Option<i64>, String) = match target {
+        AckTarget::Outage(id) => {
+            let start: String = conn
+                .query_row("SELECT start_time FROM outages WHERE id = ?", [id], |row| row.get(0))
+                .optional()?
+                .ok_or_else(|| WebgoneError::InvalidInput(format!("no outage with id {}", id)))?;
+            (Some(id), start)
+        }
+        AckTarget::Current => {
+            let start = current(conn)?
+                .ok_or_else(|| WebgoneError::InvalidInput("the watcher isn't in an outage right now".to_string()))?;
+            (None, start.to_rfc3339())
+        }
+    };
+
+    let tx = conn.unchecked_transaction()?;
+    match outage_id {
+        Some(id) => tx.execute("DELETE FROM outage_acks WHERE outage_id = ?", [id])?,
+        None => tx.execute("DELETE FROM outage_acks WHERE outage_id IS NULL AND outage_start = ?", [&start])?,
+    };
+    tx.execute(
+        "INSERT INTO outage_acks (outage_id, outage_start, acked_by, acked_at, comment) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![outage_id, start, by, Local::now().to_rfc3339(), comment],
+    )?;
+    tx.commit()?;
+
+    Ok(DateTime::parse_from_rfc3339(&start)?.with_timezone(&Local))
+}
+
+fn read_ack(row: &rusqlite::Row) -> rusqlite::Result<Ack> {
+    let at: String = row.get(1)?;
+    Ok(Ack {
+        by: row.get(0)?,
+        at: DateTime::parse_from_rfc3339(&at)
+            .map(|time| time.with_timezone(&Local))
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, Box::new(e)))?,
+        comment: row.get(2)?,
+    })
+}
+
+pub fn for_outage(conn: &Connection, outage_id: i64) -> Result<Option<Ack>> {
+    Ok(conn
+        .query_row(
+            "SELECT acked_by, acked_at, comment FROM outage_acks WHERE outage_id = ?",
+            [outage_id],
+            read_ack,
+        )
+        .optional()?)
+}
+
+/// The acknowledgment of the ongoing outage that started at `start`, if any.
+pub fn for_ongoing(conn: &Connection, start: DateTime<Local>) -> Result<Option<Ack>> {
+    Ok(conn
+        .query_row(
+            "SELECT acked_by, acked_at, comment FROM outage_acks WHERE outage_id IS NULL AND outage_start = ?",
+            [start.to_rfc3339()],
+            read_ack,
+        )
+        .optional()?)
+}
+
+/// Links acknowledgments of the ongoing outage to the outage it was logged as.
+pub fn attach(conn: &Connection, start: DateTime<Local>, outage_id: i64) -> Result<()> {
+    conn.execute(
+        "UPDATE outage_acks SET outage_id = ?1
+        WHERE outage_id IS NULL AND outage_start = ?2
+            AND NOT EXISTS (SELECT 1 FROM outage_acks WHERE outage_id = ?1)",
+        params![outage_id, start.to_rfc3339()],
+    )?;
+    // Left over if the outage was merged into one that was already acknowledged
+    conn.execute(
+        "DELETE FROM outage_acks WHERE outage_id IS NULL AND outage_start = ?",
+        [start.to_rfc3339()],
+    )?;
+    Ok(())
+}
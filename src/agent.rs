@@ -0,0 +1,276 @@
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::{Certificate, ClientTlsConfig, Endpoint, Identity};
+
+use crate::error::WebgoneError;
+use crate::proto::agent_message::Payload;
+use crate::proto::collector_client::CollectorClient;
+use crate::proto::{AgentMessage, Hello, Outage, Probe};
+use crate::tls;
+
+/// Rows read from the database at once.
+const BATCH_SIZE: i64 = 200;
+/// Messages sent but not yet acknowledged. Beyond this the agent waits for
+/// the server instead of reading more, so a slow link only delays data that
+/// is safe in the local database anyway.
+const MAX_IN_FLIGHT: usize = 1000;
+/// Messages queued for the HTTP/2 stream.
+const OUTBOX_CAPACITY: usize = 64;
+/// How often the acknowledged position is saved while data is flowing.
+const CURSOR_SAVE_INTERVAL: Duration = Duration::from_secs(5);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+pub struct AgentOptions {
+    /// `http://` or `https://` URL of the `webgone collect` server
+    pub server: String,
+    pub site: String,
+    /// CA certificate (PEM) to verify the server with, instead of the system roots
+    pub server_ca: Option<Vec<u8>>,
+    /// Client certificate for servers that require mutual TLS
+    pub identity: Option<tls::Certificate>,
+    pub poll_interval: Duration,
+}
+
+/// Position up to which the server confirmed storing data. Saved in the
+/// metadata table, so a restarted agent continues where it left off.
+#[derive(Clone, Default)]
+struct Cursor {
+    probe_id: i64,
+    outage_id: i64,
+    /// End of the last sent outage, which grows when later outages are merged into it
+    outage_end: String,
+}
+
+const CURSOR_KEYS: [&str; 3] = ["agent_probe_cursor", "agent_outage_cursor", "agent_outage_end"];
+
+impl Cursor {
+    fn load(conn: &Connection) -> Result<Self> {
+        let get = |key: &str| -> Result<Option<String>> {
+            Ok(conn
+                .query_row("SELECT value FROM metadata WHERE key = ?", [key], |row| row.get(0))
+                .optional()?)
+        };
+        Ok(Cursor {
+            probe_id: get(CURSOR_KEYS[0])?.and_then(|value| value.parse().ok()).unwrap_or(0),
+            outage_id: get(CURSOR_KEYS[1])?.and_then(|value| value.parse().ok()).unwrap_or(0),
+            outage_end: get(CURSOR_KEYS[2])?.unwrap_or_default(),
+        })
+    }
+
+    fn save(&self, conn: &Connection) -> Result<()> {
+        let values = [self.probe_id.to_string(), self.outage_id.to_string(), self.outage_end.clone()];
+        for (key, value) in CURSOR_KEYS.iter().zip(values) {
+            conn.execute(
+                "INSERT INTO metadata (key, value) VALUES (?1, ?2)
+                ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+                params![key, value],
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads the next probes and outages after `sent`, each with the cursor
+/// that is reached once the server acknowledged it.
+fn next_batch(conn: &Connection, sent: &Cursor) -> Result<Vec<(Payload, Cursor)>> {
+    let mut batch = Vec::new();
+
+    // Remote rows of a central server that also runs an agent aren't forwarded again
+    let mut stmt = conn.prepare(
+        "SELECT id, time, target, success, latency_ms, error, weight FROM probes
+        WHERE id > ? AND site IS NULL ORDER BY id LIMIT ?"
+    )?;
+    let probes = stmt
+        .query_map(params![sent.probe_id, BATCH_SIZE], |row| {
+            Ok(Probe {
+                id: row.get(0)?,
+                time: row.get(1)?,
+                target: row.get(2)?,
+                success: row.get(3)?,
+                latency_ms: row.get(4)?,
+                error: row.get(5)?,
+                weight: row.get(6)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for probe in probes {
+        let cursor = Cursor { probe_id: probe.id, ..sent.clone() };
+        batch.push((Payload::Probe(probe), cursor));
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT id, start_time, end_time, duration_seconds, wifi_ssid FROM outages
+        WHERE (id > ?1 OR (id = ?1 AND end_time != ?2)) AND site IS NULL
+        ORDER BY id LIMIT ?3"
+    )?;
+    let outages = stmt
+        .query_map(params![sent.outage_id, sent.outage_end, BATCH_SIZE], |row| {
+            Ok(Outage {
+                id: row.get(0)?,
+                start_time: row.get(1)?,
+                end_time: row.get(2)?,
+                duration_seconds: row.get(3)?,
+                wifi_ssid: row.get(4)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut cursor = batch.last().map(|(_, cursor)| cursor.clone()).unwrap_or_else(|| sent.clone());
+    for outage in outages {
+        cursor = Cursor { outage_id: outage.id, outage_end: outage.end_time.clone(), ..cursor };
+        batch.push((Payload::Outage(outage), cursor.clone()));
+    }
+
+    Ok(batch)
+}
+
+/// Describes an error together with its causes, which hold the useful part
+/// of tonic's transport errors.
+fn describe(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        let cause_message = cause.to_string();
+        if !message.ends_with(&cause_message) {
+            message = format!("{}: {}", message, cause_message);
+        }
+        source = cause.source();
+    }
+    message
+}
+
+fn stream_error(server: &str, status: tonic::Status) -> anyhow::Error {
+    let message = match std::error::Error::source(&status) {
+        Some(cause) => format!("{}: {}", status.message(), describe(cause)),
+        None => status.message().to_string(),
+    };
+    WebgoneError::Network(format!("connection to {} failed: {}", server, message)).into()
+}
+
+async fn connect(options: &AgentOptions) -> Result<CollectorClient<tonic::transport::Channel>> {
+    let mut endpoint = Endpoint::from_shared(options.server.clone())
+        .map_err(|e| WebgoneError::InvalidInput(format!("invalid server URL {}: {}", options.server, e)))?
+        .connect_timeout(Duration::from_secs(10))
+        .http2_keep_alive_interval(Duration::from_secs(30))
+        .keep_alive_timeout(Duration::from_secs(10));
+
+    if options.server.starts_with("https://") {
+        let mut config = match &options.server_ca {
+            Some(ca) => ClientTlsConfig::new().ca_certificate(Certificate::from_pem(ca)),
+            None => ClientTlsConfig::new().with_native_roots(),
+        };
+        if let Some(identity) = &options.identity {
+            config = config.identity(Identity::from_pem(&identity.certificate, &identity.private_key));
+        }
+        endpoint = endpoint
+            .tls_config(config)
+            .map_err(|e| WebgoneError::InvalidInput(format!("invalid TLS configuration: {}", e)))?;
+    }
+
+    let channel = endpoint
+        .connect()
+        .await
+        .map_err(|e| WebgoneError::Network(format!("failed to connect to {}: {}", options.server, describe(&e))))?;
+    Ok(CollectorClient::new(channel))
+}
+
+/// Streams new data until the connection fails, advancing `committed` as the
+/// server acknowledges it.
+async fn report(conn: &Connection, options: &AgentOptions, committed: &mut Cursor) -> Result<()> {
+    let mut client = connect(options).await?;
+    let (outbox, rx) = mpsc::channel(OUTBOX_CAPACITY);
+    outbox
+        .send(AgentMessage {
+            sequence: 0,
+            payload: Some(Payload::Hello(Hello {
+                site: options.site.clone(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+            })),
+        })
+        .await?;
+    let mut acks = client
+        .report(ReceiverStream::new(rx))
+        .await
+        .map_err(|status| stream_error(&options.server, status))?
+        .into_inner();
+    println!("Connected to {}", options.server);
+
+    let mut sent = committed.clone();
+    let mut sequence = 0;
+    let mut queue: VecDeque<(Payload, Cursor)> = VecDeque::new();
+    let mut in_flight: VecDeque<(u64, Cursor)> = VecDeque::new();
+    let mut last_save = Instant::now();
+
+    loop {
+        if queue.is_empty() && in_flight.len() < MAX_IN_FLIGHT {
+            queue.extend(next_batch(conn, &sent)?);
+            if let Some((_, cursor)) = queue.back() {
+                sent = cursor.clone();
+            }
+        }
+
+        tokio::select! {
+            permit = outbox.reserve(), if !queue.is_empty() && in_flight.len() < MAX_IN_FLIGHT => {
+                let (payload, cursor) = queue.pop_front().expect("queue is not empty");
+                sequence += 1;
+                permit?.send(AgentMessage { sequence, payload: Some(payload) });
+                in_flight.push_back((sequence, cursor));
+            }
+            ack = acks.message() => {
+                let Some(ack) = ack.map_err(|status| stream_error(&options.server, status))? else {
+                    committed.save(conn)?;
+                    return Err(WebgoneError::Network("server closed the stream".to_string()).into());
+                };
+                while in_flight.front().is_some_and(|(sequence, _)| *sequence <= ack.sequence) {
+                    let (_, cursor) = in_flight.pop_front().expect("checked above");
+                    *committed = cursor;
+                }
+                if in_flight.is_empty() || last_save.elapsed() >= CURSOR_SAVE_INTERVAL {
+                    committed.save(conn)?;
+                    last_save = Instant::now();
+                }
+            }
+            _ = tokio::time::sleep(options.poll_interval), if queue.is_empty() => {}
+        }
+    }
+}
+
+/// Forwards probe results and outages recorded in this database to a central
+/// server, reconnecting with backoff until interrupted.
+pub fn run(conn: &Connection, options: &AgentOptions) -> Result<()> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("Failed to start the async runtime")?;
+
+    runtime.block_on(async {
+        let mut committed = Cursor::load(conn)?;
+        let mut delay = Duration::from_secs(1);
+        loop {
+            let before = (committed.probe_id, committed.outage_id);
+            let result = tokio::select! {
+                result = report(conn, options, &mut committed) => result,
+                _ = tokio::signal::ctrl_c() => {
+                    committed.save(conn)?;
+                    return Ok(());
+                }
+            };
+            if let Err(e) = result {
+                // Only back off further while nothing gets through
+                if (committed.probe_id, committed.outage_id) != before {
+                    delay = Duration::from_secs(1);
+                }
+                println!("{:#}; retrying in {}s", e, delay.as_secs());
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = tokio::signal::ctrl_c() => return Ok(()),
+            }
+            delay = (delay * 2).min(MAX_RETRY_DELAY);
+        }
+    })
+}
@@ -0,0 +1,107 @@
+//! Statistics over outages already loaded into memory, e.g. from an export.
+//! The command line computes the same numbers in SQL; this is for places
+//! without a database, like the WebAssembly build for the status page.
+
+use chrono::{DateTime, Datelike, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::model::{InternetOutage, MonthlyOutage, OutageStats};
+
+/// Outages whose duration falls into `[min_seconds, max_seconds)`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HistogramBucket {
+    pub min_seconds: i64,
+    /// `None` for the last bucket, which has no upper bound
+    pub max_seconds: Option<i64>,
+    pub outages: i64,
+}
+
+/// Bucket bounds of `histogram` that read well as labels: 1 minute, 5 minutes, ...
+pub const DEFAULT_BUCKETS: &[i64] = &[60, 5 * 60, 15 * 60, 60 * 60, 4 * 60 * 60];
+
+pub fn stats(outages: &[InternetOutage]) -> OutageStats {
+    let durations: Vec<i64> = outages.iter().map(|outage| outage.duration_seconds).collect();
+    let total: i64 = durations.iter().sum();
+    let average = |delays: Vec<f64>| (!delays.is_empty()).then(|| delays.iter().sum::<f64>() / delays.len() as f64);
+    let seconds = |delay: chrono::TimeDelta| delay.num_milliseconds() as f64 / 1000.0;
+
+    OutageStats {
+        total_outages: outages.len() as i64,
+        total_duration: total,
+        average_duration: if outages.is_empty() { 0.0 } else { total as f64 / outages.len() as f64 },
+        longest_outage: durations.iter().copied().max().unwrap_or(0),
+        shortest_outage: durations.iter().copied().min().unwrap_or(0),
+        average_detection_delay: average(
+            outages
+                .iter()
+                .filter_map(|outage| outage.confirmed_at.map(|at| seconds(at - outage.start_time)))
+                .collect(),
+        ),
+        average_recovery_delay: average(
+            outages
+                .iter()
+                .filter_map(|outage| outage.recovered_at.map(|at| seconds(at - outage.end_time)))
+                .collect(),
+        ),
+    }
+}
+
+/// Outages and downtime per month they started in, newest first.
+pub fn monthly(outages: &[InternetOutage]) -> Vec<MonthlyOutage> {
+    let mut months: Vec<MonthlyOutage> = Vec::new();
+    for outage in outages {
+        let (year, month) = (outage.start_time.year(), outage.start_time.month());
+        match months.iter_mut().find(|entry| entry.year == year && entry.month == month) {
+            Some(entry) => {
+                entry.num_outages += 1;
+                entry.total_seconds += outage.duration_seconds;
+            }
+            None => months.push(MonthlyOutage { year, month, total_seconds: outage.duration_seconds, num_outages: 1 }),
+        }
+    }
+    months.sort_by_key(|entry| std::cmp::Reverse((entry.year, entry.month)));
+    months
+}
+
+/// Exponent applied to the minutes an outage lasted in [`heat_score`].
+pub const HEAT_EXPONENT: f64 = 1.5;
+
+/// How badly an outage hurts, for comparing months with a similar total
+/// downtime: every outage counts at least as much as one of a minute, and
+/// longer ones count super-linearly. Ten 1-minute outages score 10, a single
+/// 10-minute one about 31.6.
+pub fn heat_score(duration_seconds: i64) -> f64 {
+    (duration_seconds as f64 / 60.0).max(1.0).powf(HEAT_EXPONENT)
+}
+
+/// Number of outages per duration range, split at `bounds` (in seconds, ascending).
+pub fn histogram(outages: &[InternetOutage], bounds: &[i64]) -> Vec<HistogramBucket> {
+    let mut buckets: Vec<HistogramBucket> = std::iter::once(0)
+        .chain(bounds.iter().copied())
+        .zip(bounds.iter().copied().map(Some).chain(std::iter::once(None)))
+        .map(|(min_seconds, max_seconds)| HistogramBucket { min_seconds, max_seconds, outages: 0 })
+        .collect();
+    for outage in outages {
+        if let Some(bucket) = buckets
+            .iter_mut()
+            .find(|bucket| bucket.max_seconds.is_none_or(|max| outage.duration_seconds < max))
+        {
+            bucket.outages += 1;
+        }
+    }
+    buckets
+}
+
+/// Share of the time between `from` and `to` without an outage, counting
+/// only the parts of outages within the period.
+pub fn uptime_percent(outages: &[InternetOutage], from: DateTime<Local>, to: DateTime<Local>) -> f64 {
+    let period = (to - from).num_seconds();
+    if period <= 0 {
+        return 100.0;
+    }
+    let downtime: i64 = outages
+        .iter()
+        .map(|outage| (outage.end_time.min(to) - outage.start_time.max(from)).num_seconds().max(0))
+        .sum();
+    (1.0 - downtime.min(period) as f64 / period as f64) * 100.0
+}
@@ -0,0 +1,129 @@
+use anyhow::Result;
+use chrono::{Datelike, Local, NaiveDate, TimeZone, Utc};
+use rusqlite::Connection;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::WebgoneError;
+use crate::storage::{self, Granularity};
+
+/// Columns of the `probes` table, in the main database and in archives alike.
+const COLUMNS: &str = "id, time, target, success, latency_ms, error, weight, site, remote_id";
+
+/// File holding the raw probes of `year`, next to the database.
+pub fn archive_path(db_path: &Path, year: i32) -> PathBuf {
+    db_path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+        .join(format!("outages-{}.db", year))
+}
+
+/// Years with an archive file next to the database, oldest first.
+pub fn archived_years(db_path: &Path) -> Result<Vec<i32>> {
+    let dir = archive_path(db_path, 0).parent().expect("archives are in a directory").to_path_buf();
+    let entries = fs::read_dir(&dir)
+        .map_err(|e| WebgoneError::Io(format!("failed to read {}: {}", dir.display(), e)))?;
+    let mut years: Vec<i32> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let year = name.strip_prefix("outages-")?.strip_suffix(".db")?;
+            (year.len() == 4).then(|| year.parse().ok()).flatten()
+        })
+        .collect();
+    years.sort();
+    Ok(years)
+}
+
+/// Moves raw probes of past years into one archive file per year, e.g.
+/// `outages-2023.db`, after rolling them up into `granularity` aggregates,
+/// which stay in the main database. Returns how many probes each year moved.
+///
+/// Probes are copied with their ids before they are deleted, so a rotation
+/// that was interrupted can simply be run again.
+pub fn rotate(conn: &Connection, db_path: &Path, granularity: Granularity) -> Result<Vec<(i32, usize)>> {
+    let new_year = Local
+        .with_ymd_and_hms(Local::now().year(), 1, 1, 0, 0, 0)
+        .earliest()
+        .expect("new year's midnight exists");
+    let cutoff = storage::start_of_hour(new_year.with_timezone(&Utc));
+
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT CAST(strftime('%Y', time, 'localtime') AS INTEGER) FROM probes
+        WHERE julianday(time) < julianday(?)
+        ORDER BY 1",
+    )?;
+    let years = stmt
+        .query_map([cutoff.to_rfc3339()], |row| row.get::<_, i32>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut moved = Vec::new();
+    for year in years {
+        let path = archive_path(db_path, year);
+        create_archive(&path)?;
+        conn.execute("ATTACH DATABASE ? AS archive", [path.to_string_lossy()])?;
+        let copied = copy_year(conn, year, &cutoff.to_rfc3339());
+        conn.execute("DETACH DATABASE archive", [])?;
+        moved.push((year, copied?));
+    }
+    if !moved.is_empty() {
+        storage::roll_up_probes_before(conn, cutoff, granularity)?;
+    }
+    Ok(moved)
+}
+
+/// Creates the archive file if needed; the main database is opened without
+/// permission to create files, and so are the databases it attaches.
+fn create_archive(path: &Path) -> Result<()> {
+    let archive = Connection::open(path)?;
+    archive.execute(
+        "CREATE TABLE IF NOT EXISTS probes (
+            id INTEGER PRIMARY KEY,
+            time TEXT NOT NULL,
+            target TEXT NOT NULL,
+            success INTEGER NOT NULL,
+            latency_ms REAL,
+            error TEXT,
+            weight INTEGER NOT NULL DEFAULT 1,
+            site TEXT,
+            remote_id INTEGER
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn copy_year(conn: &Connection, year: i32, cutoff: &str) -> Result<usize> {
+    Ok(conn.execute(
+        &format!(
+            "INSERT OR IGNORE INTO archive.probes ({COLUMNS})
+            SELECT {COLUMNS} FROM main.probes
+            WHERE julianday(time) < julianday(?1) AND CAST(strftime('%Y', time, 'localtime') AS INTEGER) = ?2"
+        ),
+        rusqlite::params![cutoff, year],
+    )?)
+}
+
+/// Makes the raw probes of archived years from `since` on (all with `None`)
+/// part of `probes` for queries on a read-only connection, through a
+/// temporary view that hides the main table. Must be called before a
+/// snapshot is started. Returns the years attached.
+pub fn attach(conn: &Connection, db_path: &Path, since: Option<NaiveDate>) -> Result<Vec<i32>> {
+    let years: Vec<i32> = archived_years(db_path)?
+        .into_iter()
+        .filter(|year| since.is_none_or(|since| *year >= since.year()))
+        .collect();
+    if years.is_empty() {
+        return Ok(years);
+    }
+
+    let mut selects = vec![format!("SELECT {COLUMNS} FROM main.probes")];
+    for year in &years {
+        let schema = format!("archive_{}", year);
+        conn.execute(&format!("ATTACH DATABASE ? AS {schema}"), [archive_path(db_path, *year).to_string_lossy()])?;
+        selects.push(format!("SELECT {COLUMNS} FROM {schema}.probes"));
+    }
+    conn.execute_batch(&format!("CREATE TEMP VIEW probes AS {}", selects.join(" UNION ALL ")))?;
+    Ok(years)
+}
@@ -0,0 +1,79 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use rusqlite::{params, Connection};
+
+use crate::acks;
+
+/// A change someone made to the recorded history.
+pub struct AuditEntry {
+    pub time: DateTime<Local>,
+    pub actor: String,
+    pub command: String,
+    /// Ids of the outages, tickets, epochs or tokens that were changed
+    pub ids: Vec<i64>,
+    pub details: String,
+}
+
+pub fn init_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS audit_log (
+            id INTEGER PRIMARY KEY,
+            time TEXT NOT NULL,
+            actor TEXT NOT NULL,
+            command TEXT NOT NULL,
+            ids TEXT NOT NULL,
+            details TEXT NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Records a manual change by the user running webgone, e.g. `tag` with the
+/// id of the tagged outage and the tags that were added.
+pub fn record(conn: &Connection, command: &str, ids: &[i64], details: &str) -> Result<()> {
+    record_as(conn, &acks::default_user(), command, ids, details)
+}
+
+/// Records a change made on behalf of someone else than the local user.
+pub fn record_as(conn: &Connection, actor: &str, command: &str, ids: &[i64], details: &str) -> Result<()> {
+    let ids: Vec<String> = ids.iter().map(i64::to_string).collect();
+    conn.execute(
+        "INSERT INTO audit_log (time, actor, command, ids, details) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![Local::now().to_rfc3339(), actor, command, ids.join(","), details],
+    )?;
+    Ok(())
+}
+
+/// The most recent `limit` entries, newest first, optionally only those of
+/// one actor.
+pub fn list(conn: &Connection, limit: u32, actor: Option<&str>) -> Result<Vec<AuditEntry>> {
+    let mut stmt = conn.prepare(
+        "SELECT time, actor, command, ids, details FROM audit_log
+        WHERE ?1 IS NULL OR actor = ?1
+        ORDER BY id DESC LIMIT ?2",
+    )?;
+    let rows = stmt
+        .query_map(params![actor, limit], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    rows.into_iter()
+        .map(|(time, actor, command, ids, details)| {
+            Ok(AuditEntry {
+                time: DateTime::parse_from_rfc3339(&time)?.with_timezone(&Local),
+                actor,
+                command,
+                ids: ids.split(',').filter_map(|id| id.parse().ok()).collect(),
+                details,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,106 @@
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate};
+use rusqlite::Connection;
+use std::collections::BTreeMap;
+
+use crate::epochs;
+use crate::error::WebgoneError;
+use webgone::model;
+
+/// Block characters for eighths of a cell, from one eighth to a full cell.
+const EIGHTHS: [char; 8] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉', '█'];
+
+/// Downtime within one week, Monday to Sunday.
+pub struct WeekBar {
+    pub start: NaiveDate,
+    /// Seconds of the week that have passed, less than a week for the current one
+    pub elapsed_seconds: i64,
+    pub outages: i64,
+    pub downtime_seconds: i64,
+    /// Day with the most downtime and its downtime, if there was any
+    pub worst_day: Option<(NaiveDate, i64)>,
+}
+
+impl WeekBar {
+    pub fn uptime_percent(&self) -> f64 {
+        if self.elapsed_seconds <= 0 {
+            return 100.0;
+        }
+        100.0 * (1.0 - self.downtime_seconds.min(self.elapsed_seconds) as f64 / self.elapsed_seconds as f64)
+    }
+}
+
+/// The last `count` weeks of outages recorded here, oldest first, ending
+/// with the current week. Downtime is split at midnight.
+pub fn weeks(conn: &Connection, count: u32, now: DateTime<Local>) -> Result<Vec<WeekBar>> {
+    let today = now.date_naive();
+    let current = today - Days::new(today.weekday().num_days_from_monday() as u64);
+    let first = current.checked_sub_days(Days::new(7 * count.saturating_sub(1) as u64)).ok_or_else(|| {
+        WebgoneError::InvalidInput(format!("{} weeks go back further than the calendar does", count))
+    })?;
+    let from = epochs::start_of_day(first);
+
+    let mut stmt = conn.prepare(
+        "SELECT start_time, end_time FROM outages
+        WHERE site IS NULL AND julianday(end_time) > julianday(?1) AND julianday(start_time) < julianday(?2)",
+    )?;
+    let rows = stmt
+        .query_map([from.to_rfc3339(), now.to_rfc3339()], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    // Outages per day they started on, and downtime per day
+    let mut started: BTreeMap<NaiveDate, i64> = BTreeMap::new();
+    let mut downtime: BTreeMap<NaiveDate, i64> = BTreeMap::new();
+    for (start, end) in rows {
+        let (Some(start), Some(end)) = (model::parse_timestamp(&start), model::parse_timestamp(&end)) else {
+            continue;
+        };
+        let (start, end) = (start.max(from), end.min(now));
+        *started.entry(start.date_naive()).or_default() += 1;
+        let mut day = start.date_naive();
+        while epochs::start_of_day(day) < end {
+            let next = day + Days::new(1);
+            let seconds = (end.min(epochs::start_of_day(next)) - start.max(epochs::start_of_day(day))).num_seconds();
+            if seconds > 0 {
+                *downtime.entry(day).or_default() += seconds;
+            }
+            day = next;
+        }
+    }
+
+    let bars = (0..count as u64)
+        .map(|index| {
+            let start = first + Days::new(7 * index);
+            let end = start + Days::new(7);
+            let outages = started.range(start..end).map(|(_, outages)| *outages).sum();
+            let worst_day = downtime
+                .range(start..end)
+                .max_by_key(|(day, seconds)| (**seconds, std::cmp::Reverse(**day)))
+                .map(|(day, seconds)| (*day, *seconds));
+            WeekBar {
+                start,
+                elapsed_seconds: (epochs::start_of_day(end).min(now) - epochs::start_of_day(start)).num_seconds().max(0),
+                outages,
+                downtime_seconds: downtime.range(start..end).map(|(_, seconds)| *seconds).sum(),
+                worst_day,
+            }
+        })
+        .collect();
+    Ok(bars)
+}
+
+/// A bar of `width` cells filled in proportion to `value / max`, in eighths
+/// of a cell. Any value above zero shows at least one eighth.
+pub fn render(value: i64, max: i64, width: usize) -> String {
+    if value <= 0 || max <= 0 {
+        return String::new();
+    }
+    let eighths = ((value as f64 / max as f64) * (width * 8) as f64).round().max(1.0) as usize;
+    let mut bar = "█".repeat(eighths / 8);
+    if let Some(rest) = (eighths % 8).checked_sub(1) {
+        bar.push(EIGHTHS[rest]);
+    }
+    bar
+}
@@ -0,0 +1,307 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::{json, Value};
+use std::path::Path;
+
+use crate::coverage::{self, GapMode};
+use crate::error::WebgoneError;
+use crate::latency;
+use crate::quality;
+use webgone::model;
+
+/// Average number of days in a month, for the per-month measures.
+const DAYS_PER_MONTH: f64 = 365.25 / 12.0;
+
+pub fn init_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS baselines (
+            name TEXT NOT NULL,
+            metric TEXT NOT NULL,
+            value REAL NOT NULL,
+            region TEXT,
+            source TEXT,
+            imported_at TEXT NOT NULL,
+            PRIMARY KEY (name, metric)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Measures a baseline can give a typical value for.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    AvailabilityPercent,
+    OutagesPerMonth,
+    DowntimeMinutesPerMonth,
+    LatencyP95Ms,
+    LossPercent,
+}
+
+impl Metric {
+    const ALL: [Metric; 5] = [
+        Metric::AvailabilityPercent,
+        Metric::OutagesPerMonth,
+        Metric::DowntimeMinutesPerMonth,
+        Metric::LatencyP95Ms,
+        Metric::LossPercent,
+    ];
+
+    /// Name in the `metric` column of baseline files.
+    pub fn name(self) -> &'static str {
+        match self {
+            Metric::AvailabilityPercent => "availability_percent",
+            Metric::OutagesPerMonth => "outages_per_month",
+            Metric::DowntimeMinutesPerMonth => "downtime_minutes_per_month",
+            Metric::LatencyP95Ms => "latency_p95_ms",
+            Metric::LossPercent => "loss_percent",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Metric::AvailabilityPercent => "Availability",
+            Metric::OutagesPerMonth => "Outages per month",
+            Metric::DowntimeMinutesPerMonth => "Downtime per month",
+            Metric::LatencyP95Ms => "Latency (95th percentile)",
+            Metric::LossPercent => "Probe loss",
+        }
+    }
+
+    fn format(self, value: f64) -> String {
+        match self {
+            Metric::AvailabilityPercent => format!("{:.3}%", value),
+            Metric::OutagesPerMonth => format!("{:.1}", value),
+            Metric::DowntimeMinutesPerMonth => format!("{:.0} min", value),
+            Metric::LatencyP95Ms => format!("{:.1} ms", value),
+            Metric::LossPercent => format!("{:.2}%", value),
+        }
+    }
+
+    fn higher_is_better(self) -> bool {
+        self == Metric::AvailabilityPercent
+    }
+
+    fn parse(name: &str) -> Option<Metric> {
+        Metric::ALL.into_iter().find(|metric| metric.name() == name)
+    }
+}
+
+/// Splits a CSV line into its fields, unquoting quoted ones.
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().expect("one field").push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            c => fields.last_mut().expect("one field").push(c),
+        }
+    }
+    fields.into_iter().map(|field| field.trim().to_string()).collect()
+}
+
+/// Imports a CSV file with the columns `metric` and `value`, and optionally
+/// `region` and `source`, as the baseline `name`, replacing a baseline of
+/// that name. Returns the number of metrics imported.
+pub fn import(conn: &Connection, name: &str, path: &Path) -> Result<usize> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| WebgoneError::Io(format!("failed to read {}: {}", path.display(), e)))?;
+    let invalid = |line: usize, reason: String| WebgoneError::InvalidInput(format!("{}:{}: {}", path.display(), line, reason));
+
+    let mut lines = text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+    let header: Vec<String> = lines.next().map(|(_, line)| csv_fields(line)).unwrap_or_default();
+    let column = |name: &str| header.iter().position(|column| column.eq_ignore_ascii_case(name));
+    let (Some(metric_column), Some(value_column)) = (column("metric"), column("value")) else {
+        return Err(invalid(1, "the header needs the columns `metric` and `value`".to_string()).into());
+    };
+    let (region_column, source_column) = (column("region"), column("source"));
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM baselines WHERE name = ?", [name])?;
+    let now = Local::now().to_rfc3339();
+    let mut imported = 0;
+    for (index, line) in lines {
+        let fields = csv_fields(line);
+        let field = |column: Option<usize>| column.and_then(|column| fields.get(column)).filter(|field| !field.is_empty());
+        let metric_name = field(Some(metric_column)).map(String::as_str).unwrap_or_default();
+        let metric = Metric::parse(metric_name).ok_or_else(|| {
+            let names: Vec<&str> = Metric::ALL.iter().map(|metric| metric.name()).collect();
+            invalid(index + 1, format!("unknown metric `{}` (available: {})", metric_name, names.join(", ")))
+        })?;
+        let value: f64 = field(Some(value_column))
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| invalid(index + 1, format!("no numeric value for {}", metric.name())))?;
+        let inserted = tx.execute(
+            "INSERT OR IGNORE INTO baselines (name, metric, value, region, source, imported_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![name, metric.name(), value, field(region_column), field(source_column), now],
+        )?;
+        if inserted == 0 {
+            return Err(invalid(index + 1, format!("{} is listed twice", metric.name())).into());
+        }
+        imported += 1;
+    }
+    if imported == 0 {
+        return Err(invalid(1, "no metrics found".to_string()).into());
+    }
+    tx.commit()?;
+    Ok(imported)
+}
+
+pub fn remove(conn: &Connection, name: &str) -> Result<()> {
+    if conn.execute("DELETE FROM baselines WHERE name = ?", [name])? == 0 {
+        return Err(WebgoneError::InvalidInput(format!("no baseline named {}", name)).into());
+    }
+    Ok(())
+}
+
+/// An imported baseline.
+pub struct Baseline {
+    pub name: String,
+    pub region: Option<String>,
+    pub source: Option<String>,
+    pub values: Vec<(Metric, f64)>,
+}
+
+/// The imported baselines, most recently imported first.
+pub fn list(conn: &Connection) -> Result<Vec<Baseline>> {
+    let mut stmt = conn.prepare(
+        "SELECT name, metric, value, region, source FROM baselines
+        ORDER BY imported_at DESC, name, rowid",
+    )?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, f64>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, Option<String>>(4)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut baselines: Vec<Baseline> = Vec::new();
+    for (name, metric, value, region, source) in rows {
+        let index = match baselines.iter().position(|baseline| baseline.name == name) {
+            Some(index) => index,
+            None => {
+                baselines.push(Baseline { name, region: None, source: None, values: Vec::new() });
+                baselines.len() - 1
+            }
+        };
+        let baseline = &mut baselines[index];
+        baseline.region = baseline.region.take().or(region);
+        baseline.source = baseline.source.take().or(source);
+        if let Some(metric) = Metric::parse(&metric) {
+            baseline.values.push((metric, value));
+        }
+    }
+    Ok(baselines)
+}
+
+/// The baseline `name`, or the most recently imported one.
+pub fn find(conn: &Connection, name: Option<&str>) -> Result<Option<Baseline>> {
+    let baselines = list(conn)?;
+    match name {
+        Some(name) => match baselines.into_iter().find(|baseline| baseline.name == name) {
+            Some(baseline) => Ok(Some(baseline)),
+            None => Err(WebgoneError::InvalidInput(format!("no baseline named {} (see `webgone baseline list`)", name)).into()),
+        },
+        None => Ok(baselines.into_iter().next()),
+    }
+}
+
+/// This connection's values of the metrics between `from` and `to`, given
+/// the outages and downtime the report counts in that time.
+fn measure(conn: &Connection, from: DateTime<Local>, to: DateTime<Local>, outages: i64, downtime_seconds: i64) -> Result<Vec<(Metric, Option<f64>)>> {
+    let period_seconds = (to - from).num_seconds();
+    let gap_seconds = coverage::gap_seconds(conn, from, to)?;
+    // Watchers of older versions didn't record when they were monitoring
+    let gaps = if gap_seconds >= period_seconds { GapMode::Up } else { GapMode::Exclude };
+    let monitored_seconds = if gaps == GapMode::Up { period_seconds } else { period_seconds - gap_seconds };
+    let months = monitored_seconds as f64 / 86400.0 / DAYS_PER_MONTH;
+    let per_month = |value: f64| (months > 0.0).then(|| value / months);
+
+    Ok(vec![
+        (Metric::AvailabilityPercent, Some(gaps.uptime_percent(period_seconds, downtime_seconds, gap_seconds))),
+        (Metric::OutagesPerMonth, per_month(outages as f64)),
+        (Metric::DowntimeMinutesPerMonth, per_month(downtime_seconds as f64 / 60.0)),
+        (Metric::LatencyP95Ms, latency::stats(conn, from, to)?.last().map(|stats| stats.p95_ms)),
+        (Metric::LossPercent, quality::loss_percent(conn, from, to)?),
+    ])
+}
+
+/// Start of the data, for reports without `--since`.
+fn first_record(conn: &Connection) -> Result<Option<DateTime<Local>>> {
+    let first: Option<String> = conn
+        .query_row(
+            "SELECT MIN(start_time) FROM (
+                SELECT MIN(start_time) AS start_time FROM monitored_periods
+                UNION ALL SELECT MIN(start_time) FROM outages WHERE site IS NULL
+            )",
+            [],
+            |row| row.get(0),
+        )
+        .optional()?
+        .flatten();
+    Ok(first.and_then(|first| model::parse_timestamp(&first)))
+}
+
+/// The comparison section of reports: this connection's values between
+/// `since` (or the start of the data) and `until` (or now) next to those of
+/// `baseline`, or `null` without one.
+pub fn comparison(
+    conn: &Connection,
+    baseline: Option<&Baseline>,
+    since: Option<DateTime<Local>>,
+    until: Option<DateTime<Local>>,
+    outages: i64,
+    downtime_seconds: i64,
+) -> Result<Value> {
+    let Some(baseline) = baseline else {
+        return Ok(Value::Null);
+    };
+    let to = until.unwrap_or_else(Local::now).min(Local::now());
+    let Some(from) = since.or(first_record(conn)?).filter(|from| *from < to) else {
+        return Ok(Value::Null);
+    };
+
+    let yours = measure(conn, from, to, outages, downtime_seconds)?;
+    let metrics: Vec<Value> = baseline
+        .values
+        .iter()
+        .filter_map(|&(metric, typical)| {
+            let value = yours.iter().find(|(measured, _)| *measured == metric)?.1?;
+            let verdict = if (value - typical).abs() < f64::EPSILON {
+                "same"
+            } else if (value > typical) == metric.higher_is_better() {
+                "better"
+            } else {
+                "worse"
+            };
+            Some(json!({
+                "metric": metric.name(),
+                "label": metric.label(),
+                "yours": metric.format(value),
+                "baseline": metric.format(typical),
+                "verdict": verdict,
+            }))
+        })
+        .collect();
+
+    Ok(json!({
+        "baseline": baseline.name,
+        "region": baseline.region,
+        "source": baseline.source,
+        "from": from.format("%Y-%m-%d").to_string(),
+        "to": to.format("%Y-%m-%d").to_string(),
+        "metrics": metrics,
+    }))
+}
@@ -0,0 +1,148 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Packets tcpdump stops after with `--capture-interface`
+const MAX_PACKETS: u32 = 100_000;
+
+/// Bytes kept of each packet with `--capture-interface`, enough for the
+/// headers and payloads of DHCP, DNS and ARP
+const SNAPLEN: u32 = 512;
+
+/// How long a capture gets to exit after being asked to stop before it is killed.
+const STOP_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How the watcher captures packets during outages.
+pub struct CaptureSettings {
+    /// Interface the built-in tcpdump command captures on
+    pub interface: Option<String>,
+    /// Shell command that writes the capture to `WEBGONE_CAPTURE_FILE`, instead of the built-in one
+    pub command: Option<String>,
+    pub dir: PathBuf,
+    pub max_duration: Duration,
+}
+
+impl CaptureSettings {
+    pub fn enabled(&self) -> bool {
+        self.interface.is_some() || self.command.is_some()
+    }
+
+    /// Starts capturing into a file named after the start of the outage.
+    pub fn start(&self, outage_start: DateTime<Local>) -> Result<Capture> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create capture directory {}", self.dir.display()))?;
+        let path = self.dir.join(format!("webgone-{}.pcap", outage_start.format("%Y%m%d-%H%M%S")));
+
+        let mut command = match (&self.command, &self.interface) {
+            (Some(command), _) if cfg!(windows) => {
+                let mut shell = Command::new("cmd");
+                shell.args(["/C", command]);
+                shell
+            }
+            (Some(command), _) => {
+                let mut shell = Command::new("sh");
+                shell.args(["-c", command]);
+                shell
+            }
+            (None, interface) => {
+                let mut tcpdump = Command::new("tcpdump");
+                // -U writes every packet right away, so nothing is lost if tcpdump has to be killed
+                tcpdump
+                    .args(["-i", interface.as_deref().unwrap_or("any"), "-U"])
+                    .args(["-s", &SNAPLEN.to_string(), "-c", &MAX_PACKETS.to_string(), "-w"])
+                    .arg(&path);
+                tcpdump
+            }
+        };
+        command
+            .env("WEBGONE_CAPTURE_FILE", &path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        // Its own process group, so stopping a shell command stops what it started too
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+
+        let child = command.spawn().context("Failed to start packet capture")?;
+        Ok(Capture { child, path, started: Instant::now(), max_duration: self.max_duration, ended: false })
+    }
+}
+
+/// A running packet capture, stopped when dropped.
+pub struct Capture {
+    child: Child,
+    path: PathBuf,
+    started: Instant,
+    max_duration: Duration,
+    ended: bool,
+}
+
+impl Capture {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Stops the capture once it ran for its maximum duration. Returns a
+    /// message the first time the capture is found to have ended.
+    pub fn check(&mut self) -> Option<String> {
+        if self.ended {
+            return None;
+        }
+        if let Ok(Some(status)) = self.child.try_wait() {
+            self.ended = true;
+            return Some(describe_exit(status));
+        }
+        if self.started.elapsed() >= self.max_duration {
+            self.stop();
+            return Some(format!("Packet capture stopped after {} seconds", self.max_duration.as_secs()));
+        }
+        None
+    }
+
+    /// Stops the capture and returns its file, if one was written.
+    pub fn finish(mut self) -> Option<PathBuf> {
+        self.stop();
+        self.path.exists().then(|| self.path.clone())
+    }
+
+    fn stop(&mut self) {
+        self.ended = true;
+        if !matches!(self.child.try_wait(), Ok(None)) {
+            return;
+        }
+
+        // Ask politely first, so the capture program can flush its file
+        let id = self.child.id().to_string();
+        #[cfg(unix)]
+        let _ = Command::new("kill").args(["-TERM", &format!("-{}", id)]).stderr(Stdio::null()).status();
+        #[cfg(windows)]
+        let _ = Command::new("taskkill").args(["/T", "/PID", &id]).stdout(Stdio::null()).stderr(Stdio::null()).status();
+
+        let deadline = Instant::now() + STOP_TIMEOUT;
+        while Instant::now() < deadline {
+            if !matches!(self.child.try_wait(), Ok(None)) {
+                return;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Drop for Capture {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn describe_exit(status: ExitStatus) -> String {
+    if status.success() {
+        "Packet capture finished".to_string()
+    } else {
+        format!("Packet capture exited with {}", status)
+    }
+}
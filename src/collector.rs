@@ -0,0 +1,193 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
+use rusqlite::{params, Connection};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::{Certificate, Identity, Server, ServerTlsConfig};
+use tonic::{Request, Response, Status, Streaming};
+
+use crate::error::WebgoneError;
+use crate::proto::agent_message::Payload;
+use crate::proto::collector_server::{Collector, CollectorServer};
+use crate::proto::{Ack, AgentMessage, Outage, Probe};
+use crate::sites;
+use crate::tls;
+
+/// Acknowledgements buffered per agent. When an agent doesn't read them, the
+/// server stops reading its messages and HTTP/2 flow control slows the agent down.
+const ACK_BUFFER: usize = 64;
+
+pub struct CollectOptions {
+    pub listen: SocketAddr,
+    pub tls: Option<tls::Certificate>,
+    /// Only accept agents with a client certificate signed by this CA (PEM)
+    pub client_ca: Option<Vec<u8>>,
+}
+
+struct CollectorService {
+    conn: Arc<Mutex<Connection>>,
+}
+
+fn parse_time(field: &str, value: &str) -> Result<String> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Local).to_rfc3339())
+        .map_err(|_| WebgoneError::InvalidInput(format!("{} must be an RFC 3339 timestamp", field)).into())
+}
+
+/// Stores a probe of an agent; probes it sent before are ignored.
+fn store_probe(conn: &Connection, site: &str, probe: &Probe) -> Result<()> {
+    conn.execute(
+        "INSERT OR IGNORE INTO probes (time, target, success, latency_ms, error, weight, site, remote_id)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            parse_time("time", &probe.time)?,
+            probe.target,
+            probe.success,
+            probe.latency_ms,
+            probe.error,
+            probe.weight.max(1),
+            site,
+            probe.id,
+        ],
+    )?;
+    Ok(())
+}
+
+/// Stores an outage of an agent, replacing the copy it sent before if merging extended it.
+fn store_outage(conn: &Connection, site: &str, outage: &Outage) -> Result<()> {
+    conn.execute(
+        // An agent whose clock was stepped backwards may send an end before the start
+        "INSERT INTO outages (start_time, end_time, duration_seconds, wifi_ssid, site, remote_id, actor, clock_anomaly)
+        VALUES (
+            ?1, CASE WHEN julianday(?2) < julianday(?1) THEN ?1 ELSE ?2 END, MAX(?3, 0), ?4, ?5, ?6, ?7,
+            julianday(?2) < julianday(?1) OR ?3 < 0
+        )
+        ON CONFLICT (site, remote_id) DO UPDATE SET
+            end_time = excluded.end_time,
+            duration_seconds = excluded.duration_seconds,
+            wifi_ssid = excluded.wifi_ssid,
+            clock_anomaly = excluded.clock_anomaly",
+        params![
+            parse_time("start_time", &outage.start_time)?,
+            parse_time("end_time", &outage.end_time)?,
+            outage.duration_seconds,
+            outage.wifi_ssid,
+            site,
+            outage.id,
+            agent_actor(site),
+        ],
+    )?;
+    Ok(())
+}
+
+/// How outages received from an agent are attributed.
+fn agent_actor(site: &str) -> String {
+    format!("agent {}", site)
+}
+
+/// The site an agent announced in its Hello.
+fn require_site(site: &Option<String>) -> Result<&str> {
+    site.as_deref()
+        .ok_or_else(|| WebgoneError::InvalidInput("the first message must be a Hello".to_string()).into())
+}
+
+fn handle(conn: &Mutex<Connection>, site: &mut Option<String>, payload: Option<Payload>) -> Result<()> {
+    let lock = || conn.lock().map_err(|_| anyhow!("database connection poisoned"));
+    match payload {
+        Some(Payload::Hello(hello)) => {
+            if hello.site.trim().is_empty() {
+                return Err(WebgoneError::InvalidInput("site must not be empty".to_string()).into());
+            }
+            if hello.site.eq_ignore_ascii_case(sites::LOCAL_SITE) {
+                return Err(WebgoneError::InvalidInput(format!(
+                    "site name {} is reserved for the server's own data",
+                    sites::LOCAL_SITE
+                ))
+                .into());
+            }
+            println!("Agent for site {} connected (webgone {})", hello.site, hello.version);
+            *site = Some(hello.site);
+            Ok(())
+        }
+        Some(Payload::Probe(probe)) => store_probe(&*lock()?, require_site(site)?, &probe),
+        Some(Payload::Outage(outage)) => store_outage(&*lock()?, require_site(site)?, &outage),
+        None => Err(WebgoneError::InvalidInput("empty message".to_string()).into()),
+    }
+}
+
+fn to_status(e: anyhow::Error) -> Status {
+    match e.downcast_ref::<WebgoneError>() {
+        Some(WebgoneError::InvalidInput(message)) => Status::invalid_argument(message.clone()),
+        _ => {
+            println!("Failed to store agent data: {:#}", e);
+            Status::internal("failed to store data")
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl Collector for CollectorService {
+    type ReportStream = ReceiverStream<Result<Ack, Status>>;
+
+    async fn report(&self, request: Request<Streaming<AgentMessage>>) -> Result<Response<Self::ReportStream>, Status> {
+        let mut messages = request.into_inner();
+        let conn = self.conn.clone();
+        let (acks, rx) = mpsc::channel(ACK_BUFFER);
+
+        tokio::spawn(async move {
+            let mut site = None;
+            loop {
+                let message = match messages.message().await {
+                    Ok(Some(message)) => message,
+                    Ok(None) => break,
+                    Err(status) => {
+                        println!("Agent stream failed: {}", status.message());
+                        break;
+                    }
+                };
+
+                let reply = handle(&conn, &mut site, message.payload)
+                    .map(|()| Ack { sequence: message.sequence })
+                    .map_err(to_status);
+                let failed = reply.is_err();
+                if acks.send(reply).await.is_err() || failed {
+                    break;
+                }
+            }
+            if let Some(site) = site {
+                println!("Agent for site {} disconnected", site);
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+/// Receives probe results and outages from agents until interrupted.
+pub fn collect(conn: Connection, options: CollectOptions) -> Result<()> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let mut server = Server::builder();
+        if let Some(tls) = &options.tls {
+            let mut config = ServerTlsConfig::new().identity(Identity::from_pem(&tls.certificate, &tls.private_key));
+            if let Some(ca) = &options.client_ca {
+                config = config.client_ca_root(Certificate::from_pem(ca));
+            }
+            server = server
+                .tls_config(config)
+                .map_err(|e| WebgoneError::InvalidInput(format!("invalid TLS configuration: {}", e)))?;
+        }
+
+        let service = CollectorService { conn: Arc::new(Mutex::new(conn)) };
+        server
+            .add_service(CollectorServer::new(service))
+            .serve_with_shutdown(options.listen, async {
+                tokio::signal::ctrl_c().await.ok();
+            })
+            .await
+            .map_err(|e| WebgoneError::Io(format!("failed to serve on {}: {}", options.listen, e)))?;
+        Ok(())
+    })
+}
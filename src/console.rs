@@ -0,0 +1,126 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use std::fs::{File, OpenOptions};
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::recovery;
+
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+
+/// What the live status line shows.
+pub struct Status {
+    pub connected: bool,
+    /// When the current state began
+    pub since: DateTime<Local>,
+    pub latency: Option<Duration>,
+}
+
+/// Output of the watcher. By default every message scrolls by; in live mode
+/// a single status line is updated in place instead, and only messages about
+/// state changes are printed above it. Everything, including per-probe
+/// messages, is also appended to the log file if there is one.
+pub struct Console {
+    live: bool,
+    log: Option<File>,
+    /// The status line currently on screen
+    status_line: Option<String>,
+    frame: usize,
+    /// Set once stdout was closed, e.g. by `head` or a log reader that
+    /// exited; the watcher keeps monitoring and logging without it
+    stdout_closed: bool,
+}
+
+impl Console {
+    /// Live mode is ignored unless stdout is a terminal, so redirected output stays readable.
+    pub fn new(live: bool, log_path: Option<&Path>) -> Result<Self> {
+        let log = log_path
+            .map(|path| {
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .with_context(|| format!("Failed to open log file {}", path.display()))
+            })
+            .transpose()?;
+        Ok(Console { live: live && std::io::stdout().is_terminal(), log, status_line: None, frame: 0, stdout_closed: false })
+    }
+
+    /// Prints a message, above the status line in live mode.
+    pub fn print(&mut self, message: &str) {
+        self.append_to_log(message);
+        let status_line = self.status_line.take();
+        self.clear();
+        self.write(format_args!("{}\n", message));
+        if let Some(line) = status_line {
+            self.draw(line);
+        }
+    }
+
+    /// Prints a message about a single probe, which the status line replaces in live mode.
+    pub fn detail(&mut self, message: &str) {
+        if self.live {
+            self.append_to_log(message);
+        } else {
+            self.print(message);
+        }
+    }
+
+    /// Redraws the status line in live mode.
+    pub fn status(&mut self, status: &Status) {
+        if !self.live {
+            return;
+        }
+        self.frame = (self.frame + 1) % SPINNER.len();
+        let elapsed = (Local::now() - status.since).to_std().unwrap_or_default();
+        let mut line = format!(
+            "{} {} since {} ({})",
+            SPINNER[self.frame],
+            if status.connected { "Online" } else { "OFFLINE" },
+            status.since.format("%Y-%m-%d %H:%M:%S"),
+            recovery::describe(elapsed),
+        );
+        if let Some(latency) = status.latency {
+            line.push_str(&format!(", last latency {:.1} ms", latency.as_secs_f64() * 1000.0));
+        }
+        self.draw(line);
+    }
+
+    /// Removes the status line, e.g. before the watcher exits.
+    pub fn clear(&mut self) {
+        if let Some(line) = self.status_line.take() {
+            // Overwrite with spaces, which unlike escape codes works in every console
+            self.write(format_args!("\r{}\r", " ".repeat(line.chars().count())));
+        }
+    }
+
+    fn draw(&mut self, line: String) {
+        let previous = self.status_line.as_ref().map_or(0, |previous| previous.chars().count());
+        let padding = previous.saturating_sub(line.chars().count());
+        self.write(format_args!("\r{}{}", line, " ".repeat(padding)));
+        self.status_line = Some(line);
+    }
+
+    /// Writes to stdout, unlike `print!` without panicking when it was
+    /// closed. Other write errors only lose the message.
+    fn write(&mut self, text: std::fmt::Arguments) {
+        if self.stdout_closed {
+            return;
+        }
+        let mut stdout = std::io::stdout().lock();
+        if let Err(e) = stdout.write_fmt(text).and_then(|()| stdout.flush()) {
+            if e.kind() == std::io::ErrorKind::BrokenPipe {
+                self.stdout_closed = true;
+                self.append_to_log("Standard output was closed, only writing to the log from now on");
+            }
+        }
+    }
+
+    fn append_to_log(&mut self, message: &str) {
+        if let Some(log) = &mut self.log {
+            // Losing a log line isn't worth stopping the watcher for
+            let _ = writeln!(log, "{} {}", Local::now().format("%Y-%m-%d %H:%M:%S"), message);
+        }
+    }
+}
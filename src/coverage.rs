@@ -0,0 +1,253 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use rusqlite::{params, Connection};
+use std::env;
+use std::fs;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use webgone::model::{self, MonitoredPeriod};
+
+/// How often the watcher extends the period it has been monitoring, so a
+/// crash loses at most this much of it.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How time without monitoring counts in uptime.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GapMode {
+    /// Leave gaps out of the period uptime is computed over
+    Exclude,
+    /// Count gaps as downtime
+    Down,
+    /// Count gaps as uptime, as if the connection had been fine
+    Up,
+}
+
+impl GapMode {
+    /// Uptime in percent of a period of `period_seconds` with `downtime_seconds`
+    /// of outages and `gap_seconds` without monitoring.
+    pub fn uptime_percent(self, period_seconds: i64, downtime_seconds: i64, gap_seconds: i64) -> f64 {
+        let (downtime, period) = match self {
+            GapMode::Exclude => (downtime_seconds, period_seconds - gap_seconds),
+            GapMode::Down => (downtime_seconds + gap_seconds, period_seconds),
+            GapMode::Up => (downtime_seconds, period_seconds),
+        };
+        if period <= 0 {
+            return 100.0;
+        }
+        100.0 * (1.0 - downtime.min(period) as f64 / period as f64)
+    }
+}
+
+pub fn init_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS monitored_periods (
+            id INTEGER PRIMARY KEY,
+            start_time TEXT NOT NULL,
+            end_time TEXT NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// The machine and configuration a watcher runs with, recorded with each
+/// period it monitors so data merged from several machines stays
+/// attributable and a run can be reproduced.
+pub struct Watcher {
+    pub hostname: Option<String>,
+    pub os: String,
+    pub version: String,
+    pub config: String,
+}
+
+impl Watcher {
+    /// This machine and binary, probing as `config` says.
+    pub fn current(config: String) -> Self {
+        Watcher { hostname: hostname(), os: os(), version: env!("CARGO_PKG_VERSION").to_string(), config }
+    }
+}
+
+pub fn hostname() -> Option<String> {
+    let name = env::var("COMPUTERNAME")
+        .ok()
+        .or_else(|| fs::read_to_string("/proc/sys/kernel/hostname").ok())
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .or_else(|| {
+            let output = Command::new("hostname").output().ok()?;
+            output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+        })?;
+    let name = name.trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// The distribution's name where `/etc/os-release` has one, with the
+/// platform webgone was built for.
+fn os() -> String {
+    let platform = format!("{} {}", env::consts::OS, env::consts::ARCH);
+    let release = fs::read_to_string("/etc/os-release").ok().and_then(|release| {
+        release.lines().find_map(|line| Some(line.strip_prefix("PRETTY_NAME=")?.trim_matches('"').to_string()))
+    });
+    match release {
+        Some(name) => format!("{} ({})", name, platform),
+        None => platform,
+    }
+}
+
+/// The period the watcher of this process has been monitoring without
+/// interruption.
+pub struct Session {
+    id: Option<i64>,
+    last_write: Option<Instant>,
+    watcher: Watcher,
+}
+
+impl Session {
+    pub fn new(watcher: Watcher) -> Self {
+        Session { id: None, last_write: None, watcher }
+    }
+
+    /// Extends the current period to `now`, or starts one. Written at most
+    /// every `HEARTBEAT_INTERVAL`.
+    pub fn touch(&mut self, conn: &Connection, now: DateTime<Local>) -> Result<()> {
+        match self.id {
+            None => {
+                conn.execute(
+                    "INSERT INTO monitored_periods (start_time, end_time, hostname, os, version, config)
+                    VALUES (?1, ?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        now.to_rfc3339(),
+                        self.watcher.hostname,
+                        self.watcher.os,
+                        self.watcher.version,
+                        self.watcher.config
+                    ],
+                )?;
+                self.id = Some(conn.last_insert_rowid());
+            }
+            Some(id) => {
+                if self.last_write.is_some_and(|at| at.elapsed() < HEARTBEAT_INTERVAL) {
+                    return Ok(());
+                }
+                conn.execute("UPDATE monitored_periods SET end_time = ?1 WHERE id = ?2", params![now.to_rfc3339(), id])?;
+            }
+        }
+        self.last_write = Some(Instant::now());
+        Ok(())
+    }
+
+    /// Ends the current period at `at`, e.g. when monitoring is paused or the
+    /// machine was suspended. The next `touch` starts a new one.
+    pub fn end(&mut self, conn: &Connection, at: DateTime<Local>) -> Result<()> {
+        if let Some(id) = self.id.take() {
+            conn.execute("UPDATE monitored_periods SET end_time = ?1 WHERE id = ?2", params![at.to_rfc3339(), id])?;
+        }
+        self.last_write = None;
+        Ok(())
+    }
+}
+
+/// Monitored periods overlapping `from` to `to`, earliest first.
+pub fn periods(conn: &Connection, from: DateTime<Local>, to: DateTime<Local>) -> Result<Vec<MonitoredPeriod>> {
+    let mut stmt = conn.prepare(
+        "SELECT start_time, end_time, hostname, os, version, config FROM monitored_periods
+        WHERE julianday(end_time) >= julianday(?1) AND julianday(start_time) <= julianday(?2)
+        ORDER BY julianday(start_time)",
+    )?;
+    let rows = stmt
+        .query_map([from.to_rfc3339(), to.to_rfc3339()], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get(2)?,
+                row.get(3)?,
+                row.get(4)?,
+                row.get(5)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|(start, end, hostname, os, version, config)| {
+            Some(MonitoredPeriod {
+                start: model::parse_timestamp(&start)?,
+                end: model::parse_timestamp(&end)?,
+                hostname,
+                os,
+                version,
+                config,
+            })
+        })
+        .collect())
+}
+
+/// Monitored periods during the outages of an export, so every outage can
+/// be traced to the watcher that recorded it.
+pub fn for_export(conn: &Connection, outages: &[model::InternetOutage]) -> Result<Vec<MonitoredPeriod>> {
+    let from = outages.iter().map(|outage| outage.start_time).min();
+    let to = outages.iter().map(|outage| outage.end_time).max();
+    match from.zip(to) {
+        Some((from, to)) => periods(conn, from, to),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Seconds between `from` and `to` in which no watcher was monitoring on
+/// this machine. Older versions didn't record their periods, so the time
+/// they were watching counts as a gap too.
+pub fn gap_seconds(conn: &Connection, from: DateTime<Local>, to: DateTime<Local>) -> Result<i64> {
+    let mut stmt = conn.prepare(
+        "SELECT start_time, end_time FROM monitored_periods
+        WHERE julianday(end_time) > julianday(?1) AND julianday(start_time) < julianday(?2)
+        ORDER BY julianday(start_time)",
+    )?;
+    let periods = stmt
+        .query_map([from.to_rfc3339(), to.to_rfc3339()], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    // Periods of watchers running side by side may overlap, so merge them
+    let mut covered = 0;
+    let mut covered_until = from;
+    for (start, end) in periods {
+        let (Some(start), Some(end)) = (model::parse_timestamp(&start), model::parse_timestamp(&end)) else {
+            continue;
+        };
+        let start = start.max(covered_until);
+        let end = end.min(to);
+        if end > start {
+            covered += (end - start).num_seconds();
+            covered_until = end;
+        }
+    }
+    Ok(((to - from).num_seconds() - covered).max(0))
+}
+
+/// What the watcher monitored from `since` on.
+pub struct SessionSummary {
+    pub monitored_seconds: i64,
+    /// Outages of the internet connection that ended since then
+    pub outages: i64,
+    pub downtime_seconds: i64,
+}
+
+/// Sums up the monitoring since `since`, or `None` if nothing was
+/// monitored, e.g. because the watcher failed to start.
+pub fn session_summary(conn: &Connection, since: DateTime<Local>) -> Result<Option<SessionSummary>> {
+    let monitored_seconds: Option<f64> = conn.query_row(
+        "SELECT SUM((julianday(end_time) - julianday(start_time)) * 86400) FROM monitored_periods
+        WHERE julianday(start_time) >= julianday(?1)",
+        [since.to_rfc3339()],
+        |row| row.get(0),
+    )?;
+    let Some(monitored_seconds) = monitored_seconds else {
+        return Ok(None);
+    };
+    let (outages, downtime_seconds) = conn.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(duration_seconds), 0) FROM outages
+        WHERE site IS NULL AND julianday(end_time) >= julianday(?1)",
+        [since.to_rfc3339()],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    Ok(Some(SessionSummary { monitored_seconds: monitored_seconds.round() as i64, outages, downtime_seconds }))
+}
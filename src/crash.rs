@@ -0,0 +1,166 @@
+use anyhow::{Context, Result};
+use chrono::{Local, Utc};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::backtrace::Backtrace;
+use std::env;
+use std::fs;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::Duration;
+
+/// What to include in crash reports and where to send them.
+pub struct CrashContext {
+    /// Directory crash reports are written to
+    pub report_dir: PathBuf,
+    /// Sentry DSN to forward crash reports to
+    pub sentry_dsn: Option<String>,
+    pub schema_version: i64,
+}
+
+struct CrashReport {
+    message: String,
+    location: String,
+    thread: String,
+    command_line: String,
+    schema_version: i64,
+    backtrace: String,
+}
+
+impl CrashReport {
+    fn render(&self) -> String {
+        format!(
+            "webgone crash report\n\
+             time: {}\n\
+             version: {}\n\
+             platform: {}-{}\n\
+             schema version: {}\n\
+             command line: {}\n\
+             thread: {}\n\
+             location: {}\n\
+             message: {}\n\n\
+             backtrace:\n{}\n",
+            Local::now().to_rfc3339(),
+            env!("CARGO_PKG_VERSION"),
+            env::consts::ARCH,
+            env::consts::OS,
+            self.schema_version,
+            self.command_line,
+            self.thread,
+            self.location,
+            self.message,
+            self.backtrace,
+        )
+    }
+}
+
+/// Installs a panic hook that writes a crash report to `report_dir` and,
+/// when a DSN is configured, forwards it to Sentry. The default hook still
+/// prints the panic message first.
+pub fn install(context: CrashContext) {
+    let default_hook = panic::take_hook();
+
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+
+        let message = info
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic payload".to_string());
+        let report = CrashReport {
+            message,
+            location: info.location().map(ToString::to_string).unwrap_or_default(),
+            thread: thread::current().name().unwrap_or("unnamed").to_string(),
+            command_line: command_line(context.sentry_dsn.as_deref()),
+            schema_version: context.schema_version,
+            backtrace: Backtrace::force_capture().to_string(),
+        };
+
+        match write_report(&context.report_dir, &report) {
+            Ok(path) => eprintln!("Crash report written to {}", path.display()),
+            Err(e) => eprintln!("Failed to write crash report: {:#}", e),
+        }
+
+        if let Some(dsn) = &context.sentry_dsn {
+            match send_to_sentry(dsn, &report) {
+                Ok(()) => eprintln!("Crash report sent to Sentry"),
+                Err(e) => eprintln!("Failed to send crash report to Sentry: {:#}", e),
+            }
+        }
+    }));
+}
+
+/// The command line, with the Sentry DSN redacted since it contains a key.
+fn command_line(sentry_dsn: Option<&str>) -> String {
+    env::args()
+        .map(|arg| match sentry_dsn {
+            Some(dsn) if arg.contains(dsn) => arg.replace(dsn, "<redacted>"),
+            _ => arg,
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn write_report(dir: &Path, report: &CrashReport) -> Result<PathBuf> {
+    let path = dir.join(format!(
+        "webgone-crash-{}-{}.txt",
+        Local::now().format("%Y%m%d-%H%M%S"),
+        process::id()
+    ));
+    fs::write(&path, report.render()).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Sends the report as an event to Sentry's store endpoint. A DSN has the form
+/// `https://<key>@<host>[/<path>]/<project id>`.
+fn send_to_sentry(dsn: &str, report: &CrashReport) -> Result<()> {
+    let (scheme, rest) = dsn.split_once("://").context("Sentry DSN has no scheme")?;
+    let (key, rest) = rest.split_once('@').context("Sentry DSN has no public key")?;
+    let (host_and_path, project_id) = rest.trim_end_matches('/').rsplit_once('/').context("Sentry DSN has no project id")?;
+    let key = key.split(':').next().unwrap_or(key);
+    let url = format!("{}://{}/api/{}/store/", scheme, host_and_path, project_id);
+
+    let event_id = hex::encode(&Sha256::digest(format!("{:?}{}", Utc::now(), process::id()))[..16]);
+    let event = json!({
+        "event_id": event_id,
+        "timestamp": Utc::now().to_rfc3339(),
+        "level": "fatal",
+        "platform": "native",
+        "logger": "panic",
+        "release": concat!("webgone@", env!("CARGO_PKG_VERSION")),
+        "message": { "formatted": format!("{} at {}", report.message, report.location) },
+        "tags": {
+            "os": env::consts::OS,
+            "arch": env::consts::ARCH,
+            "thread": report.thread,
+        },
+        "extra": {
+            "command_line": report.command_line,
+            "schema_version": report.schema_version,
+            "backtrace": report.backtrace,
+        },
+    });
+
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(Duration::from_secs(10)))
+        .build()
+        .into();
+    agent
+        .post(&url)
+        .header(
+            "X-Sentry-Auth",
+            &format!(
+                "Sentry sentry_version=7, sentry_client=webgone/{}, sentry_key={}",
+                env!("CARGO_PKG_VERSION"),
+                key
+            ),
+        )
+        .header("Content-Type", "application/json")
+        .send(event.to_string())
+        .context("Sentry request failed")?;
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use anyhow::Result;
+use serde_json::Value;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tiny_http::{Header, Method, Response, Server};
+
+use crate::error::WebgoneError;
+
+/// The dashboard page, compiled into the binary so `webgone web` needs no other files.
+const PAGE: &str = include_str!("../web/dashboard.html");
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).expect("valid header")
+}
+
+/// Serves the dashboard at `/` and the data it shows at `/api/dashboard`
+/// until `running` is cleared. The data is loaded anew for every request.
+pub fn serve(listen: SocketAddr, running: &AtomicBool, data: &mut dyn FnMut() -> Result<Value>) -> Result<()> {
+    let server = Server::http(listen).map_err(|e| WebgoneError::Io(format!("failed to listen on {}: {}", listen, e)))?;
+
+    while running.load(Ordering::SeqCst) {
+        let Some(request) = server.recv_timeout(Duration::from_millis(500))? else {
+            continue;
+        };
+
+        let response = match (request.method(), request.url().split('?').next()) {
+            (Method::Get, Some("/" | "/index.html")) => {
+                Response::from_string(PAGE).with_header(header("Content-Type", "text/html; charset=utf-8"))
+            }
+            (Method::Get, Some("/api/dashboard")) => match data() {
+                Ok(value) => Response::from_string(value.to_string())
+                    .with_header(header("Content-Type", "application/json"))
+                    .with_header(header("Cache-Control", "no-store")),
+                Err(e) => {
+                    println!("Failed to load the dashboard data: {:#}", e);
+                    Response::from_string(r#"{"error": "internal error"}"#)
+                        .with_status_code(500)
+                        .with_header(header("Content-Type", "application/json"))
+                }
+            },
+            (Method::Get, _) => Response::from_string("not found\n").with_status_code(404),
+            _ => Response::from_string("method not allowed\n")
+                .with_status_code(405)
+                .with_header(header("Allow", "GET")),
+        };
+        if let Err(e) = request.respond(response) {
+            println!("Failed to send response: {}", e);
+        }
+    }
+
+    Ok(())
+}
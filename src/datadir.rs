@@ -0,0 +1,38 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// Name of the database in the working directory, where webgone kept it
+/// before it had a data directory.
+const LEGACY_DATABASE: &str = "internet_outages.db";
+
+/// Name of the database in the data directory.
+const DATABASE: &str = "outages.db";
+
+/// The database to use without `--db` or `WEBGONE_DB`: one already in the
+/// working directory, so existing setups keep working, or otherwise the one
+/// in the platform's data directory.
+pub fn default_database() -> PathBuf {
+    if Path::new(LEGACY_DATABASE).exists() {
+        return PathBuf::from(LEGACY_DATABASE);
+    }
+    data_dir().map_or_else(|| PathBuf::from(LEGACY_DATABASE), |dir| dir.join(DATABASE))
+}
+
+/// webgone's directory for user data: `$XDG_DATA_HOME/webgone` (by default
+/// `~/.local/share/webgone`) on Linux and BSD, `~/Library/Application
+/// Support/webgone` on macOS and `%APPDATA%\webgone` on Windows.
+fn data_dir() -> Option<PathBuf> {
+    let var = |name: &str| env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+    let base = if cfg!(windows) {
+        var("APPDATA")?
+    } else if cfg!(target_os = "macos") {
+        var("HOME")?.join("Library").join("Application Support")
+    } else {
+        // Relative paths in XDG_DATA_HOME are invalid and to be ignored
+        match var("XDG_DATA_HOME").filter(|dir| dir.is_absolute()) {
+            Some(dir) => dir,
+            None => var("HOME")?.join(".local").join("share"),
+        }
+    };
+    Some(base.join("webgone"))
+}
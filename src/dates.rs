@@ -0,0 +1,187 @@
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, Local, Months, NaiveDate, NaiveTime, TimeZone, Weekday};
+
+/// Parses a point in time given on the command line: an RFC 3339 timestamp,
+/// a local date and time like `2024-06-01 14:00`, a day like `2024-06-01`,
+/// `yesterday` or `last monday`, optionally followed by a time, a month like
+/// `2024-06`, a time of today like `14:00`, or a relative time like
+/// `2 weeks ago` or `in 3h`. Days and months stand for their start.
+pub fn parse_time(value: &str) -> Result<DateTime<Local>, String> {
+    resolve(value, Local::now()).ok_or_else(|| {
+        format!(
+            "expected a time like `2024-06-01 14:00`, `2024-06`, `14:00`, `yesterday`, `last monday` or `2 weeks ago`, got `{}`",
+            value
+        )
+    })
+}
+
+/// Parses a day the way `parse_time` parses a time, e.g. `2024-06-01` or `yesterday`.
+pub fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    parse_time(value).map(|time| time.date_naive())
+}
+
+fn resolve(value: &str, now: DateTime<Local>) -> Option<DateTime<Local>> {
+    let value = value.trim().to_lowercase();
+    if let Ok(time) = DateTime::parse_from_rfc3339(&value.replacen(' ', "T", 1)) {
+        return Some(time.with_timezone(&Local));
+    }
+    if value == "now" {
+        return Some(now);
+    }
+
+    // `2024-06-01T14:00`, like RFC 3339 without an offset
+    let value = match value.split_once('t') {
+        Some((date, time)) if NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok() => format!("{} {}", date, time),
+        _ => value,
+    };
+    let words: Vec<&str> = value.split_whitespace().collect();
+    match words.as_slice() {
+        [amount @ .., "ago"] => return offset(now, amount, false),
+        ["in", amount @ ..] => return offset(now, amount, true),
+        _ => {}
+    }
+
+    let (day, time) = match words.split_last() {
+        Some((last, rest)) => match parse_time_of_day(last) {
+            Some(time) => (rest, Some(time)),
+            None => (words.as_slice(), None),
+        },
+        None => return None,
+    };
+    let today = now.date_naive();
+    let date = match day {
+        [] => today,
+        ["today"] => today,
+        ["yesterday"] => today.pred_opt()?,
+        ["tomorrow"] => today.succ_opt()?,
+        ["last", weekday] => {
+            let weekday: Weekday = weekday.parse().ok()?;
+            let back = (today.weekday().days_since(weekday) + 6) % 7 + 1;
+            today - ChronoDuration::days(i64::from(back))
+        }
+        ["next", weekday] => {
+            let weekday: Weekday = weekday.parse().ok()?;
+            let ahead = (weekday.days_since(today.weekday()) + 6) % 7 + 1;
+            today + ChronoDuration::days(i64::from(ahead))
+        }
+        [date] => NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .or_else(|_| NaiveDate::parse_from_str(&format!("{}-01", date), "%Y-%m-%d"))
+            .ok()?,
+        _ => return None,
+    };
+    Local.from_local_datetime(&date.and_time(time.unwrap_or(NaiveTime::MIN))).earliest()
+}
+
+fn parse_time_of_day(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M:%S"))
+        .ok()
+}
+
+/// `now` moved by an amount like `2 weeks`, `3h` or `a month`, into the
+/// future if `ahead` and into the past otherwise.
+fn offset(now: DateTime<Local>, amount: &[&str], ahead: bool) -> Option<DateTime<Local>> {
+    let (number, unit): (u32, &str) = match amount {
+        ["a" | "an", unit] => (1, unit),
+        [number, unit] => (number.parse().ok()?, unit),
+        [amount] => {
+            let (number, unit) = amount.split_at(amount.find(|c: char| !c.is_ascii_digit())?);
+            (number.parse().ok()?, unit)
+        }
+        _ => return None,
+    };
+    let seconds = match unit {
+        "s" | "sec" | "secs" | "second" | "seconds" => 1,
+        "m" | "min" | "mins" | "minute" | "minutes" => 60,
+        "h" | "hr" | "hrs" | "hour" | "hours" => 60 * 60,
+        "d" | "day" | "days" => 24 * 60 * 60,
+        "w" | "week" | "weeks" => 7 * 24 * 60 * 60,
+        // Months and years differ in length, so they move the calendar date
+        "mo" | "month" | "months" | "y" | "year" | "years" => {
+            let months = Months::new(if unit.starts_with('y') { number.checked_mul(12)? } else { number });
+            return if ahead { now.checked_add_months(months) } else { now.checked_sub_months(months) };
+        }
+        _ => return None,
+    };
+    // Far enough out to leave the calendar is no time at all
+    let offset = ChronoDuration::try_seconds(i64::from(number) * seconds)?;
+    if ahead { now.checked_add_signed(offset) } else { now.checked_sub_signed(offset) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTime<Local> {
+        // A Wednesday
+        Local.with_ymd_and_hms(2026, 3, 18, 15, 30, 0).unwrap()
+    }
+
+    fn local(value: &str) -> DateTime<Local> {
+        let time = chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").unwrap();
+        Local.from_local_datetime(&time).earliest().unwrap()
+    }
+
+    #[test]
+    fn resolves_days_and_times() {
+        for (value, expected) in [
+            ("2024-06-01", "2024-06-01 00:00:00"),
+            ("2024-06-01 14:00", "2024-06-01 14:00:00"),
+            ("2024-06-01T14:00:30", "2024-06-01 14:00:30"),
+            ("2024-06", "2024-06-01 00:00:00"),
+            ("14:00", "2026-03-18 14:00:00"),
+            ("today", "2026-03-18 00:00:00"),
+            ("Yesterday", "2026-03-17 00:00:00"),
+            ("tomorrow 08:15", "2026-03-19 08:15:00"),
+            ("last monday", "2026-03-16 00:00:00"),
+            ("last wednesday", "2026-03-11 00:00:00"),
+            ("next wed 09:00", "2026-03-25 09:00:00"),
+        ] {
+            assert_eq!(resolve(value, now()), Some(local(expected)), "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn resolves_relative_times() {
+        for (value, expected) in [
+            ("now", ChronoDuration::zero()),
+            ("2 weeks ago", -ChronoDuration::weeks(2)),
+            ("in 3h", ChronoDuration::hours(3)),
+            ("90s ago", -ChronoDuration::seconds(90)),
+            ("an hour ago", -ChronoDuration::hours(1)),
+            ("in 10 minutes", ChronoDuration::minutes(10)),
+        ] {
+            assert_eq!(resolve(value, now()).map(|time| time - now()), Some(expected), "{:?}", value);
+        }
+        assert_eq!(resolve("a month ago", now()), now().checked_sub_months(Months::new(1)));
+        assert_eq!(resolve("in 2 years", now()), now().checked_add_months(Months::new(24)));
+    }
+
+    #[test]
+    fn resolves_rfc3339_in_any_offset() {
+        let time = resolve("2024-06-01T12:00:00+02:00", now()).unwrap();
+        assert_eq!(time.timestamp(), 1_717_236_000);
+    }
+
+    #[test]
+    fn rejects_malformed_and_out_of_range_times() {
+        for value in [
+            "",
+            "garbage",
+            "2024-13-01",
+            "2024-06-31",
+            "25:00",
+            "last someday",
+            "2 fortnights ago",
+            "ago",
+            "in",
+            "-3 days ago",
+            "99999999999 days ago",
+            "99999999 days ago",
+            "in 4000000000 weeks",
+            "4294967295 years ago",
+            "in 4294967295 months",
+        ] {
+            assert_eq!(resolve(value, now()), None, "{:?}", value);
+        }
+    }
+}
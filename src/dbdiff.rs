@@ -0,0 +1,160 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use rusqlite::{Connection, OpenFlags};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::error::WebgoneError;
+use crate::i18n::Text;
+
+/// An outage as far as comparing databases is concerned.
+pub struct OutageRow {
+    /// Id in the database the outage was read from
+    pub id: i64,
+    pub site: Option<String>,
+    pub start_time: DateTime<Local>,
+    pub end_time: DateTime<Local>,
+    pub duration_seconds: i64,
+}
+
+/// A summary value of both databases.
+pub struct MetricDiff {
+    pub name: Text,
+    pub a: Option<i64>,
+    pub b: Option<i64>,
+}
+
+/// Differences between two databases. Outages are matched by site and start
+/// time, so ids may differ, e.g. between an agent and the server it reports to.
+pub struct Diff {
+    pub only_a: Vec<OutageRow>,
+    pub only_b: Vec<OutageRow>,
+    /// Outages in both whose duration differs, e.g. because one of them was merged
+    pub changed: Vec<(OutageRow, OutageRow)>,
+    pub metrics: Vec<MetricDiff>,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.only_a.is_empty()
+            && self.only_b.is_empty()
+            && self.changed.is_empty()
+            && self.metrics.iter().all(|metric| metric.a == metric.b)
+    }
+}
+
+/// Opens a database read-only without upgrading it, so comparing a backup
+/// leaves it exactly as it was.
+pub fn open(path: &Path) -> Result<Connection> {
+    if !path.exists() {
+        return Err(WebgoneError::DatabaseMissing { path: path.to_path_buf() }.into());
+    }
+    let flags = (OpenFlags::default() - OpenFlags::SQLITE_OPEN_READ_WRITE - OpenFlags::SQLITE_OPEN_CREATE)
+        | OpenFlags::SQLITE_OPEN_READ_ONLY;
+    Connection::open_with_flags(path, flags)
+        .map_err(|e| WebgoneError::DatabaseOpen { path: path.to_path_buf(), reason: e.to_string() }.into())
+}
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({table})"))?;
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(columns.iter().any(|name| name == column))
+}
+
+/// Outages keyed by site and start time in milliseconds. Databases older
+/// than the `site` column only have local outages.
+fn outages(conn: &Connection, as_site: Option<&str>) -> Result<BTreeMap<(Option<String>, i64), OutageRow>> {
+    if !has_column(conn, "outages", "start_time")? {
+        return Err(WebgoneError::Database("no outages table, is this a webgone database?".to_string()).into());
+    }
+    let site = if has_column(conn, "outages", "site")? { "site" } else { "NULL" };
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, {site}, start_time, end_time, duration_seconds FROM outages ORDER BY start_time"
+    ))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, i64>(4)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    rows.into_iter()
+        .map(|(id, site, start_time, end_time, duration_seconds)| {
+            let parse = |time: &str| {
+                DateTime::parse_from_rfc3339(time)
+                    .map(|time| time.with_timezone(&Local))
+                    .with_context(|| format!("Invalid time {} of outage {}", time, id))
+            };
+            let row = OutageRow {
+                id,
+                site: site.or_else(|| as_site.map(str::to_string)),
+                start_time: parse(&start_time)?,
+                end_time: parse(&end_time)?,
+                duration_seconds,
+            };
+            Ok(((row.site.clone(), row.start_time.timestamp_millis()), row))
+        })
+        .collect()
+}
+
+fn scalar(conn: &Connection, sql: &str) -> Result<Option<i64>> {
+    Ok(conn.query_row(sql, [], |row| row.get(0))?)
+}
+
+/// Summary values, `None` where the database has no such table or column.
+fn metrics(conn: &Connection) -> Result<Vec<(Text, Option<i64>)>> {
+    let probes = has_column(conn, "probes", "success")?;
+    let weight = if has_column(conn, "probes", "weight")? { "weight" } else { "1" };
+    let tags = has_column(conn, "outage_tags", "tag")?;
+    Ok(vec![
+        (Text::Outages, scalar(conn, "SELECT COUNT(*) FROM outages")?),
+        (Text::DowntimeSeconds, scalar(conn, "SELECT COALESCE(SUM(duration_seconds), 0) FROM outages")?),
+        (
+            Text::Probes,
+            if probes { scalar(conn, &format!("SELECT COALESCE(SUM({weight}), 0) FROM probes"))? } else { None },
+        ),
+        (
+            Text::FailedProbes,
+            if probes {
+                scalar(conn, &format!("SELECT COALESCE(SUM(CASE WHEN success THEN 0 ELSE {weight} END), 0) FROM probes"))?
+            } else {
+                None
+            },
+        ),
+        (Text::Tags, if tags { scalar(conn, "SELECT COUNT(*) FROM outage_tags")? } else { None }),
+        (Text::SchemaVersion, scalar(conn, "PRAGMA user_version")?),
+    ])
+}
+
+/// Compares the outages and summary metrics of two databases. With
+/// `as_site`, local outages of either database count as outages of that
+/// site, to check what an agent forwarded to a central server.
+pub fn diff(a: &Connection, b: &Connection, as_site: Option<&str>) -> Result<Diff> {
+    let mut outages_b = outages(b, as_site)?;
+    let mut only_a = Vec::new();
+    let mut changed = Vec::new();
+    for (key, outage_a) in outages(a, as_site)? {
+        match outages_b.remove(&key) {
+            Some(outage_b) if outage_b.duration_seconds != outage_a.duration_seconds => {
+                changed.push((outage_a, outage_b))
+            }
+            Some(_) => {}
+            None => only_a.push(outage_a),
+        }
+    }
+
+    let metrics = metrics(a)?
+        .into_iter()
+        .zip(metrics(b)?)
+        .map(|((name, a), (_, b))| MetricDiff { name, a, b })
+        .collect();
+
+    Ok(Diff { only_a, only_b: outages_b.into_values().collect(), changed, metrics })
+}
@@ -0,0 +1,61 @@
+use chrono::{DateTime, Local};
+
+/// A change of the connection state, dated back to the first check of the
+/// run of checks that confirmed it: `since` is when that check started and
+/// `seen` when its result came in.
+pub enum Transition {
+    Lost { since: DateTime<Local>, seen: DateTime<Local> },
+    Restored { since: DateTime<Local> },
+}
+
+/// Declares the connection lost only after `fail_threshold` consecutive
+/// failed checks, and restored only after `recover_threshold` consecutive
+/// successful ones, so a single dropped probe doesn't count as an outage.
+pub struct Debounce {
+    fail_threshold: u32,
+    recover_threshold: u32,
+    connected: bool,
+    /// Start and end of the first check disagreeing with `connected`, and how many followed
+    run_start: Option<(DateTime<Local>, DateTime<Local>)>,
+    run_length: u32,
+}
+
+impl Debounce {
+    pub fn new(fail_threshold: u32, recover_threshold: u32) -> Self {
+        Debounce {
+            fail_threshold: fail_threshold.max(1),
+            recover_threshold: recover_threshold.max(1),
+            connected: true,
+            run_start: None,
+            run_length: 0,
+        }
+    }
+
+    /// Starts out with the connection lost, e.g. when an outage is resumed.
+    pub fn disconnected(mut self) -> Self {
+        self.connected = false;
+        self
+    }
+
+    /// Records the result of a check that ran from `started` to `finished`.
+    /// Returns the transition once enough consecutive checks confirm it.
+    pub fn record(&mut self, started: DateTime<Local>, finished: DateTime<Local>, success: bool) -> Option<Transition> {
+        if success == self.connected {
+            self.run_start = None;
+            self.run_length = 0;
+            return None;
+        }
+
+        let (since, seen) = *self.run_start.get_or_insert((started, finished));
+        self.run_length += 1;
+        let threshold = if self.connected { self.fail_threshold } else { self.recover_threshold };
+        if self.run_length < threshold {
+            return None;
+        }
+
+        self.connected = success;
+        self.run_start = None;
+        self.run_length = 0;
+        Some(if success { Transition::Restored { since } } else { Transition::Lost { since, seen } })
+    }
+}
@@ -0,0 +1,221 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use rusqlite::{params, Connection};
+use std::collections::VecDeque;
+
+use webgone::model;
+
+pub fn init_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS degraded_periods (
+            id INTEGER PRIMARY KEY,
+            start_time TEXT NOT NULL,
+            end_time TEXT NOT NULL,
+            duration_seconds INTEGER NOT NULL,
+            checks INTEGER NOT NULL,
+            failures INTEGER NOT NULL,
+            loss_percent REAL NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// A time in which part of the checks failed without the connection going
+/// down, from the first to the last lost probe.
+pub struct DegradedPeriod {
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    pub checks: u32,
+    pub failures: u32,
+}
+
+impl DegradedPeriod {
+    pub fn duration_seconds(&self) -> i64 {
+        (self.end - self.start).num_seconds()
+    }
+
+    pub fn loss_percent(&self) -> f64 {
+        if self.checks == 0 {
+            return 0.0;
+        }
+        self.failures as f64 / self.checks as f64 * 100.0
+    }
+}
+
+/// The connection started or stopped being degraded.
+pub enum Change {
+    Began { since: DateTime<Local>, loss_percent: f64 },
+    Ended(DegradedPeriod),
+}
+
+struct Open {
+    start: DateTime<Local>,
+    last_failure: DateTime<Local>,
+    /// Checks from the start up to and including the last failed one
+    checks: u32,
+    failures: u32,
+    /// Successful checks since the last failed one
+    trailing: u32,
+}
+
+impl Open {
+    fn close(self) -> DegradedPeriod {
+        DegradedPeriod { start: self.start, end: self.last_failure, checks: self.checks, failures: self.failures }
+    }
+}
+
+/// Tracks the share of failed checks over the last `window` checks. The
+/// connection counts as degraded once at least `threshold` percent of them
+/// failed, and as fine again when fewer than half as many did, so a loss
+/// rate around the threshold doesn't start a new period with every check.
+///
+/// Failed checks that become part of an outage don't count: they are held
+/// back until a successful check shows they didn't, and dropped when the
+/// outage is confirmed.
+pub struct Tracker {
+    window: usize,
+    threshold: f64,
+    recent: VecDeque<(DateTime<Local>, bool)>,
+    /// Failed checks not yet known to be lost probes rather than an outage
+    pending: Vec<DateTime<Local>>,
+    current: Option<Open>,
+}
+
+impl Tracker {
+    pub fn new(window: usize, threshold: f64) -> Self {
+        let window = window.max(1);
+        Tracker { window, threshold, recent: VecDeque::with_capacity(window), pending: Vec::new(), current: None }
+    }
+
+    /// Records a check made at `time` while the connection was up.
+    pub fn record(&mut self, time: DateTime<Local>, success: bool) -> Vec<Change> {
+        if !success {
+            self.pending.push(time);
+            return Vec::new();
+        }
+        let mut changes: Vec<Change> = std::mem::take(&mut self.pending)
+            .into_iter()
+            .filter_map(|failed| self.push(failed, false))
+            .collect();
+        changes.extend(self.push(time, true));
+        changes
+    }
+
+    fn push(&mut self, time: DateTime<Local>, success: bool) -> Option<Change> {
+        self.recent.push_back((time, success));
+        if self.recent.len() > self.window {
+            self.recent.pop_front();
+        }
+        if let Some(open) = &mut self.current {
+            if success {
+                open.trailing += 1;
+            } else {
+                open.checks += open.trailing + 1;
+                open.failures += 1;
+                open.trailing = 0;
+                open.last_failure = time;
+            }
+        }
+        if self.recent.len() < self.window {
+            return None;
+        }
+
+        let failures = self.recent.iter().filter(|(_, success)| !success).count();
+        let loss_percent = failures as f64 / self.recent.len() as f64 * 100.0;
+        match &self.current {
+            None if loss_percent >= self.threshold && failures > 0 => {
+                let first = self.recent.iter().position(|(_, success)| !success)?;
+                let last = self.recent.iter().rposition(|(_, success)| !success)?;
+                let since = self.recent[first].0;
+                self.current = Some(Open {
+                    start: since,
+                    last_failure: self.recent[last].0,
+                    checks: (last - first + 1) as u32,
+                    failures: failures as u32,
+                    trailing: (self.recent.len() - last - 1) as u32,
+                });
+                Some(Change::Began { since, loss_percent })
+            }
+            Some(_) if loss_percent < self.threshold / 2.0 => self.current.take().map(|open| Change::Ended(open.close())),
+            _ => None,
+        }
+    }
+
+    /// An outage was confirmed: the failed checks leading up to it belong to
+    /// it, and a degraded period going on ends with its last lost probe.
+    pub fn outage_started(&mut self) -> Option<DegradedPeriod> {
+        self.pending.clear();
+        self.recent.clear();
+        self.current.take().map(Open::close)
+    }
+
+    /// Ends a degraded period going on, e.g. when the watcher stops.
+    pub fn finish(&mut self) -> Option<DegradedPeriod> {
+        self.pending.clear();
+        self.current.take().map(Open::close)
+    }
+}
+
+pub fn save(conn: &Connection, period: &DegradedPeriod) -> Result<()> {
+    conn.execute(
+        "INSERT INTO degraded_periods (start_time, end_time, duration_seconds, checks, failures, loss_percent)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            period.start.to_rfc3339(),
+            period.end.to_rfc3339(),
+            period.duration_seconds(),
+            period.checks,
+            period.failures,
+            period.loss_percent(),
+        ],
+    )?;
+    Ok(())
+}
+
+/// Degraded periods that ended at or after `since`, newest first.
+pub fn list(conn: &Connection, since: DateTime<Local>) -> Result<Vec<DegradedPeriod>> {
+    let mut stmt = conn.prepare(
+        "SELECT start_time, end_time, checks, failures FROM degraded_periods
+        WHERE julianday(end_time) >= julianday(?1)
+        ORDER BY julianday(start_time) DESC",
+    )?;
+    let rows = stmt
+        .query_map([since.to_rfc3339()], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get(2)?, row.get(3)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|(start, end, checks, failures)| {
+            Some(DegradedPeriod {
+                start: model::parse_timestamp(&start)?,
+                end: model::parse_timestamp(&end)?,
+                checks,
+                failures,
+            })
+        })
+        .collect())
+}
+
+/// Totals over a number of degraded periods.
+pub struct Summary {
+    pub periods: usize,
+    pub total_seconds: i64,
+    pub longest_seconds: i64,
+    /// Share of lost probes over all periods together
+    pub average_loss_percent: f64,
+    pub worst_loss_percent: f64,
+}
+
+pub fn summarize(periods: &[DegradedPeriod]) -> Summary {
+    let checks: u32 = periods.iter().map(|period| period.checks).sum();
+    let failures: u32 = periods.iter().map(|period| period.failures).sum();
+    Summary {
+        periods: periods.len(),
+        total_seconds: periods.iter().map(DegradedPeriod::duration_seconds).sum(),
+        longest_seconds: periods.iter().map(DegradedPeriod::duration_seconds).max().unwrap_or(0),
+        average_loss_percent: if checks == 0 { 0.0 } else { failures as f64 / checks as f64 * 100.0 },
+        worst_loss_percent: periods.iter().map(DegradedPeriod::loss_percent).fold(0.0, f64::max),
+    }
+}
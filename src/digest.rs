@@ -0,0 +1,156 @@
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveTime, TimeZone, Weekday};
+use clap::ValueEnum;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::i18n::{Lang, Text};
+
+/// Average length of a month, to turn a monthly rate into the cost of downtime.
+const SECONDS_PER_MONTH: f64 = 365.25 / 12.0 * 24.0 * 60.0 * 60.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Period {
+    Daily,
+    Weekly,
+}
+
+impl Period {
+    fn length(self) -> Duration {
+        match self {
+            Period::Daily => Duration::days(1),
+            Period::Weekly => Duration::weeks(1),
+        }
+    }
+
+}
+
+/// When digests are due: daily at `at`, and weekly on `weekday` at `at`.
+pub struct Schedule {
+    pub period: Period,
+    pub at: NaiveTime,
+    pub weekday: Weekday,
+    /// Mailed through `[email]` instead of sent to the notification
+    /// channels, which keeps track of its own last digest
+    pub email: bool,
+}
+
+/// Monthly rate and currency to include the cost of downtime in digests.
+pub struct Pricing {
+    pub monthly_rate: f64,
+    pub currency: String,
+}
+
+impl Schedule {
+    /// Metadata key holding when the digest was last sent.
+    fn metadata_key(&self) -> &'static str {
+        match (self.period, self.email) {
+            (Period::Daily, false) => "digest_daily_sent_at",
+            (Period::Weekly, false) => "digest_weekly_sent_at",
+            (Period::Daily, true) => "digest_daily_email_sent_at",
+            (Period::Weekly, true) => "digest_weekly_email_sent_at",
+        }
+    }
+
+    /// The most recent scheduled time at or before `now`.
+    fn latest(&self, now: DateTime<Local>) -> DateTime<Local> {
+        let mut day = now.date_naive();
+        loop {
+            let matches_day = self.period == Period::Daily || day.weekday() == self.weekday;
+            // A day on which a DST change skips the time has no digest
+            if let Some(time) = Local.from_local_datetime(&day.and_time(self.at)).earliest() {
+                if matches_day && time <= now {
+                    return time;
+                }
+            }
+            day = day.pred_opt().expect("date in range");
+        }
+    }
+
+    /// Returns the end of the period to report on if a digest is due.
+    ///
+    /// When the watcher first runs with a schedule, nothing is sent until the
+    /// next scheduled time. A digest missed while the watcher was stopped is
+    /// sent once it starts again.
+    pub fn due(&self, conn: &Connection, now: DateTime<Local>) -> Result<Option<DateTime<Local>>> {
+        let latest = self.latest(now);
+        let sent_at: Option<String> = conn
+            .query_row("SELECT value FROM metadata WHERE key = ?", [self.metadata_key()], |row| row.get(0))
+            .optional()?;
+        match sent_at.and_then(|value| DateTime::parse_from_rfc3339(&value).ok()) {
+            Some(sent_at) if sent_at >= latest => Ok(None),
+            Some(_) => Ok(Some(latest)),
+            None => {
+                self.mark_sent(conn, now)?;
+                Ok(None)
+            }
+        }
+    }
+
+    pub fn mark_sent(&self, conn: &Connection, at: DateTime<Local>) -> Result<()> {
+        conn.execute(
+            "INSERT INTO metadata (key, value) VALUES (?1, ?2)
+            ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+            params![self.metadata_key(), at.to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// Subject and text of the digest for the period ending at `end`.
+    pub fn render(
+        &self,
+        conn: &Connection,
+        end: DateTime<Local>,
+        pricing: Option<&Pricing>,
+        lang: Lang,
+    ) -> Result<(String, String)> {
+        let start = end - self.period.length();
+        let parse = |value: String| DateTime::parse_from_rfc3339(&value).ok().map(|time| time.with_timezone(&Local));
+
+        let mut stmt = conn.prepare(
+            "SELECT start_time, end_time FROM outages
+            WHERE site IS NULL AND julianday(end_time) >= julianday(?1) AND julianday(start_time) < julianday(?2)"
+        )?;
+        // Only count the part of outages that falls into the period
+        let durations = stmt
+            .query_map(params![start.to_rfc3339(), end.to_rfc3339()], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?
+            .into_iter()
+            .filter_map(|(from, to)| Some((parse(from)?, parse(to)?)))
+            .map(|(from, to)| (to.min(end) - from.max(start)).num_seconds().max(0))
+            .collect::<Vec<_>>();
+        let downtime: i64 = durations.iter().sum();
+        let period_seconds = self.period.length().num_seconds();
+
+        let title = lang.text(match self.period {
+            Period::Daily => Text::DailyDigest,
+            Period::Weekly => Text::WeeklyDigest,
+        });
+        let mut lines = vec![
+            format!("{} ({} - {})", title, start.format("%Y-%m-%d %H:%M"), end.format("%Y-%m-%d %H:%M")),
+            format!("{}: {}", lang.text(Text::TotalOutages), durations.len()),
+            format!("{}: {}", lang.text(Text::TotalDowntime), hms(downtime)),
+            format!("{}: {}", lang.text(Text::LongestOutage), hms(durations.iter().copied().max().unwrap_or(0))),
+            format!(
+                "{}: {:.3}%",
+                lang.text(Text::Uptime),
+                100.0 * (1.0 - downtime.min(period_seconds) as f64 / period_seconds as f64)
+            ),
+        ];
+        if let Some(pricing) = pricing {
+            lines.push(format!(
+                "{}: {}{:.3}",
+                lang.text(Text::CostImpact),
+                pricing.currency,
+                downtime as f64 / SECONDS_PER_MONTH * pricing.monthly_rate
+            ));
+        }
+
+        Ok((format!("webgone: {}", title), lines.join("\n")))
+    }
+}
+
+fn hms(seconds: i64) -> String {
+    format!("{:02}:{:02}:{:02}", seconds / 3600, (seconds % 3600) / 60, seconds % 60)
+}
@@ -0,0 +1,117 @@
+use chrono::{DateTime, Local};
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+
+use crate::recovery;
+use webgone::model::Severity;
+
+/// How long one request may take; sending blocks the watcher.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Wait after the first failed request; it doubles with every failure in a
+/// row up to `MAX_RETRY_DELAY`.
+const RETRY_DELAY: Duration = Duration::from_secs(30);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(10 * 60);
+
+/// Most embeds Discord accepts in one message.
+const MAX_EMBEDS: usize = 10;
+
+const RED: u32 = 0xcf222e;
+const GREEN: u32 = 0x1a7f37;
+
+/// Discord timestamp markup, shown in the reader's time zone.
+fn timestamp(time: DateTime<Local>) -> String {
+    format!("<t:{}:f>", time.timestamp())
+}
+
+/// Sends outage notifications as embeds to a Discord webhook. A lost
+/// connection can't be reported while it's lost, so embeds are queued and
+/// sent once the watcher is connected again, oldest first.
+pub struct Discord {
+    url: String,
+    agent: ureq::Agent,
+    host: Option<String>,
+    queue: Vec<Value>,
+    retry_delay: Duration,
+    /// When sending may be tried again after a failure
+    retry_at: Option<Instant>,
+}
+
+impl Discord {
+    pub fn new(url: String, host: Option<String>) -> Self {
+        let agent = ureq::Agent::config_builder().timeout_global(Some(TIMEOUT)).build().into();
+        Discord { url, agent, host, queue: Vec::new(), retry_delay: RETRY_DELAY, retry_at: None }
+    }
+
+    fn footer(&self) -> Value {
+        match &self.host {
+            Some(host) => json!({ "text": format!("webgone on {}", host) }),
+            None => json!({ "text": "webgone" }),
+        }
+    }
+
+    /// Queues the embed of a connection lost at `since`.
+    pub fn lost(&mut self, since: DateTime<Local>, severity: Severity, target: &str) {
+        let embed = json!({
+            "title": "Internet connection lost",
+            "description": format!("Lost at {}", timestamp(since)),
+            "color": RED,
+            "fields": [
+                { "name": "Severity", "value": severity.name(), "inline": true },
+                { "name": "Target", "value": target, "inline": true },
+            ],
+            "timestamp": since.to_rfc3339(),
+            "footer": self.footer(),
+        });
+        self.queue.push(embed);
+    }
+
+    /// Queues the embed of a connection restored at `end` after an outage
+    /// since `start`.
+    pub fn restored(&mut self, start: DateTime<Local>, end: DateTime<Local>, severity: Severity, target: &str) {
+        let duration = recovery::describe((end - start).to_std().unwrap_or_default());
+        let embed = json!({
+            "title": "Internet connection restored",
+            "description": format!("Back after {}", duration),
+            "color": GREEN,
+            "fields": [
+                { "name": "Lost", "value": timestamp(start), "inline": true },
+                { "name": "Restored", "value": timestamp(end), "inline": true },
+                { "name": "Duration", "value": duration, "inline": true },
+                { "name": "Severity", "value": severity.name(), "inline": true },
+                { "name": "Target", "value": target, "inline": true },
+            ],
+            "timestamp": end.to_rfc3339(),
+            "footer": self.footer(),
+        });
+        self.queue.push(embed);
+    }
+
+    /// Sends the queued embeds, unless a failed attempt is too recent.
+    /// Only call this while connected. Embeds that couldn't be sent stay
+    /// queued for the next call.
+    pub fn flush(&mut self) -> Result<(), String> {
+        if self.queue.is_empty() || self.retry_at.is_some_and(|at| Instant::now() < at) {
+            return Ok(());
+        }
+        while !self.queue.is_empty() {
+            let count = self.queue.len().min(MAX_EMBEDS);
+            let message = json!({ "username": "webgone", "embeds": &self.queue[..count] });
+            let result = self
+                .agent
+                .post(&self.url)
+                .header("Content-Type", "application/json")
+                .send(message.to_string());
+            if let Err(e) = result {
+                self.retry_at = Some(Instant::now() + self.retry_delay);
+                let retry_delay = self.retry_delay;
+                self.retry_delay = (self.retry_delay * 2).min(MAX_RETRY_DELAY);
+                return Err(format!("{} (retrying in {})", e, recovery::describe(retry_delay)));
+            }
+            self.queue.drain(..count);
+        }
+        self.retry_delay = RETRY_DELAY;
+        self.retry_at = None;
+        Ok(())
+    }
+}
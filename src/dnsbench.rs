@@ -0,0 +1,349 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use rusqlite::{params, Connection};
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::locations;
+
+/// Public resolvers benchmarked alongside the system's own by default.
+const PUBLIC_RESOLVERS: &[(&str, [u8; 4])] = &[
+    ("cloudflare", [1, 1, 1, 1]),
+    ("google", [8, 8, 8, 8]),
+    ("quad9", [9, 9, 9, 9]),
+];
+
+/// Popular domains the benchmark rotates through, so resolvers answer from
+/// their cache about as often as during everyday browsing.
+const DOMAINS: &[&str] = &[
+    "google.com",
+    "youtube.com",
+    "wikipedia.org",
+    "amazon.com",
+    "facebook.com",
+    "instagram.com",
+    "netflix.com",
+    "microsoft.com",
+    "apple.com",
+    "github.com",
+    "reddit.com",
+    "whatsapp.net",
+];
+
+/// How long to wait for an answer before a query counts as failed.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A DNS server to benchmark, given as `NAME=IP`, `IP` or `IP:PORT`.
+#[derive(Clone)]
+pub struct Resolver {
+    pub name: String,
+    pub addr: SocketAddr,
+}
+
+impl FromStr for Resolver {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, address) = match s.split_once('=') {
+            Some((name, address)) => (Some(name.trim()), address.trim()),
+            None => (None, s.trim()),
+        };
+        let addr = address
+            .parse::<SocketAddr>()
+            .or_else(|_| address.parse::<IpAddr>().map(|ip| SocketAddr::new(ip, 53)))
+            .map_err(|_| format!("expected NAME=IP, IP or IP:PORT, got `{}`", s))?;
+        Ok(Resolver { name: name.unwrap_or(address).to_string(), addr })
+    }
+}
+
+/// Latency and failure rate of one resolver over a benchmark.
+pub struct ResolverSummary {
+    pub name: String,
+    pub address: String,
+    pub queries: i64,
+    pub failures: i64,
+    pub median_ms: Option<f64>,
+    pub average_ms: Option<f64>,
+    pub p95_ms: Option<f64>,
+}
+
+impl ResolverSummary {
+    pub fn failure_percent(&self) -> f64 {
+        if self.queries > 0 { self.failures as f64 / self.queries as f64 * 100.0 } else { 0.0 }
+    }
+
+    /// Whether the resolver is the one the machine uses, rather than one to compare against.
+    pub fn is_system(&self) -> bool {
+        self.name.starts_with("system") || self.name.starts_with("local")
+    }
+}
+
+pub fn init_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS dns_benchmarks (
+            id INTEGER PRIMARY KEY,
+            run_at TEXT NOT NULL,
+            resolver TEXT NOT NULL,
+            address TEXT NOT NULL,
+            queries INTEGER NOT NULL,
+            failures INTEGER NOT NULL,
+            median_ms REAL,
+            average_ms REAL,
+            p95_ms REAL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// The resolvers the machine is configured with, its router and the public
+/// resolvers. Loopback resolvers like systemd-resolved are named `local`,
+/// the others, usually handed out by the ISP, `system`.
+pub fn default_resolvers() -> Vec<Resolver> {
+    let mut resolvers: Vec<Resolver> = Vec::new();
+    let mut add = |name: &str, ip: IpAddr| {
+        let addr = SocketAddr::new(ip, 53);
+        if resolvers.iter().any(|resolver| resolver.addr == addr) {
+            return;
+        }
+        let taken = resolvers.iter().filter(|resolver| resolver.name.starts_with(name)).count();
+        let name = if taken == 0 { name.to_string() } else { format!("{} {}", name, taken + 1) };
+        resolvers.push(Resolver { name, addr });
+    };
+
+    for ip in system_resolvers() {
+        add(if ip.is_loopback() { "local" } else { "system" }, ip);
+    }
+    if let Some(ip) = locations::gateway_ip() {
+        add("router", ip);
+    }
+    for (name, ip) in PUBLIC_RESOLVERS {
+        add(name, IpAddr::from(*ip));
+    }
+    resolvers
+}
+
+#[cfg(unix)]
+pub fn system_resolvers() -> Vec<IpAddr> {
+    // With systemd-resolved, the second file lists the upstream servers behind the local stub
+    ["/etc/resolv.conf", "/run/systemd/resolve/resolv.conf"]
+        .iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .flat_map(|contents| {
+            contents
+                .lines()
+                .filter_map(|line| line.trim().strip_prefix("nameserver"))
+                // Link-local IPv6 servers carry a zone like `%eth0`, which UDP sockets can't use
+                .filter_map(|address| address.trim().parse::<IpAddr>().ok())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+#[cfg(windows)]
+pub fn system_resolvers() -> Vec<IpAddr> {
+    let output = std::process::Command::new("powershell")
+        .args([
+            "-NoProfile",
+            "-Command",
+            "Get-DnsClientServerAddress -AddressFamily IPv4 | Select-Object -ExpandProperty ServerAddresses",
+        ])
+        .output();
+    match output {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.trim().parse().ok())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn system_resolvers() -> Vec<IpAddr> {
+    Vec::new()
+}
+
+/// Builds a recursive query for the A records of `domain`.
+fn encode_query(id: u16, domain: &str) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(domain.len() + 18);
+    packet.extend_from_slice(&id.to_be_bytes());
+    // Recursion desired, one question, no answer, authority or additional records
+    packet.extend_from_slice(&[0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+    for label in domain.split('.') {
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label.as_bytes());
+    }
+    // End of name, type A, class IN
+    packet.extend_from_slice(&[0x00, 0x00, 0x01, 0x00, 0x01]);
+    packet
+}
+
+/// Resolves `domain` at `addr` and returns how long the answer took, failing
+/// if there is no answer within `timeout`.
+pub fn query(addr: SocketAddr, domain: &str, id: u16, timeout: Duration) -> Result<Duration, String> {
+    let bind: SocketAddr = if addr.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { ([0u16; 8], 0).into() };
+    let socket = UdpSocket::bind(bind).map_err(|e| e.to_string())?;
+
+    let start = Instant::now();
+    socket.send_to(&encode_query(id, domain), addr).map_err(|e| e.to_string())?;
+
+    let mut buffer = [0u8; 512];
+    loop {
+        let remaining = timeout.checked_sub(start.elapsed()).filter(|left| !left.is_zero());
+        let Some(remaining) = remaining else { return Err("timed out".to_string()) };
+        socket.set_read_timeout(Some(remaining)).map_err(|e| e.to_string())?;
+
+        let (len, from) = match socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => {
+                return Err("timed out".to_string());
+            }
+            Err(e) => return Err(e.to_string()),
+        };
+        // Ignore stray datagrams and answers to other queries
+        if from != addr || len < 12 || buffer[..2] != id.to_be_bytes() || buffer[2] & 0x80 == 0 {
+            continue;
+        }
+        let elapsed = start.elapsed();
+        return match buffer[3] & 0x0f {
+            0 => Ok(elapsed),
+            2 => Err("server failure".to_string()),
+            3 => Err("no such domain".to_string()),
+            5 => Err("refused".to_string()),
+            code => Err(format!("response code {}", code)),
+        };
+    }
+}
+
+/// Queries every resolver once per `interval` for `duration`, rotating over
+/// popular domains, and summarizes latency and failures per resolver.
+pub fn run(resolvers: &[Resolver], duration: Duration, interval: Duration) -> Vec<ResolverSummary> {
+    let mut latencies: Vec<Vec<f64>> = vec![Vec::new(); resolvers.len()];
+    let mut failures = vec![0i64; resolvers.len()];
+    let start = Instant::now();
+    let mut round = 0usize;
+
+    while round == 0 || start.elapsed() < duration {
+        let round_start = Instant::now();
+        let domain = DOMAINS[round % DOMAINS.len()];
+        for (index, resolver) in resolvers.iter().enumerate() {
+            let id = (round * resolvers.len() + index) as u16;
+            match query(resolver.addr, domain, id, QUERY_TIMEOUT) {
+                Ok(elapsed) => latencies[index].push(elapsed.as_secs_f64() * 1000.0),
+                Err(_) => failures[index] += 1,
+            }
+        }
+        round += 1;
+
+        if let Some(wait) = interval.checked_sub(round_start.elapsed()) {
+            thread::sleep(wait.min(duration.saturating_sub(start.elapsed())));
+        }
+    }
+
+    resolvers
+        .iter()
+        .zip(latencies)
+        .zip(failures)
+        .map(|((resolver, mut latencies), failures)| {
+            latencies.sort_by(f64::total_cmp);
+            let percentile = |p: f64| {
+                (!latencies.is_empty()).then(|| latencies[((latencies.len() - 1) as f64 * p).round() as usize])
+            };
+            ResolverSummary {
+                name: resolver.name.clone(),
+                address: resolver.addr.to_string(),
+                queries: round as i64,
+                failures,
+                median_ms: percentile(0.5),
+                average_ms: (!latencies.is_empty()).then(|| latencies.iter().sum::<f64>() / latencies.len() as f64),
+                p95_ms: percentile(0.95),
+            }
+        })
+        .collect()
+}
+
+pub fn store(conn: &Connection, run_at: DateTime<Local>, summaries: &[ResolverSummary]) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    for summary in summaries {
+        tx.execute(
+            "INSERT INTO dns_benchmarks (run_at, resolver, address, queries, failures, median_ms, average_ms, p95_ms)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                run_at.to_rfc3339(),
+                summary.name,
+                summary.address,
+                summary.queries,
+                summary.failures,
+                summary.median_ms,
+                summary.average_ms,
+                summary.p95_ms,
+            ],
+        )?;
+    }
+    tx.commit().context("Failed to store the DNS benchmark")?;
+    Ok(())
+}
+
+/// The results of the last `runs` benchmarks, newest first.
+pub fn history(conn: &Connection, runs: u32) -> Result<Vec<(DateTime<Local>, ResolverSummary)>> {
+    let mut stmt = conn.prepare(
+        "SELECT run_at, resolver, address, queries, failures, median_ms, average_ms, p95_ms
+         FROM dns_benchmarks
+         WHERE run_at IN (SELECT DISTINCT run_at FROM dns_benchmarks ORDER BY run_at DESC LIMIT ?)
+         ORDER BY run_at DESC, id",
+    )?;
+    let rows = stmt
+        .query_map([runs], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                ResolverSummary {
+                    name: row.get(1)?,
+                    address: row.get(2)?,
+                    queries: row.get(3)?,
+                    failures: row.get(4)?,
+                    median_ms: row.get(5)?,
+                    average_ms: row.get(6)?,
+                    p95_ms: row.get(7)?,
+                },
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    rows.into_iter()
+        .map(|(run_at, summary)| Ok((DateTime::parse_from_rfc3339(&run_at)?.with_timezone(&Local), summary)))
+        .collect()
+}
+
+/// Compares the machine's own resolver with the fastest reliable one, to
+/// tell whether slow browsing is down to DNS.
+pub fn verdict(summaries: &[ResolverSummary]) -> Option<String> {
+    let usable = |summary: &&ResolverSummary| summary.median_ms.is_some() && summary.failure_percent() < 5.0;
+    let system = summaries.iter().filter(|summary| summary.is_system()).find(usable);
+    let fastest = summaries
+        .iter()
+        .filter(usable)
+        .min_by(|a, b| a.median_ms.unwrap_or(f64::MAX).total_cmp(&b.median_ms.unwrap_or(f64::MAX)))?;
+
+    let Some(system) = system else {
+        return summaries.iter().any(ResolverSummary::is_system).then(|| {
+            format!(
+                "The system resolver is unreliable, while {} answered reliably; consider switching to it",
+                fastest.name
+            )
+        });
+    };
+    let (system_ms, fastest_ms) = (system.median_ms?, fastest.median_ms?);
+    if std::ptr::eq(system, fastest) || system_ms - fastest_ms < 20.0 {
+        Some(format!("The {} resolver answers in {:.1} ms, DNS is unlikely to slow you down", system.name, system_ms))
+    } else {
+        Some(format!(
+            "The {} resolver takes {:.1} ms longer than {} ({:.1} ms); switching resolvers may make browsing feel faster",
+            system.name,
+            system_ms - fastest_ms,
+            fastest.name,
+            fastest_ms
+        ))
+    }
+}
@@ -0,0 +1,166 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+
+use crate::error::WebgoneError;
+use crate::recovery;
+use webgone::model::Severity;
+
+/// How long talking to the SMTP server may take; sending blocks the watcher.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Wait after the first failed attempt; it doubles with every failure in a
+/// row up to `MAX_RETRY_DELAY`.
+const RETRY_DELAY: Duration = Duration::from_secs(60);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30 * 60);
+
+/// How the connection to the SMTP server is secured.
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Security {
+    /// Upgrade a plain connection, usually on port 587
+    #[default]
+    Starttls,
+    /// TLS from the start, usually on port 465
+    Tls,
+    /// Unencrypted, e.g. for a relay on the local network
+    None,
+}
+
+/// The `[email]` table of the configuration file.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// SMTP server
+    pub host: Option<String>,
+    /// [default: 587 with STARTTLS, 465 with TLS, 25 without]
+    pub port: Option<u16>,
+    pub security: Security,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Sender, e.g. `webgone <webgone@example.org>`
+    pub from: Option<String>,
+    pub to: Vec<String>,
+    /// Also mail a digest of the past week
+    pub weekly_digest: bool,
+}
+
+impl Settings {
+    /// A mailer sending through the configured server, checked for the
+    /// settings it needs. `host` names this machine in the messages.
+    pub fn mailer(&self, host: Option<String>) -> Result<Mailer> {
+        let invalid = |message: String| WebgoneError::InvalidInput(message);
+        let server = self
+            .host
+            .as_deref()
+            .ok_or_else(|| invalid("--notify email needs a [email] host in --config".to_string()))?;
+        let mailbox = |address: &str| {
+            address
+                .parse::<Mailbox>()
+                .map_err(|e| invalid(format!("invalid [email] address {}: {}", address, e)))
+        };
+        let from = mailbox(self.from.as_deref().ok_or_else(|| invalid("[email] needs a from address".to_string()))?)?;
+        let to = self.to.iter().map(|address| mailbox(address)).collect::<Result<Vec<_>, _>>()?;
+        if to.is_empty() {
+            return Err(invalid("[email] needs at least one address in to".to_string()).into());
+        }
+
+        let builder = match self.security {
+            Security::Starttls => SmtpTransport::starttls_relay(server),
+            Security::Tls => SmtpTransport::relay(server),
+            Security::None => Ok(SmtpTransport::builder_dangerous(server)),
+        }
+        .map_err(|e| invalid(format!("invalid [email] host {}: {}", server, e)))?;
+        let mut builder = builder.timeout(Some(TIMEOUT));
+        if let Some(port) = self.port {
+            builder = builder.port(port);
+        }
+        match (&self.username, &self.password) {
+            (Some(username), Some(password)) => builder = builder.credentials(Credentials::new(username.clone(), password.clone())),
+            (None, None) => {}
+            _ => return Err(invalid("[email] needs both a username and a password, or neither".to_string()).into()),
+        }
+
+        Ok(Mailer {
+            transport: builder.build(),
+            from,
+            to,
+            host,
+            queue: Vec::new(),
+            retry_delay: RETRY_DELAY,
+            retry_at: None,
+        })
+    }
+}
+
+/// Mails outage summaries and digests. Like Discord embeds, messages are
+/// queued and sent while the watcher is connected, oldest first.
+pub struct Mailer {
+    transport: SmtpTransport,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+    host: Option<String>,
+    /// Subjects and texts waiting to be sent
+    queue: Vec<(String, String)>,
+    retry_delay: Duration,
+    /// When sending may be tried again after a failure
+    retry_at: Option<Instant>,
+}
+
+impl Mailer {
+    pub fn queue(&mut self, subject: &str, text: &str) {
+        self.queue.push((subject.to_string(), text.to_string()));
+    }
+
+    /// Queues the summary of the outage recorded as `outage_id`, which
+    /// lasted from `start` to `end`.
+    pub fn restored(&mut self, outage_id: i64, start: DateTime<Local>, end: DateTime<Local>, severity: Severity, target: &str) {
+        let duration = recovery::describe((end - start).to_std().unwrap_or_default());
+        let mut lines = vec![
+            format!("The internet connection was down for {}.", duration),
+            String::new(),
+            format!("Lost:      {}", start.format("%Y-%m-%d %H:%M:%S")),
+            format!("Restored:  {}", end.format("%Y-%m-%d %H:%M:%S")),
+            format!("Duration:  {}", duration),
+            format!("Severity:  {}", severity),
+            format!("Target:    {}", target),
+            format!("Outage id: {}", outage_id),
+        ];
+        if let Some(host) = &self.host {
+            lines.push(format!("Host:      {}", host));
+        }
+        self.queue(&format!("webgone: connection was down for {}", duration), &lines.join("\n"));
+    }
+
+    /// Sends the queued messages, unless a failed attempt is too recent.
+    /// Only call this while connected. Messages that couldn't be sent stay
+    /// queued for the next call.
+    pub fn flush(&mut self) -> Result<(), String> {
+        if self.queue.is_empty() || self.retry_at.is_some_and(|at| Instant::now() < at) {
+            return Ok(());
+        }
+        while let Some((subject, text)) = self.queue.first() {
+            let mut message = Message::builder().from(self.from.clone()).subject(subject).header(ContentType::TEXT_PLAIN);
+            for to in &self.to {
+                message = message.to(to.clone());
+            }
+            // Addresses were checked up front, so building only fails on a bug
+            let message = message.body(text.clone()).map_err(|e| e.to_string())?;
+            if let Err(e) = self.transport.send(&message) {
+                self.retry_at = Some(Instant::now() + self.retry_delay);
+                let retry_delay = self.retry_delay;
+                self.retry_delay = (self.retry_delay * 2).min(MAX_RETRY_DELAY);
+                return Err(format!("{} (retrying in {})", e, recovery::describe(retry_delay)));
+            }
+            self.queue.remove(0);
+        }
+        self.retry_delay = RETRY_DELAY;
+        self.retry_at = None;
+        Ok(())
+    }
+}
@@ -0,0 +1,194 @@
+use anyhow::Result;
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::coverage::{self, GapMode};
+use crate::error::WebgoneError;
+
+/// A period of history starting on a marked date, e.g. after switching ISPs.
+/// It lasts until the next epoch starts.
+pub struct Epoch {
+    pub id: i64,
+    pub start: NaiveDate,
+    pub label: String,
+}
+
+/// Availability, outage frequency and latency within one epoch.
+pub struct EpochSummary {
+    /// `None` for the history recorded before the first epoch
+    pub epoch: Option<Epoch>,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    pub outages: i64,
+    pub downtime_seconds: i64,
+    /// Time without monitoring within the epoch
+    pub gap_seconds: i64,
+    pub availability_percent: f64,
+    /// Average latency of successful probes, including rolled up ones
+    pub average_latency_ms: Option<f64>,
+}
+
+impl EpochSummary {
+    pub fn days(&self) -> f64 {
+        (self.end - self.start).num_seconds().max(0) as f64 / 86400.0
+    }
+
+    /// Outages per 30 days, so epochs of different length can be compared.
+    pub fn outages_per_month(&self) -> f64 {
+        let days = self.days();
+        if days > 0.0 { self.outages as f64 * 30.0 / days } else { 0.0 }
+    }
+}
+
+pub fn init_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS epochs (
+            id INTEGER PRIMARY KEY,
+            start_date TEXT NOT NULL UNIQUE,
+            label TEXT NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+pub fn add(conn: &Connection, start: NaiveDate, label: &str) -> Result<i64> {
+    if start > Local::now().date_naive() {
+        return Err(WebgoneError::InvalidInput(format!("epoch start {} is in the future", start)).into());
+    }
+    let exists = conn
+        .query_row("SELECT 1 FROM epochs WHERE start_date = ?", [start.to_string()], |_| Ok(()))
+        .optional()?
+        .is_some();
+    if exists {
+        return Err(WebgoneError::InvalidInput(format!("an epoch already starts on {}", start)).into());
+    }
+
+    conn.execute("INSERT INTO epochs (start_date, label) VALUES (?1, ?2)", params![start.to_string(), label])?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn remove(conn: &Connection, id: i64) -> Result<()> {
+    if conn.execute("DELETE FROM epochs WHERE id = ?", [id])? == 0 {
+        return Err(WebgoneError::InvalidInput(format!("no epoch with id {}", id)).into());
+    }
+    Ok(())
+}
+
+/// All epochs, oldest first.
+pub fn list(conn: &Connection) -> Result<Vec<Epoch>> {
+    let mut stmt = conn.prepare("SELECT id, start_date, label FROM epochs ORDER BY start_date")?;
+    let epochs = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?
+        .into_iter()
+        .filter_map(|(id, start, label)| Some(Epoch { id, start: start.parse().ok()?, label }))
+        .collect();
+    Ok(epochs)
+}
+
+pub fn start_of_day(date: NaiveDate) -> DateTime<Local> {
+    let midnight = date.and_hms_opt(0, 0, 0).expect("midnight is valid");
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .unwrap_or_else(|| Local.from_utc_datetime(&midnight))
+}
+
+fn parse_time(value: &str) -> Option<DateTime<Local>> {
+    DateTime::parse_from_rfc3339(value).ok().map(|time| time.with_timezone(&Local))
+}
+
+/// Time of the oldest outage or probe recorded on this machine.
+fn first_record(conn: &Connection) -> Result<Option<DateTime<Local>>> {
+    let first: Option<String> = conn.query_row(
+        "SELECT MIN(time) FROM (
+            SELECT MIN(start_time) AS time FROM outages WHERE site IS NULL
+            UNION ALL SELECT MIN(time) FROM probes WHERE site IS NULL
+        )",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(first.as_deref().and_then(parse_time))
+}
+
+fn summarize(
+    conn: &Connection,
+    epoch: Option<Epoch>,
+    start: DateTime<Local>,
+    end: DateTime<Local>,
+    gaps: GapMode,
+) -> Result<EpochSummary> {
+    let mut stmt = conn.prepare(
+        "SELECT start_time, end_time FROM outages
+        WHERE site IS NULL AND julianday(end_time) >= julianday(?1) AND julianday(start_time) < julianday(?2)"
+    )?;
+    let periods = stmt
+        .query_map(params![start.to_rfc3339(), end.to_rfc3339()], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?
+        .into_iter()
+        .filter_map(|(from, to)| Some((parse_time(&from)?, parse_time(&to)?)))
+        .collect::<Vec<_>>();
+
+    // Outages count for the epoch they started in, downtime for the epoch it falls into
+    let outages = periods.iter().filter(|(from, _)| *from >= start).count() as i64;
+    let downtime_seconds: i64 =
+        periods.iter().map(|(from, to)| ((*to).min(end) - (*from).max(start)).num_seconds().max(0)).sum();
+    let period_seconds = (end - start).num_seconds().max(1);
+    let gap_seconds = coverage::gap_seconds(conn, start, end)?;
+
+    // Raw probes stand for `weight` probes each; aggregates for their successful probes
+    let (latency_sum, latency_count): (Option<f64>, Option<f64>) = conn.query_row(
+        "SELECT SUM(total), SUM(count) FROM (
+            SELECT SUM(latency_ms * weight) AS total, SUM(weight) AS count FROM probes
+            WHERE site IS NULL AND success AND julianday(time) >= julianday(?1) AND julianday(time) < julianday(?2)
+            UNION ALL
+            SELECT SUM(avg_latency_ms * (probes - failures)), SUM(probes - failures) FROM probe_hourly
+            WHERE avg_latency_ms IS NOT NULL AND julianday(period) >= julianday(?1) AND julianday(period) < julianday(?2)
+            UNION ALL
+            SELECT SUM(avg_latency_ms * (probes - failures)), SUM(probes - failures) FROM probe_minutely
+            WHERE avg_latency_ms IS NOT NULL AND julianday(period) >= julianday(?1) AND julianday(period) < julianday(?2)
+        )",
+        params![start.to_rfc3339(), end.to_rfc3339()],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+
+    Ok(EpochSummary {
+        epoch,
+        start,
+        end,
+        outages,
+        downtime_seconds,
+        gap_seconds,
+        availability_percent: gaps.uptime_percent(period_seconds, downtime_seconds, gap_seconds),
+        average_latency_ms: match (latency_sum, latency_count) {
+            (Some(sum), Some(count)) if count > 0.0 => Some(sum / count),
+            _ => None,
+        },
+    })
+}
+
+/// Summaries of every epoch, oldest first, preceded by the history recorded
+/// before the first epoch if there is any. Monitoring gaps count as `gaps` says.
+pub fn compare(conn: &Connection, gaps: GapMode) -> Result<Vec<EpochSummary>> {
+    let epochs = list(conn)?;
+    let now = Local::now();
+    let mut summaries = Vec::new();
+
+    if let (Some(first_epoch), Some(first_record)) = (epochs.first(), first_record(conn)?) {
+        let first_start = start_of_day(first_epoch.start);
+        if first_record < first_start {
+            summaries.push(summarize(conn, None, first_record, first_start, gaps)?);
+        }
+    }
+
+    let ends: Vec<_> = epochs.iter().skip(1).map(|epoch| start_of_day(epoch.start)).chain([now]).collect();
+    for (epoch, end) in epochs.into_iter().zip(ends) {
+        let start = start_of_day(epoch.start);
+        summaries.push(summarize(conn, Some(epoch), start, end, gaps)?);
+    }
+
+    Ok(summaries)
+}
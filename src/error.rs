@@ -0,0 +1,169 @@
+use rusqlite::ErrorCode;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::lock;
+
+/// Errors surfaced to the user, each with an actionable message and a stable
+/// exit code so scripts and service managers can react to them.
+#[derive(Debug, Clone)]
+pub enum WebgoneError {
+    /// The database is locked by another process for longer than the busy timeout
+    DatabaseLocked { pid: Option<u32> },
+    /// The database file couldn't be opened or created
+    DatabaseOpen { path: PathBuf, reason: String },
+    /// A command that needs existing data was run without a database
+    DatabaseMissing { path: PathBuf },
+    /// The database schema was written by a newer webgone
+    IncompatibleDatabase { schema_version: i64, supported: i64, writer: Option<String> },
+    /// The database is corrupt, not a webgone database, or a query failed
+    Database(String),
+    /// Invalid command line input
+    InvalidInput(String),
+    /// Reading or writing a file failed
+    Io(String),
+    /// A network request failed
+    Network(String),
+    Other(String),
+}
+
+impl WebgoneError {
+    /// Exit codes, documented in the README:
+    /// 1 unexpected error, 2 invalid input, 3 database locked,
+    /// 4 database error, 5 file error, 6 network error.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            WebgoneError::Other(_) => 1,
+            WebgoneError::InvalidInput(_) => 2,
+            WebgoneError::DatabaseLocked { .. } => 3,
+            WebgoneError::DatabaseOpen { .. }
+            | WebgoneError::DatabaseMissing { .. }
+            | WebgoneError::IncompatibleDatabase { .. }
+            | WebgoneError::Database(_) => 4,
+            WebgoneError::Io(_) => 5,
+            WebgoneError::Network(_) => 6,
+        }
+    }
+
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            WebgoneError::DatabaseLocked { pid: Some(pid) } => Some(format!(
+                "wait for the other process to finish its write, or stop it (e.g. `kill {}`) and try again",
+                pid
+            )),
+            WebgoneError::DatabaseLocked { pid: None } => {
+                Some("wait for the other process to finish its write and try again".to_string())
+            }
+            WebgoneError::DatabaseOpen { path, .. } => Some(format!(
+                "check that the directory of {} exists and is writable",
+                path.display()
+            )),
+            WebgoneError::DatabaseMissing { .. } => Some(
+                "pass your database with --db or WEBGONE_DB, or start recording outages with `webgone watch` (`webgone init` creates an empty database)".to_string()
+            ),
+            WebgoneError::IncompatibleDatabase { writer, .. } => Some(format!(
+                "upgrade webgone{} (e.g. with `webgone self-update`); newer versions migrate older databases automatically, but not the other way around",
+                writer.as_ref().map(|version| format!(" to {} or later", version)).unwrap_or_default()
+            )),
+            _ => None,
+        }
+    }
+
+    /// Whether an error may go away by itself, like a database locked by
+    /// another process for too long, a failing disk write or an unreachable
+    /// network, so the watcher is worth starting again.
+    pub fn is_transient(err: &anyhow::Error) -> bool {
+        for cause in err.chain() {
+            if let Some(error) = cause.downcast_ref::<WebgoneError>() {
+                return matches!(error, WebgoneError::DatabaseLocked { .. } | WebgoneError::Io(_) | WebgoneError::Network(_));
+            }
+            if let Some(rusqlite::Error::SqliteFailure(error, _)) = cause.downcast_ref::<rusqlite::Error>() {
+                return matches!(
+                    error.code,
+                    ErrorCode::DatabaseBusy
+                        | ErrorCode::DatabaseLocked
+                        | ErrorCode::SystemIoFailure
+                        | ErrorCode::DiskFull
+                        | ErrorCode::CannotOpen
+                );
+            }
+            if cause.is::<ureq::Error>() || cause.is::<std::io::Error>() {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Classifies an error from anywhere in the application, looking through
+    /// its chain of causes for a known error type.
+    pub fn from_anyhow(err: &anyhow::Error, db_path: &Path) -> Self {
+        let message = format!("{:#}", err);
+
+        for cause in err.chain() {
+            if let Some(error) = cause.downcast_ref::<WebgoneError>() {
+                return error.clone();
+            }
+            if let Some(rusqlite::Error::SqliteFailure(error, _)) = cause.downcast_ref::<rusqlite::Error>() {
+                return match error.code {
+                    ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked => {
+                        WebgoneError::DatabaseLocked { pid: lock::watcher_pid(db_path) }
+                    }
+                    ErrorCode::CannotOpen | ErrorCode::PermissionDenied | ErrorCode::ReadOnly => {
+                        WebgoneError::DatabaseOpen { path: db_path.to_path_buf(), reason: message }
+                    }
+                    ErrorCode::NotADatabase => WebgoneError::Database(format!(
+                        "{} is not a webgone database or is corrupted",
+                        db_path.display()
+                    )),
+                    _ => WebgoneError::Database(message),
+                };
+            }
+            if cause.is::<rusqlite::Error>() {
+                return WebgoneError::Database(message);
+            }
+            if cause.is::<ureq::Error>() {
+                return WebgoneError::Network(message);
+            }
+            if cause.is::<std::io::Error>() {
+                return WebgoneError::Io(message);
+            }
+        }
+
+        WebgoneError::Other(message)
+    }
+}
+
+impl fmt::Display for WebgoneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WebgoneError::DatabaseLocked { pid: Some(pid) } => {
+                write!(f, "database is locked by another webgone process (pid {})", pid)
+            }
+            WebgoneError::DatabaseLocked { pid: None } => write!(f, "database is locked by another process"),
+            WebgoneError::DatabaseOpen { path, reason } => {
+                write!(f, "failed to open database {}: {}", path.display(), reason)
+            }
+            WebgoneError::DatabaseMissing { path } => write!(f, "database {} doesn't exist", path.display()),
+            WebgoneError::IncompatibleDatabase { schema_version, supported, writer } => {
+                write!(
+                    f,
+                    "database schema version {} is newer than the version {} supported by webgone {}",
+                    schema_version,
+                    supported,
+                    env!("CARGO_PKG_VERSION")
+                )?;
+                if let Some(writer) = writer {
+                    write!(f, " (last written by webgone {})", writer)?;
+                }
+                Ok(())
+            }
+            WebgoneError::Database(message) => write!(f, "database error: {}", message),
+            WebgoneError::InvalidInput(message) => write!(f, "{}", message),
+            WebgoneError::Io(message) => write!(f, "file error: {}", message),
+            WebgoneError::Network(message) => write!(f, "network error: {}", message),
+            WebgoneError::Other(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for WebgoneError {}
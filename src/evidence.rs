@@ -0,0 +1,232 @@
+use anyhow::{bail, Context, Result};
+use chrono::{Local, NaiveDate};
+use rusqlite::{params, Connection, OptionalExtension};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+use crate::error::WebgoneError;
+
+pub const DEFAULT_TSA_URL: &str = "https://freetsa.org/tsr";
+
+/// DER encoding of the SHA-256 AlgorithmIdentifier (OID 2.16.840.1.101.3.4.2.1, NULL parameters)
+const SHA256_ALGORITHM: [u8; 15] = [
+    0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05, 0x00,
+];
+
+/// A stored RFC 3161 timestamp for one day's outage digest.
+pub struct Stamp {
+    pub day: NaiveDate,
+    pub digest: [u8; 32],
+    pub tsa_url: String,
+    pub request: Vec<u8>,
+    pub response: Vec<u8>,
+}
+
+/// Result of checking a stored timestamp against the current outage records.
+pub enum Verification {
+    Valid,
+    /// The outages of that day were changed after they were timestamped
+    DigestMismatch,
+    /// The stored response doesn't cover the stored digest
+    InvalidToken,
+}
+
+pub fn init_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS evidence_stamps (
+            day TEXT PRIMARY KEY,
+            digest BLOB NOT NULL,
+            tsa_url TEXT NOT NULL,
+            stamped_at TEXT NOT NULL,
+            request BLOB NOT NULL,
+            response BLOB NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// SHA-256 over the outages that started on `day` (local time), one
+/// `id,start,end,duration` line each, in the order they started.
+pub fn day_digest(conn: &Connection, day: NaiveDate) -> Result<[u8; 32]> {
+    let mut stmt = conn.prepare(
+        "SELECT id, start_time, end_time, duration_seconds FROM outages
+        WHERE date(start_time, 'localtime') = ?
+        ORDER BY start_time, id"
+    )?;
+    let mut rows = stmt.query([day.format("%Y-%m-%d").to_string()])?;
+
+    let mut hasher = Sha256::new();
+    while let Some(row) = rows.next()? {
+        let id: i64 = row.get(0)?;
+        let start: String = row.get(1)?;
+        let end: String = row.get(2)?;
+        let duration: i64 = row.get(3)?;
+        hasher.update(format!("{},{},{},{}\n", id, start, end, duration));
+    }
+    Ok(hasher.finalize().into())
+}
+
+fn der_length(len: usize) -> Vec<u8> {
+    if len < 0x80 {
+        vec![len as u8]
+    } else {
+        let bytes: Vec<u8> = len.to_be_bytes().into_iter().skip_while(|b| *b == 0).collect();
+        let mut out = vec![0x80 | bytes.len() as u8];
+        out.extend(bytes);
+        out
+    }
+}
+
+fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    out.extend(der_length(content.len()));
+    out.extend_from_slice(content);
+    out
+}
+
+/// Builds a DER encoded RFC 3161 TimeStampReq for a SHA-256 digest,
+/// asking the TSA to include its certificate in the response.
+fn timestamp_request(digest: &[u8; 32], nonce: u64) -> Vec<u8> {
+    let mut imprint = SHA256_ALGORITHM.to_vec();
+    imprint.extend(der(0x04, digest));
+
+    // Keep the nonce a positive INTEGER
+    let nonce = (nonce & (u64::MAX >> 1)).to_be_bytes();
+
+    let mut request = der(0x02, &[1]);
+    request.extend(der(0x30, &imprint));
+    request.extend(der(0x02, &nonce));
+    request.extend(der(0x01, &[0xff]));
+    der(0x30, &request)
+}
+
+/// Reads the tag, content start and content length of the DER element at `offset`.
+fn der_element(data: &[u8], offset: usize) -> Option<(u8, usize, usize)> {
+    let tag = *data.get(offset)?;
+    let first = *data.get(offset + 1)? as usize;
+    if first < 0x80 {
+        return Some((tag, offset + 2, first));
+    }
+    let count = first & 0x7f;
+    let bytes = data.get(offset + 2..offset + 2 + count)?;
+    let len = bytes.iter().fold(0usize, |len, b| (len << 8) | *b as usize);
+    Some((tag, offset + 2 + count, len))
+}
+
+/// PKIStatus of a TimeStampResp: 0 (granted) or 1 (granted with modifications) mean success.
+fn response_status(response: &[u8]) -> Option<u8> {
+    let (_, resp_start, _) = der_element(response, 0)?;
+    let (_, status_info_start, _) = der_element(response, resp_start)?;
+    let (tag, status_start, len) = der_element(response, status_info_start)?;
+    (tag == 0x02 && len == 1).then(|| response[status_start])
+}
+
+fn token_covers(response: &[u8], digest: &[u8; 32]) -> bool {
+    matches!(response_status(response), Some(0 | 1))
+        && response.windows(digest.len()).any(|window| window == digest)
+}
+
+/// Requests a timestamp for `day` from the TSA and stores it. Days that
+/// already have a timestamp are left alone; returns whether one was added.
+pub fn stamp_day(conn: &Connection, day: NaiveDate, tsa_url: &str) -> Result<bool> {
+    let exists = conn
+        .query_row("SELECT 1 FROM evidence_stamps WHERE day = ?", [day.to_string()], |_| Ok(()))
+        .optional()?
+        .is_some();
+    if exists {
+        return Ok(false);
+    }
+
+    let digest = day_digest(conn, day)?;
+    let nonce = Local::now().timestamp_nanos_opt().unwrap_or_default() as u64;
+    let request = timestamp_request(&digest, nonce);
+
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(Duration::from_secs(30)))
+        .build()
+        .into();
+    let response = agent
+        .post(tsa_url)
+        .header("Content-Type", "application/timestamp-query")
+        .send(&request[..])
+        .context("Timestamp request failed")?
+        .body_mut()
+        .read_to_vec()?;
+
+    match response_status(&response) {
+        Some(0 | 1) if token_covers(&response, &digest) => {}
+        Some(status) => bail!(WebgoneError::Network(format!(
+            "timestamp authority {} rejected the request (status {})",
+            tsa_url, status
+        ))),
+        None => bail!(WebgoneError::Network(format!(
+            "timestamp authority {} sent an invalid response",
+            tsa_url
+        ))),
+    }
+
+    conn.execute(
+        "INSERT INTO evidence_stamps (day, digest, tsa_url, stamped_at, request, response)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![day.to_string(), &digest[..], tsa_url, Local::now().to_rfc3339(), request, response],
+    )?;
+    Ok(true)
+}
+
+/// Past days with outages that haven't been timestamped yet.
+pub fn unstamped_days(conn: &Connection) -> Result<Vec<NaiveDate>> {
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT date(start_time, 'localtime') AS day FROM outages
+        WHERE day < date('now', 'localtime')
+        AND day NOT IN (SELECT day FROM evidence_stamps)
+        ORDER BY day"
+    )?;
+    let days = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    days.iter()
+        .map(|day| NaiveDate::parse_from_str(day, "%Y-%m-%d").map_err(Into::into))
+        .collect()
+}
+
+pub fn stamps(conn: &Connection, day: Option<NaiveDate>) -> Result<Vec<Stamp>> {
+    let mut stmt = conn.prepare(
+        "SELECT day, digest, tsa_url, request, response FROM evidence_stamps
+        WHERE ?1 IS NULL OR day = ?1
+        ORDER BY day"
+    )?;
+    let stamps = stmt
+        .query_map([day.map(|day| day.to_string())], |row| {
+            let day: String = row.get(0)?;
+            let digest: Vec<u8> = row.get(1)?;
+            Ok((day, digest, row.get(2)?, row.get(3)?, row.get(4)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    stamps
+        .into_iter()
+        .map(|(day, digest, tsa_url, request, response)| {
+            Ok(Stamp {
+                day: NaiveDate::parse_from_str(&day, "%Y-%m-%d")?,
+                digest: digest.try_into().map_err(|_| anyhow::anyhow!("stored digest for {} is not SHA-256", day))?,
+                tsa_url,
+                request,
+                response,
+            })
+        })
+        .collect()
+}
+
+/// Checks that the outages of a day still match the digest that was
+/// timestamped, and that the stored token covers that digest. The token's
+/// signature itself can be verified with `openssl ts -verify`.
+pub fn verify(conn: &Connection, stamp: &Stamp) -> Result<Verification> {
+    if !token_covers(&stamp.response, &stamp.digest) {
+        return Ok(Verification::InvalidToken);
+    }
+    if day_digest(conn, stamp.day)? != stamp.digest {
+        return Ok(Verification::DigestMismatch);
+    }
+    Ok(Verification::Valid)
+}
@@ -0,0 +1,403 @@
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Local, NaiveDate};
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::error::WebgoneError;
+use webgone::model::{InternetOutage, MonitoredPeriod, OutageExport, PlannedDowntime};
+
+/// File listing the files of a split export.
+const MANIFEST: &str = "manifest.json";
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+impl Format {
+    fn name(self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Json => "json",
+        }
+    }
+
+    /// Renders outages; `exported_at`, planned downtime and monitored periods
+    /// are only written to JSON.
+    pub fn render(
+        self,
+        outages: Vec<InternetOutage>,
+        planned: Vec<PlannedDowntime>,
+        monitored: Vec<MonitoredPeriod>,
+        schema: &Schema,
+        exported_at: DateTime<Local>,
+    ) -> Result<String> {
+        Ok(match self {
+            Format::Csv => csv(&outages, schema),
+            Format::Json => json(outages, planned, monitored, schema, exported_at)?,
+        })
+    }
+}
+
+/// Period each file of a split export covers.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Split {
+    Month,
+    Year,
+}
+
+impl Split {
+    fn name(self) -> &'static str {
+        match self {
+            Split::Month => "month",
+            Split::Year => "year",
+        }
+    }
+
+    /// The period a day falls into, e.g. `2024-03`, and the day after it.
+    fn period(self, day: NaiveDate) -> (String, NaiveDate) {
+        match self {
+            Split::Month => {
+                let (year, month) = if day.month() == 12 { (day.year() + 1, 1) } else { (day.year(), day.month() + 1) };
+                (
+                    format!("{}-{:02}", day.year(), day.month()),
+                    NaiveDate::from_ymd_opt(year, month, 1).expect("first of a month exists"),
+                )
+            }
+            Split::Year => (
+                day.year().to_string(),
+                NaiveDate::from_ymd_opt(day.year() + 1, 1, 1).expect("first of a year exists"),
+            ),
+        }
+    }
+}
+
+/// A field of an outage as named in the `v2` schema and in mapping files.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Id,
+    Site,
+    StartTime,
+    EndTime,
+    DurationSeconds,
+    DetectedAt,
+    ConfirmedAt,
+    RecoveredAt,
+    WifiSsid,
+    WifiSignalDbm,
+    WeatherCode,
+    TemperatureC,
+    PrecipitationMm,
+    WindSpeedKmh,
+    Tags,
+    CapturePath,
+    FailedComponents,
+    Actor,
+    FailedProbes,
+    Severity,
+    ClockAnomaly,
+    DerivedFrom,
+}
+
+impl Field {
+    const ALL: [Field; 22] = [
+        Field::Id,
+        Field::Site,
+        Field::StartTime,
+        Field::EndTime,
+        Field::DurationSeconds,
+        Field::DetectedAt,
+        Field::ConfirmedAt,
+        Field::RecoveredAt,
+        Field::WifiSsid,
+        Field::WifiSignalDbm,
+        Field::WeatherCode,
+        Field::TemperatureC,
+        Field::PrecipitationMm,
+        Field::WindSpeedKmh,
+        Field::Tags,
+        Field::CapturePath,
+        Field::FailedComponents,
+        Field::Actor,
+        Field::FailedProbes,
+        Field::Severity,
+        Field::ClockAnomaly,
+        Field::DerivedFrom,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Field::Id => "id",
+            Field::Site => "site",
+            Field::StartTime => "start_time",
+            Field::EndTime => "end_time",
+            Field::DurationSeconds => "duration_seconds",
+            Field::DetectedAt => "detected_at",
+            Field::ConfirmedAt => "confirmed_at",
+            Field::RecoveredAt => "recovered_at",
+            Field::WifiSsid => "wifi_ssid",
+            Field::WifiSignalDbm => "wifi_signal_dbm",
+            Field::WeatherCode => "weather_code",
+            Field::TemperatureC => "temperature_c",
+            Field::PrecipitationMm => "precipitation_mm",
+            Field::WindSpeedKmh => "wind_speed_kmh",
+            Field::Tags => "tags",
+            Field::CapturePath => "capture_path",
+            Field::FailedComponents => "failed_components",
+            Field::Actor => "actor",
+            Field::FailedProbes => "failed_probes",
+            Field::Severity => "severity",
+            Field::ClockAnomaly => "clock_anomaly",
+            Field::DerivedFrom => "derived_from",
+        }
+    }
+
+    fn value(self, outage: &InternetOutage) -> Value {
+        let time = |time: Option<DateTime<Local>>| json!(time.map(|time| time.to_rfc3339()));
+        let weather = outage.weather.as_ref();
+        match self {
+            Field::Id => json!(outage.id),
+            Field::Site => json!(outage.site),
+            Field::StartTime => time(Some(outage.start_time)),
+            Field::EndTime => time(Some(outage.end_time)),
+            Field::DurationSeconds => json!(outage.duration_seconds),
+            Field::DetectedAt => time(outage.detected_at),
+            Field::ConfirmedAt => time(outage.confirmed_at),
+            Field::RecoveredAt => time(outage.recovered_at),
+            Field::WifiSsid => json!(outage.wifi.as_ref().and_then(|wifi| wifi.ssid.as_deref())),
+            Field::WifiSignalDbm => json!(outage.wifi.as_ref().and_then(|wifi| wifi.signal_dbm)),
+            Field::WeatherCode => json!(weather.and_then(|weather| weather.weather_code)),
+            Field::TemperatureC => json!(weather.and_then(|weather| weather.temperature_c)),
+            Field::PrecipitationMm => json!(weather.and_then(|weather| weather.precipitation_mm)),
+            Field::WindSpeedKmh => json!(weather.and_then(|weather| weather.wind_speed_kmh)),
+            Field::Tags => json!(outage.tags),
+            Field::CapturePath => json!(outage.capture_path),
+            Field::FailedComponents => json!(outage.failed_components),
+            Field::Actor => json!(outage.actor),
+            Field::FailedProbes => json!(outage.failed_probes),
+            Field::Severity => json!(outage.severity),
+            Field::ClockAnomaly => json!(outage.clock_anomaly),
+            Field::DerivedFrom => json!(outage.derived_from),
+        }
+    }
+}
+
+impl FromStr for Field {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Field::ALL.into_iter().find(|field| field.name() == s).ok_or_else(|| {
+            let names: Vec<&str> = Field::ALL.iter().map(|field| field.name()).collect();
+            format!("unknown field `{}`, expected one of {}", s, names.join(", "))
+        })
+    }
+}
+
+/// Field names and layout of an export, given as `--schema legacy|v2|FILE`.
+#[derive(Clone)]
+pub enum Schema {
+    /// What webgone always wrote: three CSV columns with English headers, and
+    /// the data model for JSON, which the static status page reads
+    Legacy,
+    /// Every field under its snake_case name, in CSV and JSON alike
+    V2,
+    /// Fields and their names from a mapping file with one `field = name` per line
+    Custom(Vec<(Field, String)>),
+}
+
+impl Schema {
+    /// Exported fields and their names, `None` for the data model.
+    fn columns(&self, json: bool) -> Option<Vec<(Field, String)>> {
+        match self {
+            Schema::Legacy if json => None,
+            Schema::Legacy => Some(vec![
+                (Field::StartTime, "Start Time".to_string()),
+                (Field::EndTime, "End Time".to_string()),
+                (Field::DurationSeconds, "Duration (seconds)".to_string()),
+            ]),
+            Schema::V2 => Some(Field::ALL.iter().map(|field| (*field, field.name().to_string())).collect()),
+            Schema::Custom(columns) => Some(columns.clone()),
+        }
+    }
+}
+
+impl FromStr for Schema {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "legacy" => Ok(Schema::Legacy),
+            "v2" => Ok(Schema::V2),
+            path => {
+                let contents = fs::read_to_string(path)
+                    .map_err(|e| format!("expected legacy, v2 or a mapping file; failed to read {}: {}", path, e))?;
+                let mut columns = Vec::new();
+                for (number, line) in contents.lines().enumerate() {
+                    let line = line.trim();
+                    if line.is_empty() || line.starts_with('#') {
+                        continue;
+                    }
+                    let (field, name) = line
+                        .split_once('=')
+                        .ok_or_else(|| format!("{} line {}: expected `field = name`", path, number + 1))?;
+                    let field = field.trim().parse().map_err(|e| format!("{} line {}: {}", path, number + 1, e))?;
+                    columns.push((field, name.trim().to_string()));
+                }
+                if columns.is_empty() {
+                    return Err(format!("{} doesn't map any fields", path));
+                }
+                Ok(Schema::Custom(columns))
+            }
+        }
+    }
+}
+
+/// Quotes a CSV value if it contains a separator, quote or line break.
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn csv_value(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        Value::Array(items) => items.iter().map(csv_value).collect::<Vec<_>>().join(";"),
+        other => other.to_string(),
+    }
+}
+
+/// Outages as CSV with a header line. Tags are joined with `;`.
+pub fn csv(outages: &[InternetOutage], schema: &Schema) -> String {
+    let columns = schema.columns(false).expect("CSV always has columns");
+    let header: Vec<String> = columns.iter().map(|(_, name)| csv_field(name)).collect();
+    let mut out = format!("{}\n", header.join(","));
+    for outage in outages {
+        let row: Vec<String> =
+            columns.iter().map(|(field, _)| csv_field(&csv_value(&field.value(outage)))).collect();
+        let _ = writeln!(out, "{}", row.join(","));
+    }
+    out
+}
+
+/// Outages as JSON, in the data model's form for the legacy schema and as
+/// flat objects with the schema's field names otherwise. Planned downtime and
+/// monitored periods are left out when there are none, so older readers see
+/// the same layout.
+pub fn json(
+    outages: Vec<InternetOutage>,
+    planned: Vec<PlannedDowntime>,
+    monitored_periods: Vec<MonitoredPeriod>,
+    schema: &Schema,
+    exported_at: DateTime<Local>,
+) -> serde_json::Result<String> {
+    let Some(columns) = schema.columns(true) else {
+        return serde_json::to_string_pretty(&OutageExport { exported_at, outages, planned, monitored_periods });
+    };
+    let outages: Vec<Value> = outages
+        .iter()
+        .map(|outage| {
+            let fields: Map<String, Value> =
+                columns.iter().map(|(field, name)| (name.clone(), field.value(outage))).collect();
+            Value::Object(fields)
+        })
+        .collect();
+    let mut export = Map::new();
+    export.insert("exported_at".to_string(), json!(exported_at));
+    export.insert("outages".to_string(), Value::Array(outages));
+    if !planned.is_empty() {
+        export.insert("planned".to_string(), json!(planned));
+    }
+    if !monitored_periods.is_empty() {
+        export.insert("monitored_periods".to_string(), json!(monitored_periods));
+    }
+    serde_json::to_string_pretty(&Value::Object(export))
+}
+
+/// A file of a split export, as listed in the manifest.
+#[derive(Serialize)]
+pub struct SplitFile {
+    pub file: String,
+    pub period: String,
+    pub outages: usize,
+    pub downtime_seconds: i64,
+    pub sha256: String,
+    /// Whether the file was new or changed; unchanged files aren't touched
+    #[serde(skip)]
+    pub written: bool,
+}
+
+#[derive(Serialize)]
+struct Manifest<'a> {
+    exported_at: DateTime<Local>,
+    split: &'static str,
+    format: &'static str,
+    files: &'a [SplitFile],
+}
+
+/// Writes one file per period with outages, named e.g. `outages-2024-03.csv`,
+/// and a manifest listing them. Outages belong to the period they started
+/// in. JSON files of past periods carry the period's end as their export
+/// time, so files whose outages didn't change stay byte for byte the same
+/// and aren't rewritten, which keeps incremental syncing cheap.
+pub fn write_split(
+    dir: &Path,
+    outages: Vec<InternetOutage>,
+    split: Split,
+    format: Format,
+    schema: &Schema,
+) -> Result<Vec<SplitFile>> {
+    let io_error = |path: &Path, e: std::io::Error| WebgoneError::Io(format!("failed to write {}: {}", path.display(), e));
+    fs::create_dir_all(dir).map_err(|e| io_error(dir, e))?;
+
+    let mut periods: BTreeMap<String, (NaiveDate, Vec<InternetOutage>)> = BTreeMap::new();
+    for outage in outages {
+        let (period, end) = split.period(outage.start_time.date_naive());
+        periods.entry(period).or_insert_with(|| (end, Vec::new())).1.push(outage);
+    }
+
+    let now = Local::now();
+    let mut files = Vec::new();
+    for (period, (end, outages)) in periods {
+        let exported_at = end
+            .and_hms_opt(0, 0, 0)
+            .and_then(|midnight| midnight.and_local_timezone(Local).earliest())
+            .filter(|end| *end < now)
+            .unwrap_or(now);
+        let count = outages.len();
+        let downtime_seconds = outages.iter().map(|outage| outage.duration_seconds).sum();
+        // Files of past periods have to stay the same, so planned downtime and monitored periods are only in a full export
+        let data = format.render(outages, Vec::new(), Vec::new(), schema, exported_at)?;
+
+        let file = format!("outages-{}.{}", period, format.name());
+        let path = dir.join(&file);
+        let written = fs::read(&path).map_or(true, |existing| existing != data.as_bytes());
+        if written {
+            fs::write(&path, &data).map_err(|e| io_error(&path, e))?;
+        }
+        files.push(SplitFile {
+            file,
+            period,
+            outages: count,
+            downtime_seconds,
+            sha256: hex::encode(Sha256::digest(data.as_bytes())),
+            written,
+        });
+    }
+
+    let manifest = Manifest { exported_at: now, split: split.name(), format: format.name(), files: &files };
+    let path = dir.join(MANIFEST);
+    fs::write(&path, serde_json::to_string_pretty(&manifest)?).map_err(|e| io_error(&path, e))?;
+    Ok(files)
+}
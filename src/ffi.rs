@@ -0,0 +1,127 @@
+//! C interface to the outage state machine, for projects that do their own
+//! probing, e.g. router firmware. See `include/webgone.h` for the C side.
+//! Times are milliseconds since the Unix epoch.
+
+use chrono::{DateTime, Local, TimeZone};
+use std::ffi::c_void;
+
+use crate::debounce::{Debounce, Transition};
+
+pub const WEBGONE_EVENT_LOST: i32 = 1;
+pub const WEBGONE_EVENT_RESTORED: i32 = 2;
+
+/// A change of the connection state passed to the event callback.
+#[repr(C)]
+pub struct WebgoneEvent {
+    /// `WEBGONE_EVENT_LOST` or `WEBGONE_EVENT_RESTORED`
+    pub kind: i32,
+    /// Start of the outage: when the first failed check of the run that confirmed it started
+    pub outage_start_ms: i64,
+    /// Lost: when the result of that first failed check came in.
+    /// Restored: when the first successful check of the run that confirmed it started.
+    pub at_ms: i64,
+    /// Restored: length of the outage; 0 for lost events
+    pub duration_ms: i64,
+}
+
+pub type WebgoneEventCallback = Option<unsafe extern "C" fn(user_data: *mut c_void, event: *const WebgoneEvent)>;
+
+pub struct WebgoneTracker {
+    debounce: Debounce,
+    callback: WebgoneEventCallback,
+    user_data: *mut c_void,
+    outage_start: Option<DateTime<Local>>,
+}
+
+fn time(ms: i64) -> Option<DateTime<Local>> {
+    Local.timestamp_millis_opt(ms).single()
+}
+
+/// Creates a tracker that declares the connection lost after
+/// `fail_threshold` consecutive failed checks and restored after
+/// `recover_threshold` successful ones (0 counts as 1). `callback` is called
+/// with `user_data` on every change; it may be NULL.
+///
+/// # Safety
+///
+/// `callback` must be safe to call with `user_data` for as long as the tracker lives.
+#[no_mangle]
+pub unsafe extern "C" fn webgone_tracker_new(
+    fail_threshold: u32,
+    recover_threshold: u32,
+    callback: WebgoneEventCallback,
+    user_data: *mut c_void,
+) -> *mut WebgoneTracker {
+    Box::into_raw(Box::new(WebgoneTracker {
+        debounce: Debounce::new(fail_threshold, recover_threshold),
+        callback,
+        user_data,
+        outage_start: None,
+    }))
+}
+
+/// Feeds the result of a check that ran from `started_ms` to `finished_ms`,
+/// calling the callback if it changes the connection state. Returns 0, or
+/// -1 if the tracker is NULL or a time is out of range.
+///
+/// # Safety
+///
+/// `tracker` must come from `webgone_tracker_new` and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn webgone_tracker_feed(
+    tracker: *mut WebgoneTracker,
+    started_ms: i64,
+    finished_ms: i64,
+    success: bool,
+) -> i32 {
+    let Some(tracker) = tracker.as_mut() else { return -1 };
+    let (Some(started), Some(finished)) = (time(started_ms), time(finished_ms)) else { return -1 };
+
+    let event = match tracker.debounce.record(started, finished, success) {
+        Some(Transition::Lost { since, seen }) => {
+            tracker.outage_start = Some(since);
+            WebgoneEvent {
+                kind: WEBGONE_EVENT_LOST,
+                outage_start_ms: since.timestamp_millis(),
+                at_ms: seen.timestamp_millis(),
+                duration_ms: 0,
+            }
+        }
+        Some(Transition::Restored { since }) => {
+            let start = tracker.outage_start.take().unwrap_or(since);
+            WebgoneEvent {
+                kind: WEBGONE_EVENT_RESTORED,
+                outage_start_ms: start.timestamp_millis(),
+                at_ms: since.timestamp_millis(),
+                duration_ms: (since - start).num_milliseconds(),
+            }
+        }
+        None => return 0,
+    };
+    if let Some(callback) = tracker.callback {
+        callback(tracker.user_data, &event);
+    }
+    0
+}
+
+/// Whether the tracker currently considers the connection up.
+///
+/// # Safety
+///
+/// `tracker` must come from `webgone_tracker_new` and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn webgone_tracker_is_connected(tracker: *const WebgoneTracker) -> bool {
+    tracker.as_ref().is_some_and(|tracker| tracker.outage_start.is_none())
+}
+
+/// Frees a tracker. NULL is ignored.
+///
+/// # Safety
+///
+/// `tracker` must come from `webgone_tracker_new` and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn webgone_tracker_free(tracker: *mut WebgoneTracker) {
+    if !tracker.is_null() {
+        drop(Box::from_raw(tracker));
+    }
+}
@@ -0,0 +1,212 @@
+use chrono::{DateTime, Local};
+use clap::Args;
+use rusqlite::types::Value;
+use std::time::Duration;
+
+use crate::dates;
+use crate::sites;
+
+/// Narrows down which outages a report looks at. Shared by all report
+/// commands, so a filter means the same thing everywhere.
+#[derive(Args, Clone, Default)]
+pub struct OutageFilter {
+    /// Only include outages going on at or after this time (e.g. 2024-06-01, 2024-06, yesterday, last monday, "2 weeks ago")
+    #[arg(long, value_parser = dates::parse_time)]
+    pub since: Option<DateTime<Local>>,
+    /// Only include outages that began before this time
+    #[arg(long, value_parser = dates::parse_time)]
+    pub until: Option<DateTime<Local>>,
+    /// Only include outages lasting at least this long (e.g. 90, 30s, 5m, 2h, 1d)
+    #[arg(long, value_parser = parse_duration)]
+    pub min_duration: Option<Duration>,
+    /// Only include outages lasting at most this long
+    #[arg(long, value_parser = parse_duration)]
+    pub max_duration: Option<Duration>,
+    /// Only include outages recorded while connected to this Wi-Fi network
+    #[arg(long)]
+    pub ssid: Option<String>,
+    /// Only include outages with this tag (repeat to require several tags)
+    #[arg(long = "tag", value_name = "TAG")]
+    pub tags: Vec<String>,
+    /// Leave out outages with this tag, e.g. a network location (repeat to exclude several tags)
+    #[arg(long = "exclude-tag", value_name = "TAG")]
+    pub exclude_tags: Vec<String>,
+    /// Only include outages of this site or monitored service, as reported by its agent (`local` for this machine)
+    #[arg(long, visible_alias = "monitor")]
+    pub site: Option<String>,
+    /// Only include outages in which this component failed, e.g. `dns` for `--up-when` or a target like `1.1.1.1:53`
+    #[arg(long)]
+    pub cause: Option<String>,
+    /// Only include outages reported by this API token or agent, e.g. `agent home` (`local` for this machine's watcher)
+    #[arg(long)]
+    pub actor: Option<String>,
+    /// Also include outages of services recorded as derived from what they depend on (see `watch --depends-on`)
+    #[arg(long)]
+    pub include_derived: bool,
+}
+
+impl OutageFilter {
+    pub fn is_empty(&self) -> bool {
+        self.since.is_none()
+            && self.until.is_none()
+            && self.min_duration.is_none()
+            && self.max_duration.is_none()
+            && self.ssid.is_none()
+            && self.tags.is_empty()
+            && self.exclude_tags.is_empty()
+            && self.site.is_none()
+            && self.actor.is_none()
+            && self.cause.is_none()
+            && !self.include_derived
+    }
+
+    /// Builds a `WHERE` clause over the `outages` table together with its
+    /// positional parameters.
+    pub fn where_clause(&self) -> (String, Vec<Value>) {
+        let mut conditions = Vec::new();
+        let mut params = Vec::new();
+
+        if let Some(since) = self.since {
+            conditions.push("julianday(end_time) >= julianday(?)");
+            params.push(Value::Text(since.to_rfc3339()));
+        }
+        if let Some(until) = self.until {
+            conditions.push("julianday(start_time) < julianday(?)");
+            params.push(Value::Text(until.to_rfc3339()));
+        }
+        if let Some(min) = self.min_duration {
+            conditions.push("duration_seconds >= ?");
+            params.push(Value::Integer(min.as_secs() as i64));
+        }
+        if let Some(max) = self.max_duration {
+            conditions.push("duration_seconds <= ?");
+            params.push(Value::Integer(max.as_secs() as i64));
+        }
+        if let Some(ssid) = &self.ssid {
+            conditions.push("wifi_ssid = ?");
+            params.push(Value::Text(ssid.clone()));
+        }
+        for tag in &self.tags {
+            conditions.push("id IN (SELECT outage_id FROM outage_tags WHERE tag = ?)");
+            params.push(Value::Text(tag.clone()));
+        }
+        for tag in &self.exclude_tags {
+            conditions.push("id NOT IN (SELECT outage_id FROM outage_tags WHERE tag = ?)");
+            params.push(Value::Text(tag.clone()));
+        }
+        if let Some(site) = &self.site {
+            conditions.push("site IS ?");
+            params.push(sites::column_value(site));
+        }
+        if let Some(actor) = &self.actor {
+            conditions.push("actor IS ?");
+            params.push(sites::column_value(actor));
+        }
+
+        if let Some(cause) = &self.cause {
+            // One of the comma separated components, not a part of one
+            conditions.push("instr(', ' || failed_components || ', ', ', ' || ? || ', ') > 0");
+            params.push(Value::Text(cause.clone()));
+        }
+
+        if !self.include_derived {
+            conditions.push("derived_from IS NULL");
+        }
+        // A start time SQLite can't read has no month to count the outage in,
+        // so every report leaves such rows out alike
+        conditions.push("julianday(start_time) IS NOT NULL");
+
+        (format!("WHERE {}", conditions.join(" AND ")), params)
+    }
+}
+
+/// Parses durations like `90`, `30s`, `5m`, `2h` or `1d`; plain numbers are seconds.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => value.split_at(index),
+        None => (value, "s"),
+    };
+    let number: u64 = number.parse().map_err(|_| format!("invalid duration: {}", value))?;
+    let multiplier: u64 = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(format!("invalid duration unit in {} (use s, m, h or d)", value)),
+    };
+    // Kept within what SQLite's integers hold, as durations are compared in SQL
+    number
+        .checked_mul(multiplier)
+        .filter(|seconds| i64::try_from(*seconds).is_ok())
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("duration out of range: {}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+
+    #[test]
+    fn parses_durations() {
+        for (value, seconds) in [("90", 90), ("30s", 30), ("5m", 300), ("2h", 7200), ("1d", 86_400), (" 0s ", 0)] {
+            assert_eq!(parse_duration(value), Ok(Duration::from_secs(seconds)), "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_and_overflowing_durations() {
+        for value in ["", "s", "5w", "1.5h", "-5m", "5 m", "m5", "18446744073709551616", "999999999999999999d", "9223372036854775808s"] {
+            assert!(parse_duration(value).is_err(), "{:?}", value);
+        }
+    }
+
+    /// Ids of the outages matching `filter` in a database with a few local,
+    /// remote and derived outages.
+    fn matching(filter: &OutageFilter) -> Vec<i64> {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE outages (id INTEGER PRIMARY KEY, start_time TEXT, end_time TEXT, duration_seconds INTEGER,
+                wifi_ssid TEXT, site TEXT, actor TEXT, failed_components TEXT, derived_from INTEGER);
+            CREATE TABLE outage_tags (outage_id INTEGER, tag TEXT);
+            INSERT INTO outages VALUES
+                (1, '2026-03-01T10:00:00+00:00', '2026-03-01T10:00:30+00:00', 30, 'home', NULL, NULL, 'dns', NULL),
+                (2, '2026-03-02T10:00:00+00:00', '2026-03-02T11:00:00+00:00', 3600, NULL, NULL, NULL, 'gateway, dns', NULL),
+                (3, '2026-03-03T10:00:00+00:00', '2026-03-03T10:10:00+00:00', 600, NULL, 'nas', 'agent office', 'dnsmasq', NULL),
+                (4, '2026-03-03T10:00:00+00:00', '2026-03-03T10:10:00+00:00', 600, NULL, 'web', NULL, NULL, 3),
+                (5, 'garbage', 'garbage', 60, NULL, NULL, NULL, NULL, NULL);
+            INSERT INTO outage_tags VALUES (1, 'storm'), (2, 'storm'), (2, 'ticket');",
+        )
+        .unwrap();
+        let (condition, params) = filter.where_clause();
+        let mut stmt = conn.prepare(&format!("SELECT id FROM outages {} ORDER BY id", condition)).unwrap();
+        let ids = stmt.query_map(rusqlite::params_from_iter(params), |row| row.get(0)).unwrap();
+        ids.collect::<rusqlite::Result<_>>().unwrap()
+    }
+
+    #[test]
+    fn composes_filters() {
+        let time = |value: &str| DateTime::parse_from_rfc3339(value).unwrap().with_timezone(&Local);
+        let minutes = |count: u64| Some(Duration::from_secs(count * 60));
+        for (filter, expected) in [
+            (OutageFilter::default(), vec![1, 2, 3]),
+            (OutageFilter { include_derived: true, ..Default::default() }, vec![1, 2, 3, 4]),
+            (OutageFilter { since: Some(time("2026-03-02T10:30:00+00:00")), ..Default::default() }, vec![2, 3]),
+            (OutageFilter { until: Some(time("2026-03-02T10:00:00+00:00")), ..Default::default() }, vec![1]),
+            (OutageFilter { min_duration: minutes(5), max_duration: minutes(30), ..Default::default() }, vec![3]),
+            (OutageFilter { ssid: Some("home".to_string()), ..Default::default() }, vec![1]),
+            (OutageFilter { tags: vec!["storm".to_string(), "ticket".to_string()], ..Default::default() }, vec![2]),
+            (OutageFilter { exclude_tags: vec!["ticket".to_string()], ..Default::default() }, vec![1, 3]),
+            (OutageFilter { site: Some("local".to_string()), ..Default::default() }, vec![1, 2]),
+            (OutageFilter { site: Some("nas".to_string()), ..Default::default() }, vec![3]),
+            (OutageFilter { actor: Some("agent office".to_string()), ..Default::default() }, vec![3]),
+            (OutageFilter { cause: Some("dns".to_string()), ..Default::default() }, vec![1, 2]),
+            (OutageFilter { cause: Some("gateway".to_string()), min_duration: minutes(5), ..Default::default() }, vec![2]),
+            (OutageFilter { cause: Some("dns".to_string()), site: Some("nas".to_string()), ..Default::default() }, vec![]),
+            (OutageFilter { cause: Some("%".to_string()), ..Default::default() }, vec![]),
+        ] {
+            assert_eq!(matching(&filter), expected);
+        }
+    }
+}
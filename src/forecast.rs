@@ -0,0 +1,82 @@
+use chrono::{Datelike, Months, NaiveDate};
+
+/// Number of recent complete months whose average is the baseline of a forecast.
+const TRAILING_MONTHS: usize = 3;
+/// Complete months of history needed before calendar months are weighted by season.
+const SEASONAL_HISTORY: usize = 12;
+
+/// Expected downtime of an upcoming month.
+pub struct MonthForecast {
+    pub year: i32,
+    pub month: u32,
+    pub downtime_seconds: f64,
+    /// Downtime of this calendar month relative to the average month, 1.0 without enough history
+    pub seasonal_factor: f64,
+}
+
+impl MonthForecast {
+    pub fn seconds_in_month(&self) -> f64 {
+        let first = NaiveDate::from_ymd_opt(self.year, self.month, 1).expect("valid month");
+        let next = first + Months::new(1);
+        (next - first).num_seconds() as f64
+    }
+}
+
+/// Projects the downtime of the `months` months after `today`'s month from
+/// the downtime per month in `history` (year, month, seconds; months without
+/// outages may be missing). Only complete months count, from the first month
+/// with an outage. Returns `None` if there isn't a complete month yet.
+pub fn project(history: &[(i32, u32, i64)], today: NaiveDate, months: u32) -> Option<Vec<MonthForecast>> {
+    let current = today.with_day(1)?;
+    let first = history
+        .iter()
+        .filter_map(|&(year, month, _)| NaiveDate::from_ymd_opt(year, month, 1))
+        .min()?;
+
+    // Downtime of every complete month, including those without outages
+    let mut complete = Vec::new();
+    let mut month = first;
+    while month < current {
+        let seconds = history
+            .iter()
+            .filter(|&&(y, m, _)| y == month.year() && m == month.month())
+            .map(|&(_, _, seconds)| seconds)
+            .sum::<i64>();
+        complete.push((month, seconds as f64));
+        month = month + Months::new(1);
+    }
+    if complete.is_empty() {
+        return None;
+    }
+
+    let trailing = &complete[complete.len().saturating_sub(TRAILING_MONTHS)..];
+    let baseline = trailing.iter().map(|(_, seconds)| seconds).sum::<f64>() / trailing.len() as f64;
+    let overall = complete.iter().map(|(_, seconds)| seconds).sum::<f64>() / complete.len() as f64;
+
+    let seasonal_factor = |calendar_month: u32| {
+        if complete.len() < SEASONAL_HISTORY || overall <= 0.0 {
+            return 1.0;
+        }
+        let same_month: Vec<f64> = complete
+            .iter()
+            .filter(|(month, _)| month.month() == calendar_month)
+            .map(|(_, seconds)| *seconds)
+            .collect();
+        same_month.iter().sum::<f64>() / same_month.len() as f64 / overall
+    };
+
+    Some(
+        (1..=months)
+            .map(|offset| {
+                let month = current + Months::new(offset);
+                let factor = seasonal_factor(month.month());
+                MonthForecast {
+                    year: month.year(),
+                    month: month.month(),
+                    downtime_seconds: baseline * factor,
+                    seasonal_factor: factor,
+                }
+            })
+            .collect(),
+    )
+}
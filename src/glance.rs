@@ -0,0 +1,120 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration as ChronoDuration, Local};
+use rusqlite::Connection;
+
+use crate::acks;
+use crate::epochs;
+use webgone::model;
+
+/// Characters of a sparkline, from the lowest value to the highest.
+const LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Shown for a period without successful probes.
+const NO_DATA: char = '·';
+
+/// The last day of this machine's connection, for `webgone status`.
+pub struct Glance {
+    /// Average latency per hour of the last 24, oldest first; `None` without successful probes
+    pub hourly_latency_ms: Vec<Option<f64>>,
+    pub p95_latency_ms: Option<f64>,
+    /// Start of the outage the watcher is in, if any
+    pub down_since: Option<DateTime<Local>>,
+    /// End of the last outage, if the connection is up
+    pub up_since: Option<DateTime<Local>>,
+    /// Downtime since midnight, including an ongoing outage
+    pub downtime_today_seconds: i64,
+}
+
+pub fn load(conn: &Connection, now: DateTime<Local>) -> Result<Glance> {
+    let from = now - ChronoDuration::hours(24);
+    let samples = latency_samples(conn, from)?;
+
+    let mut hourly: Vec<(f64, usize)> = vec![(0.0, 0); 24];
+    for (time, latency) in &samples {
+        let hour = ((*time - from).num_seconds() / 3600).clamp(0, 23) as usize;
+        hourly[hour].0 += latency;
+        hourly[hour].1 += 1;
+    }
+    let mut latencies: Vec<f64> = samples.iter().map(|(_, latency)| *latency).collect();
+    latencies.sort_by(f64::total_cmp);
+    let p95_latency_ms = (!latencies.is_empty())
+        .then(|| latencies[((latencies.len() - 1) as f64 * 0.95).round() as usize]);
+
+    let down_since = acks::current(conn)?;
+    let up_since = match down_since {
+        Some(_) => None,
+        None => conn
+            .query_row(
+                "SELECT end_time FROM outages WHERE site IS NULL ORDER BY julianday(end_time) DESC LIMIT 1",
+                [],
+                |row| row.get::<_, String>(0),
+            )
+            .ok()
+            .and_then(|end| model::parse_timestamp(&end)),
+    };
+
+    let midnight = epochs::start_of_day(now.date_naive());
+    let mut stmt = conn.prepare(
+        "SELECT start_time, end_time FROM outages
+        WHERE site IS NULL AND julianday(end_time) > julianday(?1)",
+    )?;
+    let rows = stmt
+        .query_map([midnight.to_rfc3339()], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut downtime_today_seconds: i64 = rows
+        .iter()
+        .filter_map(|(start, end)| Some((model::parse_timestamp(start)?, model::parse_timestamp(end)?)))
+        .map(|(start, end)| (end.min(now) - start.max(midnight)).num_seconds().max(0))
+        .sum();
+    if let Some(since) = down_since {
+        downtime_today_seconds += (now - since.max(midnight)).num_seconds().max(0);
+    }
+
+    Ok(Glance {
+        hourly_latency_ms: hourly
+            .into_iter()
+            .map(|(total, count)| (count > 0).then(|| total / count as f64))
+            .collect(),
+        p95_latency_ms,
+        down_since,
+        up_since,
+        downtime_today_seconds,
+    })
+}
+
+/// Latencies of successful probes since `from`. Every probe is in `probes`;
+/// watchers of earlier versions only recorded a single target's checks with
+/// `--record-probes`.
+fn latency_samples(conn: &Connection, from: DateTime<Local>) -> Result<Vec<(DateTime<Local>, f64)>> {
+    let mut samples = Vec::new();
+    for query in [
+        "SELECT time, latency_ms FROM probes
+        WHERE site IS NULL AND success AND latency_ms IS NOT NULL AND julianday(time) >= julianday(?1)",
+        "SELECT time, latency_ms FROM probe_history WHERE success AND julianday(time) >= julianday(?1)",
+    ] {
+        let mut stmt = conn.prepare(query)?;
+        let rows = stmt
+            .query_map([from.to_rfc3339()], |row| Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        samples.extend(rows.into_iter().filter_map(|(time, latency)| Some((model::parse_timestamp(&time)?, latency))));
+        // Both tables cover the same checks when --record-probes is used with them
+        if !samples.is_empty() {
+            break;
+        }
+    }
+    Ok(samples)
+}
+
+/// One character per value, scaled between the lowest and highest value.
+pub fn sparkline(values: &[Option<f64>]) -> String {
+    let known = values.iter().flatten();
+    let (min, max) = known.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), value| (min.min(*value), max.max(*value)));
+    values
+        .iter()
+        .map(|value| match value {
+            None => NO_DATA,
+            Some(_) if max <= min => LEVELS[0],
+            Some(value) => LEVELS[(((value - min) / (max - min)) * (LEVELS.len() - 1) as f64).round() as usize],
+        })
+        .collect()
+}
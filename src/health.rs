@@ -0,0 +1,142 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::time::Duration;
+
+/// Internal health metrics of a running watcher. They are saved to the
+/// database periodically, so `webgone status` can show them from another process.
+pub struct WatcherMetrics {
+    pub started_at: DateTime<Local>,
+    pub iterations: u64,
+    /// How much longer than the interval the last iteration of the probe loop took
+    pub loop_lag: Duration,
+    pub max_loop_lag: Duration,
+    /// Time the last connectivity check took, including retries
+    pub check_duration: Duration,
+    /// Time the last write of buffered probes took
+    pub db_write_latency: Option<Duration>,
+    pub max_db_write_latency: Option<Duration>,
+    /// Probe results waiting in the write buffer
+    pub queue_depth: usize,
+    pub weather_failures: u64,
+    /// Webhook, Discord, email, command and desktop notifications that failed
+    pub notification_failures: u64,
+}
+
+/// Metrics as last saved by a watcher.
+pub struct WatcherStatus {
+    pub updated_at: DateTime<Local>,
+    pub metrics: WatcherMetrics,
+}
+
+impl WatcherMetrics {
+    pub fn new() -> Self {
+        WatcherMetrics {
+            started_at: Local::now(),
+            iterations: 0,
+            loop_lag: Duration::ZERO,
+            max_loop_lag: Duration::ZERO,
+            check_duration: Duration::ZERO,
+            db_write_latency: None,
+            max_db_write_latency: None,
+            queue_depth: 0,
+            weather_failures: 0,
+            notification_failures: 0,
+        }
+    }
+
+    /// Records the time between the starts of two loop iterations.
+    pub fn record_iteration(&mut self, elapsed: Duration, interval: Duration) {
+        self.iterations += 1;
+        self.loop_lag = elapsed.saturating_sub(interval);
+        self.max_loop_lag = self.max_loop_lag.max(self.loop_lag);
+    }
+
+    pub fn record_db_write(&mut self, latency: Option<Duration>) {
+        self.db_write_latency = latency;
+        self.max_db_write_latency = self.max_db_write_latency.max(latency);
+    }
+}
+
+pub fn init_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS watcher_status (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            pid INTEGER NOT NULL,
+            started_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            iterations INTEGER NOT NULL,
+            loop_lag_ms REAL NOT NULL,
+            max_loop_lag_ms REAL NOT NULL,
+            check_ms REAL NOT NULL,
+            db_write_ms REAL,
+            max_db_write_ms REAL,
+            queue_depth INTEGER NOT NULL,
+            weather_failures INTEGER NOT NULL,
+            notification_failures INTEGER NOT NULL DEFAULT 0
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn from_millis(ms: f64) -> Duration {
+    Duration::from_secs_f64(ms.max(0.0) / 1000.0)
+}
+
+pub fn save(conn: &Connection, metrics: &WatcherMetrics) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO watcher_status (
+            id, pid, started_at, updated_at, iterations, loop_lag_ms, max_loop_lag_ms,
+            check_ms, db_write_ms, max_db_write_ms, queue_depth, weather_failures, notification_failures
+        ) VALUES (1, ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![
+            std::process::id(),
+            metrics.started_at.to_rfc3339(),
+            Local::now().to_rfc3339(),
+            metrics.iterations,
+            millis(metrics.loop_lag),
+            millis(metrics.max_loop_lag),
+            millis(metrics.check_duration),
+            metrics.db_write_latency.map(millis),
+            metrics.max_db_write_latency.map(millis),
+            metrics.queue_depth,
+            metrics.weather_failures,
+            metrics.notification_failures,
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn load(conn: &Connection) -> Result<Option<WatcherStatus>> {
+    let parse_time = |value: String| {
+        DateTime::parse_from_rfc3339(&value)
+            .map(|time| time.with_timezone(&Local))
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e)))
+    };
+
+    let status = conn
+        .query_row("SELECT * FROM watcher_status WHERE id = 1", [], |row| {
+            Ok(WatcherStatus {
+                updated_at: parse_time(row.get("updated_at")?)?,
+                metrics: WatcherMetrics {
+                    started_at: parse_time(row.get("started_at")?)?,
+                    iterations: row.get("iterations")?,
+                    loop_lag: from_millis(row.get("loop_lag_ms")?),
+                    max_loop_lag: from_millis(row.get("max_loop_lag_ms")?),
+                    check_duration: from_millis(row.get("check_ms")?),
+                    db_write_latency: row.get::<_, Option<f64>>("db_write_ms")?.map(from_millis),
+                    max_db_write_latency: row.get::<_, Option<f64>>("max_db_write_ms")?.map(from_millis),
+                    queue_depth: row.get("queue_depth")?,
+                    weather_failures: row.get("weather_failures")?,
+                    notification_failures: row.get("notification_failures")?,
+                },
+            })
+        })
+        .optional()?;
+    Ok(status)
+}
@@ -0,0 +1,146 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use rusqlite::{params, Connection};
+
+use crate::debounce::{Debounce, Transition};
+use crate::target::ProbeResult;
+use webgone::model::InternetOutage;
+
+/// Number of checks kept by `--record-probes` unless `--probe-history-size` is given.
+pub const DEFAULT_CAPACITY: u64 = 1_000_000;
+
+pub fn init_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS probe_history (
+            id INTEGER PRIMARY KEY,
+            time TEXT NOT NULL,
+            finished_at TEXT NOT NULL,
+            target TEXT NOT NULL,
+            success INTEGER NOT NULL,
+            latency_ms REAL NOT NULL,
+            error TEXT
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Records the outcome of every check, unsampled and in order, so outages
+/// can be derived again later with different thresholds. Only the newest
+/// `capacity` checks are kept.
+pub struct Recorder {
+    capacity: u64,
+}
+
+impl Recorder {
+    pub fn new(capacity: u64) -> Self {
+        Recorder { capacity: capacity.max(1) }
+    }
+
+    /// Stores a check that started at `started` and finished at `finished`,
+    /// decided by `result`, and drops the oldest checks beyond the capacity.
+    pub fn record(
+        &self,
+        conn: &Connection,
+        started: DateTime<Local>,
+        finished: DateTime<Local>,
+        result: &ProbeResult,
+    ) -> Result<()> {
+        conn.execute(
+            "INSERT INTO probe_history (time, finished_at, target, success, latency_ms, error)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                started.to_rfc3339(),
+                finished.to_rfc3339(),
+                result.target.to_string(),
+                result.is_success(),
+                result.elapsed.as_secs_f64() * 1000.0,
+                result.error,
+            ],
+        )?;
+        // Row ids only grow while the newest row is kept, so this keeps exactly `capacity` rows
+        let newest = conn.last_insert_rowid();
+        conn.execute(
+            "DELETE FROM probe_history WHERE id <= ?",
+            [newest.saturating_sub(self.capacity as i64)],
+        )?;
+        Ok(())
+    }
+}
+
+/// A recorded check, as far as deriving outages is concerned.
+pub struct Check {
+    pub started: DateTime<Local>,
+    pub finished: DateTime<Local>,
+    pub success: bool,
+}
+
+/// All recorded checks, oldest first.
+pub fn load(conn: &Connection) -> Result<Vec<Check>> {
+    let mut stmt = conn.prepare("SELECT time, finished_at, success FROM probe_history ORDER BY id")?;
+    let mut rows = stmt.query([])?;
+    let mut checks = Vec::new();
+    while let Some(row) = rows.next()? {
+        let started: String = row.get(0)?;
+        let finished: String = row.get(1)?;
+        checks.push(Check {
+            started: DateTime::parse_from_rfc3339(&started)?.with_timezone(&Local),
+            finished: DateTime::parse_from_rfc3339(&finished)?.with_timezone(&Local),
+            success: row.get(2)?,
+        });
+    }
+    Ok(checks)
+}
+
+/// Derives outages from recorded checks the way the watcher does with the
+/// given thresholds. An outage still going on after the last check is left out.
+pub fn derive(checks: &[Check], fail_threshold: u32, recover_threshold: u32) -> Vec<InternetOutage> {
+    let mut debounce = Debounce::new(fail_threshold, recover_threshold);
+    let mut outages = Vec::new();
+    let mut lost: Option<(DateTime<Local>, DateTime<Local>, DateTime<Local>)> = None;
+
+    for check in checks {
+        match debounce.record(check.started, check.finished, check.success) {
+            Some(Transition::Lost { since, seen }) => lost = Some((since, seen, check.finished)),
+            Some(Transition::Restored { since }) => {
+                if let Some((start_time, detected, confirmed)) = lost.take() {
+                    outages.push(InternetOutage {
+                        id: 0,
+                        site: None,
+                        start_time,
+                        detected_at: Some(detected),
+                        confirmed_at: Some(confirmed),
+                        end_time: since,
+                        recovered_at: Some(check.finished),
+                        duration_seconds: (since - start_time).num_seconds(),
+                        wifi: None,
+                        weather: None,
+                        tags: Vec::new(),
+                        capture_path: None,
+                        failed_components: None,
+                        actor: None,
+                        clock_anomaly: false,
+                        failed_probes: None,
+                        severity: None,
+                        derived_from: None,
+                    });
+                }
+            }
+            None => {}
+        }
+    }
+    outages
+}
+
+/// Number of recorded checks and the time of the oldest one.
+pub fn summary(conn: &Connection) -> Result<Option<(i64, DateTime<Local>)>> {
+    let (count, oldest): (i64, Option<String>) = conn.query_row(
+        "SELECT COUNT(*), (SELECT time FROM probe_history ORDER BY id LIMIT 1) FROM probe_history",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    Ok(match oldest {
+        Some(oldest) => Some((count, DateTime::parse_from_rfc3339(&oldest)?.with_timezone(&Local))),
+        None => None,
+    })
+}
@@ -0,0 +1,716 @@
+use clap::ValueEnum;
+use std::env;
+
+/// Language of user-facing report output.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Lang {
+    En,
+    De,
+}
+
+/// Translatable report strings.
+#[derive(Clone, Copy)]
+pub enum Text {
+    StatsTitle,
+    TotalOutages,
+    TotalOutageDuration,
+    AverageOutageDuration,
+    LongestOutage,
+    ShortestOutage,
+    AverageDetectionDelay,
+    AverageRecoveryDelay,
+    Seconds,
+    StartTime,
+    EndTime,
+    ConfirmedAt,
+    DurationSeconds,
+    WiFi,
+    Weather,
+    SystemEventsAround,
+    MonthlyCostAnalysis,
+    Year,
+    Month,
+    Outages,
+    TotalTime,
+    PercentDowntime,
+    CostImpact,
+    RatePerHour,
+    Summary,
+    Metric,
+    Value,
+    TotalCost,
+    AverageMonthlyCost,
+    TotalDowntime,
+    Hours,
+    HoursPerMonthAvg,
+    CostPerHourOfDowntime,
+    NoOutages,
+    PerTargetHealth,
+    Target,
+    Failures,
+    MedianLatency24h,
+    NoProbes,
+    WatcherStatus,
+    Watcher,
+    RunningWithPid,
+    NotRunning,
+    LastUpdate,
+    RunningSince,
+    LoopIterations,
+    LoopLag,
+    CheckDuration,
+    DbWriteLatency,
+    WriteQueueDepth,
+    WeatherFailures,
+    NotificationFailures,
+    ProbeHistory,
+    ChecksSince,
+    Max,
+    NoWatcherStatus,
+    Id,
+    Tag,
+    Tags,
+    TagSummary,
+    NoTags,
+    SupportTickets,
+    Ticket,
+    Status,
+    StatusOpen,
+    StatusClosed,
+    Opened,
+    Closed,
+    Note,
+    NoTickets,
+    ApiTokens,
+    Name,
+    Scope,
+    Created,
+    LastUsed,
+    NoTokens,
+    Site,
+    SiteOverview,
+    SiteComparison,
+    LastSeen,
+    Uptime,
+    Days,
+    StateUp,
+    StateDown,
+    StateStale,
+    NoSites,
+    CustomMetrics,
+    Count,
+    Average,
+    Minimum,
+    Maximum,
+    LastValue,
+    Time,
+    Message,
+    ScriptEvents,
+    NoCustomMetrics,
+    DailyDigest,
+    WeeklyDigest,
+    CostForecast,
+    ExpectedDowntime,
+    ExpectedCost,
+    Seasonality,
+    ForecastBasis,
+    NotEnoughHistory,
+    Epoch,
+    EpochComparison,
+    Since,
+    DayCount,
+    OutagesPerMonth,
+    AverageLatency,
+    BeforeFirstEpoch,
+    NoEpochs,
+    Acknowledged,
+    Capture,
+    FailedComponents,
+    NetworkLocations,
+    GatewayMac,
+    Action,
+    NoLocations,
+    DnsBenchmark,
+    Resolver,
+    Address,
+    Median,
+    NoDnsBenchmarks,
+    RouteChanges,
+    Anchor,
+    ChangedHops,
+    Hops,
+    LatencyBefore,
+    LatencyAfter,
+    NoRouteChanges,
+    WeeklyTrend,
+    Week,
+    Loss,
+    Change,
+    DatabaseDiff,
+    DowntimeSeconds,
+    Probes,
+    FailedProbes,
+    SchemaVersion,
+    Difference,
+    OnlyIn,
+    Changed,
+    NoDifferences,
+    AuditLog,
+    Actor,
+    Command,
+    Ids,
+    Details,
+    NoAuditEntries,
+    ReportedBy,
+    MonitoringGaps,
+    GapsExcluded,
+    GapsCountedAsDown,
+    GapsCountedAsUp,
+    GapsLocalOnly,
+    HeatScore,
+    HeatScoreNote,
+    WeeklyDowntime,
+    Downtime,
+    WorstDay,
+    BarsScale,
+    Last24Hours,
+    HourlyLatency,
+    LatencyP95,
+    UpSince,
+    DownSince,
+    DowntimeToday,
+    PlannedDowntimeTitle,
+    NoPlannedDowntime,
+    Duration,
+    StatePlanned,
+    StateInProgress,
+    StateOver,
+    SearchResults,
+    FoundIn,
+    NoSearchResults,
+    MonitoredPeriods,
+    NoMonitoredPeriods,
+    Host,
+    OperatingSystem,
+    Version,
+    Configuration,
+    LatencyReport,
+    NoLatency,
+    AllTargets,
+    EstimatedPercentiles,
+    DegradedPeriods,
+    NoDegradedPeriods,
+    DegradedStatsTitle,
+    DegradedPeriodCount,
+    TotalDegradedTime,
+    LongestDegradedPeriod,
+    AverageLoss,
+    WorstLoss,
+    QualityTitle,
+    NoQualityScores,
+    Score,
+    P95Latency,
+    Jitter,
+    MeasuredDays,
+    BaselinesTitle,
+    NoBaselines,
+    Region,
+    Source,
+    Severity,
+    ImpactTitle,
+    NoImpacts,
+    Outage,
+    Impact,
+    Impacts,
+    Amount,
+    TotalImpactCost,
+    TotalCostWithImpact,
+    ImpactSummary,
+}
+
+impl Lang {
+    /// Picks the language from the usual locale environment variables,
+    /// falling back to English.
+    pub fn detect() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .map(|locale| if locale.to_lowercase().starts_with("de") { Lang::De } else { Lang::En })
+            .unwrap_or(Lang::En)
+    }
+
+    pub fn month_name(self, month: u32) -> &'static str {
+        const EN: [&str; 12] = [
+            "January", "February", "March", "April", "May", "June",
+            "July", "August", "September", "October", "November", "December",
+        ];
+        const DE: [&str; 12] = [
+            "Januar", "Februar", "März", "April", "Mai", "Juni",
+            "Juli", "August", "September", "Oktober", "November", "Dezember",
+        ];
+
+        let names = match self {
+            Lang::En => &EN,
+            Lang::De => &DE,
+        };
+        match month {
+            1..=12 => names[month as usize - 1],
+            _ => match self {
+                Lang::En => "Unknown",
+                Lang::De => "Unbekannt",
+            },
+        }
+    }
+
+    pub fn text(self, text: Text) -> &'static str {
+        match self {
+            Lang::En => english(text),
+            Lang::De => german(text),
+        }
+    }
+}
+
+fn english(text: Text) -> &'static str {
+    match text {
+        Text::StatsTitle => "Internet Outage Statistics:",
+        Text::TotalOutages => "Total number of outages",
+        Text::TotalOutageDuration => "Total outage duration",
+        Text::AverageOutageDuration => "Average outage duration",
+        Text::LongestOutage => "Longest outage",
+        Text::ShortestOutage => "Shortest outage",
+        Text::AverageDetectionDelay => "Average detection delay",
+        Text::AverageRecoveryDelay => "Average recovery delay",
+        Text::Seconds => "seconds",
+        Text::StartTime => "Start Time",
+        Text::EndTime => "End Time",
+        Text::ConfirmedAt => "Confirmed At",
+        Text::DurationSeconds => "Duration (seconds)",
+        Text::WiFi => "Wi-Fi",
+        Text::Weather => "Weather",
+        Text::SystemEventsAround => "System events around outage at",
+        Text::MonthlyCostAnalysis => "Monthly Cost Analysis:",
+        Text::Year => "Year",
+        Text::Month => "Month",
+        Text::Outages => "Outages",
+        Text::TotalTime => "Total Time",
+        Text::PercentDowntime => "% Downtime",
+        Text::CostImpact => "Cost Impact",
+        Text::RatePerHour => "Rate/Hour",
+        Text::Summary => "Summary:",
+        Text::Metric => "Metric",
+        Text::Value => "Value",
+        Text::TotalCost => "Total cost of outages",
+        Text::AverageMonthlyCost => "Average monthly cost",
+        Text::TotalDowntime => "Total downtime",
+        Text::Hours => "hours",
+        Text::HoursPerMonthAvg => "hours/month avg",
+        Text::CostPerHourOfDowntime => "Cost per hour of downtime",
+        Text::NoOutages => "No outages recorded yet.",
+        Text::PerTargetHealth => "Per-Target Health:",
+        Text::Target => "Target",
+        Text::Failures => "Failures",
+        Text::MedianLatency24h => "Median Latency 24h",
+        Text::NoProbes => {
+            "No probes recorded in the last 7 days. They are recorded while `webgone watch` runs."
+        }
+        Text::WatcherStatus => "Watcher Status:",
+        Text::Watcher => "Watcher",
+        Text::RunningWithPid => "running, pid",
+        Text::NotRunning => "not running",
+        Text::LastUpdate => "Last update",
+        Text::RunningSince => "Running since",
+        Text::LoopIterations => "Loop iterations",
+        Text::LoopLag => "Loop lag",
+        Text::CheckDuration => "Check duration",
+        Text::DbWriteLatency => "Database write latency",
+        Text::WriteQueueDepth => "Write queue depth",
+        Text::WeatherFailures => "Weather refresh failures",
+        Text::NotificationFailures => "Notification failures",
+        Text::ProbeHistory => "Recorded checks",
+        Text::ChecksSince => "checks since",
+        Text::Max => "max",
+        Text::NoWatcherStatus => "No watcher status recorded yet. Start monitoring with `webgone watch`.",
+        Text::Id => "ID",
+        Text::Tag => "Tag",
+        Text::Tags => "Tags",
+        Text::TagSummary => "Tag Summary:",
+        Text::NoTags => "No outages tagged yet. Tag one with `webgone tag <id> <tag>...`.",
+        Text::SupportTickets => "Support Tickets:",
+        Text::Ticket => "Ticket",
+        Text::Status => "Status",
+        Text::StatusOpen => "open",
+        Text::StatusClosed => "closed",
+        Text::Opened => "Opened",
+        Text::Closed => "Closed",
+        Text::Note => "Note",
+        Text::NoTickets => "No tickets recorded yet. Open one with `webgone ticket open <reference>`.",
+        Text::ApiTokens => "API Tokens:",
+        Text::Name => "Name",
+        Text::Scope => "Scope",
+        Text::Created => "Created",
+        Text::LastUsed => "Last used",
+        Text::NoTokens => "No API tokens yet. Create one with `webgone token create --scope read`.",
+        Text::Site => "Site",
+        Text::SiteOverview => "Uptime by Site",
+        Text::SiteComparison => "Downtime by Site (outages):",
+        Text::LastSeen => "Last seen",
+        Text::Uptime => "Uptime",
+        Text::Days => "days",
+        Text::StateUp => "up",
+        Text::StateDown => "down",
+        Text::StateStale => "no recent data",
+        Text::NoSites => "No data recorded yet. Start monitoring with `webgone watch` or connect agents with `webgone collect`.",
+        Text::CustomMetrics => "Custom Metrics",
+        Text::Count => "Count",
+        Text::Average => "Average",
+        Text::Minimum => "Min",
+        Text::Maximum => "Max",
+        Text::LastValue => "Last value",
+        Text::Time => "Time",
+        Text::Message => "Message",
+        Text::ScriptEvents => "Script Events:",
+        Text::NoCustomMetrics => "No custom metrics recorded yet. Run `webgone watch --script <file.rhai>` with a script that calls metric() or event().",
+        Text::DailyDigest => "Daily digest",
+        Text::WeeklyDigest => "Weekly digest",
+        Text::CostForecast => "Cost Forecast:",
+        Text::ExpectedDowntime => "Expected Downtime",
+        Text::ExpectedCost => "Expected cost",
+        Text::Seasonality => "Season",
+        Text::ForecastBasis => "Based on the average of the last three complete months, weighted by season once a year of history is available.",
+        Text::NotEnoughHistory => "Not enough history for a forecast yet; it needs at least one complete month since the first outage.",
+        Text::Epoch => "Epoch",
+        Text::EpochComparison => "Comparison of Epochs:",
+        Text::Since => "Since",
+        Text::DayCount => "Days",
+        Text::OutagesPerMonth => "Outages/30 days",
+        Text::AverageLatency => "Avg Latency",
+        Text::BeforeFirstEpoch => "(before first epoch)",
+        Text::Acknowledged => "Acknowledged",
+        Text::Capture => "Packet Capture",
+        Text::FailedComponents => "Failed",
+        Text::NoEpochs => "No epochs marked yet. Mark one with `webgone epoch add <date> <label>`, e.g. when you switched ISPs.",
+        Text::NetworkLocations => "Network Locations:",
+        Text::GatewayMac => "Gateway MAC",
+        Text::Action => "Action",
+        Text::NoLocations => "No network locations yet. Add one with `webgone location add <name> --ssid <ssid>`.",
+        Text::DnsBenchmark => "DNS Resolver Benchmark:",
+        Text::Resolver => "Resolver",
+        Text::Address => "Address",
+        Text::Median => "Median",
+        Text::NoDnsBenchmarks => "No DNS benchmarks yet. Run one with `webgone dnsbench`.",
+        Text::RouteChanges => "Route Changes:",
+        Text::Anchor => "Anchor",
+        Text::ChangedHops => "Changed hops",
+        Text::Hops => "Hops",
+        Text::LatencyBefore => "Latency before",
+        Text::LatencyAfter => "Latency after",
+        Text::NoRouteChanges => "No route changes recorded. Trace routes with `webgone watch --trace-routes`.",
+        Text::WeeklyTrend => "Weekly Latency and Loss:",
+        Text::Week => "Week",
+        Text::Loss => "Loss",
+        Text::Change => "Change",
+        Text::DatabaseDiff => "Database Differences:",
+        Text::DowntimeSeconds => "Downtime (seconds)",
+        Text::Probes => "Probes",
+        Text::FailedProbes => "Failed probes",
+        Text::SchemaVersion => "Schema version",
+        Text::Difference => "Difference",
+        Text::OnlyIn => "only in",
+        Text::Changed => "duration differs",
+        Text::NoDifferences => "The databases contain the same outages and summary values.",
+        Text::AuditLog => "Audit Log:",
+        Text::Actor => "Actor",
+        Text::Command => "Command",
+        Text::Ids => "Ids",
+        Text::Details => "Details",
+        Text::NoAuditEntries => "No changes recorded yet.",
+        Text::ReportedBy => "Reported by",
+        Text::MonitoringGaps => "Monitoring Gaps",
+        Text::GapsExcluded => "Uptime leaves out time without monitoring (--gaps exclude).",
+        Text::GapsCountedAsDown => "Time without monitoring counts as downtime (--gaps down).",
+        Text::GapsCountedAsUp => "Time without monitoring counts as uptime (--gaps up).",
+        Text::GapsLocalOnly => "Gaps are only known for this machine; agents don't report theirs.",
+        Text::HeatScore => "Heat Score",
+        Text::HeatScoreNote => "Heat score: every outage counts as its minutes to the power of 1.5, at least 1, so long outages weigh more than many short ones.",
+        Text::WeeklyDowntime => "Downtime per Week:",
+        Text::Downtime => "Downtime",
+        Text::WorstDay => "Worst Day",
+        Text::BarsScale => "A full bar is the week with the most downtime",
+        Text::Last24Hours => "Last 24 Hours:",
+        Text::HourlyLatency => "Latency per hour",
+        Text::LatencyP95 => "Latency (95th percentile)",
+        Text::UpSince => "Up since",
+        Text::DownSince => "Down since",
+        Text::DowntimeToday => "Downtime today",
+        Text::PlannedDowntimeTitle => "Planned Downtime:",
+        Text::NoPlannedDowntime => "No downtime planned. Add some with `webgone plan add`.",
+        Text::Duration => "Duration",
+        Text::StatePlanned => "planned",
+        Text::StateInProgress => "in progress",
+        Text::StateOver => "over",
+        Text::SearchResults => "Matching Outages:",
+        Text::FoundIn => "Found In",
+        Text::NoSearchResults => "No outages match the search.",
+        Text::MonitoredPeriods => "Monitored Periods:",
+        Text::NoMonitoredPeriods => "No monitored periods in this time. They are recorded while `webgone watch` runs.",
+        Text::Host => "Host",
+        Text::OperatingSystem => "OS",
+        Text::Version => "Version",
+        Text::Configuration => "Configuration",
+        Text::LatencyReport => "Latency of Successful Probes",
+        Text::NoLatency => "No successful probes recorded in this time. They are recorded while `webgone watch` runs.",
+        Text::AllTargets => "All targets",
+        Text::EstimatedPercentiles => "Percentiles include rolled up periods and are estimated from theirs.",
+        Text::DegradedPeriods => "Degraded Periods:",
+        Text::NoDegradedPeriods => "No degraded periods in this time. They are recorded while `webgone watch` runs.",
+        Text::DegradedStatsTitle => "Degraded Connection Statistics:",
+        Text::DegradedPeriodCount => "Degraded periods",
+        Text::TotalDegradedTime => "Total degraded time",
+        Text::LongestDegradedPeriod => "Longest degraded period",
+        Text::AverageLoss => "Average loss",
+        Text::WorstLoss => "Worst loss",
+        Text::QualityTitle => "Connection Quality per Month:",
+        Text::NoQualityScores => "No quality scores yet. Days are scored once they are over.",
+        Text::Score => "Score",
+        Text::P95Latency => "P95 latency",
+        Text::Jitter => "Jitter",
+        Text::MeasuredDays => "Days",
+        Text::BaselinesTitle => "Baselines:",
+        Text::NoBaselines => "No baselines imported. Import one with `webgone baseline import`.",
+        Text::Region => "Region",
+        Text::Source => "Source",
+        Text::Severity => "Severity",
+        Text::ImpactTitle => "Outage Impact:",
+        Text::NoImpacts => "No impact recorded. Attach some to an outage with `webgone impact add`.",
+        Text::Outage => "Outage",
+        Text::Impact => "Impact",
+        Text::Impacts => "Impacts",
+        Text::Amount => "Amount",
+        Text::TotalImpactCost => "Cost of recorded impact",
+        Text::TotalCostWithImpact => "Total cost including impact",
+        Text::ImpactSummary => "Impact of Outages:",
+    }
+}
+
+fn german(text: Text) -> &'static str {
+    match text {
+        Text::StatsTitle => "Statistik der Internetausfälle:",
+        Text::TotalOutages => "Anzahl der Ausfälle",
+        Text::TotalOutageDuration => "Gesamtdauer der Ausfälle",
+        Text::AverageOutageDuration => "Durchschnittliche Ausfalldauer",
+        Text::LongestOutage => "Längster Ausfall",
+        Text::ShortestOutage => "Kürzester Ausfall",
+        Text::AverageDetectionDelay => "Durchschnittliche Erkennungsverzögerung",
+        Text::AverageRecoveryDelay => "Durchschnittliche Verzögerung bis zur Entwarnung",
+        Text::Seconds => "Sekunden",
+        Text::StartTime => "Beginn",
+        Text::EndTime => "Ende",
+        Text::ConfirmedAt => "Bestätigt",
+        Text::DurationSeconds => "Dauer (Sekunden)",
+        Text::WiFi => "WLAN",
+        Text::Weather => "Wetter",
+        Text::SystemEventsAround => "Systemereignisse um den Ausfall am",
+        Text::MonthlyCostAnalysis => "Monatliche Kostenanalyse:",
+        Text::Year => "Jahr",
+        Text::Month => "Monat",
+        Text::Outages => "Ausfälle",
+        Text::TotalTime => "Gesamtzeit",
+        Text::PercentDowntime => "% Ausfallzeit",
+        Text::CostImpact => "Kosten",
+        Text::RatePerHour => "Preis/Stunde",
+        Text::Summary => "Zusammenfassung:",
+        Text::Metric => "Kennzahl",
+        Text::Value => "Wert",
+        Text::TotalCost => "Gesamtkosten der Ausfälle",
+        Text::AverageMonthlyCost => "Durchschnittliche Kosten pro Monat",
+        Text::TotalDowntime => "Gesamte Ausfallzeit",
+        Text::Hours => "Stunden",
+        Text::HoursPerMonthAvg => "Stunden/Monat im Schnitt",
+        Text::CostPerHourOfDowntime => "Kosten pro Stunde Ausfallzeit",
+        Text::NoOutages => "Bisher wurden keine Ausfälle aufgezeichnet.",
+        Text::PerTargetHealth => "Zustand je Ziel:",
+        Text::Target => "Ziel",
+        Text::Failures => "Fehler",
+        Text::MedianLatency24h => "Median-Latenz 24h",
+        Text::NoProbes => {
+            "In den letzten 7 Tagen wurden keine Prüfungen aufgezeichnet. Sie werden aufgezeichnet, während `webgone watch` läuft."
+        }
+        Text::WatcherStatus => "Status der Überwachung:",
+        Text::Watcher => "Überwachung",
+        Text::RunningWithPid => "läuft, PID",
+        Text::NotRunning => "läuft nicht",
+        Text::LastUpdate => "Letzte Aktualisierung",
+        Text::RunningSince => "Läuft seit",
+        Text::LoopIterations => "Schleifendurchläufe",
+        Text::LoopLag => "Schleifenverzögerung",
+        Text::CheckDuration => "Dauer der Prüfung",
+        Text::DbWriteLatency => "Schreiblatenz der Datenbank",
+        Text::WriteQueueDepth => "Länge der Schreibwarteschlange",
+        Text::WeatherFailures => "Fehlgeschlagene Wetterabrufe",
+        Text::NotificationFailures => "Fehlgeschlagene Benachrichtigungen",
+        Text::ProbeHistory => "Aufgezeichnete Prüfungen",
+        Text::ChecksSince => "Prüfungen seit",
+        Text::Max => "max.",
+        Text::NoWatcherStatus => "Bisher wurde kein Status aufgezeichnet. Starte die Überwachung mit `webgone watch`.",
+        Text::Id => "ID",
+        Text::Tag => "Tag",
+        Text::Tags => "Tags",
+        Text::TagSummary => "Übersicht der Tags:",
+        Text::NoTags => "Bisher wurden keine Ausfälle getaggt. Tagge einen mit `webgone tag <id> <tag>...`.",
+        Text::SupportTickets => "Support-Tickets:",
+        Text::Ticket => "Ticket",
+        Text::Status => "Status",
+        Text::StatusOpen => "offen",
+        Text::StatusClosed => "geschlossen",
+        Text::Opened => "Eröffnet",
+        Text::Closed => "Geschlossen",
+        Text::Note => "Notiz",
+        Text::NoTickets => "Bisher wurden keine Tickets erfasst. Eröffne eines mit `webgone ticket open <referenz>`.",
+        Text::ApiTokens => "API-Tokens:",
+        Text::Name => "Name",
+        Text::Scope => "Berechtigung",
+        Text::Created => "Erstellt",
+        Text::LastUsed => "Zuletzt benutzt",
+        Text::NoTokens => "Bisher gibt es keine API-Tokens. Erstelle eines mit `webgone token create --scope read`.",
+        Text::Site => "Standort",
+        Text::SiteOverview => "Verfügbarkeit nach Standort",
+        Text::SiteComparison => "Ausfallzeit nach Standort (Ausfälle):",
+        Text::LastSeen => "Zuletzt gesehen",
+        Text::Uptime => "Verfügbarkeit",
+        Text::Days => "Tage",
+        Text::StateUp => "verbunden",
+        Text::StateDown => "gestört",
+        Text::StateStale => "keine aktuellen Daten",
+        Text::NoSites => "Bisher wurden keine Daten aufgezeichnet. Starte die Überwachung mit `webgone watch` oder verbinde Agenten mit `webgone collect`.",
+        Text::CustomMetrics => "Eigene Kennzahlen",
+        Text::Count => "Anzahl",
+        Text::Average => "Durchschnitt",
+        Text::Minimum => "Min",
+        Text::Maximum => "Max",
+        Text::LastValue => "Letzter Wert",
+        Text::Time => "Zeit",
+        Text::Message => "Meldung",
+        Text::ScriptEvents => "Skript-Ereignisse:",
+        Text::NoCustomMetrics => "Bisher wurden keine eigenen Kennzahlen aufgezeichnet. Starte `webgone watch --script <datei.rhai>` mit einem Skript, das metric() oder event() aufruft.",
+        Text::DailyDigest => "Tägliche Zusammenfassung",
+        Text::WeeklyDigest => "Wöchentliche Zusammenfassung",
+        Text::CostForecast => "Kostenprognose:",
+        Text::ExpectedDowntime => "Erwartete Ausfallzeit",
+        Text::ExpectedCost => "Erwartete Kosten",
+        Text::Seasonality => "Saison",
+        Text::ForecastBasis => "Basiert auf dem Durchschnitt der letzten drei vollständigen Monate, nach Saison gewichtet, sobald ein Jahr an Daten vorliegt.",
+        Text::NotEnoughHistory => "Noch nicht genug Daten für eine Prognose; dafür ist mindestens ein vollständiger Monat seit dem ersten Ausfall nötig.",
+        Text::Epoch => "Abschnitt",
+        Text::EpochComparison => "Vergleich der Abschnitte:",
+        Text::Since => "Seit",
+        Text::DayCount => "Tage",
+        Text::OutagesPerMonth => "Ausfälle/30 Tage",
+        Text::AverageLatency => "Mittl. Latenz",
+        Text::BeforeFirstEpoch => "(vor dem ersten Abschnitt)",
+        Text::Acknowledged => "Bestätigt",
+        Text::Capture => "Paketmitschnitt",
+        Text::FailedComponents => "Ausgefallen",
+        Text::NoEpochs => "Noch keine Abschnitte markiert. Markiere einen mit `webgone epoch add <Datum> <Bezeichnung>`, z. B. beim Wechsel des Anbieters.",
+        Text::NetworkLocations => "Netzwerkstandorte:",
+        Text::GatewayMac => "Gateway-MAC",
+        Text::Action => "Aktion",
+        Text::NoLocations => "Bisher gibt es keine Netzwerkstandorte. Füge einen mit `webgone location add <Name> --ssid <SSID>` hinzu.",
+        Text::DnsBenchmark => "Vergleich der DNS-Resolver:",
+        Text::Resolver => "Resolver",
+        Text::Address => "Adresse",
+        Text::Median => "Median",
+        Text::NoDnsBenchmarks => "Bisher gibt es keine DNS-Vergleiche. Starte einen mit `webgone dnsbench`.",
+        Text::RouteChanges => "Routenänderungen:",
+        Text::Anchor => "Ziel",
+        Text::ChangedHops => "Geänderte Hops",
+        Text::Hops => "Hops",
+        Text::LatencyBefore => "Latenz vorher",
+        Text::LatencyAfter => "Latenz nachher",
+        Text::NoRouteChanges => "Bisher wurden keine Routenänderungen erfasst. Verfolge Routen mit `webgone watch --trace-routes`.",
+        Text::WeeklyTrend => "Wöchentliche Latenz und Verluste:",
+        Text::Week => "Woche",
+        Text::Loss => "Verlust",
+        Text::Change => "Änderung",
+        Text::DatabaseDiff => "Unterschiede der Datenbanken:",
+        Text::DowntimeSeconds => "Ausfallzeit (Sekunden)",
+        Text::Probes => "Prüfungen",
+        Text::FailedProbes => "Fehlgeschlagene Prüfungen",
+        Text::SchemaVersion => "Schemaversion",
+        Text::Difference => "Differenz",
+        Text::OnlyIn => "nur in",
+        Text::Changed => "Dauer weicht ab",
+        Text::NoDifferences => "Die Datenbanken enthalten dieselben Ausfälle und Kennzahlen.",
+        Text::AuditLog => "Änderungsprotokoll:",
+        Text::Actor => "Akteur",
+        Text::Command => "Befehl",
+        Text::Ids => "IDs",
+        Text::Details => "Details",
+        Text::NoAuditEntries => "Bisher wurden keine Änderungen erfasst.",
+        Text::ReportedBy => "Gemeldet von",
+        Text::MonitoringGaps => "Überwachungslücken",
+        Text::GapsExcluded => "Zeit ohne Überwachung fließt nicht in die Verfügbarkeit ein (--gaps exclude).",
+        Text::GapsCountedAsDown => "Zeit ohne Überwachung zählt als Ausfallzeit (--gaps down).",
+        Text::GapsCountedAsUp => "Zeit ohne Überwachung zählt als verfügbar (--gaps up).",
+        Text::GapsLocalOnly => "Lücken sind nur für diesen Rechner bekannt; Agenten melden ihre nicht.",
+        Text::HeatScore => "Auswirkung",
+        Text::HeatScoreNote => "Auswirkung: jeder Ausfall zählt mit seinen Minuten hoch 1,5, mindestens 1, sodass lange Ausfälle schwerer wiegen als viele kurze.",
+        Text::WeeklyDowntime => "Ausfallzeit pro Woche:",
+        Text::Downtime => "Ausfallzeit",
+        Text::WorstDay => "Schlimmster Tag",
+        Text::BarsScale => "Ein voller Balken ist die Woche mit der meisten Ausfallzeit",
+        Text::Last24Hours => "Letzte 24 Stunden:",
+        Text::HourlyLatency => "Latenz pro Stunde",
+        Text::LatencyP95 => "Latenz (95. Perzentil)",
+        Text::UpSince => "Online seit",
+        Text::DownSince => "Offline seit",
+        Text::DowntimeToday => "Ausfallzeit heute",
+        Text::PlannedDowntimeTitle => "Geplante Ausfallzeiten:",
+        Text::NoPlannedDowntime => "Keine Ausfallzeit geplant. Füge eine mit `webgone plan add` hinzu.",
+        Text::Duration => "Dauer",
+        Text::StatePlanned => "geplant",
+        Text::StateInProgress => "läuft",
+        Text::StateOver => "vorbei",
+        Text::SearchResults => "Passende Ausfälle:",
+        Text::FoundIn => "Gefunden in",
+        Text::NoSearchResults => "Keine Ausfälle passen zur Suche.",
+        Text::MonitoredPeriods => "Überwachte Zeiträume:",
+        Text::NoMonitoredPeriods => "Keine überwachten Zeiträume in dieser Zeit. Sie werden aufgezeichnet, während `webgone watch` läuft.",
+        Text::Host => "Rechner",
+        Text::OperatingSystem => "Betriebssystem",
+        Text::Version => "Version",
+        Text::Configuration => "Konfiguration",
+        Text::LatencyReport => "Latenz erfolgreicher Prüfungen",
+        Text::NoLatency => "In dieser Zeit wurden keine erfolgreichen Prüfungen aufgezeichnet. Sie werden aufgezeichnet, während `webgone watch` läuft.",
+        Text::AllTargets => "Alle Ziele",
+        Text::EstimatedPercentiles => "Die Perzentile enthalten zusammengefasste Zeiträume und sind aus deren Perzentilen geschätzt.",
+        Text::DegradedPeriods => "Beeinträchtigte Zeiträume:",
+        Text::NoDegradedPeriods => "Keine beeinträchtigten Zeiträume in dieser Zeit. Sie werden aufgezeichnet, während `webgone watch` läuft.",
+        Text::DegradedStatsTitle => "Statistik der beeinträchtigten Verbindung:",
+        Text::DegradedPeriodCount => "Beeinträchtigte Zeiträume",
+        Text::TotalDegradedTime => "Gesamte beeinträchtigte Zeit",
+        Text::LongestDegradedPeriod => "Längster beeinträchtigter Zeitraum",
+        Text::AverageLoss => "Durchschnittlicher Verlust",
+        Text::WorstLoss => "Höchster Verlust",
+        Text::QualityTitle => "Verbindungsqualität pro Monat:",
+        Text::NoQualityScores => "Noch keine Qualitätswerte. Tage werden bewertet, sobald sie vorbei sind.",
+        Text::Score => "Wert",
+        Text::P95Latency => "P95-Latenz",
+        Text::Jitter => "Jitter",
+        Text::MeasuredDays => "Tage",
+        Text::BaselinesTitle => "Vergleichswerte:",
+        Text::NoBaselines => "Keine Vergleichswerte importiert. Importiere welche mit `webgone baseline import`.",
+        Text::Region => "Region",
+        Text::Source => "Quelle",
+        Text::Severity => "Schweregrad",
+        Text::ImpactTitle => "Auswirkungen von Ausfällen:",
+        Text::NoImpacts => "Keine Auswirkungen erfasst. Hänge welche mit `webgone impact add` an einen Ausfall an.",
+        Text::Outage => "Ausfall",
+        Text::Impact => "Auswirkung",
+        Text::Impacts => "Auswirkungen",
+        Text::Amount => "Betrag",
+        Text::TotalImpactCost => "Kosten der erfassten Auswirkungen",
+        Text::TotalCostWithImpact => "Gesamtkosten mit Auswirkungen",
+        Text::ImpactSummary => "Auswirkungen der Ausfälle:",
+    }
+}
@@ -0,0 +1,98 @@
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+use std::io::{self, ErrorKind, Read};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::time::{Duration, Instant};
+
+const ECHO_REQUEST_V4: u8 = 8;
+const ECHO_REPLY_V4: u8 = 0;
+const ECHO_REQUEST_V6: u8 = 128;
+const ECHO_REPLY_V6: u8 = 129;
+
+/// Marks the payload of our echo requests, so replies to other programs'
+/// pings arriving on a raw socket are told apart from ours.
+const PAYLOAD: &[u8] = b"webgone echo";
+
+/// Sequence number of the next echo request.
+static SEQUENCE: AtomicU16 = AtomicU16::new(0);
+
+/// Sends an ICMP echo request to `ip` and waits up to `timeout` for the reply.
+/// Uses an unprivileged ICMP socket where the system allows it (on Linux when
+/// `net.ipv4.ping_group_range` includes the user's group, and on macOS), and
+/// a raw socket, which needs root or `CAP_NET_RAW`, otherwise.
+pub fn ping(ip: IpAddr, timeout: Duration) -> io::Result<()> {
+    let deadline = Instant::now() + timeout;
+    let (domain, protocol, request, reply) = match ip {
+        IpAddr::V4(_) => (Domain::IPV4, Protocol::ICMPV4, ECHO_REQUEST_V4, ECHO_REPLY_V4),
+        IpAddr::V6(_) => (Domain::IPV6, Protocol::ICMPV6, ECHO_REQUEST_V6, ECHO_REPLY_V6),
+    };
+    let socket = Socket::new(domain, Type::DGRAM, Some(protocol))
+        .or_else(|_| Socket::new(domain, Type::RAW, Some(protocol)))
+        .map_err(|e| match e.kind() {
+            ErrorKind::PermissionDenied => io::Error::new(
+                ErrorKind::PermissionDenied,
+                "ICMP needs root, CAP_NET_RAW or a group in net.ipv4.ping_group_range",
+            ),
+            _ => e,
+        })?;
+    socket.connect(&SockAddr::from(SocketAddr::new(ip, 0)))?;
+
+    let sequence = SEQUENCE.fetch_add(1, Ordering::Relaxed);
+    let mut packet = vec![request, 0, 0, 0];
+    // The kernel replaces the identifier of unprivileged sockets with their port
+    packet.extend_from_slice(&(std::process::id() as u16).to_be_bytes());
+    packet.extend_from_slice(&sequence.to_be_bytes());
+    packet.extend_from_slice(PAYLOAD);
+    // The kernel computes the checksum of ICMPv6, which covers the addresses
+    if ip.is_ipv4() {
+        let checksum = checksum(&packet);
+        packet[2..4].copy_from_slice(&checksum.to_be_bytes());
+    }
+    socket.send(&packet)?;
+
+    let mut buffer = [0; 1024];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::Error::new(ErrorKind::TimedOut, "no echo reply"));
+        }
+        socket.set_read_timeout(Some(remaining))?;
+        let length = match (&socket).read(&mut buffer) {
+            Ok(length) => length,
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                return Err(io::Error::new(ErrorKind::TimedOut, "no echo reply"));
+            }
+            Err(e) => return Err(e),
+        };
+        if is_reply(ip, &buffer[..length], reply, sequence) {
+            return Ok(());
+        }
+    }
+}
+
+/// Whether `packet` is the reply to our echo request with `sequence`. IPv4
+/// raw sockets, and unprivileged ones on macOS, receive the IP header too.
+fn is_reply(ip: IpAddr, packet: &[u8], reply: u8, sequence: u16) -> bool {
+    let icmp = match packet.first() {
+        Some(first) if ip.is_ipv4() && first >> 4 == 4 => packet.get(usize::from(first & 0x0f) * 4..),
+        _ => Some(packet),
+    };
+    icmp.is_some_and(|icmp| {
+        icmp.len() >= 8 + PAYLOAD.len()
+            && icmp[0] == reply
+            && icmp[6..8] == sequence.to_be_bytes()
+            && icmp[8..].starts_with(PAYLOAD)
+    })
+}
+
+/// Internet checksum (RFC 1071) of an ICMPv4 message.
+fn checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data
+        .chunks(2)
+        .map(|pair| u32::from(u16::from_be_bytes([pair[0], pair.get(1).copied().unwrap_or(0)])))
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
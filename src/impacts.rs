@@ -0,0 +1,140 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+
+use crate::error::WebgoneError;
+use crate::filter::OutageFilter;
+use webgone::model;
+
+/// What an outage got in the way of, e.g. a missed standup, and optionally
+/// what that cost.
+pub struct Impact {
+    pub id: i64,
+    pub outage_id: i64,
+    /// `None` if the outage's start time can't be read
+    pub outage_start: Option<DateTime<Local>>,
+    pub description: String,
+    pub amount: Option<f64>,
+}
+
+/// Impact entries with the same description, summed up.
+#[derive(serde::Serialize)]
+pub struct Summary {
+    pub description: String,
+    pub entries: i64,
+    pub outages: i64,
+    /// Sum of the entries with an amount
+    pub amount: f64,
+}
+
+/// Impact entries of the outages that started in a month.
+#[derive(Default, Clone, Copy)]
+pub struct MonthImpact {
+    pub entries: i64,
+    pub amount: f64,
+}
+
+pub fn init_table(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS outage_impacts (
+            id INTEGER PRIMARY KEY,
+            outage_id INTEGER NOT NULL REFERENCES outages(id),
+            description TEXT NOT NULL,
+            amount REAL,
+            recorded_at TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS outage_impacts_outage ON outage_impacts (outage_id);"
+    )?;
+    Ok(())
+}
+
+/// Attaches an impact entry to an outage and returns its id.
+pub fn add(conn: &Connection, outage_id: i64, description: &str, amount: Option<f64>) -> Result<i64> {
+    let description = description.trim();
+    if description.is_empty() {
+        return Err(WebgoneError::InvalidInput("describe the impact, e.g. \"VoIP call dropped\"".to_string()).into());
+    }
+    if amount.is_some_and(|amount| !amount.is_finite() || amount < 0.0) {
+        return Err(WebgoneError::InvalidInput("the amount must be a positive number".to_string()).into());
+    }
+    if !conn.prepare("SELECT 1 FROM outages WHERE id = ?")?.exists([outage_id])? {
+        return Err(WebgoneError::InvalidInput(format!("no outage with id {}", outage_id)).into());
+    }
+
+    conn.execute(
+        "INSERT INTO outage_impacts (outage_id, description, amount, recorded_at) VALUES (?1, ?2, ?3, ?4)",
+        params![outage_id, description, amount, Local::now().to_rfc3339()],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Removes an impact entry and returns the outage it was attached to.
+pub fn remove(conn: &Connection, id: i64) -> Result<i64> {
+    let outage_id: Option<i64> = conn
+        .query_row("SELECT outage_id FROM outage_impacts WHERE id = ?", [id], |row| row.get(0))
+        .optional()?;
+    let outage_id = outage_id.ok_or_else(|| WebgoneError::InvalidInput(format!("no impact entry with id {}", id)))?;
+    conn.execute("DELETE FROM outage_impacts WHERE id = ?", [id])?;
+    Ok(outage_id)
+}
+
+/// Impact entries, of one outage or of all, newest outage first.
+pub fn list(conn: &Connection, outage_id: Option<i64>) -> Result<Vec<Impact>> {
+    let mut stmt = conn.prepare(
+        "SELECT i.id, i.outage_id, o.start_time, i.description, i.amount
+        FROM outage_impacts i
+        JOIN outages o ON o.id = i.outage_id
+        WHERE ?1 IS NULL OR i.outage_id = ?1
+        ORDER BY julianday(o.start_time) DESC, i.id",
+    )?;
+    let impacts = stmt
+        .query_map([outage_id], |row| {
+            Ok(Impact {
+                id: row.get(0)?,
+                outage_id: row.get(1)?,
+                outage_start: model::parse_timestamp(&row.get::<_, String>(2)?),
+                description: row.get(3)?,
+                amount: row.get(4)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(impacts)
+}
+
+/// Impact entries of the outages matching `filter`, by the year and month
+/// the outages started in.
+pub fn by_month(conn: &Connection, filter: &OutageFilter) -> Result<HashMap<(i32, u32), MonthImpact>> {
+    let (condition, params) = filter.where_clause();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT CAST(strftime('%Y', o.start_time) AS INTEGER), CAST(strftime('%m', o.start_time) AS INTEGER),
+            COUNT(*), COALESCE(SUM(i.amount), 0)
+        FROM outage_impacts i
+        JOIN outages o ON o.id = i.outage_id
+        WHERE i.outage_id IN (SELECT id FROM outages {condition})
+        GROUP BY 1, 2"
+    ))?;
+    let rows = stmt.query_map(rusqlite::params_from_iter(params), |row| {
+        Ok(((row.get::<_, i32>(0)?, row.get::<_, u32>(1)?), MonthImpact { entries: row.get(2)?, amount: row.get(3)? }))
+    })?;
+    Ok(rows.collect::<rusqlite::Result<HashMap<_, _>>>()?)
+}
+
+/// Impact entries of the outages matching `filter`, grouped by description
+/// ignoring case, costliest and then most frequent first.
+pub fn summary(conn: &Connection, filter: &OutageFilter) -> Result<Vec<Summary>> {
+    let (condition, params) = filter.where_clause();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT MIN(description), COUNT(*), COUNT(DISTINCT outage_id), COALESCE(SUM(amount), 0)
+        FROM outage_impacts
+        WHERE outage_id IN (SELECT id FROM outages {condition})
+        GROUP BY lower(description)
+        ORDER BY 4 DESC, 2 DESC, 1"
+    ))?;
+    let summaries = stmt
+        .query_map(rusqlite::params_from_iter(params), |row| {
+            Ok(Summary { description: row.get(0)?, entries: row.get(1)?, outages: row.get(2)?, amount: row.get(3)? })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(summaries)
+}
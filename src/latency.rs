@@ -0,0 +1,132 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use rusqlite::{params, Connection};
+use std::collections::BTreeMap;
+
+/// Successful probes of a target within some time, either a single raw
+/// probe or a rolled up period.
+struct Period {
+    /// Number of successful probes the period stands for
+    count: f64,
+    min_ms: f64,
+    avg_ms: f64,
+    max_ms: f64,
+    p95_ms: f64,
+    p99_ms: f64,
+    rolled_up: bool,
+}
+
+/// Latency of the successful probes to a target within a window.
+pub struct LatencyStats {
+    /// `None` for the statistics over all targets
+    pub target: Option<String>,
+    /// Number of successful probes, counting sampled ones with their weight
+    pub probes: i64,
+    pub min_ms: f64,
+    pub avg_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+    /// Whether rolled up periods contributed, whose percentiles can only be
+    /// estimated from the percentiles of each period
+    pub estimated: bool,
+}
+
+/// The value below which `p` percent of the weighted samples lie.
+fn weighted_percentile(samples: &mut [(f64, f64)], p: f64) -> f64 {
+    samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let total: f64 = samples.iter().map(|(_, weight)| weight).sum();
+    let rank = p / 100.0 * total;
+    let mut seen = 0.0;
+    for (value, weight) in samples.iter() {
+        seen += weight;
+        if seen >= rank {
+            return *value;
+        }
+    }
+    samples.last().map_or(0.0, |(value, _)| *value)
+}
+
+fn summarize(target: Option<String>, periods: &[&Period]) -> LatencyStats {
+    let count: f64 = periods.iter().map(|period| period.count).sum();
+    let percentile = |value: fn(&Period) -> f64, p| {
+        let mut samples: Vec<(f64, f64)> = periods.iter().map(|period| (value(period), period.count)).collect();
+        weighted_percentile(&mut samples, p)
+    };
+    LatencyStats {
+        target,
+        probes: count as i64,
+        min_ms: periods.iter().map(|period| period.min_ms).fold(f64::INFINITY, f64::min),
+        avg_ms: periods.iter().map(|period| period.avg_ms * period.count).sum::<f64>() / count,
+        p95_ms: percentile(|period| period.p95_ms, 95.0),
+        p99_ms: percentile(|period| period.p99_ms, 99.0),
+        max_ms: periods.iter().map(|period| period.max_ms).fold(f64::NEG_INFINITY, f64::max),
+        estimated: periods.iter().any(|period| period.rolled_up),
+    }
+}
+
+/// Latency statistics of this machine's successful probes between `from`
+/// and `to`, per target ordered by name, followed by the statistics over
+/// all targets if there are several. Raw probes are included as well as the
+/// periods they were rolled up into.
+pub fn stats(conn: &Connection, from: DateTime<Local>, to: DateTime<Local>) -> Result<Vec<LatencyStats>> {
+    let mut by_target: BTreeMap<String, Vec<Period>> = BTreeMap::new();
+
+    let mut stmt = conn.prepare(
+        "SELECT target, latency_ms, weight FROM probes
+        WHERE site IS NULL AND success AND latency_ms IS NOT NULL
+            AND julianday(time) >= julianday(?1) AND julianday(time) < julianday(?2)",
+    )?;
+    let rows = stmt.query_map(params![from.to_rfc3339(), to.to_rfc3339()], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?, row.get::<_, i64>(2)?))
+    })?;
+    for row in rows {
+        let (target, latency, weight) = row?;
+        by_target.entry(target).or_default().push(Period {
+            count: weight as f64,
+            min_ms: latency,
+            avg_ms: latency,
+            max_ms: latency,
+            p95_ms: latency,
+            p99_ms: latency,
+            rolled_up: false,
+        });
+    }
+
+    for table in ["probe_minutely", "probe_hourly"] {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT target, probes - failures, min_latency_ms, avg_latency_ms, max_latency_ms, p95_latency_ms, p99_latency_ms
+            FROM {table}
+            WHERE probes > failures AND avg_latency_ms IS NOT NULL
+                AND julianday(period) >= julianday(?1) AND julianday(period) < julianday(?2)"
+        ))?;
+        let rows = stmt.query_map(params![from.to_rfc3339(), to.to_rfc3339()], |row| {
+            let avg_ms: f64 = row.get(3)?;
+            Ok((
+                row.get::<_, String>(0)?,
+                Period {
+                    count: row.get::<_, i64>(1)? as f64,
+                    min_ms: row.get::<_, Option<f64>>(2)?.unwrap_or(avg_ms),
+                    avg_ms,
+                    max_ms: row.get::<_, Option<f64>>(4)?.unwrap_or(avg_ms),
+                    p95_ms: row.get::<_, Option<f64>>(5)?.unwrap_or(avg_ms),
+                    p99_ms: row.get::<_, Option<f64>>(6)?.unwrap_or(avg_ms),
+                    rolled_up: true,
+                },
+            ))
+        })?;
+        for row in rows {
+            let (target, period) = row?;
+            by_target.entry(target).or_default().push(period);
+        }
+    }
+
+    let mut stats: Vec<LatencyStats> = by_target
+        .iter()
+        .map(|(target, periods)| summarize(Some(target.clone()), &periods.iter().collect::<Vec<_>>()))
+        .collect();
+    if by_target.len() > 1 {
+        stats.push(summarize(None, &by_target.values().flatten().collect::<Vec<_>>()));
+    }
+    Ok(stats)
+}
@@ -0,0 +1,77 @@
+use anyhow::Result;
+use chrono::{DateTime, Local, TimeDelta};
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::error::WebgoneError;
+
+/// Lease on sending notifications, shared by watchers monitoring the same
+/// line through a database they can all reach. Only the holder notifies; the
+/// others keep recording and take over once the holder stopped renewing it.
+pub struct NotificationLease {
+    conn: Connection,
+    instance: String,
+    ttl: Duration,
+}
+
+impl NotificationLease {
+    pub fn open(path: &Path, instance: String, ttl: Duration) -> Result<Self> {
+        let conn = Connection::open(path).map_err(|e| WebgoneError::DatabaseOpen {
+            path: path.to_path_buf(),
+            reason: e.to_string(),
+        })?;
+        conn.busy_timeout(Duration::from_secs(2))?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS notification_lease (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                holder TEXT NOT NULL,
+                expires_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+        Ok(NotificationLease { conn, instance, ttl })
+    }
+
+    /// Takes or renews the lease if it is free, expired or already ours.
+    /// Returns the instance holding it afterwards.
+    pub fn renew(&mut self, now: DateTime<Local>) -> Result<String> {
+        let tx = self.conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let current: Option<(String, String)> = tx
+            .query_row("SELECT holder, expires_at FROM notification_lease WHERE id = 1", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .optional()?;
+        let free = match &current {
+            None => true,
+            Some((holder, expires_at)) => {
+                holder == &self.instance
+                    || DateTime::parse_from_rfc3339(expires_at).is_ok_and(|expires_at| expires_at <= now)
+            }
+        };
+        let holder = if free {
+            let expires_at = now + TimeDelta::from_std(self.ttl).unwrap_or(TimeDelta::days(1));
+            tx.execute(
+                "INSERT INTO notification_lease (id, holder, expires_at) VALUES (1, ?1, ?2)
+                ON CONFLICT(id) DO UPDATE SET holder = excluded.holder, expires_at = excluded.expires_at",
+                params![self.instance, expires_at.to_rfc3339()],
+            )?;
+            self.instance.clone()
+        } else {
+            current.map(|(holder, _)| holder).unwrap_or_default()
+        };
+        tx.commit()?;
+        Ok(holder)
+    }
+
+    /// Gives the lease up on a clean shutdown, so another instance takes
+    /// over right away instead of after it expired.
+    pub fn release(&self) -> Result<()> {
+        self.conn.execute("DELETE FROM notification_lease WHERE holder = ?", [&self.instance])?;
+        Ok(())
+    }
+
+    pub fn instance(&self) -> &str {
+        &self.instance
+    }
+}
@@ -0,0 +1,37 @@
+//! Read access to the outage history recorded by `webgone watch`, for
+//! applications that want to use it without writing SQL against the schema.
+//! The database has to be at the schema version of the matching webgone
+//! release; `webgone init` upgrades an older one.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use chrono::{Local, Months};
+//! use webgone::OutageQuery;
+//!
+//! let conn = rusqlite::Connection::open("internet_outages.db")?;
+//! let outages = OutageQuery::new()
+//!     .since(Local::now() - Months::new(1))
+//!     .min_duration(Duration::from_secs(60))
+//!     .monitor("local")
+//!     .run(&conn)?;
+//! for outage in outages {
+//!     println!("{} lasting {} seconds", outage.start_time, outage.duration_seconds);
+//! }
+//! # Ok::<(), rusqlite::Error>(())
+//! ```
+
+pub mod analysis;
+pub mod debounce;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod model;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod query;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+pub use model::{InternetOutage, MonthlyOutage, OutageStats, WeatherReport, WifiInfo};
+#[cfg(not(target_arch = "wasm32"))]
+pub use query::{Monitor, OutageQuery};
@@ -0,0 +1,254 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use rusqlite::{params, Connection};
+use std::fmt;
+use std::net::IpAddr;
+
+use crate::error::WebgoneError;
+use crate::wifi;
+
+/// What the watcher does while the machine is on a known network.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Action {
+    /// Stop probing until the machine leaves the network
+    Pause,
+    /// Keep probing, but tag outages with the location name
+    Tag,
+}
+
+impl Action {
+    fn as_str(self) -> &'static str {
+        match self {
+            Action::Pause => "pause",
+            Action::Tag => "tag",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pause" => Some(Action::Pause),
+            "tag" => Some(Action::Tag),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A network other than the one being monitored, e.g. a phone hotspot or the
+/// office LAN, recognized by its SSID, gateway MAC address or both.
+#[derive(Clone)]
+pub struct Location {
+    pub name: String,
+    pub ssid: Option<String>,
+    pub gateway_mac: Option<String>,
+    pub action: Action,
+}
+
+impl Location {
+    /// Every part of the fingerprint the location was defined with has to match.
+    fn matches(&self, fingerprint: &Fingerprint) -> bool {
+        let ssid_matches = self.ssid.as_ref().is_none_or(|ssid| fingerprint.ssid.as_ref() == Some(ssid));
+        let mac_matches = self
+            .gateway_mac
+            .as_ref()
+            .is_none_or(|mac| fingerprint.gateway_mac.as_ref() == Some(mac));
+        ssid_matches && mac_matches
+    }
+}
+
+/// What identifies the network the machine is currently connected to.
+pub struct Fingerprint {
+    pub ssid: Option<String>,
+    pub gateway_mac: Option<String>,
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "SSID {}, gateway MAC {}",
+            self.ssid.as_deref().unwrap_or("none"),
+            self.gateway_mac.as_deref().unwrap_or("unknown")
+        )
+    }
+}
+
+pub fn init_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS network_locations (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            ssid TEXT,
+            gateway_mac TEXT,
+            action TEXT NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Normalizes MAC addresses like `A4-2B-B0-01-02-03` or macOS' shortened
+/// `a4:2b:b0:1:2:3` to `a4:2b:b0:01:02:03`.
+pub fn normalize_mac(value: &str) -> Option<String> {
+    let octets = value
+        .trim()
+        .split([':', '-'])
+        .map(|octet| u8::from_str_radix(octet, 16).ok())
+        .collect::<Option<Vec<_>>>()?;
+    (octets.len() == 6).then(|| octets.iter().map(|octet| format!("{:02x}", octet)).collect::<Vec<_>>().join(":"))
+}
+
+pub fn add(conn: &Connection, name: &str, ssid: Option<&str>, gateway_mac: Option<&str>, action: Action) -> Result<i64> {
+    if ssid.is_none() && gateway_mac.is_none() {
+        return Err(WebgoneError::InvalidInput("a location needs an SSID, a gateway MAC address or both".to_string()).into());
+    }
+    let gateway_mac = gateway_mac
+        .map(|mac| {
+            normalize_mac(mac).ok_or_else(|| WebgoneError::InvalidInput(format!("invalid MAC address: {}", mac)))
+        })
+        .transpose()?;
+    if list(conn)?.iter().any(|location| location.name == name) {
+        return Err(WebgoneError::InvalidInput(format!("a location named {} already exists", name)).into());
+    }
+
+    conn.execute(
+        "INSERT INTO network_locations (name, ssid, gateway_mac, action) VALUES (?1, ?2, ?3, ?4)",
+        params![name, ssid, gateway_mac, action.as_str()],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn remove(conn: &Connection, name: &str) -> Result<()> {
+    if conn.execute("DELETE FROM network_locations WHERE name = ?", [name])? == 0 {
+        return Err(WebgoneError::InvalidInput(format!("no location named {}", name)).into());
+    }
+    Ok(())
+}
+
+pub fn list(conn: &Connection) -> Result<Vec<Location>> {
+    let mut stmt = conn.prepare("SELECT name, ssid, gateway_mac, action FROM network_locations ORDER BY name")?;
+    let locations = stmt
+        .query_map([], |row| {
+            let action: String = row.get(3)?;
+            Ok(Location {
+                name: row.get(0)?,
+                ssid: row.get(1)?,
+                gateway_mac: row.get(2)?,
+                action: Action::parse(&action).unwrap_or(Action::Tag),
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(locations)
+}
+
+/// The first of `locations` matching the network the machine is on.
+pub fn find<'a>(locations: &'a [Location], fingerprint: &Fingerprint) -> Option<&'a Location> {
+    locations.iter().find(|location| location.matches(fingerprint))
+}
+
+/// Reads the SSID of the wireless interface and the MAC address of the
+/// default gateway. Both work without internet access, so the location is
+/// also known during outages.
+pub fn detect(wifi_interface: Option<&str>) -> Fingerprint {
+    Fingerprint {
+        ssid: wifi::current(wifi_interface).and_then(|info| info.ssid),
+        gateway_mac: gateway_mac().and_then(|mac| normalize_mac(&mac)),
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// IPv4 address of the default gateway, usually the home router.
+#[cfg(target_os = "linux")]
+pub fn gateway_ip() -> Option<IpAddr> {
+    // Addresses in the routing table are hex in host byte order, e.g. 0101A8C0 for 192.168.1.1
+    let routes = std::fs::read_to_string("/proc/net/route").ok()?;
+    routes.lines().skip(1).find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.get(1) != Some(&"00000000") {
+            return None;
+        }
+        let address = u32::from_str_radix(fields.get(2)?, 16).ok()?;
+        Some(IpAddr::from(address.to_le_bytes()))
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn gateway_mac() -> Option<String> {
+    let gateway = gateway_ip()?.to_string();
+
+    // Entries look like "192.168.1.1  0x1  0x2  a4:2b:b0:01:02:03  *  wlan0"
+    let arp = std::fs::read_to_string("/proc/net/arp").ok()?;
+    arp.lines().skip(1).find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        // Incomplete entries have an all-zero address
+        fields
+            .get(3)
+            .filter(|mac| fields.first() == Some(&gateway.as_str()) && **mac != "00:00:00:00:00:00")
+            .map(|mac| mac.to_string())
+    })
+}
+
+#[cfg(target_os = "macos")]
+pub fn gateway_ip() -> Option<IpAddr> {
+    let route = command_output("route", &["-n", "get", "default"])?;
+    route
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("gateway:").and_then(|value| value.trim().parse().ok()))
+}
+
+#[cfg(target_os = "macos")]
+fn gateway_mac() -> Option<String> {
+    let gateway = gateway_ip()?.to_string();
+
+    // "? (192.168.1.1) at a4:2b:b0:1:2:3 on en0 ifscope [ethernet]"
+    let arp = command_output("arp", &["-n", &gateway])?;
+    arp.split_whitespace().skip_while(|word| *word != "at").nth(1).map(str::to_string)
+}
+
+#[cfg(target_os = "windows")]
+pub fn gateway_ip() -> Option<IpAddr> {
+    let gateway = command_output(
+        "powershell",
+        &[
+            "-NoProfile",
+            "-Command",
+            "(Get-NetRoute -DestinationPrefix 0.0.0.0/0 | Sort-Object RouteMetric | Select-Object -First 1).NextHop",
+        ],
+    )?;
+    gateway.trim().parse().ok()
+}
+
+#[cfg(target_os = "windows")]
+fn gateway_mac() -> Option<String> {
+    let gateway = gateway_ip()?.to_string();
+
+    // "  192.168.1.1           a4-2b-b0-01-02-03     dynamic"
+    let arp = command_output("arp", &["-a", &gateway])?;
+    arp.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        fields.get(1).filter(|_| fields.first() == Some(&gateway.as_str())).map(|mac| mac.to_string())
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub fn gateway_ip() -> Option<IpAddr> {
+    None
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn gateway_mac() -> Option<String> {
+    None
+}
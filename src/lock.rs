@@ -0,0 +1,50 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// Records the pid of a running watcher next to the database, so other
+/// webgone processes can tell the user which process holds the database.
+/// The file is removed again when dropped.
+pub struct WatcherPidFile {
+    path: PathBuf,
+}
+
+fn pid_path(db_path: &Path) -> PathBuf {
+    let mut path = db_path.as_os_str().to_owned();
+    path.push(".pid");
+    PathBuf::from(path)
+}
+
+impl WatcherPidFile {
+    pub fn create(db_path: &Path) -> std::io::Result<Self> {
+        let path = pid_path(db_path);
+        fs::write(&path, process::id().to_string())?;
+        Ok(WatcherPidFile { path })
+    }
+}
+
+impl Drop for WatcherPidFile {
+    fn drop(&mut self) {
+        // Another watcher may have started since and taken over the file
+        if fs::read_to_string(&self.path).is_ok_and(|pid| pid.trim() == process::id().to_string()) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Returns the pid of the watcher running against `db_path`, if any.
+pub fn watcher_pid(db_path: &Path) -> Option<u32> {
+    let pid: u32 = fs::read_to_string(pid_path(db_path)).ok()?.trim().parse().ok()?;
+    (pid != process::id() && is_running(pid)).then_some(pid)
+}
+
+#[cfg(target_os = "linux")]
+fn is_running(pid: u32) -> bool {
+    Path::new(&format!("/proc/{}", pid)).exists()
+}
+
+// Without a cheap portable check, trust the pid file; it is removed on a clean shutdown
+#[cfg(not(target_os = "linux"))]
+fn is_running(_pid: u32) -> bool {
+    true
+}
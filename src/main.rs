@@ -1,67 +1,158 @@
 use anyhow::{Context, Result};
-use chrono::{DateTime, Local};
-use clap::{Parser, Subcommand};
-use rusqlite::{Connection, params, Row};
-use std::io::BufWriter;
+use chrono::{DateTime, Datelike, Local, NaiveDate};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use rusqlite::{Connection, OpenFlags, OptionalExtension, params};
+use serde::Serialize;
+use serde_json::json;
 use std::path::{Path, PathBuf};
-use std::{thread, time::Duration};
-use std::net::{TcpStream, SocketAddr, IpAddr};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::Instant;
-use ascii_table::AsciiTable;
-use std::io::Write;
+use std::{thread, time::Duration};
+use std::net::{SocketAddr, IpAddr};
+use ascii_table::Align;
+use std::io::{self, Write};
 
-struct InternetOutage {
-    start_time: DateTime<Local>,
-    end_time: DateTime<Local>,
-    duration_seconds: i64,
-}
+mod acks;
+mod agent;
+mod archive;
+mod audit;
+mod baseline;
+mod bars;
+mod capture;
+mod collector;
+mod console;
+mod coverage;
+mod crash;
+mod dashboard;
+mod datadir;
+mod dates;
+mod dbdiff;
+mod degraded;
+mod digest;
+mod discord;
+mod dnsbench;
+mod email;
+mod epochs;
+mod error;
+mod evidence;
+mod export;
+mod filter;
+mod forecast;
+mod glance;
+mod health;
+mod history;
+mod i18n;
+mod icmp;
+mod impacts;
+mod latency;
+mod lease;
+mod locations;
+mod lock;
+mod notify;
+mod oslog;
+mod output;
+mod pause;
+mod plans;
+mod proto;
+mod probe_buffer;
+mod prometheus;
+mod quality;
+mod recovery;
+mod repair;
+mod replica;
+mod report;
+mod routes;
+mod schedule;
+mod script;
+mod scrub;
+mod search;
+mod selftest;
+mod server;
+mod services;
+mod severity;
+mod signing;
+mod sites;
+mod storage;
+mod tags;
+mod tickets;
+mod tls;
+mod tokens;
+#[cfg(feature = "tray")]
+mod tray;
+mod target;
+mod trend;
+mod update;
+mod verdict;
+mod views;
+mod weather;
+mod webhook;
+mod wifi;
+
+use error::WebgoneError;
+use filter::OutageFilter;
+use i18n::{Lang, Text};
+use oslog::OsEvent;
+use output::Output;
+use probe_buffer::ProbeBuffer;
+use coverage::GapMode;
+use storage::{Granularity, JournalMode, StorageProfile, Synchronous};
+use target::{AdaptiveTimeout, ProbeResult, Target, TargetPool, TargetSpec};
+use console::Console;
+use weather::{WeatherCache, WeatherReport};
+use wifi::WifiInfo;
+use webgone::debounce;
+use webgone::model::{self, InternetOutage, MonthlyOutage, OutageStats, PlannedDowntime, Severity};
 
-struct OutageStats {
-    total_outages: i64,
-    total_duration: i64,
-    average_duration: f64,
-    longest_outage: i64,
-    shortest_outage: i64,
+/// Which part of an outage counts towards its duration.
+#[derive(Clone, Copy, Default, clap::ValueEnum)]
+enum DurationMode {
+    /// From the first failed check to the first successful one
+    #[default]
+    Raw,
+    /// Only from when the outage was confirmed by the failure threshold
+    Conservative,
 }
 
-struct MonthlyOutage {
-    year: i32,
-    month: u32,
-    total_seconds: i64,
-    num_outages: i64,
-}
-
-impl InternetOutage {
-    fn from_row(row: &Row) -> rusqlite::Result<Self> {
-        let start_str: String = row.get(1)?;
-        let end_str: String = row.get(2)?;
-        let duration_seconds: i64 = row.get(3)?;
-
-        let start_time = DateTime::parse_from_rfc3339(&start_str)
-            .map(|dt| dt.with_timezone(&Local))
-            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(
-                0,
-                rusqlite::types::Type::Text,
-                Box::new(e),
-            ))?;
-        
-        let end_time = DateTime::parse_from_rfc3339(&end_str)
-            .map(|dt| dt.with_timezone(&Local))
-            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(
-                0,
-                rusqlite::types::Type::Text,
-                Box::new(e),
-            ))?;
-
-        Ok(InternetOutage {
-            start_time,
-            end_time,
-            duration_seconds,
-        })
+impl DurationMode {
+    /// SQL expression for the duration of a row of `outages`, in seconds.
+    fn sql(self) -> &'static str {
+        match self {
+            DurationMode::Raw => "duration_seconds",
+            DurationMode::Conservative => {
+                "MAX(0, CAST((julianday(end_time) - julianday(COALESCE(confirmed_at, start_time))) * 86400 AS INTEGER))"
+            }
+        }
     }
+
+    /// Duration of an outage in seconds.
+    fn of(self, outage: &InternetOutage) -> i64 {
+        match self {
+            DurationMode::Raw => outage.duration_seconds,
+            DurationMode::Conservative => outage.confirmed_duration_seconds(),
+        }
+    }
+}
+
+struct TargetHealth {
+    target: String,
+    /// Number of probes and failures per window, in the order of `HEALTH_WINDOWS`
+    windows: Vec<(i64, i64)>,
+    median_latency_ms: Option<f64>,
 }
 
+/// Windows (label, SQLite date modifier) used by the `targets` command.
+const HEALTH_WINDOWS: &[(&str, &str)] = &[("1h", "-1 hour"), ("24h", "-1 day"), ("7d", "-7 days")];
+
+/// Version of the database layout written by this binary, stored in
+/// `PRAGMA user_version`. Bump it whenever `init_database` changes the schema.
+const SCHEMA_VERSION: i64 = 37;
+
 fn init_database(conn: &Connection) -> Result<()> {
+    check_compatibility(conn)?;
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
     conn.execute(
         "CREATE TABLE IF NOT EXISTS outages (
             id INTEGER PRIMARY KEY,
@@ -71,283 +162,4865 @@ fn init_database(conn: &Connection) -> Result<()> {
         )",
         [],
     )?;
+
+    ensure_column(conn, "outages", "wifi_ssid", "TEXT")?;
+    ensure_column(conn, "outages", "wifi_signal_dbm", "INTEGER")?;
+    ensure_column(conn, "outages", "weather_observed_at", "TEXT")?;
+    ensure_column(conn, "outages", "weather_code", "INTEGER")?;
+    ensure_column(conn, "outages", "temperature_c", "REAL")?;
+    ensure_column(conn, "outages", "precipitation_mm", "REAL")?;
+    ensure_column(conn, "outages", "wind_speed_kmh", "REAL")?;
+    // When the watcher saw the outage, declared it, and declared it over; the
+    // difference to start_time and end_time is the delay caused by the check
+    // interval and failure thresholds
+    ensure_column(conn, "outages", "detected_at", "TEXT")?;
+    ensure_column(conn, "outages", "confirmed_at", "TEXT")?;
+    ensure_column(conn, "outages", "recovered_at", "TEXT")?;
+    if version == 11 {
+        // Schema 11 stored the confirmation time as detected_at
+        conn.execute(
+            "UPDATE outages SET confirmed_at = detected_at, detected_at = start_time WHERE detected_at IS NOT NULL",
+            [],
+        )?;
+    }
+    // Site and row id on the agent for outages received by `webgone collect`; NULL for local ones
+    ensure_column(conn, "outages", "site", "TEXT")?;
+    ensure_column(conn, "outages", "remote_id", "INTEGER")?;
+    // Packet capture taken during the outage, with --capture-interface or --capture-command
+    ensure_column(conn, "outages", "capture_path", "TEXT")?;
+    // Components of the --up-when verdict that failed when the outage started, e.g. `dns, http`,
+    // or the targets that failed with several --target
+    ensure_column(conn, "outages", "failed_components", "TEXT")?;
+    // Probe types of the targets that failed when the outage started, e.g. `dns` or `dns, tcp`
+    ensure_column(conn, "outages", "failed_probes", "TEXT")?;
+    // API token or agent that reported the outage; NULL for the local watcher
+    ensure_column(conn, "outages", "actor", "TEXT")?;
+    // HMAC of the row with the key of `webgone db sign`; NULL unless signing is enabled.
+    // Schema 35 signs more columns, see `signing::enable`
+    ensure_column(conn, "outages", "signature", "TEXT")?;
+    // Set when the clock was stepped backwards during the outage, see `webgone db check`
+    ensure_column(conn, "outages", "clock_anomaly", "INTEGER NOT NULL DEFAULT 0")?;
+    // For outages of a --service that began while what it depends on was down: that service, or `local`
+    ensure_column(conn, "outages", "derived_from", "TEXT")?;
+    // info, minor, major or critical, as classified when the outage was recorded
+    ensure_column(conn, "outages", "severity", "TEXT")?;
+    if version < 32 {
+        // Schemas before 32 didn't classify outages
+        severity::backfill(conn)?;
+    }
+    conn.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS outages_remote ON outages (site, remote_id)",
+        [],
+    )?;
+
+    // Materialized per-month aggregates for reports, invalidated on every change to outages
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS monthly_aggregates (
+            year INTEGER NOT NULL,
+            month INTEGER NOT NULL,
+            num_outages INTEGER NOT NULL,
+            total_seconds INTEGER NOT NULL,
+            PRIMARY KEY (year, month)
+        );
+        CREATE TRIGGER IF NOT EXISTS outages_insert_invalidate AFTER INSERT ON outages
+        BEGIN DELETE FROM monthly_aggregates; END;
+        CREATE TRIGGER IF NOT EXISTS outages_update_invalidate AFTER UPDATE ON outages
+        BEGIN DELETE FROM monthly_aggregates; END;
+        CREATE TRIGGER IF NOT EXISTS outages_delete_invalidate AFTER DELETE ON outages
+        BEGIN DELETE FROM monthly_aggregates; END;"
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS probes (
+            id INTEGER PRIMARY KEY,
+            time TEXT NOT NULL,
+            target TEXT NOT NULL,
+            success INTEGER NOT NULL,
+            latency_ms REAL,
+            error TEXT
+        )",
+        [],
+    )?;
+    // Number of probes a row stands for when successful probes are sampled
+    ensure_column(conn, "probes", "weight", "INTEGER NOT NULL DEFAULT 1")?;
+    ensure_column(conn, "probes", "site", "TEXT")?;
+    ensure_column(conn, "probes", "remote_id", "INTEGER")?;
+    conn.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS probes_remote ON probes (site, remote_id)",
+        [],
+    )?;
+
+    // Aggregates kept after raw probes are rolled up
+    storage::init_aggregate_tables(conn)?;
+    // Every check's outcome, with --record-probes
+    history::init_table(conn)?;
+
+    // Internal metrics of the running watcher
+    health::init_table(conn)?;
+    ensure_column(conn, "watcher_status", "notification_failures", "INTEGER NOT NULL DEFAULT 0")?;
+
+    tags::init_table(conn)?;
+    tickets::init_tables(conn)?;
+    evidence::init_table(conn)?;
+    tokens::init_table(conn)?;
+    script::init_tables(conn)?;
+    epochs::init_table(conn)?;
+    acks::init_table(conn)?;
+    locations::init_table(conn)?;
+    dnsbench::init_table(conn)?;
+    routes::init_table(conn)?;
+    audit::init_table(conn)?;
+    // Periods a watcher was monitoring, for --gaps
+    coverage::init_table(conn)?;
+    degraded::init_table(conn)?;
+    // Machine, version and probe configuration of the watcher; NULL for periods of older versions
+    ensure_column(conn, "monitored_periods", "hostname", "TEXT")?;
+    ensure_column(conn, "monitored_periods", "os", "TEXT")?;
+    ensure_column(conn, "monitored_periods", "version", "TEXT")?;
+    ensure_column(conn, "monitored_periods", "config", "TEXT")?;
+    services::init_table(conn)?;
+    plans::init_table(conn)?;
+    quality::init_table(conn)?;
+    baseline::init_table(conn)?;
+    impacts::init_table(conn)?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS outage_events (
+            id INTEGER PRIMARY KEY,
+            outage_id INTEGER NOT NULL REFERENCES outages(id),
+            time TEXT NOT NULL,
+            source TEXT NOT NULL,
+            message TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS metadata (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        )",
+        [],
+    )?;
+    views::init(conn)?;
+    record_writer(conn)?;
+    Ok(())
+}
+
+/// Refuses databases whose schema was written by a newer webgone, instead of
+/// failing later with SQL errors about unknown tables or columns.
+fn check_compatibility(conn: &Connection) -> Result<()> {
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version > SCHEMA_VERSION {
+        let writer = conn
+            .query_row("SELECT value FROM metadata WHERE key = 'last_writer_version'", [], |row| row.get(0))
+            .optional()?;
+        return Err(WebgoneError::IncompatibleDatabase {
+            schema_version: version,
+            supported: SCHEMA_VERSION,
+            writer,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Stores the schema version and the version of this binary, so older binaries
+/// can tell which webgone last upgraded the database. Only writes on changes,
+/// so read-only commands don't take a write lock.
+fn record_writer(conn: &Connection) -> Result<()> {
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version < SCHEMA_VERSION {
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+    }
+
+    let writer: Option<String> = conn
+        .query_row("SELECT value FROM metadata WHERE key = 'last_writer_version'", [], |row| row.get(0))
+        .optional()?;
+    if writer.as_deref() != Some(env!("CARGO_PKG_VERSION")) {
+        conn.execute(
+            "INSERT OR REPLACE INTO metadata (key, value) VALUES ('last_writer_version', ?1)",
+            [env!("CARGO_PKG_VERSION")],
+        )?;
+    }
     Ok(())
 }
 
-fn check_internet(addr: SocketAddr) -> bool {
-    let timeout = Duration::from_secs(1);
-    let start = Instant::now();
-    let result = TcpStream::connect_timeout(&addr, timeout);
+/// Adds a column to an existing table if it isn't there yet, so databases
+/// created by older versions keep working.
+fn ensure_column(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({table})"))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<rusqlite::Result<Vec<_>>>()?
+        .iter()
+        .any(|name| name == column);
+
+    if !exists {
+        conn.execute(&format!("ALTER TABLE {table} ADD COLUMN {column} {definition}"), [])?;
+    }
+    Ok(())
+}
+
+/// How long to wait for another webgone process to release a database lock.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What a command needs from the database.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Access {
+    /// Records data, creating the database if it doesn't exist yet
+    Create,
+    /// Changes an existing database
+    Write,
+    /// Only queries an existing database
+    Read,
+}
+
+/// Opens the database the way `access` requires. Only recording commands
+/// create it, so a query run in the wrong directory fails instead of leaving
+/// an empty database behind.
+fn open_database(db_path: &Path, access: Access) -> Result<Connection> {
+    if access != Access::Create && !db_path.exists() {
+        return Err(WebgoneError::DatabaseMissing { path: db_path.to_path_buf() }.into());
+    }
+    if access == Access::Create {
+        if let Some(dir) = db_path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).map_err(|e| WebgoneError::DatabaseOpen {
+                path: db_path.to_path_buf(),
+                reason: format!("failed to create {}: {}", dir.display(), e),
+            })?;
+        }
+    }
+    let flags = match access {
+        Access::Create => OpenFlags::default(),
+        Access::Write => OpenFlags::default() - OpenFlags::SQLITE_OPEN_CREATE,
+        Access::Read => {
+            (OpenFlags::default() - OpenFlags::SQLITE_OPEN_READ_WRITE - OpenFlags::SQLITE_OPEN_CREATE)
+                | OpenFlags::SQLITE_OPEN_READ_ONLY
+        }
+    };
+    let conn = Connection::open_with_flags(db_path, flags)
+        .map_err(|e| WebgoneError::DatabaseOpen { path: db_path.to_path_buf(), reason: e.to_string() })?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+
+    if access != Access::Read {
+        init_database(&conn)?;
+        signing::install(&conn, db_path)?;
+        return Ok(conn);
+    }
+
+    check_compatibility(&conn)?;
+    // Upgrade a database of an older version once, then query it read-only
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version < SCHEMA_VERSION {
+        drop(conn);
+        open_database(db_path, Access::Write)?;
+        return open_database(db_path, Access::Read);
+    }
+    Ok(conn)
+}
+
+/// Starts a read transaction so every query of a report sees the same
+/// consistent snapshot, even while a watcher keeps writing. The transaction
+/// is rolled back when dropped.
+fn begin_snapshot(conn: &Connection) -> Result<rusqlite::Transaction<'_>> {
+    let tx = conn.unchecked_transaction()?;
+    // A deferred transaction only takes its snapshot on the first read
+    tx.query_row("SELECT COUNT(*) FROM sqlite_master", [], |_| Ok(()))?;
+    Ok(tx)
+}
+
+/// How often to try taking the snapshots of `parallel_snapshots` while other
+/// processes keep writing.
+const SNAPSHOT_ATTEMPTS: u32 = 3;
+
+/// Opens `N` read connections whose transactions all see the same state of
+/// the database, so parts of a report can be computed on them in parallel and
+/// still fit together. `None` if writes kept coming in while they were taken.
+fn parallel_snapshots<const N: usize>(db_path: &Path) -> Result<Option<[Connection; N]>> {
+    let connections = (0..N).map(|_| open_database(db_path, Access::Read)).collect::<Result<Vec<_>>>()?;
+    let Ok(connections) = <[Connection; N]>::try_from(connections) else {
+        unreachable!("opened {} connections", N);
+    };
+    // A connection of its own, so its data_version changes with any commit
+    let watch = open_database(db_path, Access::Read)?;
+    let data_version = || watch.query_row("PRAGMA data_version", [], |row| row.get::<_, i64>(0));
+
+    for _ in 0..SNAPSHOT_ATTEMPTS {
+        let before = data_version()?;
+        for conn in &connections {
+            conn.execute_batch("BEGIN")?;
+            conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |_| Ok(()))?;
+        }
+        if data_version()? == before {
+            return Ok(Some(connections));
+        }
+        for conn in &connections {
+            conn.execute_batch("ROLLBACK")?;
+        }
+    }
+    Ok(None)
+}
+
+fn schema_version(conn: &Connection) -> Result<i64> {
+    Ok(conn.query_row("PRAGMA schema_version", [], |row| row.get(0))?)
+}
+
+fn check_internet(
+    conn: &Connection,
+    console: &mut Console,
+    target: &Target,
+    timeout: Duration,
+    buffer: &mut ProbeBuffer,
+    hook: Option<&mut script::ProbeHook>,
+) -> Result<ProbeResult> {
+    let result = target::probe(target, timeout);
+    let now = Local::now();
+    if let Some(hook) = hook {
+        hook.run(conn, console, now, std::slice::from_ref(&result))?;
+    }
     
-    match result {
-        Ok(_) => true,
-        Err(e) => {
-            println!("Connection failed after {:?}: {}", start.elapsed(), e);
-            false
+    if let Some(e) = &result.error {
+        console.detail(&format!("Connection failed after {:?}: {}", result.elapsed, e));
+    }
+    buffer.push(conn, now, vec![result.clone()])?;
+    Ok(result)
+}
+
+/// Checks connectivity against a pool of endpoints, recording every probe
+/// and which endpoint answered. Returns the probe that decided the outcome.
+fn check_internet_pool(
+    conn: &Connection,
+    console: &mut Console,
+    pool: &mut TargetPool,
+    timeout: Duration,
+    buffer: &mut ProbeBuffer,
+    hook: Option<&mut script::ProbeHook>,
+) -> Result<Option<ProbeResult>> {
+    let results = pool.check(timeout);
+    let now = Local::now();
+    if let Some(hook) = hook {
+        hook.run(conn, console, now, &results)?;
+    }
+
+    for result in &results {
+        if let Some(e) = &result.error {
+            console.detail(&format!("Connection to {} failed after {:?}: {}", result.target, result.elapsed, e));
+        }
+    }
+    let decisive = results.last().cloned();
+    buffer.push(conn, now, results)?;
+
+    Ok(decisive)
+}
+
+/// Probe types of `targets`, e.g. `dns, tcp`, as recorded in `failed_probes`.
+fn probe_kinds(targets: &[Target]) -> String {
+    let kinds: std::collections::BTreeSet<&str> = targets.iter().map(Target::kind).collect();
+    kinds.into_iter().collect::<Vec<_>>().join(", ")
+}
+
+/// Runs a task of `watch --config` and describes what it did.
+fn run_scheduled(conn: &Connection, db_path: &Path, config: &schedule::Config, task: schedule::Task, lang: Lang) -> Result<String> {
+    let now = Local::now();
+    match task {
+        schedule::Task::Backup => {
+            let dir = match &config.backup.dir {
+                Some(dir) => dir.clone(),
+                None => db_path.parent().unwrap_or(Path::new(".")).join("backups"),
+            };
+            let path = schedule::backup(conn, db_path, &dir, config.backup.keep.unwrap_or(7), now)?;
+            Ok(format!("Backed up the database to {}", path.display()))
+        }
+        schedule::Task::Report => {
+            let settings = &config.report;
+            let filter = OutageFilter {
+                since: settings.days.map(|days| now - chrono::Duration::days(days.into())),
+                ..Default::default()
+            };
+            let context = report_context(conn, &filter, settings.baseline.as_deref(), lang)?;
+            let rendered = report::render(settings.format()?, settings.template.as_deref(), &context)?;
+            let mut done = Vec::new();
+            if let Some(output) = &settings.output {
+                let path = PathBuf::from(now.format(output).to_string());
+                std::fs::write(&path, &rendered)?;
+                done.push(format!("written to {}", path.display()));
+            }
+            if settings.mail {
+                let mut mailer = config.email.mailer(coverage::hostname())?;
+                mailer.queue(&format!("webgone: outage report of {}", now.format("%Y-%m-%d")), &String::from_utf8_lossy(&rendered));
+                mailer.flush().map_err(|e| WebgoneError::Other(format!("failed to mail the report: {}", e)))?;
+                done.push(format!("mailed to {}", config.email.to.join(", ")));
+            }
+            Ok(format!("Report {}", done.join(" and ")))
+        }
+        schedule::Task::Compact => {
+            let settings = &config.compact;
+            let granularity = settings.granularity()?;
+            let older_than = settings.older_than.unwrap_or(30);
+            let removed = storage::roll_up_probes(conn, older_than, granularity)?;
+            conn.execute_batch("VACUUM")?;
+            audit::record(conn, "db compact", &[], &format!("rolled up {} raw probe(s) older than {} days", removed, older_than))?;
+            Ok(format!("Rolled up {} raw probe(s) older than {} days into {} aggregates", removed, older_than, granularity.name()))
+        }
+        schedule::Task::Scrub => {
+            let settings = &config.scrub;
+            let older_than = settings.older_than.unwrap_or_default();
+            let scrubbed = scrub::scrub(conn, &settings.fields, older_than, false)?;
+            scrub::purge_free_pages(conn)?;
+            let total: usize = scrubbed.iter().map(|table| table.rows).sum();
+            let names: Vec<&str> = settings.fields.iter().map(|field| field.name()).collect();
+            audit::record(conn, "db scrub", &[], &format!("scrubbed {} from {} row(s) older than {} days", names.join(", "), total, older_than))?;
+            Ok(format!("Scrubbed {} row(s) older than {} days", total, older_than))
+        }
+        schedule::Task::StatusPage => {
+            let path = config.statuspage.output.as_deref().unwrap_or(Path::new("outages.json"));
+            let outages = export_outages(conn, &OutageFilter::default())?;
+            let planned = plans::for_export(conn, now)?;
+            let monitored = coverage::for_export(conn, &outages)?;
+            let data = export::Format::Json.render(outages, planned, monitored, &export::Schema::Legacy, now)?;
+            std::fs::write(path, data)?;
+            Ok(format!("Status page data written to {}", path.display()))
+        }
+    }
+}
+
+/// Prints a change of the degraded state, storing periods that ended.
+fn record_degradation(conn: &Connection, console: &mut Console, change: degraded::Change, window: u32) -> Result<()> {
+    match change {
+        degraded::Change::Began { since, loss_percent } => console.print(&format!(
+            "Connection degraded since {}: {:.0}% of the last {} checks failed",
+            since, loss_percent, window
+        )),
+        degraded::Change::Ended(period) => {
+            degraded::save(conn, &period)?;
+            console.print(&format!(
+                "Connection no longer degraded, {} of {} probes ({:.1}%) were lost from {} to {}",
+                period.failures,
+                period.checks,
+                period.loss_percent(),
+                period.start.format("%H:%M:%S"),
+                period.end.format("%H:%M:%S")
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Checks several targets at once, recording every probe. The check fails
+/// when at least `quorum` of them fail. Returns a probe standing for the
+/// outcome (the fastest success, or a failure naming how many targets
+/// failed) and the targets that failed.
+fn check_internet_quorum(
+    conn: &Connection,
+    console: &mut Console,
+    targets: &[Target],
+    quorum: target::Quorum,
+    timeout: Duration,
+    buffer: &mut ProbeBuffer,
+    hook: Option<&mut script::ProbeHook>,
+) -> Result<(ProbeResult, Vec<Target>)> {
+    let results = target::probe_all(targets, timeout);
+    let now = Local::now();
+    if let Some(hook) = hook {
+        hook.run(conn, console, now, &results)?;
+    }
+
+    for result in &results {
+        if let Some(e) = &result.error {
+            console.detail(&format!("Connection to {} failed after {:?}: {}", result.target, result.elapsed, e));
         }
     }
+    let failed: Vec<Target> = results.iter().filter(|result| !result.is_success()).map(|result| result.target.clone()).collect();
+    let outcome = if failed.len() >= quorum.required(targets.len()) {
+        let first = results.iter().find(|result| !result.is_success()).expect("at least one target failed");
+        ProbeResult {
+            error: Some(format!("{} of {} targets failed", failed.len(), targets.len())),
+            ..first.clone()
+        }
+    } else {
+        results
+            .iter()
+            .filter(|result| result.is_success())
+            .min_by_key(|result| result.elapsed)
+            .cloned()
+            .expect("at least one target answered")
+    };
+    buffer.push(conn, now, results)?;
+
+    Ok((outcome, failed))
 }
 
-fn log_outage(conn: &Connection, outage: &InternetOutage) -> Result<()> {
+/// Stores an outage and returns its row id. An outage ending before it
+/// started, because the clock was stepped backwards, is clamped to end when
+/// it started and flagged as a clock anomaly. Outages without a severity are
+/// classified with the default thresholds.
+fn log_outage(conn: &Connection, outage: &InternetOutage) -> Result<i64> {
+    let end_time = outage.end_time.max(outage.start_time);
+    let severity = outage
+        .severity
+        .unwrap_or_else(|| severity::Classifier::default().classify(outage.start_time, end_time));
     conn.execute(
-        "INSERT INTO outages (start_time, end_time, duration_seconds) VALUES (?1, ?2, ?3)",
+        "INSERT INTO outages (
+            start_time, end_time, duration_seconds, wifi_ssid, wifi_signal_dbm,
+            weather_observed_at, weather_code, temperature_c, precipitation_mm, wind_speed_kmh,
+            detected_at, confirmed_at, recovered_at, capture_path, failed_components, actor, clock_anomaly,
+            failed_probes, severity
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)",
         params![
             outage.start_time.to_rfc3339(),
-            outage.end_time.to_rfc3339(),
-            outage.duration_seconds
+            end_time.to_rfc3339(),
+            outage.duration_seconds.max(0),
+            outage.wifi.as_ref().and_then(|w| w.ssid.as_deref()),
+            outage.wifi.as_ref().and_then(|w| w.signal_dbm),
+            outage.weather.as_ref().map(|w| w.observed_at.to_rfc3339()),
+            outage.weather.as_ref().and_then(|w| w.weather_code),
+            outage.weather.as_ref().and_then(|w| w.temperature_c),
+            outage.weather.as_ref().and_then(|w| w.precipitation_mm),
+            outage.weather.as_ref().and_then(|w| w.wind_speed_kmh),
+            outage.detected_at.map(|time| time.to_rfc3339()),
+            outage.confirmed_at.map(|time| time.to_rfc3339()),
+            outage.recovered_at.map(|time| time.to_rfc3339()),
+            outage.capture_path,
+            outage.failed_components,
+            outage.actor,
+            outage.clock_anomaly || outage.end_time < outage.start_time || outage.duration_seconds < 0,
+            outage.failed_probes,
+            severity.name(),
         ],
     )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Extends the most recent outage instead of logging a new one when it ended
+/// at most `merge_gap` before this one started, so a flapping connection
+/// doesn't leave bursts of tiny outages behind. The merged outage is
+/// classified again with `classifier`. Returns the id of the outage and
+/// whether it was merged.
+fn log_or_merge_outage(
+    conn: &Connection,
+    outage: &InternetOutage,
+    merge_gap: Duration,
+    classifier: &severity::Classifier,
+) -> Result<(i64, bool)> {
+    if !merge_gap.is_zero() {
+        let previous = conn
+            .query_row(
+                "SELECT id, start_time, end_time FROM outages WHERE site IS NULL AND actor IS NULL ORDER BY start_time DESC LIMIT 1",
+                [],
+                |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)),
+            )
+            .optional()?;
+
+        if let Some((id, start, end)) = previous {
+            // The previous outage may have been written by another tool
+            let (Some(start), Some(end)) = (model::parse_timestamp(&start), model::parse_timestamp(&end)) else {
+                return Ok((log_outage(conn, outage)?, false));
+            };
+            let gap = outage.start_time.signed_duration_since(end);
+            if gap >= chrono::Duration::zero() && gap.to_std()? <= merge_gap {
+                // The merged outage keeps its own capture, failed components and probes if it has them
+                let end_time = outage.end_time.max(start);
+                conn.execute(
+                    "UPDATE outages SET end_time = ?1, duration_seconds = ?2, capture_path = COALESCE(capture_path, ?3),
+                        failed_components = COALESCE(failed_components, ?4), clock_anomaly = clock_anomaly OR ?5,
+                        failed_probes = COALESCE(failed_probes, ?6), severity = ?7
+                    WHERE id = ?8",
+                    params![
+                        end_time.to_rfc3339(),
+                        end_time.signed_duration_since(start).num_seconds(),
+                        outage.capture_path,
+                        outage.failed_components,
+                        outage.clock_anomaly || outage.end_time < outage.start_time,
+                        outage.failed_probes,
+                        classifier.classify(start, end_time).name(),
+                        id,
+                    ],
+                )?;
+                return Ok((id, true));
+            }
+        }
+    }
+
+    Ok((log_outage(conn, outage)?, false))
+}
+
+fn log_outage_events(conn: &Connection, outage_id: i64, events: &[OsEvent]) -> Result<()> {
+    // Events of merged outages may overlap with ones already stored
+    let mut stmt = conn.prepare(
+        "INSERT INTO outage_events (outage_id, time, source, message)
+        SELECT ?1, ?2, ?3, ?4
+        WHERE NOT EXISTS (
+            SELECT 1 FROM outage_events WHERE outage_id = ?1 AND time = ?2 AND source = ?3 AND message = ?4
+        )"
+    )?;
+    for event in events {
+        stmt.execute(params![outage_id, event.time.to_rfc3339(), event.source, event.message])?;
+    }
     Ok(())
 }
 
-fn get_stats(conn: &Connection) -> Result<OutageStats> {
-    let mut stmt = conn.prepare("
+fn get_outage_events(conn: &Connection, outage_id: i64) -> Result<Vec<OsEvent>> {
+    let mut stmt = conn.prepare(
+        "SELECT time, source, message FROM outage_events WHERE outage_id = ? ORDER BY time"
+    )?;
+    let events = stmt.query_map([outage_id], |row| {
+        let value: String = row.get(0)?;
+        let time = model::parse_timestamp(&value).ok_or_else(|| rusqlite::Error::FromSqlConversionFailure(
+            0,
+            rusqlite::types::Type::Text,
+            Box::new(model::UnreadableTimestamp { id: outage_id, column: "event time".to_string(), value: value.clone() }),
+        ))?;
+        Ok(OsEvent { time, source: row.get(1)?, message: row.get(2)? })
+    })?;
+
+    Ok(events.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+/// Replaces the local outages since the oldest recorded check with ones
+/// derived again from the recorded checks. Tags, events, acknowledgements
+/// and ticket links move to the new outage overlapping the old one, which
+/// also passes on its Wi-Fi and weather conditions, packet capture and
+/// failed components. Returns the number of outages removed and created.
+fn reprocess_outages(
+    conn: &Connection,
+    fail_threshold: u32,
+    recover_threshold: u32,
+    min_outage: Duration,
+    merge_gap: Duration,
+    dry_run: bool,
+) -> Result<(usize, usize)> {
+    let checks = history::load(conn)?;
+    let Some(from) = checks.first().map(|check| check.started) else {
+        return Err(WebgoneError::InvalidInput(
+            "no recorded checks to reprocess, record them with `webgone watch --record-probes`".to_string()
+        ).into());
+    };
+
+    // Changing timestamped outages would make their evidence fail verification
+    let stamped: Option<String> = conn.query_row("SELECT MAX(day) FROM evidence_stamps", [], |row| row.get(0))?;
+    if let Some(day) = stamped.filter(|day| *day >= from.format("%Y-%m-%d").to_string()) {
+        return Err(WebgoneError::InvalidInput(format!(
+            "outages up to {} are timestamped as evidence, which reprocessing would invalidate",
+            day
+        )).into());
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    let (old, kept): (Vec<InternetOutage>, Vec<InternetOutage>) = {
+        // Outages posted through the API weren't derived from checks, so they stay
+        let mut stmt = tx.prepare("SELECT * FROM outages WHERE site IS NULL AND actor IS NULL")?;
+        let outages = stmt.query_map([], InternetOutage::from_row)?.collect::<rusqlite::Result<Vec<_>>>()?;
+        outages.into_iter().partition(|outage| outage.start_time >= from)
+    };
+    // An outage that started before the first recorded check stays as it was
+    let kept_until = kept.iter().map(|outage| outage.end_time).max();
+
+    let mut derived: Vec<InternetOutage> = Vec::new();
+    for outage in history::derive(&checks, fail_threshold, recover_threshold) {
+        if (outage.end_time - outage.start_time).to_std().unwrap_or_default() < min_outage
+            || kept_until.is_some_and(|until| outage.start_time < until)
+        {
+            continue;
+        }
+        if let Some(previous) = derived.last_mut() {
+            let gap = outage.start_time - previous.end_time;
+            if gap >= chrono::Duration::zero() && gap.to_std()? <= merge_gap {
+                previous.end_time = outage.end_time;
+                previous.recovered_at = outage.recovered_at;
+                previous.duration_seconds = (previous.end_time - previous.start_time).num_seconds();
+                continue;
+            }
+        }
+        derived.push(outage);
+    }
+
+    let overlaps = |a: &InternetOutage, b: &InternetOutage| a.start_time <= b.end_time && b.start_time <= a.end_time;
+    // Inserted before the old outages are deleted, so no new outage reuses an old id
+    for outage in &mut derived {
+        if let Some(source) = old.iter().find(|old| overlaps(old, outage)) {
+            outage.wifi = source.wifi.clone();
+            outage.weather = source.weather.clone();
+            outage.capture_path = source.capture_path.clone();
+            outage.failed_components = source.failed_components.clone();
+            outage.failed_probes = source.failed_probes.clone();
+        }
+        outage.id = log_outage(&tx, outage)?;
+    }
+    for outage in &old {
+        if let Some(target) = derived.iter().find(|new| overlaps(outage, new)) {
+            for table in ["outage_tags", "ticket_outages", "outage_acks", "outage_events", "outage_impacts"] {
+                // Links the new outage already has are dropped
+                tx.execute(
+                    &format!("UPDATE OR IGNORE {} SET outage_id = ?1 WHERE outage_id = ?2", table),
+                    params![target.id, outage.id],
+                )?;
+            }
+        }
+        for table in ["outage_tags", "ticket_outages", "outage_acks", "outage_events", "outage_impacts", "outages"] {
+            let column = if table == "outages" { "id" } else { "outage_id" };
+            tx.execute(&format!("DELETE FROM {} WHERE {} = ?", table, column), [outage.id])?;
+        }
+    }
+
+    if !dry_run {
+        tx.commit()?;
+    }
+    Ok((old.len(), derived.len()))
+}
+
+fn print_stats(stats: &OutageStats, format: output::Format, out: Output) -> Result<()> {
+    match format {
+        output::Format::Table => {
+            let seconds = out.text(Text::Seconds);
+            println!("\n{}", out.text(Text::StatsTitle));
+            out.separator()?;
+            println!("{}: {}", out.text(Text::TotalOutages), stats.total_outages);
+            println!("{}: {} {}", out.text(Text::TotalOutageDuration), stats.total_duration, seconds);
+            println!("{}: {:.2} {}", out.text(Text::AverageOutageDuration), stats.average_duration, seconds);
+            println!("{}: {} {}", out.text(Text::LongestOutage), stats.longest_outage, seconds);
+            println!("{}: {} {}", out.text(Text::ShortestOutage), stats.shortest_outage, seconds);
+            if let Some(delay) = stats.average_detection_delay {
+                println!("{}: {:.2} {}", out.text(Text::AverageDetectionDelay), delay, seconds);
+            }
+            if let Some(delay) = stats.average_recovery_delay {
+                println!("{}: {:.2} {}", out.text(Text::AverageRecoveryDelay), delay, seconds);
+            }
+            out.separator()?;
+            println!();
+        }
+        output::Format::Json => writeln!(io::stdout().lock(), "{}", serde_json::to_string_pretty(stats)?)?,
+        output::Format::Csv => {
+            let delay = |delay: Option<f64>| delay.map(|delay| delay.to_string()).unwrap_or_default();
+            output::print_csv(
+                &[
+                    "total_outages",
+                    "total_duration_seconds",
+                    "average_duration_seconds",
+                    "longest_outage_seconds",
+                    "shortest_outage_seconds",
+                    "average_detection_delay_seconds",
+                    "average_recovery_delay_seconds",
+                ],
+                vec![vec![
+                    stats.total_outages.to_string(),
+                    stats.total_duration.to_string(),
+                    stats.average_duration.to_string(),
+                    stats.longest_outage.to_string(),
+                    stats.shortest_outage.to_string(),
+                    delay(stats.average_detection_delay),
+                    delay(stats.average_recovery_delay),
+                ]],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn get_stats(conn: &Connection, filter: &OutageFilter, mode: DurationMode) -> Result<OutageStats> {
+    let (condition, params) = filter.where_clause();
+    let duration = mode.sql();
+    let mut stmt = conn.prepare(&format!("
         SELECT 
             COUNT(*) as total_outages,
-            SUM(duration_seconds) as total_duration,
-            AVG(duration_seconds) as avg_duration,
-            MAX(duration_seconds) as longest_outage,
-            MIN(duration_seconds) as shortest_outage
+            SUM({duration}) as total_duration,
+            AVG({duration}) as avg_duration,
+            MAX({duration}) as longest_outage,
+            MIN({duration}) as shortest_outage,
+            AVG((julianday(confirmed_at) - julianday(start_time)) * 86400) as detection_delay,
+            AVG((julianday(recovered_at) - julianday(end_time)) * 86400) as recovery_delay
         FROM outages
-    ")?;
+        {condition}
+    "))?;
 
-    let stats = stmt.query_row([], |row| {
+    let stats = stmt.query_row(rusqlite::params_from_iter(params), |row| {
         Ok(OutageStats {
             total_outages: row.get(0).unwrap_or_default(),
             total_duration: row.get(1).unwrap_or_default(),
             average_duration: row.get(2).unwrap_or_default(),
             longest_outage: row.get(3).unwrap_or_default(),
             shortest_outage: row.get(4).unwrap_or_default(),
+            average_detection_delay: row.get(5)?,
+            average_recovery_delay: row.get(6)?,
         })
     })?;
 
     Ok(stats)
 }
 
-fn print_recent_outages(conn: &Connection, limit: i64) -> Result<()> {
-    let mut stmt = conn.prepare("
+/// An outage as `recent` shows it, with its duration counted as asked for.
+struct RecentOutage {
+    outage: InternetOutage,
+    ack: Option<acks::Ack>,
+    /// Only looked up when the events are shown
+    events: Vec<OsEvent>,
+}
+
+fn recent_outages(
+    conn: &Connection,
+    limit: i64,
+    show_events: bool,
+    durations: DurationMode,
+    filter: &OutageFilter,
+) -> Result<Vec<RecentOutage>> {
+    let (condition, mut params) = filter.where_clause();
+    params.push(limit.into());
+    let mut stmt = conn.prepare(&format!("
         SELECT * FROM outages 
+        {condition}
         ORDER BY start_time DESC 
         LIMIT ?
-    ")?;
+    "))?;
+
+    let outages = read_outages(stmt.query_map(rusqlite::params_from_iter(params), InternetOutage::from_row)?)?;
+    outages
+        .into_iter()
+        .map(|outage| {
+            Ok(RecentOutage {
+                ack: acks::for_outage(conn, outage.id)?,
+                events: if show_events { get_outage_events(conn, outage.id)? } else { Vec::new() },
+                outage: InternetOutage {
+                    tags: tags::for_outage(conn, outage.id)?,
+                    duration_seconds: durations.of(&outage),
+                    ..outage
+                },
+            })
+        })
+        .collect()
+}
 
-    let outages = stmt.query_map([limit], InternetOutage::from_row)?;
+fn print_recent_outages(recent: &[RecentOutage], show_events: bool, format: output::Format, out: Output) -> Result<()> {
+    match format {
+        output::Format::Table => print_recent_outages_table(recent, show_events, out)?,
+        output::Format::Json => {
+            let outages = recent
+                .iter()
+                .map(|recent| {
+                    let mut value = serde_json::to_value(&recent.outage)?;
+                    let fields = value.as_object_mut().expect("outages serialize to objects");
+                    fields.insert("duration".to_string(), json!(format_hms(recent.outage.duration_seconds)));
+                    fields.insert(
+                        "acknowledged".to_string(),
+                        json!(recent.ack.as_ref().map(|ack| json!({ "by": ack.by, "at": ack.at, "comment": ack.comment }))),
+                    );
+                    if show_events {
+                        let events: Vec<_> = recent
+                            .events
+                            .iter()
+                            .map(|event| json!({ "time": event.time, "source": event.source, "message": event.message }))
+                            .collect();
+                        fields.insert("events".to_string(), json!(events));
+                    }
+                    Ok(value)
+                })
+                .collect::<Result<Vec<_>>>()?;
+            writeln!(io::stdout().lock(), "{}", serde_json::to_string_pretty(&outages)?)?;
+        }
+        output::Format::Csv => {
+            let outages: Vec<InternetOutage> = recent.iter().map(|recent| recent.outage.clone()).collect();
+            write!(io::stdout().lock(), "{}", export::csv(&outages, &export::Schema::V2))?;
+        }
+    }
+    Ok(())
+}
 
-    let mut table = AsciiTable::default();
-    table.column(0).set_header("Start Time").set_align(ascii_table::Align::Left);
-    table.column(1).set_header("End Time").set_align(ascii_table::Align::Left);
-    table.column(2).set_header("Duration (seconds)").set_align(ascii_table::Align::Right);
+fn print_recent_outages_table(recent: &[RecentOutage], show_events: bool, out: Output) -> Result<()> {
+    // Only show the optional columns when at least one outage was recorded with them
+    let show_wifi = recent.iter().any(|r| r.outage.wifi.is_some());
+    let show_weather = recent.iter().any(|r| r.outage.weather.is_some());
+    let show_tags = recent.iter().any(|r| !r.outage.tags.is_empty());
+    let show_site = recent.iter().any(|r| r.outage.site.is_some());
+    let show_acks = recent.iter().any(|r| r.ack.is_some());
+    let show_confirmed = recent.iter().any(|r| r.outage.confirmed_at.is_some_and(|at| at != r.outage.start_time));
+    let show_capture = recent.iter().any(|r| r.outage.capture_path.is_some());
+    let show_failed = recent.iter().any(|r| r.outage.failed_components.is_some());
+    let show_probes = recent.iter().any(|r| r.outage.failed_probes.is_some());
+    let show_actor = recent.iter().any(|r| r.outage.actor.is_some());
+    let show_severity = recent.iter().any(|r| r.outage.severity.is_some());
 
-    let mut data = Vec::new();
+    let mut columns = vec![(out.text(Text::Id), Align::Right)];
+    if show_site {
+        columns.push((out.text(Text::Site), Align::Left));
+    }
+    columns.extend([
+        (out.text(Text::StartTime), Align::Left),
+        (out.text(Text::EndTime), Align::Left),
+        (out.text(Text::DurationSeconds), Align::Right),
+    ]);
+    if show_severity {
+        columns.push((out.text(Text::Severity), Align::Left));
+    }
+    if show_confirmed {
+        columns.push((out.text(Text::ConfirmedAt), Align::Left));
+    }
+    if show_wifi {
+        columns.push((out.text(Text::WiFi), Align::Left));
+    }
+    if show_weather {
+        columns.push((out.text(Text::Weather), Align::Left));
+    }
+    if show_tags {
+        columns.push((out.text(Text::Tags), Align::Left));
+    }
+    if show_acks {
+        columns.push((out.text(Text::Acknowledged), Align::Left));
+    }
+    if show_capture {
+        columns.push((out.text(Text::Capture), Align::Left));
+    }
+    if show_failed {
+        columns.push((out.text(Text::FailedComponents), Align::Left));
+    }
+    if show_probes {
+        columns.push((out.text(Text::FailedProbes), Align::Left));
+    }
+    if show_actor {
+        columns.push((out.text(Text::ReportedBy), Align::Left));
+    }
 
-    for outage in outages {
-        let outage = outage.map_err(|e| anyhow::anyhow!(e))?;
+    let mut data = Vec::new();
 
-        data.push(vec![
+    for RecentOutage { outage, ack, .. } in recent {
+        let mut row = vec![outage.id.to_string()];
+        if show_site {
+            row.push(outage.site.as_deref().unwrap_or(sites::LOCAL_SITE).to_string());
+        }
+        row.extend([
             outage.start_time.format("%Y-%m-%d %H:%M:%S").to_string(),
             outage.end_time.format("%Y-%m-%d %H:%M:%S").to_string(),
             outage.duration_seconds.to_string(),
         ]);
+        if show_severity {
+            row.push(
+                outage
+                    .severity
+                    .map(|severity| out.colored(severity.name(), severity::color(severity), severity::RESET_COLOR))
+                    .unwrap_or_default(),
+            );
+        }
+        if show_confirmed {
+            row.push(outage.confirmed_at.map(|at| at.format("%Y-%m-%d %H:%M:%S").to_string()).unwrap_or_default());
+        }
+        if show_wifi {
+            row.push(outage.wifi.as_ref().map(WifiInfo::to_string).unwrap_or_default());
+        }
+        if show_weather {
+            row.push(outage.weather.as_ref().map(WeatherReport::to_string).unwrap_or_default());
+        }
+        if show_tags {
+            row.push(outage.tags.join(", "));
+        }
+        if show_acks {
+            row.push(ack.as_ref().map(acks::Ack::to_string).unwrap_or_default());
+        }
+        if show_capture {
+            row.push(outage.capture_path.clone().unwrap_or_default());
+        }
+        if show_failed {
+            row.push(outage.failed_components.clone().unwrap_or_default());
+        }
+        if show_probes {
+            row.push(outage.failed_probes.clone().unwrap_or_default());
+        }
+        if show_actor {
+            row.push(outage.actor.as_deref().unwrap_or(sites::LOCAL_SITE).to_string());
+        }
+        data.push(row);
     }
 
-    table.print(data);
+    out.print_table(&columns, data)?;
+
+    if show_events {
+        for RecentOutage { outage, events, .. } in recent {
+            if events.is_empty() {
+                continue;
+            }
 
+            println!(
+                "\n{} {}:",
+                out.text(Text::SystemEventsAround),
+                outage.start_time.format("%Y-%m-%d %H:%M:%S")
+            );
+            for event in events {
+                println!("  {} {}: {}", event.time.format("%Y-%m-%d %H:%M:%S"), event.source, event.message);
+            }
+        }
+    }
     Ok(())
 }
 
-fn generate_csv(conn: &Connection) -> Result<String> {
-    let mut wrt = BufWriter::new(Vec::new());
-
-    writeln!(wrt, "Start Time,End Time,Duration (seconds)")?;
-
-    let mut stmt = conn.prepare("SELECT * FROM outages ORDER BY start_time")?;
-    let outages = stmt.query_map([], InternetOutage::from_row)?;
+/// All outages matching `filter`, oldest first.
+fn get_outages(conn: &Connection, filter: &OutageFilter) -> Result<Vec<InternetOutage>> {
+    let (condition, params) = filter.where_clause();
+    let mut stmt = conn.prepare(&format!("SELECT * FROM outages {condition} ORDER BY start_time"))?;
+    let outages = read_outages(stmt.query_map(rusqlite::params_from_iter(params), InternetOutage::from_row)?)?;
+    Ok(outages)
+}
 
-    for outage in outages {
-        let outage = outage.map_err(|e| anyhow::anyhow!(e))?;
-        writeln!(
-            wrt,
-            "{},{},{}",
-            outage.start_time.to_rfc3339(),
-            outage.end_time.to_rfc3339(),
-            outage.duration_seconds
-        )?;
+/// Collects outages, leaving out the ones with unreadable timestamps with a
+/// warning, so a row written by another tool doesn't break the report.
+fn read_outages(rows: impl Iterator<Item = rusqlite::Result<InternetOutage>>) -> Result<Vec<InternetOutage>> {
+    let (outages, unreadable) = InternetOutage::readable(rows)?;
+    for row in &unreadable {
+        eprintln!("Warning: left out outage {}, its {} is not a time: {:?}", row.id, row.column, row.value);
     }
-
-    let data = wrt.into_inner()?;
-    String::from_utf8(data).map_err(Into::into)
+    if !unreadable.is_empty() {
+        eprintln!("Hint: `webgone db repair --timestamps` rewrites timestamps in other formats and lists the ones it can't read");
+    }
+    Ok(outages)
 }
 
-fn export_to_csv(conn: &Connection, filename: &Path) -> Result<()> {
-    use std::fs::File;
-    use std::io::Write;
-
-    let mut file = File::create(filename)?;
-    let data = generate_csv(conn)?;
-    file.write_all(data.as_bytes())?;
-
-    println!("Data exported to {}", filename.display());
+/// Warns about the outages reports leave out because SQLite can't read their
+/// start time, so they have no month to be counted in.
+fn warn_unreadable_start_times(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare("SELECT id, start_time FROM outages WHERE julianday(start_time) IS NULL")?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for (id, value) in &rows {
+        eprintln!("Warning: left out outage {}, its start_time is not a time: {:?}", id, value.as_deref().unwrap_or_default());
+    }
+    if !rows.is_empty() {
+        eprintln!("Hint: `webgone db repair --timestamps` rewrites timestamps in other formats and lists the ones it can't read");
+    }
     Ok(())
 }
 
-fn calculate_monthly_costs(conn: &Connection) -> Result<Vec<MonthlyOutage>> {
-    let mut stmt = conn.prepare("
-        SELECT 
-            strftime('%Y', start_time) as year,
-            strftime('%m', start_time) as month,
-            COUNT(*) as num_outages,
-            SUM(duration_seconds) as total_duration
-        FROM outages 
-        GROUP BY year, month
-        ORDER BY year DESC, month DESC
-    ")?;
-
-    let monthly_outages = stmt.query_map([], |row| {
-        Ok(MonthlyOutage {
-            year: row.get::<_, String>(0)?.parse().unwrap(),
-            month: row.get::<_, String>(1)?.parse().unwrap(),
-            num_outages: row.get(2)?,
-            total_seconds: row.get(3)?,
-        })
-    })?;
+fn format_hms(seconds: i64) -> String {
+    format!("{:02}:{:02}:{:02}", seconds / 3600, (seconds % 3600) / 60, seconds % 60)
+}
 
-    Ok(monthly_outages.collect::<Result<Vec<_>, _>>()?)
+/// An outage as the data model serializes it, with its tags and the duration
+/// as `HH:MM:SS`. Reports pass the format to show times in.
+fn outage_json(conn: &Connection, outage: &InternetOutage, time_format: Option<&str>) -> Result<serde_json::Value> {
+    let outage = InternetOutage { tags: tags::for_outage(conn, outage.id)?, ..outage.clone() };
+    let mut value = serde_json::to_value(&outage)?;
+    let fields = value.as_object_mut().expect("outages serialize to objects");
+    fields.insert("duration".to_string(), json!(format_hms(outage.duration_seconds)));
+    if let Some(format) = time_format {
+        let times = [
+            ("start_time", Some(outage.start_time)),
+            ("end_time", Some(outage.end_time)),
+            ("detected_at", outage.detected_at),
+            ("confirmed_at", outage.confirmed_at),
+            ("recovered_at", outage.recovered_at),
+        ];
+        for (field, time) in times {
+            fields.insert(field.to_string(), json!(time.map(|time| time.format(format).to_string())));
+        }
+    }
+    Ok(value)
 }
 
-fn print_cost_report(conn: &Connection, monthly_rate: f64, currency: &str) -> Result<()> {
-    let monthly_outages = calculate_monthly_costs(conn)?;
-    
-    println!("\nMonthly Cost Analysis:");
-
-    let mut table = AsciiTable::default();
-    table.column(0).set_header("Year").set_align(ascii_table::Align::Left);
-    table.column(1).set_header("Month").set_align(ascii_table::Align::Left);
-    table.column(2).set_header("Outages").set_align(ascii_table::Align::Right);
-    table.column(3).set_header("Total Time").set_align(ascii_table::Align::Right);
-    table.column(4).set_header("% Downtime").set_align(ascii_table::Align::Right);
-    table.column(5).set_header("Cost Impact").set_align(ascii_table::Align::Right);
-    table.column(6).set_header("Rate/Hour").set_align(ascii_table::Align::Right);
-
-    let mut total_cost = 0.0;
-    let mut total_seconds = 0_i64;
-    let mut total_months = 0;
-    let mut data = Vec::new();
+/// Most outages `/api/outages` returns at once.
+const API_MAX_OUTAGES: i64 = 1000;
+/// Period the per-site uptime in `/api/status` covers.
+const API_UPTIME_DAYS: u32 = 30;
 
-    for outage in &monthly_outages {
-        let month_name = match outage.month {
-            1 => "January",
-            2 => "February",
-            3 => "March",
-            4 => "April",
-            5 => "May",
-            6 => "June",
-            7 => "July",
-            8 => "August",
-            9 => "September",
-            10 => "October",
-            11 => "November",
-            12 => "December",
-            _ => "Unknown",
-        };
+/// Months of downtime the dashboard of `webgone web` charts.
+const DASHBOARD_MONTHS: u32 = 12;
+/// Outages the dashboard lists.
+const DASHBOARD_OUTAGES: i64 = 20;
 
-        // Calculate month-specific metrics
-        let days_in_month = match outage.month {
-            4 | 6 | 9 | 11 => 30.0,
-            2 => if outage.year % 4 == 0 && (outage.year % 100 != 0 || outage.year % 400 == 0) {
-                29.0
-            } else {
-                28.0
-            },
-            _ => 31.0,
-        };
-        
-        let seconds_in_month = days_in_month * 24.0 * 60.0 * 60.0;
-        let downtime_percentage = (outage.total_seconds as f64 / seconds_in_month) * 100.0;
-        let cost = (outage.total_seconds as f64 / seconds_in_month) * monthly_rate;
-        let hourly_rate = monthly_rate / (days_in_month * 24.0);
-
-        let hours = outage.total_seconds / 3600;
-        let minutes = (outage.total_seconds % 3600) / 60;
-        let seconds = outage.total_seconds % 60;
-
-        data.push(vec![
-            outage.year.to_string(),
-            month_name.to_string(),
-            outage.num_outages.to_string(),
-            format!("{:02}:{:02}:{:02}", hours, minutes, seconds),
-            format!("{:.3}%", downtime_percentage),
-            format!("{currency}{:.3}", cost),
-            format!("{currency}{:.3}/h", hourly_rate),
-        ]);
+/// What the dashboard of `webgone web` shows, for this machine's connection.
+fn dashboard_data(conn: &Connection, db_path: &Path, gaps: GapMode) -> Result<serde_json::Value> {
+    let now = Local::now();
+    let glance = glance::load(conn, now)?;
+    let local = sites::summaries(conn, API_UPTIME_DAYS, gaps)?
+        .into_iter()
+        .find(|site| site.site == sites::LOCAL_SITE);
 
-        total_cost += cost;
-        total_seconds += outage.total_seconds;
-        total_months += 1;
+    // Every month of the chart, oldest first, also those without outages
+    let (names, per_month) = sites::monthly_downtime(conn, DASHBOARD_MONTHS)?;
+    let index = names.iter().position(|name| name == sites::LOCAL_SITE);
+    let mut month = chrono::Datelike::with_day(&now.date_naive(), 1).expect("first of the month exists");
+    let mut months = Vec::new();
+    for _ in 0..DASHBOARD_MONTHS {
+        let key = month.format("%Y-%m").to_string();
+        let (outages, total_seconds) = per_month
+            .iter()
+            .find(|row| row.month == key)
+            .zip(index)
+            .map_or((0, 0), |(row, index)| row.sites[index]);
+        months.push(json!({
+            "year": month.year(),
+            "month": month.month(),
+            "outages": outages,
+            "total_seconds": total_seconds,
+        }));
+        month = month.pred_opt().and_then(|day| day.with_day(1)).expect("date in range");
     }
+    months.reverse();
 
-    table.print(data);
+    let mut stmt = conn.prepare(
+        "SELECT * FROM outages WHERE site IS NULL AND derived_from IS NULL ORDER BY julianday(start_time) DESC LIMIT ?",
+    )?;
+    let recent = read_outages(stmt.query_map([DASHBOARD_OUTAGES], InternetOutage::from_row)?)?
+        .iter()
+        .map(|outage| outage_json(conn, outage, None))
+        .collect::<Result<Vec<_>>>()?;
 
-    // Calculate and display overall statistics
-    if total_months > 0 {
-        let total_hours = total_seconds as f64 / 3600.0;
-        let avg_monthly_downtime = total_seconds as f64 / total_months as f64 / 3600.0;
-        let avg_cost_per_month = total_cost / total_months as f64;
-        let cost_per_hour = if total_hours > 0.0 { total_cost / total_hours } else { 0.0 };
+    Ok(json!({
+        "watcher_running": lock::watcher_pid(db_path).is_some(),
+        "down_since": glance.down_since.map(|time| time.to_rfc3339()),
+        "up_since": glance.up_since.map(|time| time.to_rfc3339()),
+        "downtime_today_seconds": glance.downtime_today_seconds,
+        "uptime_days": API_UPTIME_DAYS,
+        "uptime_percent": local.as_ref().map(|site| site.uptime_percent),
+        "outages": local.as_ref().map_or(0, |site| site.outages),
+        "downtime_seconds": local.as_ref().map_or(0, |site| site.downtime_seconds),
+        "months": months,
+        "recent": recent,
+    }))
+}
 
-        let mut summary_table = AsciiTable::default();
-        summary_table.column(0).set_header("Metric").set_align(ascii_table::Align::Left);
-        summary_table.column(1).set_header("Value").set_align(ascii_table::Align::Right);
+/// The JSON API served by `webgone serve`.
+struct WebApi<'a> {
+    conn: &'a Connection,
+    db_path: &'a Path,
+    /// How time without monitoring counts in the uptime of `/api/status`
+    gaps: GapMode,
+    /// Classifies outages posted to `/api/outages`
+    classifier: severity::Classifier,
+}
 
-        let summary_data = vec![
-            vec!["Total cost of outages".to_string(), format!("€{:.3}", total_cost)],
-            vec!["Average monthly cost".to_string(), format!("€{:.3}", avg_cost_per_month)],
-            vec!["Total downtime".to_string(), format!("{:.1} hours ({:.1} hours/month avg)", total_hours, avg_monthly_downtime)],
-            vec!["Cost per hour of downtime".to_string(), format!("€{:.3}/h", cost_per_hour)],
+impl server::Api for WebApi<'_> {
+    fn authenticate(&mut self, token: &str) -> Result<Option<(tokens::Scope, String)>> {
+        tokens::authenticate(self.conn, token)
+    }
+
+    fn get(&mut self, path: &str, query: &std::collections::HashMap<String, String>) -> Result<Option<serde_json::Value>> {
+        let snapshot = begin_snapshot(self.conn)?;
+        let filter = OutageFilter {
+            site: query.get("site").cloned(),
+            actor: query.get("actor").cloned(),
+            ..OutageFilter::default()
+        };
+        let response = match path.trim_end_matches('/') {
+            "/api/stats" => {
+                serde_json::to_value(get_stats(&snapshot, &filter, DurationMode::Raw)?)?
+            }
+            "/api/outages" => {
+                let limit = match query.get("limit") {
+                    Some(limit) => limit
+                        .parse::<i64>()
+                        .ok()
+                        .filter(|limit| (1..=API_MAX_OUTAGES).contains(limit))
+                        .ok_or_else(|| {
+                            WebgoneError::InvalidInput(format!("limit must be between 1 and {}", API_MAX_OUTAGES))
+                        })?,
+                    None => 20,
+                };
+                let (condition, mut params) = filter.where_clause();
+                params.push(limit.into());
+                let mut stmt = snapshot.prepare(&format!(
+                    "SELECT * FROM outages {condition} ORDER BY start_time DESC LIMIT ?"
+                ))?;
+                let outages = read_outages(stmt.query_map(rusqlite::params_from_iter(params), InternetOutage::from_row)?)?;
+                let outages = outages
+                    .iter()
+                    .map(|outage| outage_json(&snapshot, outage, None))
+                    .collect::<Result<Vec<_>>>()?;
+                json!({ "outages": outages })
+            }
+            "/api/status" => {
+                let status = health::load(&snapshot)?;
+                let sites = sites::summaries(&snapshot, API_UPTIME_DAYS, self.gaps)?
+                    .into_iter()
+                    .map(|site| {
+                        json!({
+                            "site": site.site,
+                            "state": site.state.map(|state| match state {
+                                sites::SiteState::Up => "up",
+                                sites::SiteState::Down => "down",
+                                sites::SiteState::Stale => "stale",
+                            }),
+                            "last_seen": site.last_seen.map(|time| time.to_rfc3339()),
+                            "outages_30d": site.outages,
+                            "downtime_seconds_30d": site.downtime_seconds,
+                            "uptime_percent_30d": site.uptime_percent,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                json!({
+                    "watcher_running": lock::watcher_pid(self.db_path).is_some(),
+                    "last_update": status.as_ref().map(|status| status.updated_at.to_rfc3339()),
+                    "notification_failures": status.as_ref().map(|status| status.metrics.notification_failures),
+                    "sites": sites,
+                })
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(response))
+    }
+
+    /// Lets remote agents report outages they recorded.
+    fn post(&mut self, path: &str, body: serde_json::Value, actor: Option<&str>) -> Result<Option<serde_json::Value>> {
+        if path.trim_end_matches('/') != "/api/outages" {
+            return Ok(None);
+        }
+
+        let time = |field: &str| -> Result<DateTime<Local>> {
+            body.get(field)
+                .and_then(|value| value.as_str())
+                .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+                .map(|time| time.with_timezone(&Local))
+                .ok_or_else(|| WebgoneError::InvalidInput(format!("{} must be an RFC 3339 timestamp", field)).into())
+        };
+        let start_time = time("start_time")?;
+        let end_time = time("end_time")?;
+        if end_time < start_time {
+            return Err(WebgoneError::InvalidInput("end_time is before start_time".to_string()).into());
+        }
+
+        let outage = InternetOutage {
+            id: 0,
+            site: None,
+            start_time,
+            detected_at: None,
+            confirmed_at: None,
+            end_time,
+            recovered_at: None,
+            duration_seconds: end_time.signed_duration_since(start_time).num_seconds(),
+            wifi: None,
+            weather: None,
+            tags: Vec::new(),
+            capture_path: None,
+            failed_components: None,
+            actor: actor.map(str::to_string),
+            clock_anomaly: false,
+            failed_probes: None,
+            severity: Some(self.classifier.classify(start_time, end_time)),
+            derived_from: None,
+        };
+        let id = log_outage(self.conn, &outage)?;
+        audit::record_as(self.conn, actor.unwrap_or("api"), "POST /api/outages", &[id], "")?;
+        Ok(Some(json!({ "id": id })))
+    }
+}
+
+/// Data available to report templates. The comparison is with the
+/// baseline named `baseline`, or the most recently imported one.
+fn report_context(conn: &Connection, filter: &OutageFilter, baseline: Option<&str>, lang: Lang) -> Result<serde_json::Value> {
+    let stats = get_stats(conn, filter, DurationMode::Raw)?;
+    let time_format = "%Y-%m-%d %H:%M:%S";
+
+    let outages = get_outages(conn, filter)?
+        .iter()
+        .map(|outage| outage_json(conn, outage, Some(time_format)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let months = calculate_monthly_costs(conn, filter)?
+        .into_iter()
+        .map(|month| {
+            let mut value = serde_json::to_value(&month)?;
+            let fields = value.as_object_mut().expect("months serialize to objects");
+            fields.insert("month_name".to_string(), json!(lang.month_name(month.month)));
+            fields.insert("total_time".to_string(), json!(format_hms(month.total_seconds)));
+            Ok(value)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut stats_json = serde_json::to_value(&stats)?;
+    let fields = stats_json.as_object_mut().expect("stats serialize to objects");
+    fields.insert("total_duration".to_string(), json!(format_hms(stats.total_duration)));
+    fields.insert("average_duration_seconds".to_string(), json!(format!("{:.2}", stats.average_duration)));
+
+    let baseline = baseline::find(conn, baseline)?;
+    let comparison =
+        baseline::comparison(conn, baseline.as_ref(), filter.since, filter.until, stats.total_outages, stats.total_duration)?;
+
+    Ok(json!({
+        "generated_at": Local::now().format(time_format).to_string(),
+        "version": env!("CARGO_PKG_VERSION"),
+        "stats": stats_json,
+        "months": months,
+        "outages": outages,
+        "comparison": comparison,
+    }))
+}
+
+/// Outages with their tags, as exported.
+fn export_outages(conn: &Connection, filter: &OutageFilter) -> Result<Vec<InternetOutage>> {
+    let mut outages = get_outages(conn, filter)?;
+    for outage in &mut outages {
+        outage.tags = tags::for_outage(conn, outage.id)?;
+    }
+    Ok(outages)
+}
+
+fn export_to_file(filename: &Path, data: &str) -> Result<()> {
+    std::fs::write(filename, data)?;
+    println!("Data exported to {}", filename.display());
+    Ok(())
+}
+
+/// Rebuilds the cached per-month aggregates from the raw outage rows.
+fn refresh_monthly_aggregates(conn: &Connection) -> Result<()> {
+    conn.execute_batch("
+        BEGIN;
+        DELETE FROM monthly_aggregates;
+        INSERT INTO monthly_aggregates (year, month, num_outages, total_seconds)
+        SELECT 
+            CAST(strftime('%Y', start_time) AS INTEGER) as year,
+            CAST(strftime('%m', start_time) AS INTEGER) as month,
+            COUNT(*) as num_outages,
+            SUM(duration_seconds) as total_duration
+        FROM outages 
+        WHERE derived_from IS NULL AND julianday(start_time) IS NOT NULL
+        GROUP BY year, month;
+        COMMIT;
+    ")?;
+    Ok(())
+}
+
+/// Rebuilds the monthly aggregate cache if it was invalidated.
+///
+/// The cache is emptied by triggers whenever outages change, so an empty
+/// cache with outages present means it needs rebuilding.
+fn ensure_monthly_aggregates(conn: &Connection) -> Result<()> {
+    if monthly_aggregates_stale(conn)? {
+        refresh_monthly_aggregates(conn)?;
+    }
+    Ok(())
+}
+
+fn monthly_aggregates_stale(conn: &Connection) -> Result<bool> {
+    let stale = conn.query_row(
+        "SELECT NOT EXISTS (SELECT 1 FROM monthly_aggregates) AND EXISTS (SELECT 1 FROM outages)",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(stale)
+}
+
+fn calculate_monthly_costs(conn: &Connection, filter: &OutageFilter) -> Result<Vec<MonthlyOutage>> {
+    // Reports run in a read-only snapshot, so if the watcher invalidated the
+    // cache in the meantime, aggregate the raw rows directly instead. The cache
+    // only covers all outages, so filtered reports aggregate the raw rows too.
+    let (condition, params) = filter.where_clause();
+    let query = if !filter.is_empty() || monthly_aggregates_stale(conn)? {
+        format!("SELECT 
+            CAST(strftime('%Y', start_time) AS INTEGER) as year,
+            CAST(strftime('%m', start_time) AS INTEGER) as month,
+            COUNT(*) as num_outages,
+            SUM(duration_seconds) as total_duration
+        FROM outages 
+        {condition}
+        GROUP BY year, month
+        ORDER BY year DESC, month DESC")
+    } else {
+        "SELECT year, month, num_outages, total_seconds
+        FROM monthly_aggregates
+        ORDER BY year DESC, month DESC".to_string()
+    };
+    let mut stmt = conn.prepare(&query)?;
+
+    let monthly_outages = stmt.query_map(rusqlite::params_from_iter(params), |row| {
+        Ok(MonthlyOutage {
+            year: row.get(0)?,
+            month: row.get(1)?,
+            num_outages: row.get(2)?,
+            total_seconds: row.get(3)?,
+        })
+    })?;
+
+    Ok(monthly_outages.collect::<Result<Vec<_>, _>>()?)
+}
+
+/// Sum of the heat scores of the outages per year and month they started in.
+fn monthly_heat_scores(conn: &Connection, filter: &OutageFilter) -> Result<std::collections::HashMap<(i32, u32), f64>> {
+    let (condition, params) = filter.where_clause();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT CAST(strftime('%Y', start_time) AS INTEGER), CAST(strftime('%m', start_time) AS INTEGER), duration_seconds
+        FROM outages
+        {condition}"
+    ))?;
+    let rows = stmt.query_map(rusqlite::params_from_iter(params), |row| {
+        Ok((row.get::<_, i32>(0)?, row.get::<_, u32>(1)?, row.get::<_, i64>(2)?))
+    })?;
+    let mut scores = std::collections::HashMap::new();
+    for row in rows {
+        let (year, month, duration) = row?;
+        *scores.entry((year, month)).or_insert(0.0) += webgone::analysis::heat_score(duration);
+    }
+    Ok(scores)
+}
+
+/// One month of the cost report.
+#[derive(Serialize)]
+struct MonthCost {
+    year: i32,
+    month: u32,
+    outages: i64,
+    downtime_seconds: i64,
+    downtime_percent: f64,
+    cost: f64,
+    rate_per_hour: f64,
+    heat_score: f64,
+    /// Impact entries of the month's outages, and the sum of their amounts
+    impacts: i64,
+    impact_cost: f64,
+}
+
+/// Totals over the months of the cost report.
+#[derive(Serialize)]
+struct CostSummary {
+    total_cost: f64,
+    average_monthly_cost: f64,
+    total_downtime_hours: f64,
+    average_monthly_downtime_hours: f64,
+    cost_per_hour_of_downtime: f64,
+    total_impact_cost: f64,
+    /// The prorated subscription cost plus the impact
+    total_cost_with_impact: f64,
+}
+
+/// A month projected by `cost --forecast`.
+#[derive(Serialize)]
+struct ForecastCost {
+    year: i32,
+    month: u32,
+    downtime_seconds: f64,
+    downtime_percent: f64,
+    seasonal_factor: f64,
+    cost: f64,
+}
+
+/// What `cost` reports, before it is rendered.
+#[derive(Serialize)]
+struct CostReport {
+    currency: String,
+    monthly_rate: f64,
+    months: Vec<MonthCost>,
+    /// `None` without any outages
+    summary: Option<CostSummary>,
+    /// Impact entries by description, see `webgone impact`
+    impacts: Vec<impacts::Summary>,
+    /// Only with `--forecast`; empty if there isn't enough history for one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    forecast: Option<Vec<ForecastCost>>,
+}
+
+type CostGroupings = (
+    Vec<MonthlyOutage>,
+    std::collections::HashMap<(i32, u32), f64>,
+    std::collections::HashMap<(i32, u32), impacts::MonthImpact>,
+    Vec<impacts::Summary>,
+);
+
+/// Computes the groupings of the cost report in parallel, each on a snapshot
+/// of its own, or one after the other on `conn` if no consistent snapshots
+/// could be taken.
+fn cost_groupings(conn: &Connection, db_path: &Path, filter: &OutageFilter) -> Result<CostGroupings> {
+    let Some([monthly, heat, by_month, summary]) = parallel_snapshots(db_path)? else {
+        return Ok((
+            calculate_monthly_costs(conn, filter)?,
+            monthly_heat_scores(conn, filter)?,
+            impacts::by_month(conn, filter)?,
+            impacts::summary(conn, filter)?,
+        ));
+    };
+    fn join<T>(handle: thread::ScopedJoinHandle<'_, T>) -> T {
+        handle.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    }
+    thread::scope(|scope| {
+        let monthly = scope.spawn(move || calculate_monthly_costs(&monthly, filter));
+        let heat = scope.spawn(move || monthly_heat_scores(&heat, filter));
+        let by_month = scope.spawn(move || impacts::by_month(&by_month, filter));
+        let summary = scope.spawn(move || impacts::summary(&summary, filter));
+        Ok((join(monthly)?, join(heat)?, join(by_month)?, join(summary)?))
+    })
+}
+
+fn cost_report(
+    conn: &Connection,
+    db_path: &Path,
+    monthly_rate: f64,
+    currency: &str,
+    forecast_months: Option<u32>,
+    filter: &OutageFilter,
+) -> Result<CostReport> {
+    let (monthly_outages, heat_scores, month_impacts, impact_summary) = cost_groupings(conn, db_path, filter)?;
+
+    let months: Vec<MonthCost> = monthly_outages
+        .iter()
+        .map(|outage| MonthCost {
+            year: outage.year,
+            month: outage.month,
+            outages: outage.num_outages,
+            downtime_seconds: outage.total_seconds,
+            downtime_percent: outage.downtime_percent(),
+            cost: outage.cost(monthly_rate),
+            rate_per_hour: monthly_rate / (outage.days() * 24.0),
+            heat_score: heat_scores.get(&(outage.year, outage.month)).copied().unwrap_or_default(),
+            impacts: month_impacts.get(&(outage.year, outage.month)).map_or(0, |impact| impact.entries),
+            impact_cost: month_impacts.get(&(outage.year, outage.month)).map_or(0.0, |impact| impact.amount),
+        })
+        .collect();
+
+    let summary = (!months.is_empty()).then(|| {
+        let total_cost: f64 = months.iter().map(|month| month.cost).sum();
+        let total_hours = months.iter().map(|month| month.downtime_seconds).sum::<i64>() as f64 / 3600.0;
+        let total_impact_cost: f64 = months.iter().map(|month| month.impact_cost).sum();
+        CostSummary {
+            total_cost,
+            average_monthly_cost: total_cost / months.len() as f64,
+            total_downtime_hours: total_hours,
+            average_monthly_downtime_hours: total_hours / months.len() as f64,
+            cost_per_hour_of_downtime: if total_hours > 0.0 { total_cost / total_hours } else { 0.0 },
+            total_impact_cost,
+            total_cost_with_impact: total_cost + total_impact_cost,
+        }
+    });
+
+    let forecast = forecast_months.map(|months| {
+        let history: Vec<_> = monthly_outages.iter().map(|month| (month.year, month.month, month.total_seconds)).collect();
+        forecast::project(&history, Local::now().date_naive(), months)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|month| {
+                let share = month.downtime_seconds / month.seconds_in_month();
+                ForecastCost {
+                    year: month.year,
+                    month: month.month,
+                    downtime_seconds: month.downtime_seconds,
+                    downtime_percent: share * 100.0,
+                    seasonal_factor: month.seasonal_factor,
+                    cost: share * monthly_rate,
+                }
+            })
+            .collect()
+    });
+
+    Ok(CostReport {
+        currency: currency.to_string(),
+        monthly_rate,
+        months,
+        summary,
+        impacts: impact_summary,
+        forecast,
+    })
+}
+
+fn print_cost_report(report: &CostReport, format: output::Format, out: Output) -> Result<()> {
+    match format {
+        output::Format::Table => print_cost_report_table(report, out)?,
+        output::Format::Json => writeln!(io::stdout().lock(), "{}", serde_json::to_string_pretty(report)?)?,
+        // One row per month, the projected ones after the recorded ones
+        output::Format::Csv => {
+            let mut rows: Vec<Vec<String>> = report
+                .months
+                .iter()
+                .map(|month| {
+                    vec![
+                        month.year.to_string(),
+                        month.month.to_string(),
+                        "false".to_string(),
+                        month.outages.to_string(),
+                        month.downtime_seconds.to_string(),
+                        month.downtime_percent.to_string(),
+                        month.cost.to_string(),
+                        month.rate_per_hour.to_string(),
+                        month.heat_score.to_string(),
+                        String::new(),
+                        month.impacts.to_string(),
+                        month.impact_cost.to_string(),
+                    ]
+                })
+                .collect();
+            for month in report.forecast.iter().flatten() {
+                rows.push(vec![
+                    month.year.to_string(),
+                    month.month.to_string(),
+                    "true".to_string(),
+                    String::new(),
+                    month.downtime_seconds.round().to_string(),
+                    month.downtime_percent.to_string(),
+                    month.cost.to_string(),
+                    String::new(),
+                    String::new(),
+                    month.seasonal_factor.to_string(),
+                    String::new(),
+                    String::new(),
+                ]);
+            }
+            output::print_csv(
+                &[
+                    "year",
+                    "month",
+                    "forecast",
+                    "outages",
+                    "downtime_seconds",
+                    "downtime_percent",
+                    "cost",
+                    "rate_per_hour",
+                    "heat_score",
+                    "seasonal_factor",
+                    "impacts",
+                    "impact_cost",
+                ],
+                rows,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn print_cost_report_table(report: &CostReport, out: Output) -> Result<()> {
+    let currency = &report.currency;
+    println!("\n{}", out.text(Text::MonthlyCostAnalysis));
+
+    // Only show the impact columns once some were recorded
+    let show_impacts = !report.impacts.is_empty();
+    let mut columns = vec![
+        (out.text(Text::Year), Align::Left),
+        (out.text(Text::Month), Align::Left),
+        (out.text(Text::Outages), Align::Right),
+        (out.text(Text::TotalTime), Align::Right),
+        (out.text(Text::PercentDowntime), Align::Right),
+        (out.text(Text::CostImpact), Align::Right),
+        (out.text(Text::RatePerHour), Align::Right),
+        (out.text(Text::HeatScore), Align::Right),
+    ];
+    if show_impacts {
+        columns.extend([(out.text(Text::Impacts), Align::Right), (out.text(Text::Amount), Align::Right)]);
+    }
+    let data = report
+        .months
+        .iter()
+        .map(|month| {
+            let mut row = vec![
+                month.year.to_string(),
+                out.lang.month_name(month.month).to_string(),
+                month.outages.to_string(),
+                format_hms(month.downtime_seconds),
+                format!("{:.3}%", month.downtime_percent),
+                format!("{currency}{:.3}", month.cost),
+                format!("{currency}{:.3}/h", month.rate_per_hour),
+                format!("{:.1}", month.heat_score),
+            ];
+            if show_impacts {
+                row.extend([month.impacts.to_string(), format!("{currency}{:.2}", month.impact_cost)]);
+            }
+            row
+        })
+        .collect();
+    out.print_table(&columns, data)?;
+
+    match &report.summary {
+        Some(summary) => {
+            println!("{}", out.text(Text::HeatScoreNote));
+            let mut summary_data = vec![
+                (out.text(Text::TotalCost).to_string(), format!("{currency}{:.3}", summary.total_cost)),
+                (out.text(Text::AverageMonthlyCost).to_string(), format!("{currency}{:.3}", summary.average_monthly_cost)),
+                (
+                    out.text(Text::TotalDowntime).to_string(),
+                    format!(
+                        "{:.1} {} ({:.1} {})",
+                        summary.total_downtime_hours,
+                        out.text(Text::Hours),
+                        summary.average_monthly_downtime_hours,
+                        out.text(Text::HoursPerMonthAvg)
+                    ),
+                ),
+                (out.text(Text::CostPerHourOfDowntime).to_string(), format!("{currency}{:.3}/h", summary.cost_per_hour_of_downtime)),
+            ];
+            if show_impacts {
+                summary_data.extend([
+                    (out.text(Text::TotalImpactCost).to_string(), format!("{currency}{:.2}", summary.total_impact_cost)),
+                    (out.text(Text::TotalCostWithImpact).to_string(), format!("{currency}{:.2}", summary.total_cost_with_impact)),
+                ]);
+            }
+
+            println!("\n{}", out.text(Text::Summary));
+            out.print_key_values(summary_data)?;
+            println!();
+
+            if show_impacts {
+                let columns = [
+                    (out.text(Text::Impact), Align::Left),
+                    (out.text(Text::Count), Align::Right),
+                    (out.text(Text::Outages), Align::Right),
+                    (out.text(Text::Amount), Align::Right),
+                ];
+                let data = report
+                    .impacts
+                    .iter()
+                    .map(|impact| {
+                        vec![
+                            impact.description.clone(),
+                            impact.entries.to_string(),
+                            impact.outages.to_string(),
+                            format!("{currency}{:.2}", impact.amount),
+                        ]
+                    })
+                    .collect();
+                println!("{}", out.text(Text::ImpactSummary));
+                out.print_table(&columns, data)?;
+                println!();
+            }
+        }
+        None => println!("\n{}\n", out.text(Text::NoOutages)),
+    }
+
+    let Some(forecast) = &report.forecast else {
+        return Ok(());
+    };
+    if forecast.is_empty() {
+        println!("{}\n", out.text(Text::NotEnoughHistory));
+        return Ok(());
+    }
+    let columns = [
+        (out.text(Text::Year), Align::Left),
+        (out.text(Text::Month), Align::Left),
+        (out.text(Text::ExpectedDowntime), Align::Right),
+        (out.text(Text::PercentDowntime), Align::Right),
+        (out.text(Text::Seasonality), Align::Right),
+        (out.text(Text::ExpectedCost), Align::Right),
+    ];
+    let data = forecast
+        .iter()
+        .map(|month| {
+            vec![
+                month.year.to_string(),
+                out.lang.month_name(month.month).to_string(),
+                format_hms(month.downtime_seconds.round() as i64),
+                format!("{:.3}%", month.downtime_percent),
+                format!("×{:.2}", month.seasonal_factor),
+                format!("{currency}{:.3}", month.cost),
+            ]
+        })
+        .collect();
+    let total_cost: f64 = forecast.iter().map(|month| month.cost).sum();
+
+    println!("{}", out.text(Text::CostForecast));
+    out.print_table(&columns, data)?;
+    out.print_key_values(vec![(out.text(Text::ExpectedCost).to_string(), format!("{currency}{:.3}", total_cost))])?;
+    println!("{}\n", out.text(Text::ForecastBasis));
+    Ok(())
+}
+
+fn get_target_health(conn: &Connection) -> Result<Vec<TargetHealth>> {
+    let mut targets: Vec<TargetHealth> = Vec::new();
+
+    for (index, (_, modifier)) in HEALTH_WINDOWS.iter().enumerate() {
+        let mut stmt = conn.prepare("
+            SELECT target, SUM(weight), SUM(CASE WHEN success THEN 0 ELSE weight END)
+            FROM probes
+            WHERE julianday(time) >= julianday('now', ?)
+            GROUP BY target
+        ")?;
+        let rows = stmt.query_map([modifier], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?, row.get::<_, i64>(2)?))
+        })?;
+
+        for row in rows {
+            let (target, probes, failures) = row?;
+            let position = match targets.iter().position(|t| t.target == target) {
+                Some(position) => position,
+                None => {
+                    targets.push(TargetHealth {
+                        target,
+                        windows: vec![(0, 0); HEALTH_WINDOWS.len()],
+                        median_latency_ms: None,
+                    });
+                    targets.len() - 1
+                }
+            };
+            targets[position].windows[index] = (probes, failures);
+        }
+    }
+
+    // SQLite has no median aggregate, so compute it from the sorted latencies
+    let mut stmt = conn.prepare("
+        SELECT latency_ms FROM probes
+        WHERE target = ? AND success AND julianday(time) >= julianday('now', '-1 day')
+        ORDER BY latency_ms
+    ")?;
+    for health in &mut targets {
+        let latencies = stmt
+            .query_map([&health.target], |row| row.get::<_, f64>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        health.median_latency_ms = match latencies.len() {
+            0 => None,
+            n if n % 2 == 0 => Some((latencies[n / 2 - 1] + latencies[n / 2]) / 2.0),
+            n => Some(latencies[n / 2]),
+        };
+    }
+
+    targets.sort_by(|a, b| a.target.cmp(&b.target));
+    Ok(targets)
+}
+
+fn print_target_health(conn: &Connection, out: Output) -> Result<()> {
+    let targets = get_target_health(conn)?;
+    if targets.is_empty() {
+        println!("\n{}\n", out.text(Text::NoProbes));
+        return Ok(());
+    }
+
+    let failure_headers: Vec<String> = HEALTH_WINDOWS
+        .iter()
+        .map(|(label, _)| format!("{} {}", out.text(Text::Failures), label))
+        .collect();
+    let mut columns = vec![(out.text(Text::Target), Align::Left)];
+    columns.extend(failure_headers.iter().map(|header| (header.as_str(), Align::Right)));
+    columns.push((out.text(Text::MedianLatency24h), Align::Right));
+
+    let mut data = Vec::new();
+    for health in targets {
+        let mut row = vec![health.target];
+        for (probes, failures) in &health.windows {
+            if *probes == 0 {
+                row.push("-".to_string());
+            } else {
+                let rate = *failures as f64 / *probes as f64 * 100.0;
+                row.push(format!("{:.1}% ({}/{})", rate, failures, probes));
+            }
+        }
+        row.push(match health.median_latency_ms {
+            Some(latency) => format!("{:.1} ms", latency),
+            None => "-".to_string(),
+        });
+        data.push(row);
+    }
+
+    println!("\n{}", out.text(Text::PerTargetHealth));
+    out.print_table(&columns, data)?;
+    println!();
+
+    Ok(())
+}
+
+fn dns_benchmark_row(summary: &dnsbench::ResolverSummary) -> Vec<String> {
+    let ms = |value: Option<f64>| value.map(|ms| format!("{:.1} ms", ms)).unwrap_or_else(|| "-".to_string());
+    vec![
+        summary.name.clone(),
+        summary.address.clone(),
+        format!("{:.1}% ({}/{})", summary.failure_percent(), summary.failures, summary.queries),
+        ms(summary.median_ms),
+        ms(summary.average_ms),
+        ms(summary.p95_ms),
+    ]
+}
+
+fn dns_benchmark_columns(out: Output) -> Vec<(&'static str, Align)> {
+    vec![
+        (out.text(Text::Resolver), Align::Left),
+        (out.text(Text::Address), Align::Left),
+        (out.text(Text::Failures), Align::Right),
+        (out.text(Text::Median), Align::Right),
+        (out.text(Text::Average), Align::Right),
+        ("P95", Align::Right),
+    ]
+}
+
+fn print_dns_benchmark(summaries: &[dnsbench::ResolverSummary], out: Output) -> Result<()> {
+    println!("\n{}", out.text(Text::DnsBenchmark));
+    out.print_table(&dns_benchmark_columns(out), summaries.iter().map(dns_benchmark_row).collect())?;
+    println!();
+    Ok(())
+}
+
+fn print_dns_benchmark_history(conn: &Connection, runs: u32, out: Output) -> Result<()> {
+    let history = dnsbench::history(conn, runs)?;
+    if history.is_empty() {
+        println!("\n{}\n", out.text(Text::NoDnsBenchmarks));
+        return Ok(());
+    }
+
+    let mut columns = vec![(out.text(Text::Time), Align::Left)];
+    columns.extend(dns_benchmark_columns(out));
+    let data = history
+        .iter()
+        .map(|(run_at, summary)| {
+            let mut row = vec![run_at.format("%Y-%m-%d %H:%M").to_string()];
+            row.extend(dns_benchmark_row(summary));
+            row
+        })
+        .collect();
+
+    println!("\n{}", out.text(Text::DnsBenchmark));
+    out.print_table(&columns, data)?;
+    println!();
+    Ok(())
+}
+
+fn print_route_changes(conn: &Connection, days: u32, out: Output) -> Result<()> {
+    let changes = routes::changes(conn, days)?;
+    if changes.is_empty() {
+        println!("\n{}\n", out.text(Text::NoRouteChanges));
+        return Ok(());
+    }
+
+    let ms = |value: Option<f64>| value.map(|ms| format!("{:.1} ms", ms)).unwrap_or_else(|| "-".to_string());
+    let columns = [
+        (out.text(Text::Time), Align::Left),
+        (out.text(Text::Anchor), Align::Left),
+        (out.text(Text::ChangedHops), Align::Right),
+        (out.text(Text::Hops), Align::Right),
+        (out.text(Text::LatencyBefore), Align::Right),
+        (out.text(Text::LatencyAfter), Align::Right),
+        (out.text(Text::Outages), Align::Right),
+    ];
+    let data = changes
+        .iter()
+        .map(|change| {
+            vec![
+                change.traced_at.format("%Y-%m-%d %H:%M").to_string(),
+                change.anchor.clone(),
+                change.changed_hops.to_string(),
+                change.hop_count.to_string(),
+                ms(change.latency_before_ms),
+                ms(change.latency_after_ms),
+                change.outages.to_string(),
+            ]
+        })
+        .collect();
+
+    println!("\n{}", out.text(Text::RouteChanges));
+    out.print_table(&columns, data)?;
+    println!();
+
+    Ok(())
+}
+
+fn print_audit_log(conn: &Connection, limit: u32, actor: Option<&str>, out: Output) -> Result<()> {
+    let entries = audit::list(conn, limit, actor)?;
+    if entries.is_empty() {
+        println!("\n{}\n", out.text(Text::NoAuditEntries));
+        return Ok(());
+    }
+
+    let columns = [
+        (out.text(Text::Time), Align::Left),
+        (out.text(Text::Actor), Align::Left),
+        (out.text(Text::Command), Align::Left),
+        (out.text(Text::Ids), Align::Left),
+        (out.text(Text::Details), Align::Left),
+    ];
+    let data = entries
+        .iter()
+        .map(|entry| {
+            let ids: Vec<String> = entry.ids.iter().map(i64::to_string).collect();
+            vec![
+                entry.time.format("%Y-%m-%d %H:%M:%S").to_string(),
+                entry.actor.clone(),
+                entry.command.clone(),
+                ids.join(", "),
+                entry.details.clone(),
+            ]
+        })
+        .collect();
+
+    println!("\n{}", out.text(Text::AuditLog));
+    out.print_table(&columns, data)?;
+    println!();
+
+    Ok(())
+}
+
+/// Width of the bars of `webgone bars`, in cells.
+const BAR_WIDTH: usize = 16;
+
+fn print_monitored_periods(conn: &Connection, days: u32, out: Output) -> Result<()> {
+    let now = Local::now();
+    let periods = coverage::periods(conn, now - chrono::Duration::days(days.into()), now)?;
+    if periods.is_empty() {
+        println!("\n{}\n", out.text(Text::NoMonitoredPeriods));
+        return Ok(());
+    }
+
+    let columns = [
+        (out.text(Text::StartTime), Align::Left),
+        (out.text(Text::EndTime), Align::Left),
+        (out.text(Text::Duration), Align::Right),
+        (out.text(Text::Host), Align::Left),
+        (out.text(Text::OperatingSystem), Align::Left),
+        (out.text(Text::Version), Align::Left),
+        (out.text(Text::Configuration), Align::Left),
+    ];
+    let data = periods
+        .into_iter()
+        .rev()
+        .map(|period| {
+            vec![
+                period.start.format("%Y-%m-%d %H:%M:%S").to_string(),
+                period.end.format("%Y-%m-%d %H:%M:%S").to_string(),
+                format_hms((period.end - period.start).num_seconds()),
+                period.hostname.unwrap_or_default(),
+                period.os.unwrap_or_default(),
+                period.version.unwrap_or_default(),
+                period.config.unwrap_or_default(),
+            ]
+        })
+        .collect();
+
+    println!("\n{}", out.text(Text::MonitoredPeriods));
+    out.print_table(&columns, data)?;
+    println!();
+    Ok(())
+}
+
+fn print_degraded_periods(conn: &Connection, days: u32, out: Output) -> Result<()> {
+    let periods = degraded::list(conn, Local::now() - chrono::Duration::days(days.into()))?;
+    if periods.is_empty() {
+        println!("\n{}\n", out.text(Text::NoDegradedPeriods));
+        return Ok(());
+    }
+
+    let columns = [
+        (out.text(Text::StartTime), Align::Left),
+        (out.text(Text::EndTime), Align::Left),
+        (out.text(Text::Duration), Align::Right),
+        (out.text(Text::Probes), Align::Right),
+        (out.text(Text::Failures), Align::Right),
+        (out.text(Text::Loss), Align::Right),
+    ];
+    let data = periods
+        .iter()
+        .map(|period| {
+            vec![
+                period.start.format("%Y-%m-%d %H:%M:%S").to_string(),
+                period.end.format("%Y-%m-%d %H:%M:%S").to_string(),
+                format_hms(period.duration_seconds()),
+                period.checks.to_string(),
+                period.failures.to_string(),
+                format!("{:.1}%", period.loss_percent()),
+            ]
+        })
+        .collect();
+
+    println!("\n{}", out.text(Text::DegradedPeriods));
+    out.print_table(&columns, data)?;
+    println!();
+    Ok(())
+}
+
+fn print_degraded_stats(conn: &Connection, days: u32, out: Output) -> Result<()> {
+    let periods = degraded::list(conn, Local::now() - chrono::Duration::days(days.into()))?;
+    if periods.is_empty() {
+        println!("\n{}\n", out.text(Text::NoDegradedPeriods));
+        return Ok(());
+    }
+
+    let summary = degraded::summarize(&periods);
+    println!("\n{}", out.text(Text::DegradedStatsTitle));
+    out.separator()?;
+    println!("{}: {}", out.text(Text::DegradedPeriodCount), summary.periods);
+    println!("{}: {}", out.text(Text::TotalDegradedTime), format_hms(summary.total_seconds));
+    println!("{}: {}", out.text(Text::LongestDegradedPeriod), format_hms(summary.longest_seconds));
+    println!("{}: {:.1}%", out.text(Text::AverageLoss), summary.average_loss_percent);
+    println!("{}: {:.1}%", out.text(Text::WorstLoss), summary.worst_loss_percent);
+    println!();
+    Ok(())
+}
+
+fn print_downtime_bars(conn: &Connection, weeks: u32, out: Output) -> Result<()> {
+    let bars = bars::weeks(conn, weeks, Local::now())?;
+    let max = bars.iter().map(|week| week.downtime_seconds).max().unwrap_or(0);
+
+    // The bars only repeat the downtime column, which screen readers read anyway
+    let mut columns = vec![(out.text(Text::Week), Align::Left)];
+    if !out.plain {
+        columns.push(("", Align::Left));
+    }
+    columns.extend([
+        (out.text(Text::Outages), Align::Right),
+        (out.text(Text::Downtime), Align::Right),
+        (out.text(Text::Uptime), Align::Right),
+        (out.text(Text::WorstDay), Align::Left),
+    ]);
+    let data = bars
+        .iter()
+        .map(|week| {
+            let mut row = vec![week.start.format("%Y-%m-%d").to_string()];
+            if !out.plain {
+                row.push(format!("{:<width$}", bars::render(week.downtime_seconds, max, BAR_WIDTH), width = BAR_WIDTH));
+            }
+            row.extend([
+                week.outages.to_string(),
+                format_hms(week.downtime_seconds),
+                format!("{:.3}%", week.uptime_percent()),
+                week.worst_day
+                    .map(|(day, seconds)| format!("{} ({})", day.format("%Y-%m-%d"), format_hms(seconds)))
+                    .unwrap_or_else(|| "-".to_string()),
+            ]);
+            row
+        })
+        .collect();
+
+    println!("\n{}", out.text(Text::WeeklyDowntime));
+    out.print_table(&columns, data)?;
+    if !out.plain && max > 0 {
+        println!("{} ({}).", out.text(Text::BarsScale), format_hms(max));
+    }
+    println!();
+
+    Ok(())
+}
+
+fn print_latency(conn: &Connection, since: DateTime<Local>, until: DateTime<Local>, out: Output) -> Result<()> {
+    let stats = latency::stats(conn, since, until)?;
+    if stats.is_empty() {
+        println!("\n{}\n", out.text(Text::NoLatency));
+        return Ok(());
+    }
+
+    let ms = |value: f64| format!("{:.1} ms", value);
+    let columns = [
+        (out.text(Text::Target), Align::Left),
+        (out.text(Text::Probes), Align::Right),
+        (out.text(Text::Minimum), Align::Right),
+        (out.text(Text::Average), Align::Right),
+        ("P95", Align::Right),
+        ("P99", Align::Right),
+        (out.text(Text::Maximum), Align::Right),
+    ];
+    let data = stats
+        .iter()
+        .map(|stats| {
+            vec![
+                stats.target.clone().unwrap_or_else(|| out.text(Text::AllTargets).to_string()),
+                stats.probes.to_string(),
+                ms(stats.min_ms),
+                ms(stats.avg_ms),
+                ms(stats.p95_ms),
+                ms(stats.p99_ms),
+                ms(stats.max_ms),
+            ]
+        })
+        .collect();
+
+    println!(
+        "\n{} {} - {}",
+        out.text(Text::LatencyReport),
+        since.format("%Y-%m-%d %H:%M"),
+        until.format("%Y-%m-%d %H:%M")
+    );
+    out.print_table(&columns, data)?;
+    if stats.iter().any(|stats| stats.estimated) {
+        println!("{}", out.text(Text::EstimatedPercentiles));
+    }
+    println!();
+
+    Ok(())
+}
+
+fn print_weekly_trend(conn: &Connection, weeks: u32, out: Output) -> Result<()> {
+    let summaries = trend::weeks(conn, weeks, Local::now())?;
+    if summaries.iter().all(|week| week.probes == 0) {
+        println!("\n{}\n", out.text(Text::NoProbes));
+        return Ok(());
+    }
+
+    let change = |metric, index: usize| {
+        index
+            .checked_sub(1)
+            .and_then(|previous| trend::change_percent(metric, &summaries[previous], &summaries[index]))
+            .map(|change| format!("{:+.1}%", change))
+            .unwrap_or_else(|| "-".to_string())
+    };
+    let columns = [
+        (out.text(Text::Week), Align::Left),
+        (out.text(Text::AverageLatency), Align::Right),
+        (out.text(Text::Change), Align::Right),
+        (out.text(Text::Loss), Align::Right),
+        (out.text(Text::Change), Align::Right),
+    ];
+    let data = summaries
+        .iter()
+        .enumerate()
+        .map(|(index, week)| {
+            vec![
+                week.start.format("%Y-%m-%d").to_string(),
+                week.average_latency_ms.map(|ms| format!("{:.1} ms", ms)).unwrap_or_else(|| "-".to_string()),
+                change(trend::Metric::Latency, index),
+                week.loss_percent().map(|loss| format!("{:.2}%", loss)).unwrap_or_else(|| "-".to_string()),
+                change(trend::Metric::Loss, index),
+            ]
+        })
+        .collect();
+
+    println!("\n{}", out.text(Text::WeeklyTrend));
+    out.print_table(&columns, data)?;
+    println!();
+
+    Ok(())
+}
+
+fn print_quality(conn: &Connection, settings: &quality::Settings, since: NaiveDate, out: Output) -> Result<()> {
+    let months = quality::months(&quality::stored(conn, since)?, settings);
+    if months.is_empty() {
+        println!("\n{}\n", out.text(Text::NoQualityScores));
+        return Ok(());
+    }
+
+    let columns = [
+        (out.text(Text::Month), Align::Left),
+        (out.text(Text::Score), Align::Right),
+        (out.text(Text::Uptime), Align::Right),
+        (out.text(Text::P95Latency), Align::Right),
+        (out.text(Text::Loss), Align::Right),
+        (out.text(Text::Jitter), Align::Right),
+        (out.text(Text::MeasuredDays), Align::Right),
+    ];
+    let data = months
+        .iter()
+        .map(|month| {
+            vec![
+                month.month.clone(),
+                format!("{:.0}", month.score),
+                format!("{:.3}%", month.availability_percent),
+                month.p95_latency_ms.map(|ms| format!("{:.1} ms", ms)).unwrap_or_else(|| "-".to_string()),
+                month.loss_percent.map(|loss| format!("{:.2}%", loss)).unwrap_or_else(|| "-".to_string()),
+                month.jitter_ms.map(|ms| format!("{:.1} ms", ms)).unwrap_or_else(|| "-".to_string()),
+                month.days.to_string(),
+            ]
+        })
+        .collect();
+
+    println!("\n{}", out.text(Text::QualityTitle));
+    out.print_table(&columns, data)?;
+    println!();
+
+    Ok(())
+}
+
+fn print_database_diff(diff: &dbdiff::Diff, a: &Path, b: &Path, out: Output) -> Result<()> {
+    let (name_a, name_b) = (a.display().to_string(), b.display().to_string());
+    let value = |value: Option<i64>| value.map(|value| value.to_string()).unwrap_or_else(|| "-".to_string());
+
+    let columns = [
+        (out.text(Text::Metric), Align::Left),
+        (name_a.as_str(), Align::Right),
+        (name_b.as_str(), Align::Right),
+        (out.text(Text::Difference), Align::Right),
+    ];
+    let data = diff
+        .metrics
+        .iter()
+        .map(|metric| {
+            vec![
+                out.text(metric.name).to_string(),
+                value(metric.a),
+                value(metric.b),
+                match (metric.a, metric.b) {
+                    (Some(a), Some(b)) if a != b => format!("{:+}", b - a),
+                    _ => String::new(),
+                },
+            ]
+        })
+        .collect();
+    println!("\n{}", out.text(Text::DatabaseDiff));
+    out.print_table(&columns, data)?;
+
+    let outage_row = |status: String, outage: &dbdiff::OutageRow, duration: String| {
+        vec![
+            outage.id.to_string(),
+            status,
+            outage.site.clone().unwrap_or_default(),
+            outage.start_time.format("%Y-%m-%d %H:%M:%S").to_string(),
+            outage.end_time.format("%Y-%m-%d %H:%M:%S").to_string(),
+            duration,
+        ]
+    };
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    for outage in &diff.only_a {
+        rows.push(outage_row(format!("{} {}", out.text(Text::OnlyIn), name_a), outage, outage.duration_seconds.to_string()));
+    }
+    for outage in &diff.only_b {
+        rows.push(outage_row(format!("{} {}", out.text(Text::OnlyIn), name_b), outage, outage.duration_seconds.to_string()));
+    }
+    for (outage_a, outage_b) in &diff.changed {
+        rows.push(outage_row(
+            out.text(Text::Changed).to_string(),
+            outage_a,
+            format!("{} / {}", outage_a.duration_seconds, outage_b.duration_seconds),
+        ));
+    }
+    if !rows.is_empty() {
+        println!();
+        let columns = [
+            (out.text(Text::Id), Align::Right),
+            (out.text(Text::Status), Align::Left),
+            (out.text(Text::Site), Align::Left),
+            (out.text(Text::StartTime), Align::Left),
+            (out.text(Text::EndTime), Align::Left),
+            (out.text(Text::DurationSeconds), Align::Right),
+        ];
+        out.print_table(&columns, rows)?;
+    }
+    if diff.is_empty() {
+        println!("\n{}", out.text(Text::NoDifferences));
+    }
+    println!();
+    Ok(())
+}
+
+fn print_tag_summary(conn: &Connection, out: Output) -> Result<()> {
+    let summary = tags::summary(conn)?;
+    if summary.is_empty() {
+        println!("\n{}\n", out.text(Text::NoTags));
+        return Ok(());
+    }
+
+    let columns = [
+        (out.text(Text::Tag), Align::Left),
+        (out.text(Text::Outages), Align::Right),
+        (out.text(Text::TotalTime), Align::Right),
+    ];
+    let data = summary
+        .into_iter()
+        .map(|tag| {
+            vec![
+                tag.tag,
+                tag.outages.to_string(),
+                format_hms(tag.total_seconds),
+            ]
+        })
+        .collect();
+
+    println!("\n{}", out.text(Text::TagSummary));
+    out.print_table(&columns, data)?;
+    println!();
+
+    Ok(())
+}
+
+fn print_tickets(conn: &Connection, only_open: bool, out: Output) -> Result<()> {
+    let tickets = tickets::list(conn, only_open)?;
+    if tickets.is_empty() {
+        println!("\n{}\n", out.text(Text::NoTickets));
+        return Ok(());
+    }
+
+    let columns = [
+        (out.text(Text::Ticket), Align::Left),
+        (out.text(Text::Status), Align::Left),
+        (out.text(Text::Opened), Align::Left),
+        (out.text(Text::Closed), Align::Left),
+        (out.text(Text::Outages), Align::Right),
+        (out.text(Text::TotalTime), Align::Right),
+        (out.text(Text::Note), Align::Left),
+    ];
+    let data = tickets
+        .into_iter()
+        .map(|ticket| {
+            vec![
+                ticket.reference,
+                out.text(if ticket.closed_at.is_some() { Text::StatusClosed } else { Text::StatusOpen }).to_string(),
+                ticket.opened_at.format("%Y-%m-%d").to_string(),
+                ticket.closed_at.map(|at| at.format("%Y-%m-%d").to_string()).unwrap_or_default(),
+                ticket.outages.to_string(),
+                format_hms(ticket.downtime_seconds),
+                ticket.note.unwrap_or_default(),
+            ]
+        })
+        .collect();
+
+    println!("\n{}", out.text(Text::SupportTickets));
+    out.print_table(&columns, data)?;
+    println!();
+
+    Ok(())
+}
+
+fn print_impacts(conn: &Connection, outage_id: Option<i64>, out: Output) -> Result<()> {
+    let impacts = impacts::list(conn, outage_id)?;
+    if impacts.is_empty() {
+        println!("\n{}\n", out.text(Text::NoImpacts));
+        return Ok(());
+    }
+
+    let columns = [
+        (out.text(Text::Id), Align::Right),
+        (out.text(Text::Outage), Align::Right),
+        (out.text(Text::StartTime), Align::Left),
+        (out.text(Text::Impact), Align::Left),
+        (out.text(Text::Amount), Align::Right),
+    ];
+    let data = impacts
+        .into_iter()
+        .map(|impact| {
+            vec![
+                impact.id.to_string(),
+                impact.outage_id.to_string(),
+                impact.outage_start.map(|start| start.format("%Y-%m-%d %H:%M:%S").to_string()).unwrap_or_default(),
+                impact.description,
+                impact.amount.map(|amount| format!("{:.2}", amount)).unwrap_or_default(),
+            ]
+        })
+        .collect();
+
+    println!("\n{}", out.text(Text::ImpactTitle));
+    out.print_table(&columns, data)?;
+    println!();
+
+    Ok(())
+}
+
+fn print_search_results(hits: &[search::Hit], out: Output) -> Result<()> {
+    if hits.is_empty() {
+        println!("\n{}\n", out.text(Text::NoSearchResults));
+        return Ok(());
+    }
+
+    let show_site = hits.iter().any(|hit| hit.site.is_some());
+    let mut columns = vec![(out.text(Text::Id), Align::Right)];
+    if show_site {
+        columns.push((out.text(Text::Site), Align::Left));
+    }
+    columns.extend([
+        (out.text(Text::StartTime), Align::Left),
+        (out.text(Text::Duration), Align::Right),
+        (out.text(Text::FoundIn), Align::Left),
+    ]);
+    let data = hits
+        .iter()
+        .map(|hit| {
+            let mut row = vec![hit.id.to_string()];
+            if show_site {
+                row.push(hit.site.as_deref().unwrap_or(sites::LOCAL_SITE).to_string());
+            }
+            row.extend([
+                hit.start_time.map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string()).unwrap_or_default(),
+                format_hms(hit.duration_seconds),
+                hit.found.iter().map(|(source, text)| format!("{}: {}", source, text)).collect::<Vec<_>>().join("; "),
+            ]);
+            row
+        })
+        .collect();
+
+    println!("\n{}", out.text(Text::SearchResults));
+    out.print_table(&columns, data)?;
+    println!();
+    Ok(())
+}
+
+/// Says how monitoring gaps were counted, below a report with uptime.
+fn print_gap_note(gaps: GapMode, out: Output) {
+    println!(
+        "{}",
+        out.text(match gaps {
+            GapMode::Exclude => Text::GapsExcluded,
+            GapMode::Down => Text::GapsCountedAsDown,
+            GapMode::Up => Text::GapsCountedAsUp,
+        })
+    );
+}
+
+fn print_baselines(conn: &Connection, out: Output) -> Result<()> {
+    let baselines = baseline::list(conn)?;
+    if baselines.is_empty() {
+        println!("\n{}\n", out.text(Text::NoBaselines));
+        return Ok(());
+    }
+
+    let columns = [
+        (out.text(Text::Name), Align::Left),
+        (out.text(Text::Metric), Align::Left),
+        (out.text(Text::Value), Align::Right),
+        (out.text(Text::Region), Align::Left),
+        (out.text(Text::Source), Align::Left),
+    ];
+    let data = baselines
+        .iter()
+        .flat_map(|baseline| {
+            baseline.values.iter().map(|(metric, value)| {
+                vec![
+                    baseline.name.clone(),
+                    metric.name().to_string(),
+                    value.to_string(),
+                    baseline.region.clone().unwrap_or_default(),
+                    baseline.source.clone().unwrap_or_default(),
+                ]
+            })
+        })
+        .collect();
+
+    println!("\n{}", out.text(Text::BaselinesTitle));
+    out.print_table(&columns, data)?;
+    println!();
+
+    Ok(())
+}
+
+fn print_planned_downtime(conn: &Connection, all: bool, out: Output) -> Result<()> {
+    let now = Local::now();
+    let planned = plans::list(conn, (!all).then_some(now))?;
+    if planned.is_empty() {
+        println!("\n{}\n", out.text(Text::NoPlannedDowntime));
+        return Ok(());
+    }
+
+    let columns = [
+        (out.text(Text::Id), Align::Right),
+        (out.text(Text::StartTime), Align::Left),
+        (out.text(Text::EndTime), Align::Left),
+        (out.text(Text::Duration), Align::Right),
+        (out.text(Text::Status), Align::Left),
+        (out.text(Text::Note), Align::Left),
+    ];
+    let data = planned
+        .into_iter()
+        .map(|plan| {
+            let state = if plan.start > now {
+                Text::StatePlanned
+            } else if plan.end > now {
+                Text::StateInProgress
+            } else {
+                Text::StateOver
+            };
+            vec![
+                plan.id.to_string(),
+                plan.start.format("%Y-%m-%d %H:%M").to_string(),
+                plan.end.format("%Y-%m-%d %H:%M").to_string(),
+                format_hms((plan.end - plan.start).num_seconds()),
+                out.text(state).to_string(),
+                plan.note.unwrap_or_default(),
+            ]
+        })
+        .collect();
+
+    println!("\n{}", out.text(Text::PlannedDowntimeTitle));
+    out.print_table(&columns, data)?;
+    println!();
+
+    Ok(())
+}
+
+fn print_epochs(conn: &Connection, gaps: GapMode, out: Output) -> Result<()> {
+    let summaries = epochs::compare(conn, gaps)?;
+    if summaries.is_empty() {
+        println!("\n{}\n", out.text(Text::NoEpochs));
+        return Ok(());
+    }
+
+    let columns = [
+        (out.text(Text::Id), Align::Right),
+        (out.text(Text::Epoch), Align::Left),
+        (out.text(Text::Since), Align::Left),
+        (out.text(Text::DayCount), Align::Right),
+        (out.text(Text::Outages), Align::Right),
+        (out.text(Text::OutagesPerMonth), Align::Right),
+        (out.text(Text::TotalDowntime), Align::Right),
+        (out.text(Text::MonitoringGaps), Align::Right),
+        (out.text(Text::Uptime), Align::Right),
+        (out.text(Text::AverageLatency), Align::Right),
+    ];
+    let data = summaries
+        .iter()
+        .map(|summary| {
+            let (id, label) = match &summary.epoch {
+                Some(epoch) => (epoch.id.to_string(), epoch.label.clone()),
+                None => (String::new(), out.text(Text::BeforeFirstEpoch).to_string()),
+            };
+            vec![
+                id,
+                label,
+                summary.start.format("%Y-%m-%d").to_string(),
+                format!("{:.0}", summary.days()),
+                summary.outages.to_string(),
+                format!("{:.1}", summary.outages_per_month()),
+                format_hms(summary.downtime_seconds),
+                format_hms(summary.gap_seconds),
+                format!("{:.3}%", summary.availability_percent),
+                summary.average_latency_ms.map(|ms| format!("{:.1} ms", ms)).unwrap_or_else(|| "-".to_string()),
+            ]
+        })
+        .collect();
+
+    println!("\n{}", out.text(Text::EpochComparison));
+    out.print_table(&columns, data)?;
+    print_gap_note(gaps, out);
+    println!();
+
+    Ok(())
+}
+
+fn print_sites(conn: &Connection, days: u32, gaps: GapMode, out: Output) -> Result<()> {
+    let summaries = sites::summaries(conn, days, gaps)?;
+    let remote_sites = summaries.iter().any(|site| site.gap_seconds.is_none());
+    if summaries.is_empty() {
+        println!("\n{}\n", out.text(Text::NoSites));
+        return Ok(());
+    }
+
+    let columns = [
+        (out.text(Text::Site), Align::Left),
+        (out.text(Text::Status), Align::Left),
+        (out.text(Text::LastSeen), Align::Left),
+        (out.text(Text::Outages), Align::Right),
+        (out.text(Text::TotalDowntime), Align::Right),
+        (out.text(Text::MonitoringGaps), Align::Right),
+        (out.text(Text::Uptime), Align::Right),
+    ];
+    let data = summaries
+        .into_iter()
+        .map(|site| {
+            vec![
+                site.site,
+                site.state
+                    .map(|state| {
+                        out.text(match state {
+                            sites::SiteState::Up => Text::StateUp,
+                            sites::SiteState::Down => Text::StateDown,
+                            sites::SiteState::Stale => Text::StateStale,
+                        })
+                    })
+                    .unwrap_or_default()
+                    .to_string(),
+                site.last_seen.map(|time| time.format("%Y-%m-%d %H:%M").to_string()).unwrap_or_default(),
+                site.outages.to_string(),
+                format_hms(site.downtime_seconds),
+                site.gap_seconds.map(format_hms).unwrap_or_else(|| "-".to_string()),
+                format!("{:.3}%", site.uptime_percent),
+            ]
+        })
+        .collect();
+
+    println!("\n{} ({} {}):", out.text(Text::SiteOverview), days, out.text(Text::Days));
+    out.print_table(&columns, data)?;
+    print_gap_note(gaps, out);
+    if remote_sites {
+        println!("{}", out.text(Text::GapsLocalOnly));
+    }
+    println!();
+
+    Ok(())
+}
+
+fn print_site_comparison(conn: &Connection, months: u32, out: Output) -> Result<()> {
+    let (sites, rows) = sites::monthly_downtime(conn, months)?;
+    if rows.is_empty() {
+        println!("\n{}\n", out.text(Text::NoOutages));
+        return Ok(());
+    }
+
+    let mut columns = vec![(out.text(Text::Month), Align::Left)];
+    columns.extend(sites.iter().map(|site| (site.as_str(), Align::Right)));
+    let data = rows
+        .into_iter()
+        .map(|comparison| {
+            let mut row = vec![comparison.month];
+            row.extend(
+                comparison.sites.into_iter().map(|(count, seconds)| format!("{} ({})", format_hms(seconds), count)),
+            );
+            row
+        })
+        .collect();
+
+    println!("\n{}", out.text(Text::SiteComparison));
+    out.print_table(&columns, data)?;
+    println!();
+
+    Ok(())
+}
+
+/// Number of script events `metrics --events` shows.
+const RECENT_SCRIPT_EVENTS: usize = 20;
+
+fn print_custom_metrics(conn: &Connection, days: u32, show_events: bool, out: Output) -> Result<()> {
+    let since = Local::now() - chrono::Duration::days(days.into());
+    let summaries = script::summaries(conn, since)?;
+    let events = if show_events { script::events(conn, since, RECENT_SCRIPT_EVENTS)? } else { Vec::new() };
+    if summaries.is_empty() && events.is_empty() {
+        println!("\n{}\n", out.text(Text::NoCustomMetrics));
+        return Ok(());
+    }
+
+    if !summaries.is_empty() {
+        let columns = [
+            (out.text(Text::Metric), Align::Left),
+            (out.text(Text::Count), Align::Right),
+            (out.text(Text::Average), Align::Right),
+            (out.text(Text::Minimum), Align::Right),
+            (out.text(Text::Maximum), Align::Right),
+            (out.text(Text::LastValue), Align::Right),
+            (out.text(Text::LastSeen), Align::Left),
         ];
+        let data = summaries
+            .into_iter()
+            .map(|metric| {
+                vec![
+                    metric.name,
+                    metric.count.to_string(),
+                    format!("{:.2}", metric.average),
+                    format!("{:.2}", metric.min),
+                    format!("{:.2}", metric.max),
+                    format!("{:.2}", metric.last_value),
+                    metric.last_time.format("%Y-%m-%d %H:%M").to_string(),
+                ]
+            })
+            .collect();
+
+        println!("\n{} ({} {}):", out.text(Text::CustomMetrics), days, out.text(Text::Days));
+        out.print_table(&columns, data)?;
+    }
+
+    if !events.is_empty() {
+        let columns = [
+            (out.text(Text::Time), Align::Left),
+            (out.text(Text::Name), Align::Left),
+            (out.text(Text::Message), Align::Left),
+        ];
+        let data = events
+            .into_iter()
+            .map(|event| vec![event.time.format("%Y-%m-%d %H:%M:%S").to_string(), event.name, event.message])
+            .collect();
+
+        println!("\n{}", out.text(Text::ScriptEvents));
+        out.print_table(&columns, data)?;
+    }
+    println!();
+
+    Ok(())
+}
+
+fn print_locations(conn: &Connection, out: Output) -> Result<()> {
+    let locations = locations::list(conn)?;
+    if locations.is_empty() {
+        println!("\n{}\n", out.text(Text::NoLocations));
+        return Ok(());
+    }
+
+    let columns = [
+        (out.text(Text::Name), Align::Left),
+        (out.text(Text::WiFi), Align::Left),
+        (out.text(Text::GatewayMac), Align::Left),
+        (out.text(Text::Action), Align::Left),
+    ];
+    let data = locations
+        .into_iter()
+        .map(|location| {
+            vec![
+                location.name,
+                location.ssid.unwrap_or_default(),
+                location.gateway_mac.unwrap_or_default(),
+                location.action.to_string(),
+            ]
+        })
+        .collect();
+
+    println!("\n{}", out.text(Text::NetworkLocations));
+    out.print_table(&columns, data)?;
+    println!();
+
+    Ok(())
+}
+
+fn print_tokens(conn: &Connection, out: Output) -> Result<()> {
+    let tokens = tokens::list(conn)?;
+    if tokens.is_empty() {
+        println!("\n{}\n", out.text(Text::NoTokens));
+        return Ok(());
+    }
+
+    let columns = [
+        (out.text(Text::Id), Align::Right),
+        (out.text(Text::Name), Align::Left),
+        (out.text(Text::Scope), Align::Left),
+        (out.text(Text::Created), Align::Left),
+        (out.text(Text::LastUsed), Align::Left),
+    ];
+    let data = tokens
+        .into_iter()
+        .map(|token| {
+            vec![
+                token.id.to_string(),
+                token.name.unwrap_or_default(),
+                token.scope.to_string(),
+                token.created_at.format("%Y-%m-%d %H:%M").to_string(),
+                token.last_used_at.map(|at| at.format("%Y-%m-%d %H:%M").to_string()).unwrap_or_default(),
+            ]
+        })
+        .collect();
+
+    println!("\n{}", out.text(Text::ApiTokens));
+    out.print_table(&columns, data)?;
+    println!();
+
+    Ok(())
+}
+
+fn print_watcher_status(conn: &Connection, db_path: &Path, out: Output) -> Result<()> {
+    let Some(status) = health::load(conn)? else {
+        println!("\n{}\n", out.text(Text::NoWatcherStatus));
+        return Ok(());
+    };
+
+    let format_ms = |duration: Duration| format!("{:.1} ms", duration.as_secs_f64() * 1000.0);
+    let with_max = |last: String, max: String| format!("{} ({} {})", last, out.text(Text::Max), max);
+    let metrics = &status.metrics;
+
+    let watcher = match lock::watcher_pid(db_path) {
+        Some(pid) => format!("{} {}", out.text(Text::RunningWithPid), pid),
+        None => out.text(Text::NotRunning).to_string(),
+    };
+    let rows = vec![
+        (out.text(Text::Watcher).to_string(), watcher),
+        (
+            out.text(Text::LastUpdate).to_string(),
+            status.updated_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+        ),
+        (
+            out.text(Text::RunningSince).to_string(),
+            metrics.started_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+        ),
+        (out.text(Text::LoopIterations).to_string(), metrics.iterations.to_string()),
+        (
+            out.text(Text::LoopLag).to_string(),
+            with_max(format_ms(metrics.loop_lag), format_ms(metrics.max_loop_lag)),
+        ),
+        (out.text(Text::CheckDuration).to_string(), format_ms(metrics.check_duration)),
+        (
+            out.text(Text::DbWriteLatency).to_string(),
+            match (metrics.db_write_latency, metrics.max_db_write_latency) {
+                (Some(last), Some(max)) => with_max(format_ms(last), format_ms(max)),
+                _ => "-".to_string(),
+            },
+        ),
+        (out.text(Text::WriteQueueDepth).to_string(), metrics.queue_depth.to_string()),
+        (out.text(Text::WeatherFailures).to_string(), metrics.weather_failures.to_string()),
+        (out.text(Text::NotificationFailures).to_string(), metrics.notification_failures.to_string()),
+        (
+            out.text(Text::ProbeHistory).to_string(),
+            match history::summary(conn)? {
+                Some((checks, since)) => format!(
+                    "{} {} {}",
+                    checks,
+                    out.text(Text::ChecksSince),
+                    since.format("%Y-%m-%d %H:%M:%S")
+                ),
+                None => "-".to_string(),
+            },
+        ),
+    ];
+
+    println!("\n{}", out.text(Text::WatcherStatus));
+    out.print_key_values(rows)?;
+    println!();
+
+    Ok(())
+}
+
+/// Latency, uptime and today's downtime over the last day, below the
+/// watcher status.
+fn print_glance(conn: &Connection, out: Output) -> Result<()> {
+    let glance = glance::load(conn, Local::now())?;
+    let format_time = |time: DateTime<Local>| time.format("%Y-%m-%d %H:%M:%S").to_string();
+
+    let mut rows = Vec::new();
+    // The sparkline means nothing to a screen reader, the percentile says enough
+    if !out.plain && glance.hourly_latency_ms.iter().any(Option::is_some) {
+        let known = || glance.hourly_latency_ms.iter().flatten();
+        let min = known().fold(f64::INFINITY, |min, latency| min.min(*latency));
+        let max = known().fold(0.0, |max: f64, latency| max.max(*latency));
+        rows.push((
+            out.text(Text::HourlyLatency).to_string(),
+            format!("{} {:.0}–{:.0} ms", glance::sparkline(&glance.hourly_latency_ms), min, max),
+        ));
+    }
+    rows.push((
+        out.text(Text::LatencyP95).to_string(),
+        glance.p95_latency_ms.map_or("-".to_string(), |latency| format!("{:.1} ms", latency)),
+    ));
+    match (glance.down_since, glance.up_since) {
+        (Some(since), _) => rows.push((out.text(Text::DownSince).to_string(), format_time(since))),
+        (None, Some(since)) => rows.push((out.text(Text::UpSince).to_string(), format_time(since))),
+        (None, None) => {},
+    }
+    rows.push((out.text(Text::DowntimeToday).to_string(), format_hms(glance.downtime_today_seconds)));
+
+    println!("{}", out.text(Text::Last24Hours));
+    out.print_key_values(rows)?;
+    println!();
+
+    Ok(())
+}
+
+/// How often the watcher checks whether a digest is due.
+const DIGEST_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often the watcher checks whether a task of `--config` is due.
+const SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// How often the watcher scores the days that are over
+const QUALITY_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Days the watcher scores if they aren't yet, e.g. after it was stopped
+const QUALITY_CATCH_UP_DAYS: u32 = 7;
+
+/// How often the watcher checks whether a week finished and its trend is due.
+const TREND_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How often the watcher looks for finished days to timestamp.
+const EVIDENCE_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
 
-        println!("\nSummary:");
-        summary_table.print(summary_data);
-        println!();
+/// How often the watcher checks which network location the machine is on.
+const LOCATION_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// First delay before the watcher is started again after a transient error
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// A run of the watcher lasting this long resets the restart backoff
+const STABLE_RUN: Duration = Duration::from_secs(10 * 60);
+
+/// How much further the wall clock has to move than the monotonic clock
+/// between two checks for the machine to count as having been suspended.
+const SUSPEND_THRESHOLD: Duration = Duration::from_secs(10);
+
+/// How much less the wall clock may move than the monotonic clock during an
+/// outage before it counts as stepped backwards, e.g. by NTP.
+const CLOCK_STEP_THRESHOLD: Duration = Duration::from_secs(2);
+
+/// Timestamps the outage digest of every finished day that doesn't have one yet.
+fn stamp_evidence(conn: &Connection, console: &mut Console, tsa_url: &str) -> Result<()> {
+    for day in evidence::unstamped_days(conn)? {
+        evidence::stamp_day(conn, day, tsa_url)?;
+        console.print(&format!("Timestamped outage evidence for {} with {}", day, tsa_url));
+    }
+    Ok(())
+}
+
+fn verify_evidence(conn: &Connection, day: Option<NaiveDate>) -> Result<()> {
+    let stamps = evidence::stamps(conn, day)?;
+    if stamps.is_empty() {
+        println!("No timestamped evidence found. Create some with `webgone evidence stamp`.");
+        return Ok(());
+    }
+
+    let mut failed = 0;
+    for stamp in &stamps {
+        let result = match evidence::verify(conn, stamp)? {
+            evidence::Verification::Valid => "ok",
+            evidence::Verification::DigestMismatch => "outages changed after timestamping",
+            evidence::Verification::InvalidToken => "timestamp token doesn't cover the digest",
+        };
+        if !matches!(result, "ok") {
+            failed += 1;
+        }
+        println!("{}: {} ({})", stamp.day, result, stamp.tsa_url);
+    }
+
+    if failed > 0 {
+        return Err(WebgoneError::Other(format!("evidence of {} day(s) failed verification", failed)).into());
+    }
+    Ok(())
+}
+
+fn verify_signatures(conn: &Connection, key_path: &Path) -> Result<()> {
+    let Some(key) = signing::load(key_path)? else {
+        return Err(WebgoneError::InvalidInput(format!(
+            "no signing key at {}; enable signing with `webgone db sign`",
+            key_path.display()
+        ))
+        .into());
+    };
+
+    let failed = signing::verify(conn, key)?;
+    for row in &failed {
+        let start = row
+            .start_time
+            .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| "invalid start time".to_string());
+        let problem = match row.problem {
+            signing::Problem::Unsigned => "not signed",
+            signing::Problem::Modified => "changed after it was signed",
+        };
+        println!("Outage {} ({}): {}", row.id, start, problem);
+    }
+
+    if !failed.is_empty() {
+        return Err(WebgoneError::Other(format!("{} outage(s) failed verification", failed.len())).into());
+    }
+    let total: i64 = conn.query_row("SELECT COUNT(*) FROM outages", [], |row| row.get(0))?;
+    println!("All {} outage(s) have valid signatures", total);
+    Ok(())
+}
+
+fn check_database(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare(
+        "SELECT id, start_time, clock_anomaly, julianday(end_time) < julianday(start_time), duration_seconds < 0
+        FROM outages
+        WHERE clock_anomaly OR julianday(end_time) < julianday(start_time) OR duration_seconds < 0
+        ORDER BY id",
+    )?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, bool>(2)?,
+                row.get::<_, Option<bool>>(3)?.unwrap_or(false),
+                row.get::<_, bool>(4)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for (id, start, clock_anomaly, ends_before_start, negative_duration) in &rows {
+        let problems: Vec<&str> = [
+            (*clock_anomaly, "the clock was stepped backwards while it lasted"),
+            (*ends_before_start, "ends before it starts"),
+            (*negative_duration, "has a negative duration"),
+        ]
+        .into_iter()
+        .filter_map(|(found, problem)| found.then_some(problem))
+        .collect();
+        let start = model::parse_timestamp(start)
+            .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| start.clone());
+        println!("Outage {} ({}): {}", id, start, problems.join(", "));
+    }
+
+    let mut stmt = conn.prepare("SELECT * FROM outages ORDER BY id")?;
+    let (outages, unreadable) = InternetOutage::readable(stmt.query_map([], InternetOutage::from_row)?)?;
+    for row in &unreadable {
+        println!("Outage {}: {} is not a time: {:?}", row.id, row.column, row.value);
+    }
+
+    let problems = rows.len() + unreadable.len();
+    if problems > 0 {
+        return Err(WebgoneError::Other(format!("found {} problem(s) in outages", problems)).into());
+    }
+    println!("All {} outage(s) have consistent times", outages.len());
+    Ok(())
+}
+
+fn export_evidence(conn: &Connection, day: NaiveDate, dir: &Path) -> Result<()> {
+    let Some(stamp) = evidence::stamps(conn, Some(day))?.into_iter().next() else {
+        return Err(WebgoneError::InvalidInput(format!("no timestamped evidence for {}", day)).into());
+    };
+
+    let query = dir.join(format!("outages-{}.tsq", day));
+    let response = dir.join(format!("outages-{}.tsr", day));
+    std::fs::write(&query, &stamp.request)?;
+    std::fs::write(&response, &stamp.response)?;
+    // Which machine and configuration recorded the day, which the timestamp doesn't cover
+    let midnight = |day: NaiveDate| day.and_hms_opt(0, 0, 0).and_then(|time| time.and_local_timezone(Local).earliest());
+    let periods = match (midnight(day), day.succ_opt().and_then(midnight)) {
+        (Some(from), Some(to)) => coverage::periods(conn, from, to)?,
+        _ => Vec::new(),
+    };
+    let monitored = dir.join(format!("monitored-{}.json", day));
+    std::fs::write(&monitored, serde_json::to_string_pretty(&periods)?)?;
+    println!("Wrote {}, {} and {}", query.display(), response.display(), monitored.display());
+    println!(
+        "Verify the signature with: openssl ts -verify -queryfile {} -in {} -CAfile <TSA CA certificate>",
+        query.display(),
+        response.display()
+    );
+    Ok(())
+}
+
+/// What the watcher probes and how, as the `watch` options that reproduce
+/// it, e.g. `--target 8.8.8.8:53 --interval 5 --timeout 1`.
+fn probe_config(args: &WatchArgs, targets: &[Target], auto: bool) -> String {
+    let mut options = Vec::new();
+    if auto {
+        let probe = args.probe.to_possible_value().map(|value| value.get_name().to_string()).unwrap_or_default();
+        options.push(format!("--target auto --probe {} --port {}", probe, args.port));
+        if args.probe == target::ProbeMode::Dns {
+            options.push(format!("--domain {}", args.domain));
+        }
+    }
+    options.extend(targets.iter().map(|target| format!("--target {}", target)));
+    if targets.iter().any(|target| matches!(target, Target::Http { .. })) {
+        options.push(format!("--expect-status {}", args.expect_status));
+    }
+    if targets.len() > 1 {
+        options.push(format!("--quorum {}", args.quorum));
+    }
+    options.push(format!("--interval {}", args.interval));
+    if args.adaptive_timeout {
+        options.push(format!(
+            "--adaptive-timeout --timeout {} --min-timeout {} --max-timeout {}",
+            args.timeout, args.min_timeout, args.max_timeout
+        ));
     } else {
-        println!("\nNo outages recorded yet.\n");
+        options.push(format!("--timeout {}", args.timeout));
+    }
+    options.push(format!("--fail-threshold {} --recover-threshold {}", args.fail_threshold, args.recover_threshold));
+    if args.merge_gap > 0 {
+        options.push(format!("--merge-gap {}", args.merge_gap));
+    }
+    if let Some(up_when) = &args.up_when {
+        options.push(format!("--up-when \"{}\"", up_when));
+    }
+    options.join(" ")
+}
+
+/// Watches the connection until stopped, then sums up the session, also
+/// when a signal or an error ended it, so a watcher that died doesn't go
+/// unnoticed.
+fn watch(conn: &Connection, db_path: &Path, args: WatchArgs, lang: Lang) -> Result<()> {
+    let started = Local::now();
+    let mut console = Console::new(args.live, args.log_file.as_deref())?;
+    let notifier = args.notify_on_exit.then(|| notify::Notifier::new(args.notify_command.clone(), args.notify_desktop));
+    // Stop the loop on Ctrl+C instead of being killed, so buffered probes get flushed
+    let running = Arc::new(AtomicBool::new(true));
+    {
+        let running = running.clone();
+        ctrlc::set_handler(move || running.store(false, Ordering::SeqCst))
+            .context("Failed to install Ctrl+C handler")?;
+    }
+    let result = supervise(conn, db_path, &args, lang, &running, &mut console);
+    if let Err(e) = &result {
+        console.clear();
+        console.print(&format!("Monitoring stopped by an error: {:#}", e));
+    }
+
+    // Nothing to sum up if monitoring never started, e.g. with invalid options
+    let summary = match coverage::session_summary(conn, started) {
+        Ok(Some(summary)) => summary,
+        Ok(None) => return result,
+        Err(e) => {
+            console.print(&format!("Failed to sum up the session: {:#}", e));
+            return result;
+        }
+    };
+    let mut message = format!(
+        "Monitored for {} since {}: {} outage(s), {} of downtime",
+        recovery::describe(Duration::from_secs(summary.monitored_seconds.max(0) as u64)),
+        started.format("%Y-%m-%d %H:%M:%S"),
+        summary.outages,
+        format_hms(summary.downtime_seconds)
+    );
+    console.print(&message);
+    if let Some(notifier) = notifier {
+        if let Err(e) = &result {
+            message = format!("{}\nStopped by an error: {:#}", message, e);
+        }
+        notifier.deliver(&mut console, "webgone: monitoring stopped", &message);
+    }
+    result
+}
+
+/// Runs the watcher, starting it again after errors that may go away by
+/// themselves, like a database locked for too long or a failing disk write.
+/// Restarts are delayed by a backoff that doubles with every failure up to
+/// `--max-backoff`, and starts over once a run lasted `STABLE_RUN`. Buffered
+/// probes and the outage going on are kept for the next run.
+fn supervise(
+    conn: &Connection,
+    db_path: &Path,
+    args: &WatchArgs,
+    lang: Lang,
+    running: &Arc<AtomicBool>,
+    console: &mut Console,
+) -> Result<()> {
+    let storage = storage_settings(args);
+    let mut carryover = Carryover {
+        probes: ProbeBuffer::new(storage.batch_size, storage.flush_interval, storage.sample_every).flush_on_drop(conn),
+        outage: None,
+        unsaved: None,
+    };
+    let mut backoff = INITIAL_BACKOFF;
+    loop {
+        let run_started = Instant::now();
+        let error = match monitor(conn, db_path, args, lang, running, console, &mut carryover) {
+            Ok(()) => return Ok(()),
+            Err(e) => e,
+        };
+        if args.no_restart || !WebgoneError::is_transient(&error) || !running.load(Ordering::SeqCst) {
+            return Err(error);
+        }
+        if run_started.elapsed() >= STABLE_RUN {
+            backoff = INITIAL_BACKOFF;
+        }
+
+        console.clear();
+        console.print(&format!("Watcher failed: {:#}; restarting in {}", error, recovery::describe(backoff)));
+        // Kept for the next run if the database is still busy
+        if let Err(e) = carryover.probes.flush(conn) {
+            console.print(&format!("Failed to write {} buffered probe(s): {:#}", carryover.probes.pending(), e));
+        }
+        let resume_at = Instant::now() + backoff;
+        while running.load(Ordering::SeqCst) && Instant::now() < resume_at {
+            thread::sleep(Duration::from_millis(200));
+        }
+        if !running.load(Ordering::SeqCst) {
+            console.print("Monitoring stopped.");
+            return Ok(());
+        }
+        backoff = (backoff * 2).min(args.max_backoff);
+    }
+}
+
+/// What a restarted `monitor` takes over from the run that failed, so an
+/// error doesn't lose buffered probes or the outage going on. The probes are
+/// also written when the watcher panics or stops on an error.
+struct Carryover<'a> {
+    probes: probe_buffer::FlushOnDrop<'a>,
+    /// Start of the outage going on, and the channels told about it
+    outage: Option<(DateTime<Local>, notify::Channels)>,
+    /// A recovered outage whose row couldn't be written
+    unsaved: Option<InternetOutage>,
+}
+
+/// Storage settings of `--storage-profile` with the options overriding it.
+fn storage_settings(args: &WatchArgs) -> storage::StorageSettings {
+    let mut storage = args.storage_profile.settings();
+    storage.synchronous = args.synchronous.or(storage.synchronous);
+    storage.journal_mode = args.journal_mode.or(storage.journal_mode);
+    storage.batch_size = args.batch_size.unwrap_or(storage.batch_size);
+    storage.flush_interval = args.flush_interval.map(Duration::from_secs).unwrap_or(storage.flush_interval);
+    storage.sample_every = args.sample_every.unwrap_or(storage.sample_every);
+    storage.raw_retention_days = args.raw_retention_days.or(storage.raw_retention_days);
+    storage.rollup_granularity = args.rollup_granularity.unwrap_or(storage.rollup_granularity);
+    storage
+}
+
+fn monitor(
+    conn: &Connection,
+    db_path: &Path,
+    args: &WatchArgs,
+    lang: Lang,
+    running: &Arc<AtomicBool>,
+    console: &mut Console,
+    carryover: &mut Carryover,
+) -> Result<()> {
+    let (targets, mut pool) = match args.target.as_slice() {
+        [] => {
+            let spec = match &args.url {
+                Some(url) => TargetSpec::Url(url.clone()),
+                None if args.probe == target::ProbeMode::Http => {
+                    return Err(WebgoneError::InvalidInput("--probe http needs a --url to fetch".to_string()).into());
+                }
+                None => TargetSpec::Ip(args.ip),
+            };
+            (vec![spec.resolve(args.probe, args.port, args.expect_status, &args.domain).map_err(WebgoneError::InvalidInput)?], None)
+        }
+        [TargetSpec::Auto] if args.probe == target::ProbeMode::Http => {
+            return Err(WebgoneError::InvalidInput("--probe http fetches --url, not the built-in endpoints of --target auto".to_string()).into());
+        }
+        [TargetSpec::Auto] => (Vec::new(), Some(TargetPool::anycast(args.probe, args.port, &args.domain))),
+        specs => {
+            let targets = specs
+                .iter()
+                .map(|spec| spec.resolve(args.probe, args.port, args.expect_status, &args.domain).map_err(WebgoneError::InvalidInput))
+                .collect::<Result<Vec<_>, _>>()?;
+            (targets, None)
+        }
+    };
+    if pool.is_none() && args.quorum.required(targets.len()) > targets.len() {
+        return Err(WebgoneError::InvalidInput(format!(
+            "--quorum {} is more than the {} target(s) to check",
+            args.quorum,
+            targets.len()
+        ))
+        .into());
+    }
+    if !(args.interval > 0.0 && args.interval.is_finite()) {
+        return Err(WebgoneError::InvalidInput("interval must be a positive number of seconds".to_string()).into());
+    }
+    let interval = Duration::from_secs_f64(args.interval);
+    let timeouts = [args.timeout, args.min_timeout, args.max_timeout];
+    if !timeouts.iter().all(|timeout| *timeout > 0.0 && timeout.is_finite()) || args.min_timeout > args.max_timeout {
+        return Err(WebgoneError::InvalidInput(
+            "timeouts must be positive numbers of seconds, with --min-timeout at most --max-timeout".to_string(),
+        )
+        .into());
+    }
+    if args.notify_lease.is_some() && args.notify_lease_ttl < interval * 3 {
+        return Err(WebgoneError::InvalidInput(
+            "--notify-lease-ttl must be at least three check intervals".to_string(),
+        )
+        .into());
+    }
+    let fixed_timeout = Duration::from_secs_f64(args.timeout);
+    let mut adaptive_timeout = args.adaptive_timeout.then(|| {
+        AdaptiveTimeout::new(
+            fixed_timeout,
+            Duration::from_secs_f64(args.min_timeout),
+            Duration::from_secs_f64(args.max_timeout),
+        )
+    });
+    let mut reported_timeout = fixed_timeout;
+    let up_when = args
+        .up_when
+        .clone()
+        .map(|expr| verdict::UpWhen::new(expr, args.components.clone()))
+        .transpose()?;
+    for (index, service) in args.services.iter().enumerate() {
+        if args.services[..index].iter().any(|other| other.name == service.name) {
+            return Err(WebgoneError::InvalidInput(format!("--service {} is given more than once", service.name)).into());
+        }
+    }
+    services::check_dependencies(&args.services, &args.dependencies)?;
+    let merge_gap = Duration::from_secs(args.merge_gap);
+    console.print("Starting internet connectivity monitoring...");
+    match (&pool, targets.as_slice()) {
+        (Some(pool), _) => console.print(&format!(
+            "Checking {} built-in anycast endpoints{} every {} seconds",
+            pool.endpoint_count(),
+            match args.probe {
+                target::ProbeMode::Icmp => " with ICMP echo".to_string(),
+                target::ProbeMode::Dns => format!(" resolving {}", args.domain),
+                _ => String::new(),
+            },
+            args.interval
+        )),
+        (None, [addr]) => console.print(&format!("Checking {} every {} seconds", addr, args.interval)),
+        (None, targets) => console.print(&format!(
+            "Checking {} every {} seconds, counting a check as failed when {} of {} fail",
+            targets.iter().map(Target::to_string).collect::<Vec<_>>().join(", "),
+            args.interval,
+            args.quorum.required(targets.len()),
+            targets.len()
+        )),
+    }
+    if args.adaptive_timeout {
+        console.print(&format!(
+            "Adapting the probe timeout to {}× the 99th percentile latency, between {} and {} seconds",
+            target::TIMEOUT_FACTOR,
+            args.min_timeout,
+            args.max_timeout
+        ));
+    }
+    if let Some(up_when) = &up_when {
+        console.print(&format!(
+            "Internet counts as up when {} (checking {})",
+            args.up_when.as_ref().map(verdict::Expr::to_string).unwrap_or_default(),
+            up_when.component_names().join(", ")
+        ));
+    }
+    // Failed checks until then are only logged, while the network comes up after start or a suspend
+    let mut grace_until = (!args.grace_period.is_zero()).then(|| Instant::now() + args.grace_period);
+    if grace_until.is_some() {
+        console.print(&format!(
+            "Not counting failed checks as outages for {} after start and after a suspend",
+            recovery::describe(args.grace_period)
+        ));
+    }
+    for service in &args.services {
+        match args.dependencies.iter().find(|dependency| dependency.child == service.name) {
+            Some(dependency) => console.print(&format!(
+                "Also watching service {} ({}), which depends on {}",
+                service.name, service.spec, dependency.parent
+            )),
+            None => console.print(&format!("Also watching service {} ({})", service.name, service.spec)),
+        }
+    }
+    console.print("Press Ctrl+C to stop monitoring.");
+
+    let _pid_file = lock::WatcherPidFile::create(db_path)
+        .context("Failed to write watcher pid file")?;
+
+    let storage = storage_settings(args);
+    storage.apply_pragmas(conn)?;
+    let probe_buffer = &mut carryover.probes;
+    if pool.is_some() {
+        console.print(&storage::describe(&storage));
+    }
+
+    let recorder = args.record_probes.then(|| history::Recorder::new(args.probe_history_size));
+    if recorder.is_some() {
+        console.print(&format!("Recording the result of every check, keeping the last {}", args.probe_history_size));
+    }
+    let mut last_retention_run: Option<Instant> = None;
+    let mut last_rotation_run: Option<Instant> = None;
+    let mut last_replica_build: Option<Instant> = None;
+    let mut last_evidence_run: Option<Instant> = None;
+    let mut known_schema_version = schema_version(conn)?;
+
+    // Saved as often as probes are flushed, so the flash profile doesn't write more often because of it
+    let mut metrics = health::WatcherMetrics::new();
+    let mut last_iteration: Option<Instant> = None;
+    let mut last_iteration_time: Option<DateTime<Local>> = None;
+    let mut session = coverage::Session::new(coverage::Watcher::current(probe_config(args, &targets, pool.is_some())));
+    let mut last_status_save: Option<Instant> = None;
+
+    let mut debounce = debounce::Debounce::new(args.fail_threshold, args.recover_threshold);
+    let mut degradation = degraded::Tracker::new(args.degraded_window as usize, args.degraded_threshold);
+    let mut is_connected = true;
+    let mut paused = false;
+    // State shown on the live status line
+    let mut state_since = Local::now();
+    let mut last_latency: Option<Duration> = None;
+    let mut outage_start: Option<DateTime<Local>> = None;
+    let mut outage_detected: Option<DateTime<Local>> = None;
+    let mut outage_confirmed: Option<DateTime<Local>> = None;
+    // Monotonic time of the confirmation, to notice the clock being stepped backwards
+    let mut outage_confirmed_instant: Option<Instant> = None;
+    let mut outage_wifi: Option<WifiInfo> = None;
+    let mut outage_weather: Option<WeatherReport> = None;
+    let mut outage_history: Option<recovery::History> = None;
+    // Name of the network location outages are tagged with
+    let mut outage_location: Option<String> = None;
+    let mut outage_capture: Option<capture::Capture> = None;
+    // Components of the --up-when verdict that failed in the first failed check of the current streak
+    let mut failed_components: Option<String> = None;
+    let mut outage_failed_components: Option<String> = None;
+    // Probe types of the targets that failed in the first failed check of the current streak
+    let mut failed_probes: Option<String> = None;
+    let mut outage_failed_probes: Option<String> = None;
+    // Planned downtime the current outage began in; it isn't notified
+    let mut outage_planned: Option<PlannedDowntime> = None;
+    // Severity of the current outage so far, and the channels told about it
+    let mut outage_severity = Severity::Info;
+    let mut outage_notified = notify::Channels::default();
+    let mut location: Option<locations::Location> = None;
+    let mut last_location_check: Option<Instant> = None;
+    let mut notices_sent = 0;
+    let mut recovery_notices = if args.no_recovery_notices { Vec::new() } else { args.recovery_notices.clone() };
+    recovery_notices.sort();
+
+    let mut notifier = notify::Notifier::new(args.notify_command.clone(), args.notify_desktop);
+    let webhooks = (!args.webhook.is_empty()).then(|| webhook::Webhooks::start(&args.webhook, args.webhook_attempts));
+    let host = coverage::hostname();
+    let mut discord = match (args.notify.contains(&notify::Service::Discord), &args.discord_webhook) {
+        (true, Some(url)) => Some(discord::Discord::new(url.clone(), host.clone())),
+        (true, None) => return Err(WebgoneError::InvalidInput("--notify discord needs a --discord-webhook".to_string()).into()),
+        (false, Some(_)) => return Err(WebgoneError::InvalidInput("--discord-webhook needs --notify discord".to_string()).into()),
+        (false, None) => None,
+    };
+    // What webhooks name as down unless a quorum tells which targets failed
+    let watched_targets = match &pool {
+        Some(_) => "auto".to_string(),
+        None => targets.iter().map(Target::to_string).collect::<Vec<_>>().join(", "),
+    };
+    let mut lease = match (&args.notify_lease, &args.instance) {
+        (Some(path), Some(instance)) => {
+            console.print(&format!("Sharing notifications through {} as {}", path.display(), instance));
+            Some(lease::NotificationLease::open(path, instance.clone(), args.notify_lease_ttl)?)
+        }
+        _ => None,
+    };
+    let mut last_lease_renewal: Option<Instant> = None;
+    let mut lease_state: Option<String> = None;
+    let capture_settings = capture::CaptureSettings {
+        interface: args.capture_interface.clone(),
+        command: args.capture_command.clone(),
+        dir: args.capture_dir.clone(),
+        max_duration: Duration::from_secs(args.capture_max_duration),
+    };
+    let mut tracer = args.trace_routes.then(|| {
+        routes::Tracer::new(args.route_anchors.clone(), Duration::from_secs(args.route_interval.max(1) * 60))
+    });
+    if tracer.is_some() {
+        let anchors: Vec<String> = args.route_anchors.iter().map(IpAddr::to_string).collect();
+        console.print(&format!("Tracing the route to {} every {} minutes", anchors.join(", "), args.route_interval.max(1)));
+    }
+    let schedules: Vec<digest::Schedule> = args
+        .digests
+        .iter()
+        .map(|&period| digest::Schedule { period, at: args.digest_time, weekday: args.digest_weekday, email: false })
+        .collect();
+    let pricing = args.digest_rate.map(|monthly_rate| digest::Pricing {
+        monthly_rate,
+        currency: args.digest_currency.clone(),
+    });
+    let mut last_digest_check: Option<Instant> = None;
+    let mut last_trend_check: Option<Instant> = None;
+    let config = args.config.as_deref().map(schedule::Config::load).transpose()?.unwrap_or_default();
+    let jobs = config.jobs()?;
+    let classifier = config.severity.classifier()?;
+    let routing = config.severity.notify;
+    let mut mailer = match args.notify.contains(&notify::Service::Email) {
+        true => Some(config.email.mailer(host.clone())?),
+        false => None,
+    };
+    // Mailed on --digest-weekday at --digest-time, apart from the --digest ones
+    let email_digest = (mailer.is_some() && config.email.weekly_digest).then_some(digest::Schedule {
+        period: digest::Period::Weekly,
+        at: args.digest_time,
+        weekday: args.digest_weekday,
+        email: true,
+    });
+    let mut last_schedule_check: Option<Instant> = None;
+    let mut last_quality_check: Option<Instant> = None;
+
+    let mut weather_cache = match (args.weather, args.latitude, args.longitude) {
+        (true, Some(latitude), Some(longitude)) => Some(WeatherCache::new(
+            args.weather_api.clone(),
+            latitude,
+            longitude,
+            Duration::from_secs(args.weather_refresh * 60),
+        )),
+        _ => None,
+    };
+
+    let mut services = (!args.services.is_empty()).then(|| {
+        services::Services::start(
+            args.services.clone(),
+            &args.dependencies,
+            interval,
+            args.fail_threshold,
+            args.recover_threshold,
+            running.clone(),
+        )
+    });
+
+    let mut hook = match &args.script {
+        Some(path) => {
+            let hook = script::ProbeHook::load(path)?;
+            console.print(&format!("Running {} with every probe result", hook.path().display()));
+            Some(hook)
+        }
+        None => None,
+    };
+
+    // Pick up where a run that failed left off
+    if let Some(outage) = carryover.unsaved.clone() {
+        probe_buffer.flush(conn)?;
+        let (outage_id, _) = log_or_merge_outage(conn, &outage, merge_gap, &classifier)?;
+        carryover.unsaved = None;
+        acks::attach(conn, outage.start_time, outage_id)?;
+        acks::set_current(conn, None)?;
+        console.print(&format!(
+            "Recorded the outage from {} to {} interrupted by the restart",
+            outage.start_time, outage.end_time
+        ));
+    }
+    if let Some((since, notified)) = carryover.outage {
+        console.print(&format!("Internet connection still lost since {}", since));
+        debounce = debounce.disconnected();
+        is_connected = false;
+        state_since = since;
+        outage_start = Some(since);
+        outage_severity = classifier.classify(since, Local::now());
+        outage_planned = plans::at(conn, since)?;
+        outage_notified = notified;
+    }
+
+    while running.load(Ordering::SeqCst) {
+        let iteration_start = Instant::now();
+        let iteration_time = Local::now();
+        if let (Some(previous), Some(previous_time)) = (last_iteration, last_iteration_time) {
+            metrics.record_iteration(iteration_start - previous, interval);
+            // The monotonic clock stands still while the machine sleeps, the wall clock doesn't
+            let slept = (iteration_time - previous_time)
+                .to_std()
+                .unwrap_or_default()
+                .saturating_sub(iteration_start - previous);
+            if slept >= SUSPEND_THRESHOLD {
+                // Nothing was monitored while the machine slept
+                session.end(conn, previous_time)?;
+                if !args.grace_period.is_zero() {
+                    console.print(&format!(
+                        "Resumed after a suspend of about {}, not counting failed checks as outages for {}",
+                        recovery::describe(slept),
+                        recovery::describe(args.grace_period)
+                    ));
+                    grace_until = Some(iteration_start + args.grace_period);
+                }
+            }
+        }
+        last_iteration = Some(iteration_start);
+        last_iteration_time = Some(iteration_time);
+
+        // Another process (e.g. a newer webgone) may have changed the schema
+        // while we were running; make sure everything we write to still exists
+        let current_schema_version = schema_version(conn)?;
+        if current_schema_version != known_schema_version {
+            console.print("Database schema was changed by another process, re-checking tables");
+            probe_buffer.flush(conn)?;
+            init_database(conn)?;
+            known_schema_version = schema_version(conn)?;
+        }
+
+        // Re-reads the known locations too, so ones added while watching take effect
+        if last_location_check.is_none_or(|at| at.elapsed() >= LOCATION_CHECK_INTERVAL) {
+            last_location_check = Some(Instant::now());
+            let known = locations::list(conn)?;
+            let found = if known.is_empty() {
+                None
+            } else {
+                locations::find(&known, &locations::detect(args.wifi_interface.as_deref())).cloned()
+            };
+            match (&location, &found) {
+                (previous, Some(current)) if previous.as_ref().is_none_or(|previous| previous.name != current.name) => {
+                    console.print(&format!("Joined network location {} ({})", current.name, current.action))
+                }
+                (Some(previous), None) => console.print(&format!("Left network location {}", previous.name)),
+                _ => {}
+            }
+            location = found;
+        }
+
+        let pause_reason = if pause::is_paused(conn)? {
+            Some("resume it with `webgone resume`".to_string())
+        } else {
+            location
+                .as_ref()
+                .filter(|location| location.action == locations::Action::Pause)
+                .map(|location| format!("while on network location {}", location.name))
+        };
+        if let Some(reason) = pause_reason {
+            if !paused {
+                paused = true;
+                session.end(conn, Local::now())?;
+                if let Some(services) = &mut services {
+                    services.interrupt(conn, Local::now())?;
+                }
+                // Let another watcher notify while this one isn't monitoring
+                if let Some(lease) = &lease {
+                    if let Err(e) = lease.release() {
+                        console.print(&format!("Failed to release the notification lease: {:#}", e));
+                    }
+                    last_lease_renewal = None;
+                    lease_state = None;
+                }
+                console.print(&format!("Monitoring paused, {}", reason));
+            }
+            if let Some(services) = &mut services {
+                services.discard();
+            }
+            thread::sleep(interval);
+            continue;
+        }
+        if paused {
+            paused = false;
+            console.print("Monitoring resumed");
+        }
+        session.touch(conn, iteration_time)?;
+        if let Some(webhooks) = &webhooks {
+            for failure in webhooks.failures() {
+                console.print(&failure);
+                metrics.notification_failures += 1;
+            }
+        }
+        if let Some(services) = &mut services {
+            for event in services.update(conn, is_connected)? {
+                console.print(&event.message);
+                if event.notify {
+                    notifier.deliver(console, &event.subject, &event.message);
+                }
+            }
+        }
+
+        if let Some(lease) = &mut lease {
+            if last_lease_renewal.is_none_or(|at| at.elapsed() >= args.notify_lease_ttl / 3) {
+                last_lease_renewal = Some(Instant::now());
+                let (active, state) = match lease.renew(iteration_time) {
+                    Ok(holder) if holder == lease.instance() => {
+                        (true, "Holding the notification lease, sending notifications".to_string())
+                    }
+                    Ok(holder) => (false, format!("{} holds the notification lease, only recording", holder)),
+                    // Rather notify twice than not at all
+                    Err(e) => (true, format!("Notification lease unavailable ({:#}), sending notifications", e)),
+                };
+                notifier.set_active(active);
+                if lease_state.as_ref() != Some(&state) {
+                    console.print(&state);
+                    lease_state = Some(state);
+                }
+            }
+        }
+
+        let checked_at = Local::now();
+        let timeout = adaptive_timeout.as_ref().map_or(fixed_timeout, AdaptiveTimeout::current);
+        let result = match (&mut pool, targets.as_slice()) {
+            (Some(pool), _) => check_internet_pool(conn, console, pool, timeout, probe_buffer, hook.as_mut())?,
+            (None, [target]) => Some(check_internet(conn, console, target, timeout, probe_buffer, hook.as_mut())?),
+            (None, targets) => {
+                let (result, failed) =
+                    check_internet_quorum(conn, console, targets, args.quorum, timeout, probe_buffer, hook.as_mut())?;
+                if !result.is_success() && failed_components.is_none() {
+                    failed_components = Some(failed.iter().map(Target::to_string).collect::<Vec<_>>().join(", "));
+                    failed_probes = Some(probe_kinds(&failed));
+                }
+                Some(result)
+            }
+        };
+        if let Some(result) = result.as_ref().filter(|result| !result.is_success()) {
+            if failed_probes.is_none() {
+                failed_probes = Some(result.target.kind().to_string());
+            }
+        }
+        if let (Some(adaptive), Some(result)) = (&mut adaptive_timeout, &result) {
+            adaptive.record(result);
+            let adapted = adaptive.current();
+            // Only mention changes that matter, not every jitter of the baseline
+            if adapted.abs_diff(reported_timeout) * 5 >= reported_timeout {
+                reported_timeout = adapted;
+                console.detail(&format!(
+                    "Probe timeout now {:.1} ms (99th percentile latency {:.1} ms)",
+                    adapted.as_secs_f64() * 1000.0,
+                    adaptive.p99().unwrap_or_default().as_secs_f64() * 1000.0
+                ));
+            }
+        }
+        // The verdict decides the outcome of the check, so the recorded checks match the outages
+        let result = match (&up_when, result) {
+            (Some(up_when), Some(mut result)) => {
+                let verdict = up_when.evaluate(&result);
+                if !verdict.failed.is_empty() {
+                    console.detail(&format!("Failed components: {}", verdict.failed.join(", ")));
+                }
+                if verdict.up {
+                    result.error = None;
+                } else {
+                    let failed = verdict.failed.join(", ");
+                    if failed_components.is_none() {
+                        failed_components = Some(failed.clone());
+                    }
+                    result.error = Some(format!("{} failed", failed));
+                }
+                Some(result)
+            }
+            (_, result) => result,
+        };
+        let checked = Local::now();
+        let current_status = result.as_ref().is_some_and(ProbeResult::is_success);
+        // An outage that was already going on isn't hidden by the grace period
+        let in_grace_period = is_connected && grace_until.is_some_and(|until| Instant::now() < until);
+        let ignored = in_grace_period && !current_status;
+        if ignored {
+            console.print("Check failed during the warm-up grace period, not counting it as an outage");
+            failed_components = None;
+            failed_probes = None;
+        }
+        if let (Some(recorder), Some(result), false) = (&recorder, &result, ignored) {
+            recorder.record(conn, checked_at, checked, result)?;
+        }
+        if current_status {
+            last_latency = result.as_ref().map(|result| result.elapsed);
+            if is_connected {
+                failed_components = None;
+                failed_probes = None;
+            }
+        }
+        if is_connected && !ignored {
+            for change in degradation.record(checked_at, current_status) {
+                record_degradation(conn, console, change, args.degraded_window)?;
+            }
+        }
+        metrics.check_duration = iteration_start.elapsed();
+        
+        let transition = if ignored { None } else { debounce.record(checked_at, checked, current_status) };
+        match transition {
+            Some(debounce::Transition::Lost { since, seen }) => {
+                let confirmed = Local::now();
+                state_since = since;
+                outage_start = Some(since);
+                outage_detected = Some(seen);
+                outage_confirmed = Some(confirmed);
+                outage_confirmed_instant = Some(Instant::now());
+                if args.fail_threshold > 1 {
+                    console.print(&format!(
+                        "Internet connection lost at {} (confirmed by {} failed checks at {})",
+                        since,
+                        args.fail_threshold,
+                        confirmed
+                    ));
+                } else {
+                    console.print(&format!("Internet connection lost at {}", since));
+                }
+                outage_failed_components = failed_components.take();
+                if let Some(failed) = &outage_failed_components {
+                    console.print(&format!("Failed components: {}", failed));
+                }
+                outage_failed_probes = failed_probes.take();
+                if let Some(period) = degradation.outage_started() {
+                    record_degradation(conn, console, degraded::Change::Ended(period), args.degraded_window)?;
+                }
+                outage_planned = plans::at(conn, since)?;
+                outage_severity = classifier.classify(since, confirmed);
+                match &outage_planned {
+                    Some(plan) => console.print(&format!("Outage is within {}, not notifying", plans::describe(plan))),
+                    None => {
+                        let channels = routing.channels(outage_severity);
+                        let target = outage_failed_components.as_deref().unwrap_or(&watched_targets);
+                        notifier.deliver_to(
+                            console,
+                            channels,
+                            "webgone: connection lost",
+                            &format!("Internet connection lost at {} ({})", since.format("%H:%M:%S"), outage_severity),
+                        );
+                        if let Some(webhooks) = webhooks.as_ref().filter(|_| notifier.is_active() && channels.webhook) {
+                            webhooks.send(&webhook::outage_started(since, outage_severity, target, host.as_deref()));
+                        }
+                        if let Some(discord) = discord.as_mut().filter(|_| notifier.is_active() && channels.discord) {
+                            discord.lost(since, outage_severity, target);
+                        }
+                        outage_notified = channels;
+                    }
+                }
+                carryover.outage = Some((since, outage_notified));
+                probe_buffer.flush(conn)?;
+                acks::set_current(conn, outage_start)?;
+                if args.wifi {
+                    outage_wifi = wifi::current(args.wifi_interface.as_deref());
+                    match &outage_wifi {
+                        Some(info) => console.print(&format!("Wi-Fi at time of outage: {}", info)),
+                        None => console.print("Wi-Fi at time of outage: no wireless link found"),
+                    }
+                }
+                if let Some(cache) = &weather_cache {
+                    outage_weather = cache.report().cloned();
+                    if let Some(report) = &outage_weather {
+                        console.print(&format!("Weather at time of outage: {} (as of {})", report, report.observed_at.format("%H:%M")));
+                    }
+                }
+                outage_location = location
+                    .as_ref()
+                    .filter(|location| location.action == locations::Action::Tag)
+                    .map(|location| location.name.clone());
+                if let Some(name) = &outage_location {
+                    console.print(&format!("Outage is on network location {}, tagging it as such", name));
+                }
+                if capture_settings.enabled() {
+                    match capture_settings.start(since) {
+                        Ok(capture) => {
+                            console.print(&format!("Capturing packets to {}", capture.path().display()));
+                            outage_capture = Some(capture);
+                        }
+                        Err(e) => console.print(&format!("{:#}", e)),
+                    }
+                }
+                // A trace cut off by the outage would look like a route change
+                if let Some(tracer) = &mut tracer {
+                    tracer.cancel();
+                }
+                if !recovery_notices.is_empty() {
+                    outage_history = Some(recovery::History::load(conn)?);
+                    notices_sent = 0;
+                }
+                is_connected = false;
+            }
+            Some(debounce::Transition::Restored { since }) => {
+                if let Some(start_time) = outage_start {
+                    let recovered = Local::now();
+                    let stepped_back = outage_confirmed.zip(outage_confirmed_instant.take()).is_some_and(|(confirmed, at)| {
+                        let wall = (recovered - confirmed).to_std().unwrap_or_default();
+                        at.elapsed().saturating_sub(wall) >= CLOCK_STEP_THRESHOLD
+                    });
+                    let clock_anomaly = stepped_back || since < start_time;
+                    if clock_anomaly {
+                        console.print("The clock was stepped backwards during the outage, its times are unreliable");
+                    }
+                    let end_time = since.max(start_time);
+                    state_since = end_time;
+                    let duration = end_time.signed_duration_since(start_time);
+                    
+                    let outage = InternetOutage {
+                        id: 0,
+                        site: None,
+                        start_time,
+                        detected_at: outage_detected.take(),
+                        confirmed_at: outage_confirmed.take(),
+                        end_time,
+                        recovered_at: Some(recovered),
+                        duration_seconds: duration.num_seconds(),
+                        wifi: outage_wifi.take(),
+                        weather: outage_weather.take(),
+                        tags: Vec::new(),
+                        capture_path: outage_capture
+                            .take()
+                            .and_then(capture::Capture::finish)
+                            .map(|path| path.display().to_string()),
+                        failed_components: outage_failed_components.take(),
+                        actor: None,
+                        clock_anomaly,
+                        failed_probes: outage_failed_probes.take(),
+                        severity: Some(classifier.classify(start_time, end_time)),
+                        derived_from: None,
+                    };
+                    
+                    // Written by the next run if this one fails before it is
+                    carryover.outage = None;
+                    carryover.unsaved = Some(outage.clone());
+                    probe_buffer.flush(conn)?;
+                    let (outage_id, merged) = log_or_merge_outage(conn, &outage, merge_gap, &classifier)?;
+                    carryover.unsaved = None;
+                    acks::attach(conn, start_time, outage_id)?;
+                    acks::set_current(conn, None)?;
+                    if let Some(name) = outage_location.take() {
+                        tags::add(conn, outage_id, &[name])?;
+                    }
+                    if plans::overlaps(conn, start_time, end_time)? {
+                        tags::add(conn, outage_id, &[plans::MAINTENANCE_TAG.to_string()])?;
+                        console.print(&format!("Outage overlaps planned downtime, tagged it as {}", plans::MAINTENANCE_TAG));
+                    }
+                    if tracer.is_some() && routes::tag_outages(conn, start_time)? > 0 {
+                        console.print(&format!("Outage is close to a route change, tagged it as {}", routes::ROUTE_CHANGE_TAG));
+                    }
+                    console.print(&format!(
+                        "Internet connection restored at {}. Outage duration: {} seconds",
+                        end_time,
+                        duration.num_seconds()
+                    ));
+                    if args.recover_threshold > 1 {
+                        console.print(&format!("Confirmed by {} successful checks at {}", args.recover_threshold, recovered));
+                    }
+                    // Only the channels told about the loss hear about the recovery
+                    let channels = std::mem::take(&mut outage_notified);
+                    let severity = outage.severity.unwrap_or(outage_severity);
+                    if outage_planned.take().is_none() {
+                        notifier.deliver_to(
+                            console,
+                            channels,
+                            "webgone: connection restored",
+                            &format!(
+                                "Internet connection restored at {} after {} ({})",
+                                end_time.format("%H:%M:%S"),
+                                recovery::describe(duration.to_std().unwrap_or_default()),
+                                severity
+                            ),
+                        );
+                        let target = outage.failed_components.as_deref().unwrap_or(&watched_targets);
+                        if let Some(webhooks) = webhooks.as_ref().filter(|_| notifier.is_active() && channels.webhook) {
+                            webhooks.send(&webhook::outage_ended(outage_id, start_time, end_time, severity, target, host.as_deref()));
+                        }
+                        if let Some(discord) = discord.as_mut().filter(|_| notifier.is_active() && channels.discord) {
+                            discord.restored(start_time, end_time, severity, target);
+                        }
+                        if let Some(mailer) = mailer.as_mut().filter(|_| notifier.is_active() && channels.email) {
+                            mailer.restored(outage_id, start_time, end_time, severity, target);
+                        }
+                    }
+                    if merged {
+                        console.print(&format!("Merged into the previous outage, which ended at most {} seconds earlier", args.merge_gap));
+                    }
+
+                    if args.os_events {
+                        let events = oslog::events_between(start_time, end_time);
+                        console.print(&format!("Found {} related system event(s) around the outage", events.len()));
+                        log_outage_events(conn, outage_id, &events)?;
+                    }
+                    
+                    is_connected = true;
+                    failed_components = None;
+                    failed_probes = None;
+                    outage_start = None;
+                    outage_history = None;
+                }
+            }
+            None => {}
+        }
+
+        if let Some(message) = outage_capture.as_mut().and_then(capture::Capture::check) {
+            console.print(&message);
+        }
+
+        // Put long outages into context once they pass each notice duration
+        if let (Some(start), Some(history)) = (outage_start, &outage_history) {
+            let elapsed = (Local::now() - start).to_std().unwrap_or_default();
+            let due = recovery_notices.iter().filter(|notice| **notice <= elapsed).count();
+            if due > notices_sent {
+                notices_sent = due;
+                let message = format!("Outage ongoing for {}: {}", recovery::describe(elapsed), history.estimate(elapsed));
+                // Once someone acknowledged the outage, others don't need to be alerted again
+                match acks::for_ongoing(conn, start)? {
+                    Some(ack) => console.print(&format!("{} (acknowledged by {})", message, ack)),
+                    None if outage_planned.is_some() => console.print(&format!("{} (planned)", message)),
+                    None => {
+                        console.print(&message);
+                        notifier.deliver_to(console, outage_notified, "webgone: outage ongoing", &message);
+                    }
+                }
+            }
+        }
+
+        // Tell the channels that only want more severe outages once the outage gets there
+        if let Some(start) = outage_start.filter(|_| outage_planned.is_none()) {
+            let severity = classifier.classify(start, Local::now());
+            if severity > outage_severity {
+                outage_severity = severity;
+                let channels = routing.channels(severity).without(outage_notified);
+                let message = format!(
+                    "Internet connection lost at {}, now {} after {}",
+                    start.format("%H:%M:%S"),
+                    severity,
+                    recovery::describe((Local::now() - start).to_std().unwrap_or_default())
+                );
+                console.print(&format!("Outage is now {}", severity));
+                notifier.deliver_to(console, channels, &format!("webgone: {} outage", severity), &message);
+                let target = outage_failed_components.as_deref().unwrap_or(&watched_targets);
+                if let Some(webhooks) = webhooks.as_ref().filter(|_| notifier.is_active() && channels.webhook) {
+                    webhooks.send(&webhook::outage_started(start, severity, target, host.as_deref()));
+                }
+                if let Some(discord) = discord.as_mut().filter(|_| notifier.is_active() && channels.discord) {
+                    discord.lost(start, severity, target);
+                }
+                outage_notified = outage_notified.and(channels);
+            }
+        }
+
+        if is_connected {
+            if let Some(cache) = &mut weather_cache {
+                if let Err(e) = cache.refresh_if_stale() {
+                    console.print(&format!("Failed to refresh weather: {:#}", e));
+                }
+            }
+
+            if let Some(tracer) = &mut tracer {
+                match tracer.poll() {
+                    Ok(Some(trace)) => {
+                        let traced_at = Local::now();
+                        if let Some(change) = routes::record(conn, traced_at, &trace)? {
+                            console.print(&format!(
+                                "Route to {} changed: {} of {} hops differ from the previous trace",
+                                trace.anchor,
+                                change.changed_hops,
+                                change.total_hops
+                            ));
+                            let tagged = routes::tag_outages(conn, traced_at)?;
+                            if tagged > 0 {
+                                console.print(&format!("Tagged {} outage(s) close to it as {}", tagged, routes::ROUTE_CHANGE_TAG));
+                            }
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => console.print(&format!("Failed to trace route: {:#}", e)),
+                }
+            }
+
+            // Embeds of a lost connection wait until it is back
+            if let Some(discord) = &mut discord {
+                if let Err(e) = discord.flush() {
+                    console.print(&format!("Discord notification failed: {}", e));
+                    metrics.notification_failures += 1;
+                }
+            }
+            if let Some(mailer) = &mut mailer {
+                if let Err(e) = mailer.flush() {
+                    console.print(&format!("Email notification failed: {}", e));
+                    metrics.notification_failures += 1;
+                }
+            }
+
+            // The TSA can only be reached while connected; failures are retried an hour later
+            if args.timestamp_evidence && last_evidence_run.is_none_or(|at| at.elapsed() >= EVIDENCE_CHECK_INTERVAL) {
+                last_evidence_run = Some(Instant::now());
+                if let Err(e) = stamp_evidence(conn, console, &args.tsa) {
+                    console.print(&format!("Failed to timestamp outage evidence: {:#}", e));
+                }
+            }
+
+            // Digests are held back during outages, so channels that need the network receive them
+            if (!schedules.is_empty() || email_digest.is_some())
+                && last_digest_check.is_none_or(|at| at.elapsed() >= DIGEST_CHECK_INTERVAL)
+            {
+                last_digest_check = Some(Instant::now());
+                probe_buffer.flush(conn)?;
+                for schedule in &schedules {
+                    if let Some(end) = schedule.due(conn, Local::now())? {
+                        let (subject, message) = schedule.render(conn, end, pricing.as_ref(), lang)?;
+                        notifier.send(console, &subject, &message);
+                        schedule.mark_sent(conn, Local::now())?;
+                    }
+                }
+                if let (Some(schedule), Some(mailer)) = (&email_digest, &mut mailer) {
+                    if let Some(end) = schedule.due(conn, Local::now())? {
+                        let (subject, message) = schedule.render(conn, end, pricing.as_ref(), lang)?;
+                        if notifier.is_active() {
+                            mailer.queue(&subject, &message);
+                        }
+                        schedule.mark_sent(conn, Local::now())?;
+                    }
+                }
+            }
+        }
+
+        if let Some(days) = storage.raw_retention_days {
+            if last_retention_run.is_none_or(|at| at.elapsed() >= storage::RETENTION_CHECK_INTERVAL) {
+                last_retention_run = Some(Instant::now());
+                probe_buffer.flush(conn)?;
+                let removed = storage::roll_up_probes(conn, days, storage.rollup_granularity)?;
+                if removed > 0 {
+                    console.print(&format!(
+                        "Rolled up {} raw probe(s) older than {} days into {} aggregates",
+                        removed,
+                        days,
+                        storage.rollup_granularity.name()
+                    ));
+                }
+            }
+        }
+
+        if args.rotate_yearly && last_rotation_run.is_none_or(|at| at.elapsed() >= storage::RETENTION_CHECK_INTERVAL) {
+            last_rotation_run = Some(Instant::now());
+            probe_buffer.flush(conn)?;
+            for (year, count) in archive::rotate(conn, db_path, storage.rollup_granularity)? {
+                console.print(&format!(
+                    "Moved {} raw probe(s) of {} to {}",
+                    count,
+                    year,
+                    archive::archive_path(db_path, year).display()
+                ));
+            }
+        }
+
+        if let Some(path) = &args.replica {
+            if last_replica_build.is_none_or(|at| at.elapsed() >= args.replica_interval) {
+                last_replica_build = Some(Instant::now());
+                probe_buffer.flush(conn)?;
+                // A dashboard that can't be updated is no reason to stop monitoring
+                if let Err(e) = replica::build(db_path, path, Local::now()) {
+                    console.print(&format!("Failed to update the replica {}: {:#}", path.display(), e));
+                }
+            }
+        }
+
+        if !jobs.is_empty() && last_schedule_check.is_none_or(|at| at.elapsed() >= SCHEDULE_CHECK_INTERVAL) {
+            last_schedule_check = Some(Instant::now());
+            for &(task, when) in &jobs {
+                if schedule::due(conn, task, when, Local::now())? {
+                    probe_buffer.flush(conn)?;
+                    // A failed task is reported and tried again at its next scheduled time
+                    match run_scheduled(conn, db_path, &config, task, lang) {
+                        Ok(done) => console.print(&done),
+                        Err(e) => console.print(&format!("Scheduled {} failed: {:#}", task, e)),
+                    }
+                    schedule::mark_ran(conn, task, Local::now())?;
+                    // VACUUM of the compact and scrub tasks changes the schema version itself
+                    known_schema_version = schema_version(conn)?;
+                }
+            }
+        }
+
+        if let Some(percent) = args.trend_alert {
+            if is_connected && last_trend_check.is_none_or(|at| at.elapsed() >= TREND_CHECK_INTERVAL) {
+                last_trend_check = Some(Instant::now());
+                if let Some(week) = trend::due(conn, Local::now())? {
+                    probe_buffer.flush(conn)?;
+                    let weeks = trend::weeks(conn, args.trend_weeks + 1, Local::now())?;
+                    for message in trend::degradations(&weeks, percent, args.trend_weeks) {
+                        notifier.send(console, "webgone: connection quality is degrading", &message);
+                    }
+                    trend::mark_checked(conn, week)?;
+                }
+            }
+        }
+
+        if last_quality_check.is_none_or(|at| at.elapsed() >= QUALITY_CHECK_INTERVAL) {
+            last_quality_check = Some(Instant::now());
+            probe_buffer.flush(conn)?;
+            quality::store_days(conn, &config.quality, QUALITY_CATCH_UP_DAYS, false, Local::now())?;
+        }
+
+        metrics.queue_depth = probe_buffer.pending();
+        metrics.record_db_write(probe_buffer.last_write_latency());
+        metrics.weather_failures = weather_cache.as_ref().map_or(0, WeatherCache::failures);
+        metrics.notification_failures += notifier.take_failures();
+        if last_status_save.is_none_or(|at| at.elapsed() >= storage.flush_interval) {
+            last_status_save = Some(Instant::now());
+            health::save(conn, &metrics)?;
+        }
+        
+        console.status(&console::Status { connected: is_connected, since: state_since, latency: last_latency });
+        thread::sleep(interval);
+    }
+
+    probe_buffer.flush(conn)?;
+    metrics.record_db_write(probe_buffer.last_write_latency());
+    metrics.queue_depth = probe_buffer.pending();
+    metrics.notification_failures += notifier.take_failures();
+    health::save(conn, &metrics)?;
+    if let Some(period) = degradation.finish() {
+        record_degradation(conn, console, degraded::Change::Ended(period), args.degraded_window)?;
+    }
+    // An outage still going on is recorded up to now, like those of services,
+    // without a recovery as the connection didn't come back
+    if let Some(start_time) = outage_start.filter(|_| !is_connected) {
+        let end_time = Local::now().max(start_time);
+        let outage = InternetOutage {
+            id: 0,
+            site: None,
+            start_time,
+            detected_at: outage_detected.take(),
+            confirmed_at: outage_confirmed.take(),
+            end_time,
+            recovered_at: None,
+            duration_seconds: end_time.signed_duration_since(start_time).num_seconds(),
+            wifi: outage_wifi.take(),
+            weather: outage_weather.take(),
+            tags: Vec::new(),
+            capture_path: outage_capture.take().and_then(capture::Capture::finish).map(|path| path.display().to_string()),
+            failed_components: outage_failed_components.take(),
+            actor: None,
+            clock_anomaly: false,
+            failed_probes: outage_failed_probes.take(),
+            severity: Some(classifier.classify(start_time, end_time)),
+            derived_from: None,
+        };
+        let (outage_id, _) = log_or_merge_outage(conn, &outage, merge_gap, &classifier)?;
+        carryover.outage = None;
+        acks::attach(conn, start_time, outage_id)?;
+        if let Some(name) = outage_location.take() {
+            tags::add(conn, outage_id, &[name])?;
+        }
+        if plans::overlaps(conn, start_time, end_time)? {
+            tags::add(conn, outage_id, &[plans::MAINTENANCE_TAG.to_string()])?;
+        }
+        console.print(&format!("Recorded the outage going on since {} up to now", start_time));
     }
-
+    session.end(conn, Local::now())?;
+    if let Some(services) = &mut services {
+        services.update(conn, is_connected)?;
+        services.interrupt(conn, Local::now())?;
+    }
+    if let Some(lease) = &lease {
+        if let Err(e) = lease.release() {
+            console.print(&format!("Failed to release the notification lease: {:#}", e));
+        }
+    }
+    acks::set_current(conn, None)?;
+    console.clear();
+    console.print("Monitoring stopped.");
     Ok(())
 }
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct CliArgs {
+    /// Language of report output (defaults to the system locale)
+    #[arg(long, global = true, value_enum)]
+    lang: Option<Lang>,
+
+    /// Print reports as `label: value` lines instead of tables, for screen readers and plain-text emails
+    #[arg(long, global = true)]
+    plain: bool,
+
+    /// Forward crash reports to this Sentry DSN, in addition to writing them next to the database
+    #[arg(long, global = true, env = "WEBGONE_SENTRY_DSN", hide_env_values = true)]
+    sentry_dsn: Option<String>,
+
+    /// Database to use [default: internet_outages.db if it is in the working directory, otherwise outages.db
+    /// in the data directory, e.g. ~/.local/share/webgone]
+    #[arg(long, global = true, env = "WEBGONE_DB")]
+    db: Option<PathBuf>,
+
     #[command(subcommand)]
     command: Commands
 }
 
+#[derive(Args)]
+struct WatchArgs {
+    /// Target to check as IP:PORT, tcp:IP:PORT, icmp:IP, dns:IP[:PORT][/DOMAIN], an http(s):// URL, an IP probed
+    /// with --probe, or `auto` to rotate over built-in anycast endpoints; repeat it to check several targets at once
+    #[arg(short, long, conflicts_with = "ip")]
+    target: Vec<TargetSpec>,
+    /// How to probe --ip, `auto` and targets given as a bare IP; `http` fetches --url, `dns` resolves --domain there
+    #[arg(long, value_enum, default_value = "tcp")]
+    probe: target::ProbeMode,
+    /// URL to fetch with --probe http, e.g. https://example.com/health
+    #[arg(long, conflicts_with = "target")]
+    url: Option<String>,
+    /// Status codes that count as success for URLs: a code, a range like 200-399 or a class like 2xx
+    #[arg(long, default_value = "2xx")]
+    expect_status: target::StatusRange,
+    /// Domain resolvers are queried for with --probe dns and by dns: targets that don't name one
+    #[arg(long, default_value = "google.com")]
+    domain: String,
+    /// With several --target, how many of them have to fail for a check to fail: `all` or a number
+    #[arg(long, default_value = "all")]
+    quorum: target::Quorum,
+    /// IP address to check
+    #[arg(short, long, default_value_t = IpAddr::from([8, 8, 8, 8]))]
+    ip: IpAddr,
+    /// Port to check
+    #[arg(short, long, default_value_t = 53)]
+    port: u16,
+    /// Interval in seconds (fractions like 0.5 are allowed)
+    #[arg(short = 'I', long, default_value_t = 5.0)]
+    interval: f64,
+    /// Seconds a probe may take before it counts as failed; with --adaptive-timeout, used until enough latencies are known
+    #[arg(long, default_value_t = 1.0)]
+    timeout: f64,
+    /// Derive the probe timeout from recent latencies (3× their 99th percentile) instead of using a fixed one
+    #[arg(long)]
+    adaptive_timeout: bool,
+    /// Lower bound in seconds for the adaptive timeout
+    #[arg(long, default_value_t = 0.2, requires = "adaptive_timeout")]
+    min_timeout: f64,
+    /// Upper bound in seconds for the adaptive timeout
+    #[arg(long, default_value_t = 10.0, requires = "adaptive_timeout")]
+    max_timeout: f64,
+    /// When the internet counts as up, e.g. `gateway AND (dns OR http)`; built-in components are
+    /// target, gateway, dns and http [default: target]
+    #[arg(long, value_name = "EXPRESSION")]
+    up_when: Option<verdict::Expr>,
+    /// Component for --up-when as NAME=gateway, NAME=dns[:IP], NAME=http:URL, NAME=tcp:IP:PORT or NAME=icmp:IP (repeatable)
+    #[arg(long = "component", value_name = "COMPONENT", requires = "up_when")]
+    components: Vec<verdict::Component>,
+    /// Another service to watch as NAME=tcp:IP:PORT, NAME=icmp:IP, NAME=http:URL, NAME=dns[:IP] or NAME=gateway, with outages
+    /// recorded under NAME as their site (repeatable)
+    #[arg(long = "service", value_name = "SERVICE")]
+    services: Vec<services::Service>,
+    /// Service that can't be reached while another one, or the internet connection (`local`), is down, as
+    /// CHILD=PARENT; its outages that begin while the parent is down are recorded as derived and not notified
+    #[arg(long = "depends-on", value_name = "CHILD=PARENT")]
+    dependencies: Vec<services::Dependency>,
+    /// Obtain an RFC 3161 timestamp for the outage digest of each finished day
+    #[arg(long)]
+    timestamp_evidence: bool,
+    /// Timestamp authority used with --timestamp-evidence
+    #[arg(long, default_value_t = String::from(evidence::DEFAULT_TSA_URL))]
+    tsa: String,
+    /// Consecutive failed checks before an outage is declared; its start is dated back to the first of them
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    fail_threshold: u32,
+    /// Consecutive successful checks before an outage is over; its end is dated back to the first of them
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    recover_threshold: u32,
+    /// Merge an outage into the previous one if the connection was back for at most this many seconds (0 disables merging)
+    #[arg(long, default_value_t = 0)]
+    merge_gap: u64,
+    /// Number of recent checks the share of lost probes is computed over, to notice a degraded connection
+    #[arg(long, default_value_t = 20, value_parser = clap::value_parser!(u32).range(2..))]
+    degraded_window: u32,
+    /// Count the connection as degraded once at least this many percent of the recent checks failed
+    #[arg(long, value_name = "PERCENT", default_value_t = 20.0)]
+    degraded_threshold: f64,
+    /// Don't count failed checks as outages for this long after start and after the machine resumes from a suspend,
+    /// while the network interface, VPN or Wi-Fi comes up (e.g. 30s, 2m)
+    #[arg(long, value_parser = filter::parse_duration, default_value = "0s")]
+    grace_period: Duration,
+    /// Storage preset; `flash` minimizes writes for SD cards and other flash media
+    #[arg(long, value_enum, default_value_t = StorageProfile::Default)]
+    storage_profile: StorageProfile,
+    /// SQLite synchronous setting (overrides the storage profile)
+    #[arg(long, value_enum)]
+    synchronous: Option<Synchronous>,
+    /// SQLite journal mode (overrides the storage profile)
+    #[arg(long, value_enum)]
+    journal_mode: Option<JournalMode>,
+    /// Number of probe results buffered in memory before they are written to the database
+    #[arg(long)]
+    batch_size: Option<usize>,
+    /// Maximum time in seconds probe results stay buffered before they are written
+    #[arg(long)]
+    flush_interval: Option<u64>,
+    /// Record only every Nth successful probe; failed probes are always recorded
+    #[arg(long)]
+    sample_every: Option<u32>,
+    /// Roll raw probes older than this many days up into aggregates
+    #[arg(long)]
+    raw_retention_days: Option<u32>,
+    /// Record the outcome of every check, so outages can be derived again with other thresholds
+    #[arg(long)]
+    record_probes: bool,
+    /// Number of checks kept by --record-probes; older ones are dropped
+    #[arg(long, requires = "record_probes", default_value_t = history::DEFAULT_CAPACITY)]
+    probe_history_size: u64,
+    /// Period length of the aggregates raw probes are rolled up into
+    #[arg(long, value_enum)]
+    rollup_granularity: Option<Granularity>,
+    /// Move raw probes of past years into one database file per year once the year is over, as `webgone db rotate` does
+    #[arg(long)]
+    rotate_yearly: bool,
+    /// Keep a denormalized copy of the data for dashboards in this file, as `webgone replica` writes it
+    #[arg(long, value_name = "FILE")]
+    replica: Option<PathBuf>,
+    /// How often to update --replica (e.g. 1m, 1h)
+    #[arg(long, value_parser = filter::parse_duration, default_value = "5m", requires = "replica")]
+    replica_interval: Duration,
+    /// Run the backup, report, compact, statuspage and scrub tasks listed in the [schedule] table of this TOML file, and weigh the quality score by its [quality] table
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+    /// Record Wi-Fi SSID and signal strength with each outage
+    #[arg(long)]
+    wifi: bool,
+    /// Wireless interface to read the SSID and signal from, for `--wifi` and network locations (defaults to the first one found)
+    #[arg(long)]
+    wifi_interface: Option<String>,
+    /// Attach nearby system log events (interface changes, DHCP, suspend) to each outage
+    #[arg(long)]
+    os_events: bool,
+    /// Capture packets on this interface with tcpdump while an outage lasts, and store the capture with it
+    #[arg(long, value_name = "INTERFACE")]
+    capture_interface: Option<String>,
+    /// Shell command capturing packets to WEBGONE_CAPTURE_FILE while an outage lasts, instead of the built-in tcpdump
+    #[arg(long, conflicts_with = "capture_interface")]
+    capture_command: Option<String>,
+    /// Directory packet captures are written to
+    #[arg(long, default_value = "captures")]
+    capture_dir: PathBuf,
+    /// Stop a packet capture after this many seconds, even if the outage goes on
+    #[arg(long, default_value_t = 300)]
+    capture_max_duration: u64,
+    /// Trace the route to anchors periodically and report significant path changes, tagging outages close to them
+    #[arg(long)]
+    trace_routes: bool,
+    /// Addresses whose route is traced with --trace-routes (comma-separated)
+    #[arg(long, value_delimiter = ',', default_value = routes::DEFAULT_ANCHORS)]
+    route_anchors: Vec<IpAddr>,
+    /// Minutes between traces to the same anchor
+    #[arg(long, default_value_t = 15)]
+    route_interval: u64,
+    /// Record local weather conditions with each outage (requires --latitude and --longitude)
+    #[arg(long, requires_all = ["latitude", "longitude"])]
+    weather: bool,
+    /// Latitude used for weather lookups
+    #[arg(long, allow_negative_numbers = true)]
+    latitude: Option<f64>,
+    /// Longitude used for weather lookups
+    #[arg(long, allow_negative_numbers = true)]
+    longitude: Option<f64>,
+    /// Open-Meteo compatible weather API endpoint
+    #[arg(long, default_value_t = String::from(weather::DEFAULT_API_URL))]
+    weather_api: String,
+    /// How long cached weather conditions are reused, in minutes
+    #[arg(long, default_value_t = 15)]
+    weather_refresh: u64,
+    /// Report ongoing outages after these durations, compared to past outages (comma-separated)
+    #[arg(long, value_parser = filter::parse_duration, value_delimiter = ',', default_value = recovery::DEFAULT_NOTICES)]
+    recovery_notices: Vec<Duration>,
+    /// Don't report how ongoing outages compare to past ones
+    #[arg(long, conflicts_with = "recovery_notices")]
+    no_recovery_notices: bool,
+    /// Send a summary of outages, downtime and uptime every day or week (repeatable)
+    #[arg(long = "digest", value_enum, value_name = "PERIOD")]
+    digests: Vec<digest::Period>,
+    /// Local time at which digests are sent
+    #[arg(long, default_value = "08:00")]
+    digest_time: chrono::NaiveTime,
+    /// Day of the week on which the weekly digest is sent
+    #[arg(long, default_value = "mon")]
+    digest_weekday: chrono::Weekday,
+    /// Monthly rate to include the cost of downtime in digests
+    #[arg(long)]
+    digest_rate: Option<f64>,
+    /// Currency symbol used with --digest-rate
+    #[arg(long, default_value_t = String::from("€"))]
+    digest_currency: String,
+    /// Notify when average latency or probe loss got worse by more than this percentage week over week
+    #[arg(long, value_name = "PERCENT")]
+    trend_alert: Option<f64>,
+    /// Number of consecutive weeks that must have gotten worse for --trend-alert
+    #[arg(long, default_value_t = 2, requires = "trend_alert", value_parser = clap::value_parser!(u32).range(1..))]
+    trend_weeks: u32,
+    /// Shell command to pass notifications to, with the message on stdin and the subject in WEBGONE_SUBJECT
+    #[arg(long)]
+    notify_command: Option<String>,
+    /// Show native desktop notifications when the connection drops or comes back, and for other notifications
+    #[arg(long)]
+    notify_desktop: bool,
+    /// Also notify when monitoring stops, e.g. on Ctrl+C, a termination signal or an error, with a summary of the session
+    #[arg(long)]
+    notify_on_exit: bool,
+    /// Also send outage notifications to this service (repeatable)
+    #[arg(long = "notify", value_enum, value_name = "SERVICE")]
+    notify: Vec<notify::Service>,
+    /// Webhook URL of the Discord channel for --notify discord
+    #[arg(long, value_name = "URL")]
+    discord_webhook: Option<String>,
+    /// POST a JSON payload to this URL when an outage starts and when it ends (repeat for several URLs)
+    #[arg(long, value_name = "URL")]
+    webhook: Vec<String>,
+    /// Number of times to try delivering each webhook payload; waits between attempts double from one second up to five minutes
+    #[arg(long, default_value_t = 12, value_parser = clap::value_parser!(u32).range(1..))]
+    webhook_attempts: u32,
+    /// Exit on any error instead of starting again after transient ones, e.g. when a service manager restarts webgone
+    #[arg(long)]
+    no_restart: bool,
+    /// Longest delay before starting again after a transient error; delays double from one second with every failure in a row
+    #[arg(long, value_parser = filter::parse_duration, default_value = "5m")]
+    max_backoff: Duration,
+    /// Database shared with other watchers of the same line, e.g. a primary and a backup monitor;
+    /// only the one holding its lease sends notifications, all of them keep recording
+    #[arg(long, value_name = "FILE", requires = "instance")]
+    notify_lease: Option<PathBuf>,
+    /// Name of this watcher in the --notify-lease database, e.g. primary or backup
+    #[arg(long)]
+    instance: Option<String>,
+    /// How long the notification lease lasts without being renewed before another watcher takes over (e.g. 30s, 2m)
+    #[arg(long, value_parser = filter::parse_duration, default_value = "60s")]
+    notify_lease_ttl: Duration,
+    /// Show a single status line that updates in place instead of a message for every failed probe
+    #[arg(long)]
+    live: bool,
+    /// Append everything the watcher prints, including every failed probe, to this file
+    #[arg(long, value_name = "FILE")]
+    log_file: Option<PathBuf>,
+    /// Rhai script whose `on_probe(probe)` function derives custom metrics and events from each probe result
+    #[arg(long, value_name = "FILE")]
+    script: Option<PathBuf>
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Watch for internet outages
-    Watch {
-        /// IP address to check
-        #[arg(short, long, default_value_t = IpAddr::from([8, 8, 8, 8]))]
-        ip: IpAddr,
-        /// Port to check
-        #[arg(short, long, default_value_t = 53)]
-        port: u16,
-        /// Interval in seconds
-        #[arg(short = 'I', long, default_value_t = 5)]
-        interval: u64
+    Watch(Box<WatchArgs>),
+    /// Create an empty database, or upgrade an existing one
+    Init,
+    /// Pause a running watcher, e.g. while working on the network
+    Pause,
+    /// Resume a paused watcher
+    Resume,
+    /// Show the state of the watcher as a tray icon (requires the `tray` feature)
+    Tray {
+        /// Page opened by the "Open dashboard" menu entry
+        #[arg(long, default_value = "http://127.0.0.1:8080/api/status")]
+        dashboard_url: String,
     },
     /// Print statistics about internet outages
-    Stats,
+    Stats {
+        /// Count outages from the first failed check (raw) or only from their confirmation (conservative)
+        #[arg(long, value_enum, default_value_t = DurationMode::Raw)]
+        durations: DurationMode,
+        /// Print a table, or JSON or CSV for scripts
+        #[arg(long, value_enum, default_value_t = output::Format::Table)]
+        format: output::Format,
+        #[command(flatten)]
+        filter: OutageFilter
+    },
     /// View recent internet outages
     Recent {
         /// Amount of outages to display
         #[arg(short, long, default_value_t = 5)]
-        limit: usize
+        limit: usize,
+        /// Also show system events recorded around each outage
+        #[arg(short, long)]
+        events: bool,
+        /// Count outages from the first failed check (raw) or only from their confirmation (conservative)
+        #[arg(long, value_enum, default_value_t = DurationMode::Raw)]
+        durations: DurationMode,
+        /// Print a table, or JSON or CSV for scripts
+        #[arg(long, value_enum, default_value_t = output::Format::Table)]
+        format: output::Format,
+        #[command(flatten)]
+        filter: OutageFilter
+    },
+    /// Find outages by words in their tags, acknowledgements, tickets, causes, events and impact entries
+    Search {
+        /// Words that all have to appear, ignoring case, e.g. `modem` or `called isp`
+        #[arg(required = true)]
+        terms: Vec<String>,
+        /// Amount of outages to display
+        #[arg(short, long, default_value_t = 20)]
+        limit: usize,
+        /// Only print the ids of the matching outages, one per line, e.g. for `webgone tag`
+        #[arg(long)]
+        ids: bool,
+        #[command(flatten)]
+        filter: OutageFilter
+    },
+    /// Serve outage statistics as a JSON API
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        listen: SocketAddr,
+        /// Public mode: rate limit requests and cache responses, unless set explicitly
+        #[arg(long)]
+        public: bool,
+        /// Allow browsers on this origin to call the API (repeatable, `*` allows any origin)
+        #[arg(long = "cors-origin", value_name = "ORIGIN")]
+        cors_origins: Vec<String>,
+        /// Maximum requests per minute and client address [public mode default: 60]
+        #[arg(long)]
+        rate_limit: Option<u32>,
+        /// Seconds responses are cached [public mode default: 10]
+        #[arg(long)]
+        cache_ttl: Option<u64>,
+        /// Require a read token for reading too (writing always needs a write token)
+        #[arg(long)]
+        require_token: bool,
+        /// Serve HTTPS with this PEM certificate (chain)
+        #[arg(long, requires = "tls_key")]
+        tls_cert: Option<PathBuf>,
+        /// PEM private key of --tls-cert
+        #[arg(long, requires = "tls_cert")]
+        tls_key: Option<PathBuf>,
+        /// Serve HTTPS with a self-signed certificate, generated once and kept next to the database
+        #[arg(long, conflicts_with = "tls_cert")]
+        tls_self_signed: bool,
+        /// How time without monitoring counts in the uptime of `/api/status`, like `sites --gaps`
+        #[arg(long, value_enum, default_value_t = GapMode::Up)]
+        gaps: GapMode,
+        /// TOML file whose [severity] table classifies outages posted to /api/outages, like the watcher's
+        #[arg(long, value_name = "FILE")]
+        config: Option<PathBuf>,
+    },
+    /// Serve a dashboard of the connection's state, recent outages and downtime per month
+    Web {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        listen: SocketAddr,
+        /// How time without monitoring counts in uptime, like `sites --gaps`
+        #[arg(long, value_enum, default_value_t = GapMode::Up)]
+        gaps: GapMode
+    },
+    /// Expose the connection state, outages and latency as Prometheus metrics
+    ServeMetrics {
+        /// Address to listen on
+        #[arg(long, default_value = "0.0.0.0:9184")]
+        listen: SocketAddr
+    },
+    /// Forward probe results and outages to a central `webgone collect` server
+    Agent {
+        /// URL of the collect server, e.g. https://central.example.org:50051
+        #[arg(long)]
+        server: String,
+        /// Name of the site this agent monitors, e.g. home or parents
+        #[arg(long)]
+        site: String,
+        /// CA certificate (PEM) to verify the server with, instead of the system's trusted roots
+        #[arg(long)]
+        server_ca: Option<PathBuf>,
+        /// Client certificate (PEM) for servers that require mutual TLS
+        #[arg(long, requires = "tls_key")]
+        tls_cert: Option<PathBuf>,
+        /// PEM private key of --tls-cert
+        #[arg(long, requires = "tls_cert")]
+        tls_key: Option<PathBuf>,
+        /// Seconds between checks for new data to forward
+        #[arg(long, default_value_t = 5)]
+        poll_interval: u64
+    },
+    /// Receive probe results and outages from agents over gRPC
+    Collect {
+        /// Address to listen on
+        #[arg(long, default_value = "0.0.0.0:50051")]
+        listen: SocketAddr,
+        /// Serve TLS with this PEM certificate (chain)
+        #[arg(long, requires = "tls_key")]
+        tls_cert: Option<PathBuf>,
+        /// PEM private key of --tls-cert
+        #[arg(long, requires = "tls_cert")]
+        tls_key: Option<PathBuf>,
+        /// Only accept agents with a client certificate signed by this CA (mutual TLS)
+        #[arg(long, requires = "tls_cert")]
+        client_ca: Option<PathBuf>
+    },
+    /// Manage tokens for the JSON API
+    Token {
+        #[command(subcommand)]
+        command: TokenCommands
+    },
+    /// Render a report of outages from a built-in or custom template
+    Report {
+        /// Output format of the built-in templates; also decides whether values are HTML-escaped
+        #[arg(short, long, value_enum, default_value_t = report::Format::Text)]
+        format: report::Format,
+        /// Tera template to render instead of the built-in one
+        #[arg(long)]
+        template: Option<PathBuf>,
+        /// Output file path (if not provided, the report will be printed to stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Compare with this imported baseline (defaults to the most recently imported one)
+        #[arg(long)]
+        baseline: Option<String>,
+        #[command(flatten)]
+        filter: OutageFilter
     },
     /// Export internet outages to a CSV file or stdout
     Export {
         /// Output file path (if not provided, data will be printed to stdout)
-        output: Option<PathBuf>
+        output: Option<PathBuf>,
+        /// JSON includes every field of the outages and can be opened by the static status page
+        #[arg(long, value_enum, default_value = "csv")]
+        format: export::Format,
+        /// Field names: `legacy` (the original CSV headers and JSON layout), `v2` (every field, snake_case)
+        /// or a mapping file with one `field = name` per line
+        #[arg(long, value_name = "legacy|v2|FILE", default_value = "legacy")]
+        schema: export::Schema,
+        /// Write one file per month or year to --out-dir, with a manifest.json listing them
+        #[arg(long, requires = "out_dir", conflicts_with = "output")]
+        split: Option<export::Split>,
+        /// Directory for the files of --split
+        #[arg(long, requires = "split")]
+        out_dir: Option<PathBuf>,
+        #[command(flatten)]
+        filter: OutageFilter
+    },
+    /// Write a denormalized copy of the data for dashboards, e.g. Grafana's SQLite datasource
+    Replica {
+        /// Database file to write; it is replaced as a whole
+        path: PathBuf
+    },
+    /// Derive the outages since the oldest check recorded with `watch --record-probes` again, with other thresholds
+    Reprocess {
+        /// Consecutive failed checks before an outage is declared
+        #[arg(long, alias = "failures-threshold", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+        fail_threshold: u32,
+        /// Consecutive successful checks before an outage is over
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+        recover_threshold: u32,
+        /// Drop outages shorter than this, e.g. 60s or 2m
+        #[arg(long, value_parser = filter::parse_duration, default_value = "0s")]
+        min_outage: Duration,
+        /// Merge outages separated by at most this many seconds of connectivity
+        #[arg(long, default_value_t = 0)]
+        merge_gap: u64,
+        /// Only show how many outages would change
+        #[arg(long)]
+        dry_run: bool
+    },
+    /// Add free-form tags to an outage (see `recent` for outage ids)
+    Tag {
+        /// Id of the outage
+        id: i64,
+        /// Tags to add, e.g. `storm ticket-4711`
+        #[arg(required = true)]
+        tags: Vec<String>,
+        /// Remove the given tags instead
+        #[arg(short, long)]
+        remove: bool
+    },
+    /// Show how many outages carry each tag and how long they lasted
+    Tags,
+    /// Track ISP support tickets and the outages they cover
+    Ticket {
+        #[command(subcommand)]
+        command: TicketCommands
+    },
+    /// Acknowledge an outage, so alerts about it aren't escalated further
+    Ack {
+        /// Outage id (see `recent`), or `current` for the outage the watcher is in right now
+        outage: acks::AckTarget,
+        /// Who acknowledges the outage [default: the current user]
+        #[arg(long)]
+        by: Option<String>,
+        /// Comment, e.g. what is being done about it
+        #[arg(short, long)]
+        comment: Option<String>
+    },
+    /// Record what outages got in the way of, e.g. a dropped call, and what it cost
+    Impact {
+        #[command(subcommand)]
+        command: ImpactCommands
+    },
+    /// Mark dates where something changed, e.g. switching ISPs, to compare the periods in between
+    Epoch {
+        #[command(subcommand)]
+        command: EpochCommands
+    },
+    /// Announce downtime ahead of time, e.g. maintenance of the ISP, so outages during it aren't notified
+    Plan {
+        #[command(subcommand)]
+        command: PlanCommands
+    },
+    /// Import typical values, e.g. published regional averages, to compare with in reports
+    Baseline {
+        #[command(subcommand)]
+        command: BaselineCommands
+    },
+    /// Compare availability, outage frequency and latency between epochs
+    Epochs {
+        /// How time without monitoring counts in availability
+        #[arg(long, value_enum, default_value_t = GapMode::Up)]
+        gaps: GapMode
+    },
+    /// Recognize other networks, e.g. a phone hotspot, to pause monitoring or tag outages there
+    Location {
+        #[command(subcommand)]
+        command: LocationCommands
+    },
+    /// Show the known network locations
+    Locations,
+    /// Show support tickets with their linked downtime, unresolved ones first
+    Tickets {
+        /// Only show unresolved tickets
+        #[arg(long)]
+        open: bool
+    },
+    /// Show uptime, downtime and current state per site
+    Sites {
+        /// Number of days to compute uptime over
+        #[arg(long, default_value_t = 30)]
+        days: u32,
+        /// How time without monitoring counts in uptime
+        #[arg(long, value_enum, default_value_t = GapMode::Up)]
+        gaps: GapMode
+    },
+    /// Compare monthly downtime across sites
+    Compare {
+        /// Number of months to show, including the current one
+        #[arg(long, default_value_t = 6)]
+        months: u32
+    },
+    /// Show custom metrics and events recorded by a `watch --script` hook
+    Metrics {
+        /// Number of days to summarize
+        #[arg(long, default_value_t = 7)]
+        days: u32,
+        /// Also show the most recent events
+        #[arg(short, long)]
+        events: bool,
+    },
+    /// Show per-target failure rates and median latency
+    Targets,
+    /// Show average latency and probe loss per week, and how they changed from the week before
+    Trend {
+        /// Number of complete weeks to show
+        #[arg(long, default_value_t = 8)]
+        weeks: u32
+    },
+    /// Score availability, latency, loss and jitter of each day from 0 to 100 and show the scores per month
+    Quality {
+        /// Number of months to show
+        #[arg(long, default_value_t = 12)]
+        months: u32,
+        /// TOML file whose [quality] table sets the weights and thresholds of the score
+        #[arg(long, value_name = "FILE")]
+        config: Option<PathBuf>,
+        /// Measure stored days again, e.g. after probes were imported
+        #[arg(long)]
+        recompute: bool,
+    },
+    /// Show the periods in which part of the probes were lost without the connection going down
+    Degraded {
+        #[command(subcommand)]
+        command: DegradedCommands
+    },
+    /// Show min/avg/p95/p99/max latency of successful probes per target over a time window
+    Latency {
+        /// Start of the window (e.g. 2024-06-01, yesterday, "2 weeks ago"); defaults to 24 hours before its end
+        #[arg(long, value_parser = dates::parse_time)]
+        since: Option<DateTime<Local>>,
+        /// End of the window; defaults to now
+        #[arg(long, value_parser = dates::parse_time)]
+        until: Option<DateTime<Local>>,
+    },
+    /// Show the downtime of the last weeks as bars, with the worst day of each
+    Bars {
+        /// Number of weeks to show, including the current one
+        #[arg(long, default_value_t = 12, value_parser = clap::value_parser!(u32).range(1..))]
+        weeks: u32
+    },
+    /// Show when the watcher was monitoring, on which machine, with which version and probe configuration
+    Sessions {
+        /// Number of days to look back
+        #[arg(long, default_value_t = 30)]
+        days: u32
+    },
+    /// Show route changes recorded by `watch --trace-routes`, with the latency before and after them
+    Routes {
+        /// Number of days to show
+        #[arg(long, default_value_t = 30)]
+        days: u32
+    },
+    /// Compare the latency and failure rate of DNS resolvers, including the system's own
+    Dnsbench {
+        /// Resolver to benchmark as NAME=IP, IP or IP:PORT (repeatable)
+        /// [default: the system resolvers, the router, Cloudflare, Google and Quad9]
+        #[arg(long = "resolver", value_name = "RESOLVER")]
+        resolvers: Vec<dnsbench::Resolver>,
+        /// How long to sample, e.g. 30s or 5m
+        #[arg(long, value_parser = filter::parse_duration, default_value = "30s")]
+        duration: Duration,
+        /// Seconds between queries to the same resolver
+        #[arg(long, default_value_t = 1)]
+        interval: u64,
+        /// Show the results of the last benchmarks instead of running one
+        #[arg(long, value_name = "RUNS", conflicts_with_all = ["resolvers", "duration", "interval"])]
+        history: Option<u32>,
+    },
+    /// Show whether a watcher is running and its internal health metrics
+    Status,
+    /// Show who changed the recorded history, e.g. tagged, acknowledged or reprocessed outages
+    Audit {
+        /// Number of entries to show
+        #[arg(long, default_value_t = 50)]
+        limit: u32,
+        /// Only show changes by this user or API token
+        #[arg(long)]
+        actor: Option<String>
+    },
+    /// Timestamp daily outage digests to prove records weren't changed later
+    Evidence {
+        #[command(subcommand)]
+        command: EvidenceCommands
+    },
+    /// Database maintenance
+    Db {
+        #[command(subcommand)]
+        command: DbCommands
     },
     /// Calculate cost impact of internet outages
     Cost {
@@ -356,90 +5029,1068 @@ enum Commands {
         currency: String,
 
         /// Monthly rate for cost analysis
-        rate: f64
+        rate: f64,
+        /// Also project downtime and cost for this many upcoming months
+        #[arg(long, value_name = "MONTHS")]
+        forecast: Option<u32>,
+        /// Print tables, or JSON or CSV for scripts
+        #[arg(long, value_enum, default_value_t = output::Format::Table)]
+        format: output::Format,
+        #[command(flatten)]
+        filter: OutageFilter
+    },
+    /// Update webgone to the latest GitHub release
+    SelfUpdate {
+        /// Only check whether a newer release is available
+        #[arg(long)]
+        check: bool
+    },
+    /// Run the watcher against a local fake target, take the target down and back up, and check the recorded outages
+    Selftest {
+        /// Keep the databases and logs of the test runs, even if every scenario passed
+        #[arg(long)]
+        keep: bool
+    }
+}
+
+impl Commands {
+    /// How the command uses the database. Besides `init`, only the commands
+    /// that record outages may create it.
+    fn access(&self) -> Access {
+        match self {
+            Commands::Watch(_) | Commands::Init | Commands::Collect { .. } => Access::Create,
+            Commands::Tag { .. }
+            | Commands::Reprocess { .. }
+            | Commands::Ticket { .. }
+            | Commands::Ack { .. }
+            | Commands::Impact { command: ImpactCommands::Add { .. } | ImpactCommands::Remove { .. } }
+            | Commands::Epoch { .. }
+            | Commands::Plan { command: PlanCommands::Add { .. } | PlanCommands::Remove { .. } }
+            | Commands::Baseline { command: BaselineCommands::Import { .. } | BaselineCommands::Remove { .. } }
+            | Commands::Location { command: LocationCommands::Add { .. } | LocationCommands::Remove { .. } }
+            | Commands::Token { command: TokenCommands::Create { .. } | TokenCommands::Revoke { .. } }
+            | Commands::Evidence { command: EvidenceCommands::Stamp { .. } }
+            | Commands::Dnsbench { history: None, .. }
+            | Commands::Db { command: DbCommands::Compact { .. } | DbCommands::Rotate { .. } | DbCommands::Repair { .. } | DbCommands::Scrub { .. } | DbCommands::Sign | DbCommands::Diff { .. } }
+            // Stores the scores of the days that are over
+            | Commands::Quality { .. }
+            | Commands::Pause
+            | Commands::Resume
+            // Pauses the watcher from its menu
+            | Commands::Tray { .. }
+            // Record when API tokens were last used
+            | Commands::Serve { .. }
+            // Record how far data was forwarded
+            | Commands::Agent { .. } => Access::Write,
+            Commands::Stats { .. }
+            | Commands::Recent { .. }
+            | Commands::Search { .. }
+            | Commands::Tags
+            | Commands::Epochs { .. }
+            | Commands::Plan { command: PlanCommands::List { .. } }
+            | Commands::Baseline { command: BaselineCommands::List }
+            | Commands::Location { command: LocationCommands::Current { .. } }
+            | Commands::Locations
+            | Commands::Tickets { .. }
+            | Commands::Impact { command: ImpactCommands::List { .. } }
+            | Commands::Sites { .. }
+            | Commands::Compare { .. }
+            | Commands::Metrics { .. }
+            | Commands::Targets
+            | Commands::Routes { .. }
+            | Commands::Trend { .. }
+            | Commands::Latency { .. }
+            | Commands::Degraded { .. }
+            | Commands::ServeMetrics { .. }
+            | Commands::Web { .. }
+            | Commands::Bars { .. }
+            | Commands::Sessions { .. }
+            | Commands::Dnsbench { history: Some(_), .. }
+            | Commands::Status
+            | Commands::Audit { .. }
+            | Commands::Token { command: TokenCommands::List }
+            | Commands::Evidence { command: EvidenceCommands::Verify { .. } | EvidenceCommands::Export { .. } }
+            | Commands::Db { command: DbCommands::Verify { .. } | DbCommands::Check }
+            | Commands::Report { .. }
+            | Commands::Export { .. }
+            | Commands::Replica { .. }
+            | Commands::Cost { .. }
+            | Commands::SelfUpdate { .. }
+            | Commands::Selftest { .. } => Access::Read,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum DegradedCommands {
+    /// List degraded periods, newest first
+    List {
+        /// Number of days to look back
+        #[arg(long, default_value_t = 30)]
+        days: u32
+    },
+    /// Show how often and how long the connection was degraded, and how many probes it lost
+    Stats {
+        /// Number of days to look back
+        #[arg(long, default_value_t = 30)]
+        days: u32
+    },
+}
+
+#[derive(Subcommand)]
+enum TokenCommands {
+    /// Create a token; it is only shown once
+    Create {
+        /// Read tokens can query the API, write tokens can also report outages
+        #[arg(long, value_enum, default_value = "read")]
+        scope: tokens::Scope,
+        /// What the token is for, e.g. the dashboard or agent using it
+        #[arg(long)]
+        name: Option<String>
+    },
+    /// List tokens
+    List,
+    /// Revoke a token so it can't be used anymore
+    Revoke {
+        id: i64
+    }
+}
+
+#[derive(Subcommand)]
+enum LocationCommands {
+    /// Add a network by its SSID, gateway MAC address or both (see `location current`)
+    Add {
+        /// Name of the location, also used as the tag of outages recorded there
+        name: String,
+        #[arg(long)]
+        ssid: Option<String>,
+        /// MAC address of the default gateway, e.g. a4:2b:b0:01:02:03
+        #[arg(long)]
+        gateway_mac: Option<String>,
+        /// Pause monitoring on this network, or keep monitoring and tag outages with its name
+        #[arg(long, value_enum, default_value = "tag")]
+        action: locations::Action
+    },
+    /// Remove a location
+    Remove {
+        name: String
+    },
+    /// Show the SSID and gateway MAC address of the current network, and which location it is
+    Current {
+        /// Wireless interface to read the SSID from (defaults to the first one found)
+        #[arg(long)]
+        wifi_interface: Option<String>
+    }
+}
+
+#[derive(Subcommand)]
+enum TicketCommands {
+    /// Record a new support ticket
+    Open {
+        /// Ticket number or reference given by the ISP
+        reference: String,
+        /// Free-form note, e.g. what the ISP said
+        #[arg(short, long)]
+        note: Option<String>
+    },
+    /// Mark a ticket as resolved
+    Close {
+        reference: String
+    },
+    /// Link outages to a ticket (see `recent` for outage ids)
+    Link {
+        reference: String,
+        #[arg(required = true)]
+        outage_ids: Vec<i64>
+    }
+}
+
+#[derive(Subcommand)]
+enum ImpactCommands {
+    /// Attach an impact entry to an outage (see `recent` for outage ids)
+    Add {
+        outage: i64,
+        /// What the outage got in the way of, e.g. "missed standup"
+        description: String,
+        /// What it cost, in the currency of `cost`
+        #[arg(long)]
+        amount: Option<f64>
+    },
+    /// Show impact entries, newest outage first
+    List {
+        /// Only show the entries of this outage
+        #[arg(long)]
+        outage: Option<i64>
+    },
+    /// Remove an impact entry (see `impact list` for ids)
+    Remove {
+        id: i64
+    }
+}
+
+#[derive(Subcommand)]
+enum EpochCommands {
+    /// Start a new epoch on a date (YYYY-MM-DD, or e.g. yesterday); it lasts until the next one starts
+    Add {
+        #[arg(value_parser = dates::parse_date)]
+        date: NaiveDate,
+        /// What changed, e.g. "Switched to FiberCo"
+        label: String
+    },
+    /// Remove an epoch (see `epochs` for ids)
+    Remove {
+        id: i64
+    }
+}
+
+#[derive(Subcommand)]
+enum BaselineCommands {
+    /// Import a CSV file with the columns `metric` and `value`, and optionally `region` and `source`
+    Import {
+        file: PathBuf,
+        /// Name to refer to the baseline by (defaults to the file name); replaces a baseline of that name
+        #[arg(long)]
+        name: Option<String>
+    },
+    /// List the imported baselines
+    List,
+    /// Remove an imported baseline
+    Remove {
+        name: String
+    }
+}
+
+#[derive(Subcommand)]
+enum PlanCommands {
+    /// Plan downtime starting at a local time, e.g. "2024-07-01 02:00" or "next monday 02:00"
+    Add {
+        #[arg(value_parser = dates::parse_time)]
+        start: DateTime<Local>,
+        /// How long it lasts (e.g. 30m, 2h)
+        #[arg(long, value_parser = filter::parse_duration)]
+        duration: Duration,
+        /// What is planned, e.g. "ISP maintenance"
+        #[arg(long)]
+        note: Option<String>
+    },
+    /// List planned downtime that isn't over yet
+    List {
+        /// Also list planned downtime in the past
+        #[arg(long)]
+        all: bool
+    },
+    /// Remove planned downtime (see `plan list` for ids)
+    Remove {
+        id: i64
+    }
+}
+
+#[derive(Subcommand)]
+enum EvidenceCommands {
+    /// Obtain RFC 3161 timestamps for daily outage digests
+    Stamp {
+        /// Day to timestamp (YYYY-MM-DD); defaults to every finished day with outages that has none yet
+        #[arg(long, value_parser = dates::parse_date)]
+        date: Option<NaiveDate>,
+        /// Timestamp authority to use
+        #[arg(long, default_value_t = String::from(evidence::DEFAULT_TSA_URL))]
+        tsa: String
+    },
+    /// Check that timestamped days still match the recorded outages
+    Verify {
+        /// Only verify this day (YYYY-MM-DD)
+        #[arg(long, value_parser = dates::parse_date)]
+        date: Option<NaiveDate>
+    },
+    /// Write a day's timestamp request and response for verification with OpenSSL
+    Export {
+        /// Day to export (YYYY-MM-DD)
+        #[arg(value_parser = dates::parse_date)]
+        date: NaiveDate,
+        /// Directory to write the files to
+        #[arg(default_value = ".")]
+        dir: PathBuf
+    }
+}
+
+#[derive(Subcommand)]
+enum DbCommands {
+    /// Show outages present in only one of two databases, e.g. a backup and the live one, and how their totals differ
+    Diff {
+        a: PathBuf,
+        b: PathBuf,
+        /// Treat local outages as outages of this site, to compare an agent's database with the server's
+        #[arg(long, value_name = "SITE")]
+        as_site: Option<String>
+    },
+    /// Roll old raw probes up into aggregates and shrink the database file
+    Compact {
+        /// Roll up raw probes older than this many days
+        #[arg(long, default_value_t = 30)]
+        older_than: u32,
+        /// Period length of the aggregates
+        #[arg(long, value_enum, default_value_t = Granularity::Hour)]
+        granularity: Granularity
+    },
+    /// Move raw probes of past years into one database file per year, e.g. outages-2023.db, keeping their aggregates
+    Rotate {
+        /// Period length of the aggregates kept in the main database
+        #[arg(long, value_enum, default_value_t = Granularity::Hour)]
+        granularity: Granularity
+    },
+    /// Report outages with inconsistent times: clock anomalies, ends before starts and unreadable timestamps
+    Check,
+    /// Fix rows written by other tools or older versions
+    Repair {
+        /// Rewrite outage timestamps in other formats (e.g. `2024-05-01 10:00:00`) as RFC 3339, and list the ones that aren't times
+        #[arg(long, required = true)]
+        timestamps: bool,
+        /// Only show what would be changed
+        #[arg(long)]
+        dry_run: bool
+    },
+    /// Remove notes, addresses and other network metadata from old records, keeping times, durations and aggregates
+    Scrub {
+        /// Scrub records older than this many days
+        #[arg(long)]
+        older_than: u32,
+        /// What to remove (repeatable or comma-separated)
+        #[arg(long = "field", value_enum, value_name = "FIELD", value_delimiter = ',', required = true)]
+        fields: Vec<scrub::Field>,
+        /// Only count the records that would be scrubbed
+        #[arg(long)]
+        dry_run: bool
+    },
+    /// Sign every outage with a key stored next to the database, so changes made without it can be detected
+    Sign,
+    /// Report outages that were changed or added without the signing key. Signatures cover every column of
+    /// an outage, including its failed probes, clock anomaly, derived_from and severity, but not its tags,
+    /// tickets, acknowledgements or impacts
+    Verify {
+        /// Signing key to verify with, e.g. a copy kept elsewhere [default: the one next to the database]
+        #[arg(long)]
+        key: Option<PathBuf>
     }
 }
 
-fn main() -> Result<()> {
+fn main() -> ExitCode {
     let args = CliArgs::parse();
+    let db_path = args.db.clone().unwrap_or_else(datadir::default_database);
 
-    let conn = Connection::open("internet_outages.db")
-        .context("Failed to open database")?;
-    
-    init_database(&conn)?;
+    match run(args, &db_path) {
+        Ok(()) => ExitCode::SUCCESS,
+        // `webgone recent | head` stops reading once it has enough
+        Err(err) if output::is_broken_pipe(&err) => ExitCode::SUCCESS,
+        Err(err) => {
+            let error = WebgoneError::from_anyhow(&err, &db_path);
+            eprintln!("Error: {}", error);
+            if let Some(hint) = error.hint() {
+                eprintln!("Hint: {}", hint);
+            }
+            ExitCode::from(error.exit_code())
+        }
+    }
+}
+
+fn self_update(check_only: bool) -> Result<()> {
+    let current = update::current_version();
+    let release = update::latest_release()?;
+
+    if release.version <= current {
+        println!("webgone {} is up to date", current);
+        return Ok(());
+    }
+    if check_only {
+        println!("webgone {} is available (installed: {}), run `webgone self-update` to install it", release.version, current);
+        return Ok(());
+    }
+
+    println!("Updating webgone {} to {}...", current, release.version);
+    let exe = update::install(&release)?;
+    println!("Installed webgone {} to {}", release.version, exe.display());
+    Ok(())
+}
+
+fn run_selftest(keep: bool) -> Result<()> {
+    let dir = selftest::default_dir();
+    println!("Running the watcher against a fake target in {}...", dir.display());
+    let outcomes = selftest::run(&dir);
+    for outcome in &outcomes {
+        match &outcome.result {
+            Ok(summary) => println!("  ok      {} ({})", outcome.name, summary),
+            Err(problem) => println!("  FAILED  {}: {} (see {})", outcome.name, problem, outcome.dir.display()),
+        }
+    }
+
+    let failed = outcomes.iter().filter(|outcome| !outcome.passed()).count();
+    if failed == 0 && !keep {
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+    if failed > 0 {
+        return Err(WebgoneError::Other(format!("{} of {} self-test scenario(s) failed", failed, outcomes.len())).into());
+    }
+    println!("All {} scenarios passed", outcomes.len());
+    Ok(())
+}
+
+fn run(args: CliArgs, db_path: &Path) -> Result<()> {
+    let out = Output { lang: args.lang.unwrap_or_else(Lang::detect), plain: args.plain };
+
+    crash::install(crash::CrashContext {
+        report_dir: db_path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new(".")).to_path_buf(),
+        sentry_dsn: args.sentry_dsn.clone(),
+        schema_version: SCHEMA_VERSION,
+    });
+
+    // Updating doesn't touch the database, so don't create one in the working directory
+    if let Commands::SelfUpdate { check } = args.command {
+        return self_update(check);
+    }
+    // The self-test runs its watchers on databases of their own
+    if let Commands::Selftest { keep } = args.command {
+        return run_selftest(keep);
+    }
+    // Neither does comparing two other databases
+    if let Commands::Db { command: DbCommands::Diff { a, b, as_site } } = &args.command {
+        let diff = dbdiff::diff(&dbdiff::open(a)?, &dbdiff::open(b)?, as_site.as_deref())?;
+        print_database_diff(&diff, a, b, out)?;
+        return Ok(());
+    }
+
+    let existed = db_path.exists();
+    let conn = open_database(db_path, args.command.access())?;
 
     match args.command {
-        Commands::Watch { ip, port, interval } => {
-            let addr = SocketAddr::new(ip, port);
-            let interval = Duration::from_secs(interval);
-            println!("Starting internet connectivity monitoring...");
-            println!("Checking {} every {} seconds", addr, interval.as_secs());
-            println!("Press Ctrl+C to stop monitoring.");
-
-            let mut is_connected = true;
-            let mut outage_start: Option<DateTime<Local>> = None;
-            
-            loop {
-                let current_status = check_internet(addr);
-                
-                match (is_connected, current_status) {
-                    (true, false) => {
-                        outage_start = Some(Local::now());
-                        println!("Internet connection lost at {}", outage_start.unwrap());
-                        is_connected = false;
+        Commands::Init => {
+            if existed {
+                println!("Database {} is up to date (schema version {})", db_path.display(), SCHEMA_VERSION);
+            } else {
+                println!("Created database {} (schema version {})", db_path.display(), SCHEMA_VERSION);
+            }
+        },
+        Commands::Watch(args) => {
+            watch(&conn, db_path, *args, out.lang)?;
+        },
+        Commands::Pause => {
+            pause::set_paused(&conn, true)?;
+            audit::record(&conn, "pause", &[], "")?;
+            println!("Paused monitoring until `webgone resume`");
+        },
+        Commands::Resume => {
+            pause::set_paused(&conn, false)?;
+            audit::record(&conn, "resume", &[], "")?;
+            println!("Resumed monitoring");
+        },
+        #[cfg(feature = "tray")]
+        Commands::Tray { dashboard_url } => tray::run(conn, db_path, dashboard_url)?,
+        #[cfg(not(feature = "tray"))]
+        Commands::Tray { .. } => {
+            return Err(WebgoneError::InvalidInput(
+                "this webgone was built without tray support; rebuild it with `--features tray`".to_string(),
+            )
+            .into());
+        },
+        Commands::Stats { durations, format, filter } => {
+            let snapshot = begin_snapshot(&conn)?;
+            warn_unreadable_start_times(&snapshot)?;
+            let stats = get_stats(&snapshot, &filter, durations)?;
+            print_stats(&stats, format, out)?;
+        },
+        Commands::Recent { limit, events, durations, format, filter } => {
+            let snapshot = begin_snapshot(&conn)?;
+            warn_unreadable_start_times(&snapshot)?;
+            let recent = recent_outages(&snapshot, limit as i64, events, durations, &filter)?;
+            print_recent_outages(&recent, events, format, out)?;
+        },
+        Commands::Search { terms, limit, ids, filter } => {
+            let snapshot = begin_snapshot(&conn)?;
+            let hits = search::search(&snapshot, &terms, &filter, limit)?;
+            if ids {
+                for hit in &hits {
+                    println!("{}", hit.id);
+                }
+            } else {
+                print_search_results(&hits, out)?;
+            }
+        },
+        Commands::Tag { id, tags: labels, remove } => {
+            if remove {
+                tags::remove(&conn, id, &labels)?;
+                audit::record(&conn, "tag --remove", &[id], &labels.join(", "))?;
+                println!("Removed {} from outage {}", labels.join(", "), id);
+            } else {
+                tags::add(&conn, id, &labels)?;
+                audit::record(&conn, "tag", &[id], &labels.join(", "))?;
+                println!("Tagged outage {} with {}", id, labels.join(", "));
+            }
+        },
+        Commands::Tags => {
+            let snapshot = begin_snapshot(&conn)?;
+            print_tag_summary(&snapshot, out)?;
+        },
+        Commands::Ack { outage, by, comment } => {
+            let by = by.unwrap_or_else(acks::default_user);
+            let start = acks::acknowledge(&conn, outage, &by, comment.as_deref())?;
+            let ids = match outage {
+                acks::AckTarget::Outage(id) => vec![id],
+                acks::AckTarget::Current => Vec::new(),
+            };
+            let details = match &comment {
+                Some(comment) => format!("as {}: {}", by, comment),
+                None => format!("as {}", by),
+            };
+            audit::record(&conn, "ack", &ids, &details)?;
+            println!("Acknowledged the outage that started at {} as {}", start.format("%Y-%m-%d %H:%M:%S"), by);
+        },
+        Commands::Epoch { command } => match command {
+            EpochCommands::Add { date, label } => {
+                let id = epochs::add(&conn, date, &label)?;
+                audit::record(&conn, "epoch add", &[id], &format!("{}: {}", date, label))?;
+                println!("Added epoch {} starting {}: {}", id, date, label);
+            }
+            EpochCommands::Remove { id } => {
+                epochs::remove(&conn, id)?;
+                audit::record(&conn, "epoch remove", &[id], "")?;
+                println!("Removed epoch {}", id);
+            }
+        },
+        Commands::Plan { command } => match command {
+            PlanCommands::Add { start, duration, note } => {
+                let id = plans::add(&conn, start, duration, note.as_deref())?;
+                let end = start + chrono::Duration::from_std(duration)?;
+                audit::record(&conn, "plan add", &[id], &format!("{} to {}", start.to_rfc3339(), end.to_rfc3339()))?;
+                println!(
+                    "Planned downtime {} from {} to {}{}",
+                    id,
+                    start.format("%Y-%m-%d %H:%M"),
+                    end.format("%Y-%m-%d %H:%M"),
+                    note.map(|note| format!(": {}", note)).unwrap_or_default()
+                );
+            }
+            PlanCommands::List { all } => {
+                let snapshot = begin_snapshot(&conn)?;
+                print_planned_downtime(&snapshot, all, out)?;
+            }
+            PlanCommands::Remove { id } => {
+                plans::remove(&conn, id)?;
+                audit::record(&conn, "plan remove", &[id], "")?;
+                println!("Removed planned downtime {}", id);
+            }
+        },
+        Commands::Baseline { command } => match command {
+            BaselineCommands::Import { file, name } => {
+                let name = name.unwrap_or_else(|| {
+                    file.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_else(|| "baseline".to_string())
+                });
+                let imported = baseline::import(&conn, &name, &file)?;
+                audit::record(&conn, "baseline import", &[], &format!("{} from {}", name, file.display()))?;
+                println!("Imported {} metric(s) as baseline {}", imported, name);
+            }
+            BaselineCommands::List => {
+                let snapshot = begin_snapshot(&conn)?;
+                print_baselines(&snapshot, out)?;
+            }
+            BaselineCommands::Remove { name } => {
+                baseline::remove(&conn, &name)?;
+                audit::record(&conn, "baseline remove", &[], &name)?;
+                println!("Removed baseline {}", name);
+            }
+        },
+        Commands::Epochs { gaps } => {
+            let snapshot = begin_snapshot(&conn)?;
+            print_epochs(&snapshot, gaps, out)?;
+        },
+        Commands::Location { command } => match command {
+            LocationCommands::Add { name, ssid, gateway_mac, action } => {
+                locations::add(&conn, &name, ssid.as_deref(), gateway_mac.as_deref(), action)?;
+                audit::record(&conn, "location add", &[], &format!("{} ({})", name, action))?;
+                println!("Added location {} ({})", name, action);
+            }
+            LocationCommands::Remove { name } => {
+                locations::remove(&conn, &name)?;
+                audit::record(&conn, "location remove", &[], &name)?;
+                println!("Removed location {}", name);
+            }
+            LocationCommands::Current { wifi_interface } => {
+                let fingerprint = locations::detect(wifi_interface.as_deref());
+                println!("Current network: {}", fingerprint);
+                match locations::find(&locations::list(&conn)?, &fingerprint) {
+                    Some(location) => println!("Location: {} ({})", location.name, location.action),
+                    None => println!("Location: none of the known locations, monitored as usual"),
+                }
+            }
+        },
+        Commands::Locations => {
+            let snapshot = begin_snapshot(&conn)?;
+            print_locations(&snapshot, out)?;
+        },
+        Commands::Ticket { command } => match command {
+            TicketCommands::Open { reference, note } => {
+                tickets::open(&conn, &reference, note.as_deref())?;
+                audit::record(&conn, "ticket open", &[], &reference)?;
+                println!("Opened ticket {}", reference);
+            }
+            TicketCommands::Close { reference } => {
+                tickets::close(&conn, &reference)?;
+                audit::record(&conn, "ticket close", &[], &reference)?;
+                println!("Closed ticket {}", reference);
+            }
+            TicketCommands::Link { reference, outage_ids } => {
+                tickets::link(&conn, &reference, &outage_ids)?;
+                audit::record(&conn, "ticket link", &outage_ids, &reference)?;
+                println!("Linked {} outage(s) to ticket {}", outage_ids.len(), reference);
+            }
+        },
+        Commands::Impact { command } => match command {
+            ImpactCommands::Add { outage, description, amount } => {
+                let id = impacts::add(&conn, outage, &description, amount)?;
+                let details = match amount {
+                    Some(amount) => format!("{} ({})", description.trim(), amount),
+                    None => description.trim().to_string(),
+                };
+                audit::record(&conn, "impact add", &[outage], &details)?;
+                println!("Recorded impact {} on outage {}", id, outage);
+            }
+            ImpactCommands::List { outage } => {
+                let snapshot = begin_snapshot(&conn)?;
+                print_impacts(&snapshot, outage, out)?;
+            }
+            ImpactCommands::Remove { id } => {
+                let outage = impacts::remove(&conn, id)?;
+                audit::record(&conn, "impact remove", &[outage], &id.to_string())?;
+                println!("Removed impact {}", id);
+            }
+        },
+        Commands::Sites { days, gaps } => {
+            let snapshot = begin_snapshot(&conn)?;
+            print_sites(&snapshot, days, gaps, out)?;
+        },
+        Commands::Compare { months } => {
+            let snapshot = begin_snapshot(&conn)?;
+            print_site_comparison(&snapshot, months, out)?;
+        },
+        Commands::Metrics { days, events } => {
+            let snapshot = begin_snapshot(&conn)?;
+            print_custom_metrics(&snapshot, days, events, out)?;
+        },
+        Commands::Tickets { open } => {
+            let snapshot = begin_snapshot(&conn)?;
+            print_tickets(&snapshot, open, out)?;
+        },
+        Commands::Serve {
+            listen,
+            public,
+            cors_origins,
+            rate_limit,
+            cache_ttl,
+            require_token,
+            tls_cert,
+            tls_key,
+            tls_self_signed,
+            gaps,
+            config,
+        } => {
+            let config = config.as_deref().map(schedule::Config::load).transpose()?.unwrap_or_default();
+            let classifier = config.severity.classifier()?;
+            let tls = match (tls_cert, tls_key) {
+                (Some(cert), Some(key)) => Some(tls::Certificate::load(&cert, &key)?),
+                _ if tls_self_signed => {
+                    let mut hosts = vec!["localhost".to_string(), "127.0.0.1".to_string(), "::1".to_string()];
+                    if !listen.ip().is_unspecified() && !listen.ip().is_loopback() {
+                        hosts.push(listen.ip().to_string());
                     }
-                    (false, true) => {
-                        if let Some(start_time) = outage_start {
-                            let end_time = Local::now();
-                            let duration = end_time.signed_duration_since(start_time);
-                            
-                            let outage = InternetOutage {
-                                start_time,
-                                end_time,
-                                duration_seconds: duration.num_seconds(),
-                            };
-                            
-                            log_outage(&conn, &outage)?;
-                            println!(
-                                "Internet connection restored at {}. Outage duration: {} seconds",
-                                end_time,
-                                duration.num_seconds()
-                            );
-                            
-                            is_connected = true;
-                            outage_start = None;
-                        }
+                    let dir = db_path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+                    let (certificate, generated) = tls::Certificate::self_signed(dir, hosts)?;
+                    if generated {
+                        println!("Generated a self-signed certificate in {}", dir.display());
                     }
-                    _ => {}
+                    println!("Certificate fingerprint (SHA-256): {}", certificate.fingerprint()?);
+                    Some(certificate)
+                }
+                _ => None,
+            };
+            let scheme = if tls.is_some() { "https" } else { "http" };
+
+            let options = server::ServeOptions {
+                listen,
+                cors_origins,
+                rate_limit: rate_limit.or(public.then_some(60)),
+                cache_ttl: Duration::from_secs(cache_ttl.unwrap_or(if public { 10 } else { 0 })),
+                require_token,
+                tls,
+            };
+            if !listen.ip().is_loopback() {
+                if !public {
+                    println!("Listening on a non-loopback address; consider --public to rate limit and cache requests");
+                }
+                if options.tls.is_none() {
+                    println!("Serving plain HTTP beyond localhost; consider --tls-cert/--tls-key or --tls-self-signed");
+                }
+            }
+
+            let running = Arc::new(AtomicBool::new(true));
+            {
+                let running = running.clone();
+                ctrlc::set_handler(move || running.store(false, Ordering::SeqCst))
+                    .context("Failed to install Ctrl+C handler")?;
+            }
+
+            println!("Serving the JSON API on {}://{}/api/ (stats, outages, status)", scheme, listen);
+            server::serve(&options, &running, &mut WebApi { conn: &conn, db_path, gaps, classifier })?;
+        },
+        Commands::Web { listen, gaps } => {
+            let running = Arc::new(AtomicBool::new(true));
+            {
+                let running = running.clone();
+                ctrlc::set_handler(move || running.store(false, Ordering::SeqCst))
+                    .context("Failed to install Ctrl+C handler")?;
+            }
+
+            println!("Serving the dashboard on http://{}/", listen);
+            dashboard::serve(listen, &running, &mut || {
+                let snapshot = begin_snapshot(&conn)?;
+                dashboard_data(&snapshot, db_path, gaps)
+            })?;
+        },
+        Commands::ServeMetrics { listen } => {
+            let running = Arc::new(AtomicBool::new(true));
+            {
+                let running = running.clone();
+                ctrlc::set_handler(move || running.store(false, Ordering::SeqCst))
+                    .context("Failed to install Ctrl+C handler")?;
+            }
+
+            println!("Serving Prometheus metrics on http://{}/metrics", listen);
+            prometheus::serve(listen, &running, &mut || {
+                let snapshot = begin_snapshot(&conn)?;
+                let metrics = prometheus::load(&snapshot, lock::watcher_pid(db_path).is_some())?;
+                Ok(prometheus::render(&metrics))
+            })?;
+        },
+        Commands::Agent { server, site, server_ca, tls_cert, tls_key, poll_interval } => {
+            let options = agent::AgentOptions {
+                server,
+                site,
+                server_ca: server_ca
+                    .as_deref().map(tls::read_pem).transpose()?,
+                identity: match (tls_cert, tls_key) {
+                    (Some(cert), Some(key)) => Some(tls::Certificate::load(&cert, &key)?),
+                    _ => None,
+                },
+                poll_interval: Duration::from_secs(poll_interval.max(1)),
+            };
+            if !options.server.starts_with("https://") {
+                println!("Sending data unencrypted; use an https:// server URL outside trusted networks");
+            }
+            println!("Forwarding data of site {} to {}", options.site, options.server);
+            agent::run(&conn, &options)?;
+        },
+        Commands::Collect { listen, tls_cert, tls_key, client_ca } => {
+            let options = collector::CollectOptions {
+                listen,
+                tls: match (tls_cert, tls_key) {
+                    (Some(cert), Some(key)) => Some(tls::Certificate::load(&cert, &key)?),
+                    _ => None,
+                },
+                client_ca: client_ca
+                    .as_deref().map(tls::read_pem).transpose()?,
+            };
+            if options.tls.is_none() && !listen.ip().is_loopback() {
+                println!("Accepting agents without TLS; consider --tls-cert/--tls-key and --client-ca");
+            }
+            println!(
+                "Collecting agent data on {}{}",
+                listen,
+                if options.client_ca.is_some() { " (mutual TLS)" } else { "" }
+            );
+            collector::collect(conn, options)?;
+        },
+        Commands::Token { command } => match command {
+            TokenCommands::Create { scope, name } => {
+                let (id, token) = tokens::create(&conn, name.as_deref(), scope)?;
+                audit::record(&conn, "token create", &[id], &scope.to_string())?;
+                println!("Created {} token {}:\n\n    {}\n", scope, id, token);
+                println!("Store it now, it can't be shown again.");
+            }
+            TokenCommands::List => {
+                let snapshot = begin_snapshot(&conn)?;
+                print_tokens(&snapshot, out)?;
+            }
+            TokenCommands::Revoke { id } => {
+                tokens::revoke(&conn, id)?;
+                audit::record(&conn, "token revoke", &[id], "")?;
+                println!("Revoked token {}", id);
+            }
+        },
+        Commands::Report { format, template, output, baseline, filter } => {
+            let snapshot = begin_snapshot(&conn)?;
+            let context = report_context(&snapshot, &filter, baseline.as_deref(), out.lang)?;
+            let rendered = report::render(format, template.as_deref(), &context)?;
+            match output {
+                Some(filename) => {
+                    std::fs::write(&filename, rendered)?;
+                    println!("Report written to {}", filename.display());
                 }
-                
-                thread::sleep(interval);
+                None => std::io::stdout().write_all(&rendered)?,
             }
         },
-        Commands::Stats => {
-            let stats = get_stats(&conn)?;
-            println!("\nInternet Outage Statistics:");
-            println!("{:-<50}", "");
-            println!("Total number of outages: {}", stats.total_outages);
-            println!("Total outage duration: {} seconds", stats.total_duration);
-            println!("Average outage duration: {:.2} seconds", stats.average_duration);
-            println!("Longest outage: {} seconds", stats.longest_outage);
-            println!("Shortest outage: {} seconds", stats.shortest_outage);
-            println!("{:-<50}\n", "");
+        Commands::Export { output, format, schema, split, out_dir, filter } => {
+            let snapshot = begin_snapshot(&conn)?;
+            warn_unreadable_start_times(&snapshot)?;
+            let outages = export_outages(&snapshot, &filter)?;
+            if let (Some(split), Some(dir)) = (split, out_dir) {
+                let files = export::write_split(&dir, outages, split, format, &schema)?;
+                let written = files.iter().filter(|file| file.written).count();
+                println!(
+                    "Exported {} file(s) to {} ({} new or changed) with a manifest.json",
+                    files.len(),
+                    dir.display(),
+                    written
+                );
+            } else {
+                let now = Local::now();
+                let planned = plans::for_export(&snapshot, now)?;
+                let monitored = coverage::for_export(&snapshot, &outages)?;
+                let data = format.render(outages, planned, monitored, &schema, now)?;
+                if let Some(ref filename) = output {
+                    export_to_file(filename, &data)?;
+                } else {
+                    println!("{}", data);
+                }
+            }
         },
-        Commands::Recent { limit } => {
-            print_recent_outages(&conn, limit as i64)?;
+        Commands::Replica { path } => {
+            let summary = replica::build(db_path, &path, Local::now())?;
+            println!(
+                "Wrote {} outage(s), {} day(s) of downtime and {} hour(s) of latency to {}",
+                summary.outages,
+                summary.days,
+                summary.latency_hours,
+                path.display()
+            );
         },
-        Commands::Export { output } => {
-            if let Some(ref filename) = output {
-                export_to_csv(&conn, filename)?;
+        Commands::Reprocess { fail_threshold, recover_threshold, min_outage, merge_gap, dry_run } => {
+            let (removed, created) = reprocess_outages(
+                &conn,
+                fail_threshold,
+                recover_threshold,
+                min_outage,
+                Duration::from_secs(merge_gap),
+                dry_run,
+            )?;
+            if dry_run {
+                println!("Would replace {} outage(s) with {} derived from the recorded checks", removed, created);
             } else {
-                println!("{}", generate_csv(&conn)?);
+                audit::record(&conn, "reprocess", &[], &format!("replaced {} outage(s) with {}", removed, created))?;
+                println!("Replaced {} outage(s) with {} derived from the recorded checks", removed, created);
+            }
+        },
+        Commands::Targets => {
+            archive::attach(&conn, db_path, Some(Local::now().date_naive() - chrono::Days::new(7)))?;
+            let snapshot = begin_snapshot(&conn)?;
+            print_target_health(&snapshot, out)?;
+        },
+        Commands::Trend { weeks } => {
+            let snapshot = begin_snapshot(&conn)?;
+            print_weekly_trend(&snapshot, weeks, out)?;
+        },
+        Commands::Quality { months, config, recompute } => {
+            let settings = match config {
+                Some(path) => schedule::Config::load(&path)?.quality,
+                None => quality::Settings::default(),
+            };
+            let today = Local::now().date_naive();
+            let since = today
+                .with_day(1)
+                .and_then(|first| first.checked_sub_months(chrono::Months::new(months.saturating_sub(1))))
+                .unwrap_or(today);
+            let tx = conn.unchecked_transaction()?;
+            quality::store_days(&tx, &settings, (today - since).num_days() as u32, recompute, Local::now())?;
+            quality::rescore(&tx, &settings)?;
+            tx.commit()?;
+            print_quality(&conn, &settings, since, out)?;
+        },
+        Commands::Degraded { command } => {
+            let snapshot = begin_snapshot(&conn)?;
+            match command {
+                DegradedCommands::List { days } => print_degraded_periods(&snapshot, days, out)?,
+                DegradedCommands::Stats { days } => print_degraded_stats(&snapshot, days, out)?,
             }
         },
-        Commands::Cost { currency, rate } => {
-            print_cost_report(&conn, rate, &currency)?;
+        Commands::Latency { since, until } => {
+            let until = until.unwrap_or_else(Local::now);
+            let since = since.unwrap_or(until - chrono::Duration::hours(24));
+            // Raw probes of the window may be archived
+            archive::attach(&conn, db_path, Some(since.date_naive()))?;
+            let snapshot = begin_snapshot(&conn)?;
+            print_latency(&snapshot, since, until, out)?;
+        },
+        Commands::Bars { weeks } => {
+            let snapshot = begin_snapshot(&conn)?;
+            print_downtime_bars(&snapshot, weeks, out)?;
+        },
+        Commands::Sessions { days } => {
+            let snapshot = begin_snapshot(&conn)?;
+            print_monitored_periods(&snapshot, days, out)?;
+        },
+        Commands::Routes { days } => {
+            // Latencies around a change come from the raw probes, which may be archived
+            archive::attach(&conn, db_path, Some(Local::now().date_naive() - chrono::Days::new(days as u64 + 1)))?;
+            let snapshot = begin_snapshot(&conn)?;
+            print_route_changes(&snapshot, days, out)?;
+        },
+        Commands::Dnsbench { history: Some(runs), .. } => {
+            let snapshot = begin_snapshot(&conn)?;
+            print_dns_benchmark_history(&snapshot, runs, out)?;
+        },
+        Commands::Dnsbench { resolvers, duration, interval, history: None } => {
+            let resolvers = if resolvers.is_empty() { dnsbench::default_resolvers() } else { resolvers };
+            println!(
+                "Benchmarking {} resolver(s) for {} seconds...",
+                resolvers.len(),
+                duration.as_secs()
+            );
+            let run_at = Local::now();
+            let summaries = dnsbench::run(&resolvers, duration, Duration::from_secs(interval.max(1)));
+            dnsbench::store(&conn, run_at, &summaries)?;
+            print_dns_benchmark(&summaries, out)?;
+            if let Some(verdict) = dnsbench::verdict(&summaries) {
+                println!("{}\n", verdict);
+            }
+        },
+        Commands::Status => {
+            let snapshot = begin_snapshot(&conn)?;
+            print_watcher_status(&snapshot, db_path, out)?;
+            print_glance(&snapshot, out)?;
+        },
+        Commands::Audit { limit, actor } => {
+            let snapshot = begin_snapshot(&conn)?;
+            print_audit_log(&snapshot, limit, actor.as_deref(), out)?;
+        },
+        Commands::Evidence { command } => match command {
+            EvidenceCommands::Stamp { date: Some(day), tsa } => {
+                if evidence::stamp_day(&conn, day, &tsa)? {
+                    println!("Timestamped outage evidence for {} with {}", day, tsa);
+                } else {
+                    println!("Outage evidence for {} is already timestamped", day);
+                }
+            }
+            EvidenceCommands::Stamp { date: None, tsa } => stamp_evidence(&conn, &mut Console::new(false, None)?, &tsa)?,
+            EvidenceCommands::Verify { date } => {
+                let snapshot = begin_snapshot(&conn)?;
+                verify_evidence(&snapshot, date)?;
+            }
+            EvidenceCommands::Export { date, dir } => export_evidence(&conn, date, &dir)?,
+        },
+        Commands::Db { command } => match command {
+            DbCommands::Diff { .. } => unreachable!("handled before opening the database"),
+            DbCommands::Sign => {
+                let (key, generated) = signing::load_or_create(db_path)?;
+                if generated {
+                    println!(
+                        "Generated signing key {}; keep a copy elsewhere to verify with `webgone db verify --key`",
+                        signing::key_path(db_path).display()
+                    );
+                }
+                signing::enable(&conn, key)?;
+                let signed = signing::sign_unsigned(&conn)?;
+                audit::record(&conn, "db sign", &[], &format!("signed {} outage(s)", signed))?;
+                println!("Signed {} outage(s); outages recorded from now on are signed as they are written", signed);
+            }
+            DbCommands::Check => {
+                let snapshot = begin_snapshot(&conn)?;
+                check_database(&snapshot)?;
+            }
+            DbCommands::Verify { key } => {
+                let snapshot = begin_snapshot(&conn)?;
+                verify_signatures(&snapshot, &key.unwrap_or_else(|| signing::key_path(db_path)))?;
+            }
+            DbCommands::Compact { older_than, granularity } => {
+                let removed = storage::roll_up_probes(&conn, older_than, granularity)?;
+                conn.execute_batch("VACUUM")?;
+                audit::record(
+                    &conn,
+                    "db compact",
+                    &[],
+                    &format!("rolled up {} raw probe(s) older than {} days", removed, older_than),
+                )?;
+                println!(
+                    "Rolled up {} raw probe(s) older than {} days into {} aggregates",
+                    removed,
+                    older_than,
+                    granularity.name()
+                );
+            }
+            DbCommands::Scrub { older_than, fields, dry_run } => {
+                let scrubbed = scrub::scrub(&conn, &fields, older_than, dry_run)?;
+                let total: usize = scrubbed.iter().map(|table| table.rows).sum();
+                if dry_run {
+                    println!("Would scrub {} row(s) older than {} days", total, older_than);
+                } else {
+                    scrub::purge_free_pages(&conn)?;
+                    let names: Vec<&str> = fields.iter().map(|field| field.name()).collect();
+                    audit::record(
+                        &conn,
+                        "db scrub",
+                        &[],
+                        &format!("scrubbed {} from {} row(s) older than {} days", names.join(", "), total, older_than),
+                    )?;
+                    println!("Scrubbed {} row(s) older than {} days", total, older_than);
+                }
+                for table in scrubbed.iter().filter(|table| table.rows > 0) {
+                    println!("  {}: {} row(s) of {}", table.field.name(), table.rows, table.table);
+                }
+            }
+            DbCommands::Repair { timestamps: _, dry_run } => {
+                let repair = repair::timestamps(&conn, dry_run)?;
+                if dry_run {
+                    println!("Would rewrite {} timestamp(s) as RFC 3339", repair.rewritten);
+                } else {
+                    audit::record(&conn, "db repair", &[], &format!("rewrote {} timestamp(s)", repair.rewritten))?;
+                    println!("Rewrote {} timestamp(s) as RFC 3339", repair.rewritten);
+                }
+                if !repair.unreadable.is_empty() {
+                    println!("{} timestamp(s) aren't times and have to be fixed by hand; reports leave these outages out:", repair.unreadable.len());
+                    for row in &repair.unreadable {
+                        println!("  {}", row);
+                    }
+                }
+            }
+            DbCommands::Rotate { granularity } => {
+                let moved = archive::rotate(&conn, db_path, granularity)?;
+                if moved.is_empty() {
+                    println!("No raw probes from past years to rotate");
+                } else {
+                    conn.execute_batch("VACUUM")?;
+                    for (year, count) in &moved {
+                        audit::record(&conn, "db rotate", &[], &format!("moved {} raw probe(s) of {}", count, year))?;
+                        println!(
+                            "Moved {} raw probe(s) of {} to {}",
+                            count,
+                            year,
+                            archive::archive_path(db_path, *year).display()
+                        );
+                    }
+                }
+            }
+        },
+        Commands::Cost { currency, rate, forecast, format, filter } => {
+            // The cache is only a shortcut, so the report still works if the
+            // database can't be written right now, and doesn't wait for a
+            // watcher or another writer holding the lock either
+            if monthly_aggregates_stale(&conn)? {
+                if let Ok(writer) = open_database(db_path, Access::Write) {
+                    if writer.busy_timeout(Duration::ZERO).is_ok() {
+                        ensure_monthly_aggregates(&writer).ok();
+                    }
+                }
+            }
+            let snapshot = begin_snapshot(&conn)?;
+            warn_unreadable_start_times(&snapshot)?;
+            let report = cost_report(&snapshot, db_path, rate, &currency, forecast, &filter)?;
+            print_cost_report(&report, format, out)?;
         }
+        Commands::SelfUpdate { .. } | Commands::Selftest { .. } => unreachable!("handled before opening the database"),
     }
 
     Ok(())
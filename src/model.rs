@@ -0,0 +1,463 @@
+//! The data webgone records and reports. These types are what the JSON
+//! outputs and the web API serialize, so their field names are part of
+//! webgone's stable interface: fields may be added, but not renamed or
+//! removed without a major version bump.
+
+use chrono::{DateTime, Local, NaiveDateTime};
+#[cfg(not(target_arch = "wasm32"))]
+use rusqlite::Row;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+
+/// Parses a timestamp as webgone writes it (RFC 3339), or as other tools and
+/// older versions did: with a space instead of the `T`, with an offset like
+/// `+0200`, or without an offset, which is taken as UTC like SQLite's date
+/// functions do.
+pub fn parse_timestamp(value: &str) -> Option<DateTime<Local>> {
+    let value = value.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.with_timezone(&Local));
+    }
+    let value = value.replacen(' ', "T", 1);
+    if let Ok(time) = DateTime::parse_from_rfc3339(&value) {
+        return Some(time.with_timezone(&Local));
+    }
+    if let Ok(time) = DateTime::parse_from_str(&value, "%Y-%m-%dT%H:%M:%S%.f%z") {
+        return Some(time.with_timezone(&Local));
+    }
+    ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(&value, format).ok())
+        .map(|time| time.and_utc().with_timezone(&Local))
+}
+
+/// A timestamp in a row that isn't a time at all, even read tolerantly.
+#[derive(Clone, Debug)]
+pub struct UnreadableTimestamp {
+    pub id: i64,
+    pub column: String,
+    pub value: String,
+}
+
+impl fmt::Display for UnreadableTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} of outage {} is not a time: {:?}", self.column, self.id, self.value)
+    }
+}
+
+impl Error for UnreadableTimestamp {}
+
+/// How bad an outage was, from its duration and whether it hit business
+/// hours. Ordered from least to most severe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Minor,
+    Major,
+    Critical,
+}
+
+impl Severity {
+    pub const ALL: [Severity; 4] = [Severity::Info, Severity::Minor, Severity::Major, Severity::Critical];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Minor => "minor",
+            Severity::Major => "major",
+            Severity::Critical => "critical",
+        }
+    }
+
+    /// The next level up, or this one if it's the highest.
+    pub fn raised(self) -> Severity {
+        match self {
+            Severity::Info => Severity::Minor,
+            Severity::Minor => Severity::Major,
+            Severity::Major | Severity::Critical => Severity::Critical,
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl std::str::FromStr for Severity {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Severity::ALL
+            .into_iter()
+            .find(|severity| severity.name() == value)
+            .ok_or_else(|| format!("unknown severity: {} (use info, minor, major or critical)", value))
+    }
+}
+
+/// Wireless link information captured alongside an outage, used to tell a
+/// weak Wi-Fi signal apart from a genuine upstream problem.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WifiInfo {
+    pub ssid: Option<String>,
+    pub signal_dbm: Option<i32>,
+}
+
+impl fmt::Display for WifiInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.ssid, self.signal_dbm) {
+            (Some(ssid), Some(signal)) => write!(f, "{} ({} dBm)", ssid, signal),
+            (Some(ssid), None) => write!(f, "{}", ssid),
+            (None, Some(signal)) => write!(f, "{} dBm", signal),
+            (None, None) => write!(f, "unknown"),
+        }
+    }
+}
+
+/// Local weather conditions as reported by the weather API.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WeatherReport {
+    pub observed_at: DateTime<Local>,
+    /// WMO weather interpretation code
+    pub weather_code: Option<i64>,
+    pub temperature_c: Option<f64>,
+    pub precipitation_mm: Option<f64>,
+    pub wind_speed_kmh: Option<f64>,
+}
+
+impl WeatherReport {
+    /// Human readable description of the WMO weather code.
+    pub fn description(&self) -> &'static str {
+        match self.weather_code {
+            Some(0) => "clear sky",
+            Some(1..=3) => "partly cloudy",
+            Some(45 | 48) => "fog",
+            Some(51..=57) => "drizzle",
+            Some(61..=67) => "rain",
+            Some(71..=77) => "snow",
+            Some(80..=82) => "rain showers",
+            Some(85 | 86) => "snow showers",
+            Some(95) => "thunderstorm",
+            Some(96 | 99) => "thunderstorm with hail",
+            _ => "unknown",
+        }
+    }
+}
+
+impl fmt::Display for WeatherReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())?;
+        if let Some(temperature) = self.temperature_c {
+            write!(f, ", {:.1}°C", temperature)?;
+        }
+        if let Some(precipitation) = self.precipitation_mm {
+            write!(f, ", {:.1} mm", precipitation)?;
+        }
+        if let Some(wind) = self.wind_speed_kmh {
+            write!(f, ", wind {:.0} km/h", wind)?;
+        }
+        Ok(())
+    }
+}
+
+/// A recorded outage.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InternetOutage {
+    pub id: i64,
+    /// Site of the agent that reported the outage, `None` if it was recorded here
+    pub site: Option<String>,
+    /// Time of the first failed check
+    pub start_time: DateTime<Local>,
+    /// When the watcher saw the first failed check
+    pub detected_at: Option<DateTime<Local>>,
+    /// When the watcher declared the outage, after enough consecutive failed checks
+    pub confirmed_at: Option<DateTime<Local>>,
+    /// Time of the first successful check
+    pub end_time: DateTime<Local>,
+    /// When the watcher declared the outage over, after enough consecutive successful checks
+    pub recovered_at: Option<DateTime<Local>>,
+    pub duration_seconds: i64,
+    pub wifi: Option<WifiInfo>,
+    pub weather: Option<WeatherReport>,
+    /// Labels added with `webgone tag` or by network locations
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Packet capture taken during the outage
+    pub capture_path: Option<String>,
+    /// Components of the `--up-when` verdict that failed when the outage started, e.g. `dns, http`,
+    /// or the targets that failed when watching several
+    pub failed_components: Option<String>,
+    /// API token or agent that reported the outage; `None` for the local watcher
+    pub actor: Option<String>,
+    /// The clock was stepped backwards while the outage lasted, so its times
+    /// are unreliable and its end was clamped to its start if needed
+    #[serde(default)]
+    pub clock_anomaly: bool,
+    /// Probe types of the targets that failed when the outage started, e.g. `dns`, or `dns, tcp` when
+    /// watching several
+    #[serde(default)]
+    pub failed_probes: Option<String>,
+    /// Severity the outage was classified as when it was recorded
+    #[serde(default)]
+    pub severity: Option<Severity>,
+    /// Parent service, or `local` for the internet connection, that was down
+    /// when this service outage began; `None` for outages of their own
+    #[serde(default)]
+    pub derived_from: Option<String>,
+}
+
+impl InternetOutage {
+    /// Seconds from when the outage was confirmed until it ended, leaving out
+    /// the time it took to confirm it.
+    pub fn confirmed_duration_seconds(&self) -> i64 {
+        (self.end_time - self.confirmed_at.unwrap_or(self.start_time)).num_seconds().max(0)
+    }
+
+    /// Reads a row of `SELECT * FROM outages`. Tags are stored in a table of
+    /// their own and left empty. Timestamps are read with [`parse_timestamp`];
+    /// a row with one that can't be read at all fails with an
+    /// [`UnreadableTimestamp`], which [`InternetOutage::readable`] sets aside.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let id: i64 = row.get("id")?;
+        let time = |column: &str| -> rusqlite::Result<Option<DateTime<Local>>> {
+            let value: Option<String> = row.get(column)?;
+            value
+                .map(|value| {
+                    parse_timestamp(&value).ok_or_else(|| {
+                        rusqlite::Error::FromSqlConversionFailure(
+                            0,
+                            rusqlite::types::Type::Text,
+                            Box::new(UnreadableTimestamp { id, column: column.to_string(), value }),
+                        )
+                    })
+                })
+                .transpose()
+        };
+        let required = |column: &str| -> rusqlite::Result<DateTime<Local>> {
+            time(column)?.ok_or_else(|| {
+                rusqlite::Error::InvalidColumnType(0, column.to_string(), rusqlite::types::Type::Null)
+            })
+        };
+
+        let wifi_ssid: Option<String> = row.get("wifi_ssid")?;
+        let wifi_signal_dbm: Option<i32> = row.get("wifi_signal_dbm")?;
+        let wifi = if wifi_ssid.is_some() || wifi_signal_dbm.is_some() {
+            Some(WifiInfo { ssid: wifi_ssid, signal_dbm: wifi_signal_dbm })
+        } else {
+            None
+        };
+
+        let weather = match time("weather_observed_at")? {
+            Some(observed_at) => Some(WeatherReport {
+                observed_at,
+                weather_code: row.get("weather_code")?,
+                temperature_c: row.get("temperature_c")?,
+                precipitation_mm: row.get("precipitation_mm")?,
+                wind_speed_kmh: row.get("wind_speed_kmh")?,
+            }),
+            None => None,
+        };
+
+        Ok(InternetOutage {
+            id,
+            site: row.get("site")?,
+            start_time: required("start_time")?,
+            detected_at: time("detected_at")?,
+            confirmed_at: time("confirmed_at")?,
+            end_time: required("end_time")?,
+            recovered_at: time("recovered_at")?,
+            duration_seconds: row.get("duration_seconds")?,
+            wifi,
+            weather,
+            tags: Vec::new(),
+            capture_path: row.get("capture_path")?,
+            failed_components: row.get("failed_components")?,
+            actor: row.get("actor")?,
+            clock_anomaly: row.get("clock_anomaly")?,
+            failed_probes: row.get("failed_probes")?,
+            severity: row.get::<_, Option<String>>("severity")?.and_then(|severity| severity.parse().ok()),
+            derived_from: row.get("derived_from")?,
+        })
+    }
+
+    /// Collects rows read with [`InternetOutage::from_row`], setting aside the
+    /// ones with unreadable timestamps instead of failing on them, so a row
+    /// written by another tool doesn't break every report.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn readable(
+        rows: impl IntoIterator<Item = rusqlite::Result<Self>>,
+    ) -> rusqlite::Result<(Vec<Self>, Vec<UnreadableTimestamp>)> {
+        let mut outages = Vec::new();
+        let mut unreadable = Vec::new();
+        for row in rows {
+            match row {
+                Ok(outage) => outages.push(outage),
+                Err(rusqlite::Error::FromSqlConversionFailure(_, _, e)) if e.is::<UnreadableTimestamp>() => {
+                    let e = e.downcast::<UnreadableTimestamp>().expect("checked above");
+                    unreadable.push(*e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok((outages, unreadable))
+    }
+}
+
+/// Summary of a set of outages. Durations are in seconds.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OutageStats {
+    pub total_outages: i64,
+    #[serde(rename = "total_duration_seconds")]
+    pub total_duration: i64,
+    #[serde(rename = "average_duration_seconds")]
+    pub average_duration: f64,
+    #[serde(rename = "longest_outage_seconds")]
+    pub longest_outage: i64,
+    #[serde(rename = "shortest_outage_seconds")]
+    pub shortest_outage: i64,
+    /// Average time from the first failed check until the outage was confirmed
+    #[serde(rename = "average_detection_delay_seconds")]
+    pub average_detection_delay: Option<f64>,
+    /// Average time from the first successful check until the outage was declared over
+    #[serde(rename = "average_recovery_delay_seconds")]
+    pub average_recovery_delay: Option<f64>,
+}
+
+/// Number of outages and downtime in one calendar month.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MonthlyOutage {
+    pub year: i32,
+    /// 1 to 12
+    pub month: u32,
+    pub total_seconds: i64,
+    #[serde(rename = "outages")]
+    pub num_outages: i64,
+}
+
+impl MonthlyOutage {
+    pub fn days(&self) -> f64 {
+        match self.month {
+            4 | 6 | 9 | 11 => 30.0,
+            2 if self.year % 4 == 0 && (self.year % 100 != 0 || self.year % 400 == 0) => 29.0,
+            2 => 28.0,
+            _ => 31.0,
+        }
+    }
+
+    pub fn downtime_percent(&self) -> f64 {
+        self.total_seconds as f64 / (self.days() * 86400.0) * 100.0
+    }
+
+    /// Share of the monthly rate paid for time without a connection.
+    pub fn cost(&self, monthly_rate: f64) -> f64 {
+        self.total_seconds as f64 / (self.days() * 86400.0) * monthly_rate
+    }
+}
+
+/// A window in which an outage is expected, e.g. announced maintenance of
+/// the ISP, added with `webgone plan add`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlannedDowntime {
+    pub id: i64,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    pub note: Option<String>,
+}
+
+/// A period in which a watcher was monitoring, with the machine and probe
+/// configuration it ran with. Periods recorded by older versions have no
+/// watcher details.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MonitoredPeriod {
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    pub hostname: Option<String>,
+    /// Operating system and architecture, e.g. `Debian GNU/Linux 12 (linux x86_64)`
+    pub os: Option<String>,
+    /// Version of webgone
+    pub version: Option<String>,
+    /// What was probed and how, as `watch` options, e.g. `--target 8.8.8.8:53 --interval 5`
+    pub config: Option<String>,
+}
+
+/// Outages written by `webgone export --format json`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OutageExport {
+    pub exported_at: DateTime<Local>,
+    pub outages: Vec<InternetOutage>,
+    /// Planned downtime that ended at most a year before the export, or is still to come
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub planned: Vec<PlannedDowntime>,
+    /// Periods the watcher was monitoring while the exported outages happened
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub monitored_periods: Vec<MonitoredPeriod>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_timestamps_in_the_formats_other_tools_write() {
+        // 2026-03-01T10:00:00Z as a Unix timestamp
+        let expected = 1_772_359_200;
+        for value in [
+            "2026-03-01T10:00:00+00:00",
+            "2026-03-01T12:00:00+02:00",
+            "2026-03-01T10:00:00Z",
+            "2026-03-01 10:00:00+00:00",
+            "2026-03-01T12:00:00+0200",
+            "2026-03-01T10:00:00",
+            "2026-03-01 10:00:00.000",
+            "2026-03-01T10:00",
+            "  2026-03-01T10:00:00Z\n",
+        ] {
+            assert_eq!(parse_timestamp(value).map(|time| time.timestamp()), Some(expected), "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn rejects_what_isnt_a_time() {
+        for value in [
+            "",
+            "garbage",
+            "2026-03-01",
+            "2026-13-01T10:00:00Z",
+            "2026-02-30T10:00:00Z",
+            "2026-03-01T25:00:00Z",
+            "2026-03-01T10:00:00+99:00",
+            "1772359200",
+            "2026-03-01T10:00:00Z trailing",
+            "２０２６-03-01T10:00:00Z",
+        ] {
+            assert!(parse_timestamp(value).is_none(), "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn reads_exported_outages_with_and_without_later_fields() {
+        let outage = |fields: &str| -> InternetOutage {
+            serde_json::from_str(&format!(
+                r#"{{"id": 1, "site": "nas", "start_time": "2026-03-01T10:00:00+00:00", "end_time": "2026-03-01T10:05:00+00:00",
+                "detected_at": null, "confirmed_at": null, "recovered_at": null, "duration_seconds": 300,
+                "wifi": null, "weather": null, "capture_path": null, "failed_components": null, "actor": null{}}}"#,
+                fields
+            ))
+            .unwrap()
+        };
+
+        let older = outage("");
+        assert!(!older.clock_anomaly);
+        assert_eq!(older.derived_from, None);
+
+        let exported = outage(r#", "clock_anomaly": true, "derived_from": "vpn""#);
+        assert!(exported.clock_anomaly);
+        assert_eq!(exported.derived_from.as_deref(), Some("vpn"));
+        let again: InternetOutage = serde_json::from_value(serde_json::to_value(&exported).unwrap()).unwrap();
+        assert_eq!((again.clock_anomaly, again.derived_from), (true, Some("vpn".to_string())));
+    }
+}
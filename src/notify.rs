@@ -0,0 +1,149 @@
+use std::cell::Cell;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use clap::ValueEnum;
+
+use crate::console::Console;
+
+/// Services `watch --notify` sends outage notifications to.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Service {
+    /// Embeds in a Discord channel, through --discord-webhook
+    Discord,
+    /// Mail on recovery, through the [email] table of --config
+    Email,
+}
+
+/// Which channels a notification goes to.
+#[derive(Clone, Copy, Default)]
+pub struct Channels {
+    pub command: bool,
+    pub desktop: bool,
+    pub webhook: bool,
+    pub discord: bool,
+    pub email: bool,
+}
+
+impl Channels {
+    pub const ALL: Channels = Channels { command: true, desktop: true, webhook: true, discord: true, email: true };
+
+    /// The channels in `self` but not in `other`.
+    pub fn without(self, other: Channels) -> Channels {
+        Channels {
+            command: self.command && !other.command,
+            desktop: self.desktop && !other.desktop,
+            webhook: self.webhook && !other.webhook,
+            discord: self.discord && !other.discord,
+            email: self.email && !other.email,
+        }
+    }
+
+    /// Both sets of channels.
+    pub fn and(self, other: Channels) -> Channels {
+        Channels {
+            command: self.command || other.command,
+            desktop: self.desktop || other.desktop,
+            webhook: self.webhook || other.webhook,
+            discord: self.discord || other.discord,
+            email: self.email || other.email,
+        }
+    }
+}
+
+/// Sends notifications of the watcher, such as lost connections, recovery
+/// estimates and scheduled digests. They are handed to every configured
+/// channel: a command, e.g. to mail them or push them to a phone, and native
+/// desktop notifications.
+pub struct Notifier {
+    /// Shell command that receives the message on stdin and the subject in
+    /// `WEBGONE_SUBJECT`
+    command: Option<String>,
+    desktop: bool,
+    /// Cleared while another instance holds the notification lease; messages
+    /// are then only printed
+    active: bool,
+    /// Deliveries that failed since the last `take_failures`
+    failures: Cell<u64>,
+}
+
+impl Notifier {
+    pub fn new(command: Option<String>, desktop: bool) -> Self {
+        Notifier { command, desktop, active: true, failures: Cell::new(0) }
+    }
+
+    pub fn set_active(&mut self, active: bool) {
+        self.active = active;
+    }
+
+    /// Number of deliveries that failed since the last call.
+    pub fn take_failures(&self) -> u64 {
+        self.failures.take()
+    }
+
+    /// Whether this instance sends notifications, see `set_active`.
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Prints a notification and sends it to the configured channels.
+    pub fn send(&self, console: &mut Console, subject: &str, message: &str) {
+        console.print(message);
+        self.deliver(console, subject, message);
+    }
+
+    /// Sends an already printed message to the configured channels. Failing
+    /// channels are reported but don't stop the watcher.
+    pub fn deliver(&self, console: &mut Console, subject: &str, message: &str) {
+        self.deliver_to(console, Channels::ALL, subject, message);
+    }
+
+    /// Sends an already printed message to those of the configured channels
+    /// in `channels`.
+    pub fn deliver_to(&self, console: &mut Console, channels: Channels, subject: &str, message: &str) {
+        if !self.active {
+            return;
+        }
+        if let Some(command) = self.command.as_ref().filter(|_| channels.command) {
+            if let Err(e) = run_command(command, subject, message) {
+                console.print(&format!("Notification command failed: {}", e));
+                self.failures.set(self.failures.get() + 1);
+            }
+        }
+        if self.desktop && channels.desktop {
+            if let Err(e) = show_desktop(subject, message) {
+                console.print(&format!("Desktop notification failed: {}", e));
+                self.failures.set(self.failures.get() + 1);
+            }
+        }
+    }
+}
+
+fn show_desktop(subject: &str, message: &str) -> Result<(), String> {
+    notify_rust::Notification::new()
+        .appname("webgone")
+        .summary(subject)
+        .body(message)
+        .show()
+        .map(drop)
+        .map_err(|e| e.to_string())
+}
+
+fn run_command(command: &str, subject: &str, message: &str) -> Result<(), String> {
+    let mut child = if cfg!(windows) {
+        Command::new("cmd").args(["/C", command]).env("WEBGONE_SUBJECT", subject).stdin(Stdio::piped()).spawn()
+    } else {
+        Command::new("sh").args(["-c", command]).env("WEBGONE_SUBJECT", subject).stdin(Stdio::piped()).spawn()
+    }
+    .map_err(|e| e.to_string())?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // A command that doesn't read its input is fine
+        let _ = stdin.write_all(message.as_bytes()).and_then(|()| stdin.write_all(b"\n"));
+    }
+    let status = child.wait().map_err(|e| e.to_string())?;
+    if !status.success() {
+        return Err(format!("{} exited with {}", command, status));
+    }
+    Ok(())
+}
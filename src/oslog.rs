@@ -0,0 +1,136 @@
+use chrono::{DateTime, Duration, Local};
+
+/// How far before the outage start and after its end to look for events.
+const EVENT_WINDOW_MINUTES: i64 = 2;
+
+/// Upper bound on events attached to a single outage, so a chatty log doesn't
+/// flood the database.
+const MAX_EVENTS: usize = 25;
+
+/// Substrings (lowercase) that mark a log line as relevant to connectivity.
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+const KEYWORDS: &[&str] = &[
+    "link is down",
+    "link is up",
+    "link down",
+    "link up",
+    "carrier",
+    "dhcp",
+    "lease",
+    "suspend",
+    "resume",
+    "sleep",
+    "wake",
+    "disconnect",
+    "deauth",
+    "association",
+    "no route",
+    "kernel-power",
+    "tcpip",
+    "wlan",
+];
+
+/// A system log entry that happened around an outage, e.g. an interface going
+/// down, a DHCP renew or the machine suspending.
+pub struct OsEvent {
+    pub time: DateTime<Local>,
+    pub source: String,
+    pub message: String,
+}
+
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+fn is_relevant(line: &str) -> bool {
+    let line = line.to_lowercase();
+    KEYWORDS.iter().any(|keyword| line.contains(keyword))
+}
+
+/// Collects relevant system events between shortly before `start` and
+/// shortly after `end`.
+///
+/// This is best-effort enrichment: if the system log can't be read (missing
+/// tool, insufficient permissions, unsupported platform) an empty list is
+/// returned.
+pub fn events_between(start: DateTime<Local>, end: DateTime<Local>) -> Vec<OsEvent> {
+    let window = Duration::minutes(EVENT_WINDOW_MINUTES);
+    let mut events = read_events(start - window, end + window);
+    events.truncate(MAX_EVENTS);
+    events
+}
+
+#[cfg(target_os = "linux")]
+fn read_events(since: DateTime<Local>, until: DateTime<Local>) -> Vec<OsEvent> {
+    use std::process::Command;
+
+    let output = Command::new("journalctl")
+        .arg("--since")
+        .arg(since.format("%Y-%m-%d %H:%M:%S").to_string())
+        .arg("--until")
+        .arg(until.format("%Y-%m-%d %H:%M:%S").to_string())
+        .args(["--no-pager", "--quiet", "--output", "short-iso"])
+        .output();
+
+    let Ok(output) = output else {
+        return Vec::new();
+    };
+
+    // Lines look like "2024-02-01T10:00:01+0100 host NetworkManager[712]: <info> ..."
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| is_relevant(line))
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, ' ');
+            let time = DateTime::parse_from_str(parts.next()?, "%Y-%m-%dT%H:%M:%S%z").ok()?;
+            let _host = parts.next()?;
+            let (source, message) = parts.next()?.split_once(": ")?;
+            let source = source.split('[').next().unwrap_or(source);
+
+            Some(OsEvent {
+                time: time.with_timezone(&Local),
+                source: source.to_string(),
+                message: message.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(target_os = "windows")]
+fn read_events(since: DateTime<Local>, until: DateTime<Local>) -> Vec<OsEvent> {
+    use std::process::Command;
+
+    let script = format!(
+        "Get-WinEvent -FilterHashtable @{{LogName='System'; StartTime='{}'; EndTime='{}'}} -ErrorAction SilentlyContinue | \
+         ForEach-Object {{ $_.TimeCreated.ToString('o') + \"`t\" + $_.ProviderName + \"`t\" + ($_.Message -split \"`n\")[0] }}",
+        since.format("%Y-%m-%dT%H:%M:%S"),
+        until.format("%Y-%m-%dT%H:%M:%S"),
+    );
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .output();
+
+    let Ok(output) = output else {
+        return Vec::new();
+    };
+
+    let mut events: Vec<OsEvent> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| is_relevant(line))
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, '\t');
+            let time = DateTime::parse_from_rfc3339(parts.next()?.trim()).ok()?;
+            Some(OsEvent {
+                time: time.with_timezone(&Local),
+                source: parts.next()?.trim().to_string(),
+                message: parts.next()?.trim().to_string(),
+            })
+        })
+        .collect();
+
+    // Get-WinEvent returns the newest events first
+    events.reverse();
+    events
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn read_events(_since: DateTime<Local>, _until: DateTime<Local>) -> Vec<OsEvent> {
+    Vec::new()
+}
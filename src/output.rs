@@ -0,0 +1,125 @@
+use ascii_table::{Align, AsciiTable};
+use clap::ValueEnum;
+use std::io::{self, IsTerminal, Write};
+
+use crate::export;
+use crate::i18n::{Lang, Text};
+
+/// How a report is printed: for people, or for jq, scripts and dashboards.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Table,
+    Json,
+    Csv,
+}
+
+/// Prints rows as CSV with a header line.
+pub fn print_csv(headers: &[&str], rows: Vec<Vec<String>>) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "{}", headers.iter().map(|header| export::csv_field(header)).collect::<Vec<_>>().join(","))?;
+    for row in rows {
+        writeln!(stdout, "{}", row.iter().map(|value| export::csv_field(value)).collect::<Vec<_>>().join(","))?;
+    }
+    Ok(())
+}
+
+/// Whether an error comes from stdout being closed, e.g. by `head` or a
+/// pager quitting early. That ends the report, it isn't a failure.
+pub fn is_broken_pipe(err: &anyhow::Error) -> bool {
+    err.chain()
+        .any(|cause| cause.downcast_ref::<io::Error>().is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe))
+}
+
+/// Columns of the terminal stdout is printed to, from `COLUMNS` or, as
+/// shells don't export it, from `stty`.
+fn terminal_width() -> Option<usize> {
+    if let Some(columns) = std::env::var("COLUMNS").ok().and_then(|columns| columns.trim().parse().ok()) {
+        return Some(columns);
+    }
+    #[cfg(unix)]
+    {
+        let tty = std::fs::File::open("/dev/tty").ok()?;
+        let output = std::process::Command::new("stty").arg("size").stdin(tty).output().ok()?;
+        // `ROWS COLUMNS`
+        let size = String::from_utf8_lossy(&output.stdout);
+        size.split_whitespace().nth(1)?.parse().ok()
+    }
+    #[cfg(not(unix))]
+    None
+}
+
+/// How report output is rendered.
+#[derive(Clone, Copy)]
+pub struct Output {
+    pub lang: Lang,
+    /// Render `label: value` lines instead of box-drawing tables, which works
+    /// better with screen readers and in plain-text emails
+    pub plain: bool,
+}
+
+impl Output {
+    pub fn text(self, text: Text) -> &'static str {
+        self.lang.text(text)
+    }
+
+    /// Prints rows as a table, or in plain mode as one block of
+    /// `header: value` lines per row.
+    pub fn print_table(self, columns: &[(&str, Align)], data: Vec<Vec<String>>) -> io::Result<()> {
+        let mut stdout = io::stdout().lock();
+        if self.plain {
+            for row in data {
+                for ((header, _), value) in columns.iter().zip(row) {
+                    writeln!(stdout, "{}: {}", header, value)?;
+                }
+                writeln!(stdout)?;
+            }
+            return Ok(());
+        }
+
+        let mut table = AsciiTable::default();
+        // Only a terminal wraps long lines; piped into a file or pager, rows stay whole
+        if !std::io::stdout().is_terminal() {
+            table.set_max_width(usize::MAX);
+        } else if let Some(width) = terminal_width() {
+            table.set_max_width(width);
+        }
+        for (index, (header, align)) in columns.iter().enumerate() {
+            table.column(index).set_header(*header).set_align(*align);
+        }
+        write!(stdout, "{}", table.format(data))
+    }
+
+    /// Prints label/value pairs as a two-column table, or in plain mode as
+    /// `label: value` lines.
+    pub fn print_key_values(self, rows: Vec<(String, String)>) -> io::Result<()> {
+        if self.plain {
+            let mut stdout = io::stdout().lock();
+            for (label, value) in rows {
+                writeln!(stdout, "{}: {}", label, value)?;
+            }
+            return Ok(());
+        }
+
+        self.print_table(
+            &[(self.text(Text::Metric), Align::Left), (self.text(Text::Value), Align::Right)],
+            rows.into_iter().map(|(label, value)| vec![label, value]).collect(),
+        )
+    }
+
+    /// Wraps `value` in an ANSI color for tables printed to a terminal,
+    /// unless plain mode or `NO_COLOR` asks for none.
+    pub fn colored(self, value: &str, color: &str, reset: &str) -> String {
+        if self.plain || !std::io::stdout().is_terminal() || std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+            return value.to_string();
+        }
+        format!("{}{}{}", color, value, reset)
+    }
+
+    /// Prints a decorative separator line, which is left out in plain mode.
+    pub fn separator(self) -> io::Result<()> {
+        if !self.plain {
+            writeln!(io::stdout().lock(), "{:-<50}", "")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,28 @@
+use anyhow::Result;
+use chrono::Local;
+use rusqlite::{params, Connection, OptionalExtension};
+
+/// Metadata key holding when monitoring was paused.
+const PAUSED_KEY: &str = "monitoring_paused_at";
+
+/// Pauses or resumes a running watcher. Kept in the database, so it can be
+/// toggled from another process such as `webgone tray`.
+pub fn set_paused(conn: &Connection, paused: bool) -> Result<()> {
+    if paused {
+        conn.execute(
+            "INSERT INTO metadata (key, value) VALUES (?1, ?2)
+            ON CONFLICT (key) DO NOTHING",
+            params![PAUSED_KEY, Local::now().to_rfc3339()],
+        )?;
+    } else {
+        conn.execute("DELETE FROM metadata WHERE key = ?", [PAUSED_KEY])?;
+    }
+    Ok(())
+}
+
+pub fn is_paused(conn: &Connection) -> Result<bool> {
+    Ok(conn
+        .query_row("SELECT 1 FROM metadata WHERE key = ?", [PAUSED_KEY], |_| Ok(()))
+        .optional()?
+        .is_some())
+}
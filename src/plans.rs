@@ -0,0 +1,117 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration as ChronoDuration, Local};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use std::time::Duration;
+
+use crate::error::WebgoneError;
+use webgone::model::{self, PlannedDowntime};
+
+/// Tag of outages that happened during planned downtime.
+pub const MAINTENANCE_TAG: &str = "maintenance";
+
+/// How long ago planned downtime may have ended to be included in exports.
+const EXPORT_HISTORY: ChronoDuration = ChronoDuration::days(365);
+
+pub fn init_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS planned_downtime (
+            id INTEGER PRIMARY KEY,
+            start_time TEXT NOT NULL,
+            end_time TEXT NOT NULL,
+            note TEXT,
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn from_row(row: &Row) -> rusqlite::Result<(i64, String, String, Option<String>)> {
+    Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+}
+
+fn read(rows: Vec<(i64, String, String, Option<String>)>) -> Vec<PlannedDowntime> {
+    rows.into_iter()
+        .filter_map(|(id, start, end, note)| {
+            Some(PlannedDowntime { id, start: model::parse_timestamp(&start)?, end: model::parse_timestamp(&end)?, note })
+        })
+        .collect()
+}
+
+pub fn add(conn: &Connection, start: DateTime<Local>, duration: Duration, note: Option<&str>) -> Result<i64> {
+    if duration.is_zero() {
+        return Err(WebgoneError::InvalidInput("planned downtime needs a --duration above zero".to_string()).into());
+    }
+    let end = start + ChronoDuration::from_std(duration).map_err(|e| WebgoneError::InvalidInput(e.to_string()))?;
+    if end <= Local::now() {
+        return Err(WebgoneError::InvalidInput(format!(
+            "planned downtime from {} would already be over",
+            start.format("%Y-%m-%d %H:%M")
+        ))
+        .into());
+    }
+    conn.execute(
+        "INSERT INTO planned_downtime (start_time, end_time, note, created_at) VALUES (?1, ?2, ?3, ?4)",
+        params![start.to_rfc3339(), end.to_rfc3339(), note, Local::now().to_rfc3339()],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn remove(conn: &Connection, id: i64) -> Result<()> {
+    if conn.execute("DELETE FROM planned_downtime WHERE id = ?", [id])? == 0 {
+        return Err(WebgoneError::InvalidInput(format!("no planned downtime with id {}", id)).into());
+    }
+    Ok(())
+}
+
+/// Planned downtime ending after `since`, or all of it, earliest first.
+pub fn list(conn: &Connection, since: Option<DateTime<Local>>) -> Result<Vec<PlannedDowntime>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, start_time, end_time, note FROM planned_downtime
+        WHERE ?1 IS NULL OR julianday(end_time) > julianday(?1)
+        ORDER BY julianday(start_time)",
+    )?;
+    let rows = stmt
+        .query_map([since.map(|time| time.to_rfc3339())], from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(read(rows))
+}
+
+/// Planned downtime to include in an export written at `now`.
+pub fn for_export(conn: &Connection, now: DateTime<Local>) -> Result<Vec<PlannedDowntime>> {
+    list(conn, Some(now - EXPORT_HISTORY))
+}
+
+/// The planned downtime `time` falls into, if any.
+pub fn at(conn: &Connection, time: DateTime<Local>) -> Result<Option<PlannedDowntime>> {
+    let row = conn
+        .query_row(
+            "SELECT id, start_time, end_time, note FROM planned_downtime
+            WHERE julianday(start_time) <= julianday(?1) AND julianday(end_time) > julianday(?1)
+            ORDER BY julianday(start_time) LIMIT 1",
+            [time.to_rfc3339()],
+            from_row,
+        )
+        .optional()?;
+    Ok(read(row.into_iter().collect()).pop())
+}
+
+/// Whether an outage from `start` to `end` overlaps planned downtime.
+pub fn overlaps(conn: &Connection, start: DateTime<Local>, end: DateTime<Local>) -> Result<bool> {
+    let overlaps = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM planned_downtime
+            WHERE julianday(start_time) < julianday(?2) AND julianday(end_time) > julianday(?1))",
+        [start.to_rfc3339(), end.max(start).to_rfc3339()],
+        |row| row.get(0),
+    )?;
+    Ok(overlaps)
+}
+
+/// Describes planned downtime for log lines, e.g. `ISP maintenance (02:00 to 04:00)`.
+pub fn describe(plan: &PlannedDowntime) -> String {
+    let window = format!("{} to {}", plan.start.format("%H:%M"), plan.end.format("%H:%M"));
+    match &plan.note {
+        Some(note) => format!("{} ({})", note, window),
+        None => format!("planned downtime ({})", window),
+    }
+}
@@ -0,0 +1,184 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use rusqlite::{params, Connection};
+use std::ops::{Deref, DerefMut};
+use std::time::{Duration, Instant};
+
+use crate::target::ProbeResult;
+
+/// Write-behind buffer for probe results.
+///
+/// Probes are kept in memory and written in a single transaction once
+/// `batch_size` results are pending or `flush_interval` has passed, which
+/// keeps SQLite's write amplification down at high probing frequencies.
+pub struct ProbeBuffer {
+    /// Pending results with the number of probes each one stands for
+    pending: Vec<(DateTime<Local>, ProbeResult, u32)>,
+    batch_size: usize,
+    flush_interval: Duration,
+    last_flush: Instant,
+    sample_every: u32,
+    successes_seen: u32,
+    /// How long the last successful flush took
+    last_write_latency: Option<Duration>,
+}
+
+impl ProbeBuffer {
+    /// Creates a buffer that keeps every `sample_every`th successful probe.
+    /// Failed probes are always kept.
+    pub fn new(batch_size: usize, flush_interval: Duration, sample_every: u32) -> Self {
+        ProbeBuffer {
+            pending: Vec::with_capacity(batch_size),
+            batch_size: batch_size.max(1),
+            flush_interval,
+            last_flush: Instant::now(),
+            sample_every: sample_every.max(1),
+            successes_seen: 0,
+            last_write_latency: None,
+        }
+    }
+
+    /// Number of results waiting to be written.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Wraps the buffer so that whatever is still pending gets written when
+    /// it is dropped, also when a panic or an error unwinds past it.
+    pub fn flush_on_drop(self, conn: &Connection) -> FlushOnDrop<'_> {
+        FlushOnDrop { buffer: self, conn }
+    }
+
+    pub fn last_write_latency(&self) -> Option<Duration> {
+        self.last_write_latency
+    }
+
+    pub fn push(&mut self, conn: &Connection, time: DateTime<Local>, results: Vec<ProbeResult>) -> Result<()> {
+        for result in results {
+            let weight = if result.is_success() {
+                self.successes_seen = (self.successes_seen + 1) % self.sample_every;
+                if self.successes_seen != 0 {
+                    continue;
+                }
+                self.sample_every
+            } else {
+                1
+            };
+            self.pending.push((time, result, weight));
+        }
+
+        if self.pending.len() >= self.batch_size || self.last_flush.elapsed() >= self.flush_interval {
+            self.flush(conn)?;
+        }
+        Ok(())
+    }
+
+    /// Writes all pending results. Nothing is dropped from the buffer unless
+    /// the transaction committed, so a failed flush is retried with the next one.
+    pub fn flush(&mut self, conn: &Connection) -> Result<()> {
+        self.last_flush = Instant::now();
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let started = Instant::now();
+        let tx = conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO probes (time, target, success, latency_ms, error, weight) VALUES (?1, ?2, ?3, ?4, ?5, ?6)"
+            )?;
+            for (time, result, weight) in &self.pending {
+                stmt.execute(params![
+                    time.to_rfc3339(),
+                    result.target.to_string(),
+                    result.is_success(),
+                    result.is_success().then_some(result.elapsed.as_secs_f64() * 1000.0),
+                    result.error,
+                    weight,
+                ])?;
+            }
+        }
+        tx.commit()?;
+
+        self.last_write_latency = Some(started.elapsed());
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+/// A `ProbeBuffer` that is flushed when dropped, see `ProbeBuffer::flush_on_drop`.
+pub struct FlushOnDrop<'a> {
+    buffer: ProbeBuffer,
+    conn: &'a Connection,
+}
+
+impl Deref for FlushOnDrop<'_> {
+    type Target = ProbeBuffer;
+
+    fn deref(&self) -> &ProbeBuffer {
+        &self.buffer
+    }
+}
+
+impl DerefMut for FlushOnDrop<'_> {
+    fn deref_mut(&mut self) -> &mut ProbeBuffer {
+        &mut self.buffer
+    }
+}
+
+impl Drop for FlushOnDrop<'_> {
+    fn drop(&mut self) {
+        // Best effort: there's nobody left to report a failure to
+        let _ = self.buffer.flush(self.conn);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::target::Target;
+
+    fn database() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE probes (time TEXT, target TEXT, success INTEGER, latency_ms REAL, error TEXT, weight INTEGER)",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn failed_probe() -> Vec<ProbeResult> {
+        let target = Target::Tcp("127.0.0.1:9".parse().unwrap());
+        vec![ProbeResult { target, elapsed: Duration::from_millis(1), error: Some("refused".to_string()) }]
+    }
+
+    fn written(conn: &Connection) -> i64 {
+        conn.query_row("SELECT COUNT(*) FROM probes", [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn writes_pending_probes_when_a_panic_unwinds_past_it() {
+        let conn = database();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let mut buffer = ProbeBuffer::new(100, Duration::from_secs(3600), 1).flush_on_drop(&conn);
+            buffer.push(&conn, Local::now(), failed_probe()).unwrap();
+            assert_eq!(written(&conn), 0);
+            panic!("the watcher crashed");
+        }));
+        assert!(result.is_err());
+        assert_eq!(written(&conn), 1);
+    }
+
+    #[test]
+    fn keeps_pending_probes_when_a_flush_fails() {
+        let conn = database();
+        let mut buffer = ProbeBuffer::new(100, Duration::from_secs(3600), 1);
+        buffer.push(&conn, Local::now(), failed_probe()).unwrap();
+        conn.execute_batch("ALTER TABLE probes RENAME TO moved").unwrap();
+        assert!(buffer.flush(&conn).is_err());
+        assert_eq!(buffer.pending(), 1);
+        conn.execute_batch("ALTER TABLE moved RENAME TO probes").unwrap();
+        buffer.flush(&conn).unwrap();
+        assert_eq!((buffer.pending(), written(&conn)), (0, 1));
+    }
+}
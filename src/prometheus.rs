@@ -0,0 +1,141 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use rusqlite::{Connection, OptionalExtension};
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tiny_http::{Header, Method, Response, Server};
+
+use crate::acks;
+use crate::error::WebgoneError;
+use crate::health::{self, WatcherMetrics};
+use webgone::model;
+
+/// Content type of the Prometheus text exposition format.
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// This machine's connection as the metrics endpoint exposes it.
+pub struct Metrics {
+    pub watcher_running: bool,
+    /// Start of the outage the watcher is in, if any
+    pub down_since: Option<DateTime<Local>>,
+    pub outages: i64,
+    pub downtime_seconds: i64,
+    pub last_outage_seconds: Option<i64>,
+    /// Time and latency of the last successful check
+    pub last_check: Option<(DateTime<Local>, f64)>,
+    /// Health of the watcher as it last saved it, see `webgone status`
+    pub health: Option<WatcherMetrics>,
+}
+
+/// Loads the metrics of local outages, leaving out services' outages derived
+/// from what they depend on, like `stats` does by default.
+pub fn load(conn: &Connection, watcher_running: bool) -> Result<Metrics> {
+    let (outages, downtime_seconds): (i64, Option<i64>) = conn.query_row(
+        "SELECT COUNT(*), SUM(duration_seconds) FROM outages WHERE site IS NULL AND derived_from IS NULL",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    let last_outage_seconds = conn
+        .query_row(
+            "SELECT duration_seconds FROM outages WHERE site IS NULL AND derived_from IS NULL
+            ORDER BY julianday(end_time) DESC LIMIT 1",
+            [],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let last_check = conn
+        .query_row(
+            "SELECT time, latency_ms FROM probes WHERE site IS NULL AND success AND latency_ms IS NOT NULL
+            ORDER BY julianday(time) DESC LIMIT 1",
+            [],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?)),
+        )
+        .optional()?
+        .and_then(|(time, latency)| Some((model::parse_timestamp(&time)?, latency)));
+
+    Ok(Metrics {
+        watcher_running,
+        down_since: acks::current(conn)?,
+        outages,
+        downtime_seconds: downtime_seconds.unwrap_or(0),
+        last_outage_seconds,
+        last_check,
+        health: health::load(conn)?.map(|status| status.metrics),
+    })
+}
+
+fn metric(out: &mut String, name: &str, kind: &str, help: &str, value: impl std::fmt::Display) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+    let _ = writeln!(out, "{name} {value}");
+}
+
+/// Renders the metrics in the Prometheus text format. Whether the connection
+/// is up and how the watcher is doing are only known while a watcher runs, so
+/// `webgone_up` and the watcher's health are left out otherwise rather than
+/// reporting a stale state.
+pub fn render(metrics: &Metrics) -> String {
+    let mut out = String::new();
+    metric(&mut out, "webgone_watcher_running", "gauge", "Whether a watcher is monitoring the connection.", metrics.watcher_running as u8);
+    if metrics.watcher_running {
+        metric(&mut out, "webgone_up", "gauge", "Whether the internet connection is up.", metrics.down_since.is_none() as u8);
+    }
+    if let Some(since) = metrics.down_since {
+        metric(&mut out, "webgone_outage_started_timestamp_seconds", "gauge", "Start of the ongoing outage.", since.timestamp());
+    }
+    metric(&mut out, "webgone_outages_total", "counter", "Number of recorded outages.", metrics.outages);
+    metric(&mut out, "webgone_downtime_seconds_total", "counter", "Total duration of recorded outages.", metrics.downtime_seconds);
+    if let Some(seconds) = metrics.last_outage_seconds {
+        metric(&mut out, "webgone_last_outage_duration_seconds", "gauge", "Duration of the last recorded outage.", seconds);
+    }
+    if let Some((time, latency_ms)) = metrics.last_check {
+        metric(&mut out, "webgone_last_check_latency_seconds", "gauge", "Latency of the last successful check.", latency_ms / 1000.0);
+        metric(&mut out, "webgone_last_check_timestamp_seconds", "gauge", "Time of the last successful check.", time.timestamp());
+    }
+    if let Some(health) = metrics.health.as_ref().filter(|_| metrics.watcher_running) {
+        metric(&mut out, "webgone_loop_lag_seconds", "gauge", "How much longer than the interval the last probe loop took.", health.loop_lag.as_secs_f64());
+        if let Some(latency) = health.db_write_latency {
+            metric(&mut out, "webgone_db_write_latency_seconds", "gauge", "Time the last write of buffered probes took.", latency.as_secs_f64());
+        }
+        metric(&mut out, "webgone_queue_depth", "gauge", "Probe results waiting in the write buffer.", health.queue_depth);
+        metric(&mut out, "webgone_notification_failures_total", "counter", "Notifications that failed since the watcher started.", health.notification_failures);
+    }
+    out
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).expect("valid header")
+}
+
+/// Serves `/metrics` until `running` is cleared, rendering a fresh page for
+/// every scrape.
+pub fn serve(listen: SocketAddr, running: &AtomicBool, scrape: &mut dyn FnMut() -> Result<String>) -> Result<()> {
+    let server = Server::http(listen).map_err(|e| WebgoneError::Io(format!("failed to listen on {}: {}", listen, e)))?;
+
+    while running.load(Ordering::SeqCst) {
+        let Some(request) = server.recv_timeout(Duration::from_millis(500))? else {
+            continue;
+        };
+
+        let response = match (request.method(), request.url().split('?').next()) {
+            (Method::Get, Some("/metrics")) => match scrape() {
+                Ok(body) => Response::from_string(body).with_header(header("Content-Type", CONTENT_TYPE)),
+                Err(e) => {
+                    println!("Failed to collect metrics: {:#}", e);
+                    Response::from_string("internal error\n").with_status_code(500)
+                }
+            },
+            (Method::Get, _) => Response::from_string("not found, metrics are at /metrics\n").with_status_code(404),
+            _ => Response::from_string("method not allowed\n")
+                .with_status_code(405)
+                .with_header(header("Allow", "GET")),
+        };
+        if let Err(e) = request.respond(response) {
+            println!("Failed to send response: {}", e);
+        }
+    }
+
+    Ok(())
+}
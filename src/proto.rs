@@ -0,0 +1,3 @@
+//! Messages and services generated from `proto/agent.proto`.
+
+tonic::include_proto!("webgone.agent.v1");
@@ -0,0 +1,90 @@
+//! Python bindings, so outage history can be loaded straight into pandas:
+//!
+//! ```python
+//! import pandas, webgone
+//! outages = pandas.DataFrame(webgone.load_outages("internet_outages.db"))
+//! ```
+
+use chrono::{DateTime, FixedOffset, Local};
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use rusqlite::{Connection, OpenFlags};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::query::OutageQuery;
+
+fn runtime_error(e: rusqlite::Error) -> PyErr {
+    PyRuntimeError::new_err(e.to_string())
+}
+
+/// Loads the outages recorded in a webgone database, oldest first, as a list
+/// of dicts with one flat column per field. Times are timezone-aware
+/// datetimes, `site` is None for outages recorded on this machine.
+#[pyfunction]
+#[pyo3(signature = (db_path, since=None, until=None, min_duration_seconds=None, site=None, tags=Vec::new()))]
+fn load_outages<'py>(
+    py: Python<'py>,
+    db_path: PathBuf,
+    since: Option<DateTime<FixedOffset>>,
+    until: Option<DateTime<FixedOffset>>,
+    min_duration_seconds: Option<u64>,
+    site: Option<String>,
+    tags: Vec<String>,
+) -> PyResult<Bound<'py, PyList>> {
+    let conn = Connection::open_with_flags(&db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(runtime_error)?;
+
+    let mut query = OutageQuery::new();
+    if let Some(since) = since {
+        query = query.since(since.with_timezone(&Local));
+    }
+    if let Some(until) = until {
+        query = query.until(until.with_timezone(&Local));
+    }
+    if let Some(seconds) = min_duration_seconds {
+        query = query.min_duration(Duration::from_secs(seconds));
+    }
+    if let Some(site) = site {
+        query = query.monitor(site);
+    }
+    for tag in tags {
+        query = query.tag(tag);
+    }
+
+    let list = PyList::empty(py);
+    for outage in query.run(&conn).map_err(runtime_error)? {
+        let row = PyDict::new(py);
+        row.set_item("id", outage.id)?;
+        row.set_item("site", outage.site)?;
+        row.set_item("start_time", outage.start_time.fixed_offset())?;
+        row.set_item("detected_at", outage.detected_at.map(|time| time.fixed_offset()))?;
+        row.set_item("confirmed_at", outage.confirmed_at.map(|time| time.fixed_offset()))?;
+        row.set_item("end_time", outage.end_time.fixed_offset())?;
+        row.set_item("recovered_at", outage.recovered_at.map(|time| time.fixed_offset()))?;
+        row.set_item("duration_seconds", outage.duration_seconds)?;
+        row.set_item("wifi_ssid", outage.wifi.as_ref().and_then(|wifi| wifi.ssid.clone()))?;
+        row.set_item("wifi_signal_dbm", outage.wifi.as_ref().and_then(|wifi| wifi.signal_dbm))?;
+        row.set_item("weather_code", outage.weather.as_ref().and_then(|weather| weather.weather_code))?;
+        row.set_item("temperature_c", outage.weather.as_ref().and_then(|weather| weather.temperature_c))?;
+        row.set_item("precipitation_mm", outage.weather.as_ref().and_then(|weather| weather.precipitation_mm))?;
+        row.set_item("wind_speed_kmh", outage.weather.as_ref().and_then(|weather| weather.wind_speed_kmh))?;
+        row.set_item("tags", outage.tags)?;
+        row.set_item("capture_path", outage.capture_path)?;
+        row.set_item("failed_components", outage.failed_components)?;
+        row.set_item("actor", outage.actor)?;
+        row.set_item("clock_anomaly", outage.clock_anomaly)?;
+        row.set_item("failed_probes", outage.failed_probes)?;
+        row.set_item("severity", outage.severity.map(|severity| severity.name()))?;
+        row.set_item("derived_from", outage.derived_from)?;
+        list.append(row)?;
+    }
+    Ok(list)
+}
+
+#[pymodule]
+fn webgone(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(load_outages, module)?)?;
+    Ok(())
+}
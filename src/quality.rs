@@ -0,0 +1,380 @@
+use anyhow::Result;
+use chrono::{DateTime, Days, Local, NaiveDate, TimeZone};
+use rusqlite::{params, Connection};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use crate::coverage;
+use crate::latency;
+use webgone::model;
+
+pub fn init_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS quality_daily (
+            day TEXT PRIMARY KEY,
+            availability_percent REAL NOT NULL,
+            p95_latency_ms REAL,
+            loss_percent REAL,
+            jitter_ms REAL,
+            score REAL NOT NULL,
+            computed_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// How much one measure counts in the score, and the values it scores 100
+/// and 0 at. Values in between score linearly.
+#[derive(Deserialize, Clone, Copy)]
+#[serde(deny_unknown_fields)]
+pub struct Scale {
+    pub weight: f64,
+    pub good: f64,
+    pub bad: f64,
+}
+
+impl Scale {
+    fn score(&self, value: f64) -> f64 {
+        if self.good == self.bad {
+            return if value == self.good { 100.0 } else { 0.0 };
+        }
+        ((value - self.bad) / (self.good - self.bad) * 100.0).clamp(0.0, 100.0)
+    }
+}
+
+/// The `[quality]` table of the configuration file.
+#[derive(Deserialize, Clone, Copy)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// Uptime in percent of the monitored time
+    pub availability: Scale,
+    /// 95th percentile of the latency of successful probes in milliseconds
+    pub latency: Scale,
+    /// Failed probes outside of outages in percent
+    pub loss: Scale,
+    /// Average difference between the latencies of consecutive probes in milliseconds
+    pub jitter: Scale,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            availability: Scale { weight: 50.0, good: 99.99, bad: 99.0 },
+            latency: Scale { weight: 20.0, good: 30.0, bad: 200.0 },
+            loss: Scale { weight: 20.0, good: 0.1, bad: 5.0 },
+            jitter: Scale { weight: 10.0, good: 5.0, bad: 50.0 },
+        }
+    }
+}
+
+/// Measures of the connection on one day.
+pub struct Day {
+    pub day: NaiveDate,
+    pub availability_percent: f64,
+    pub p95_latency_ms: Option<f64>,
+    pub loss_percent: Option<f64>,
+    pub jitter_ms: Option<f64>,
+}
+
+impl Day {
+    /// Weighted average of the scores of the measures known for the day.
+    pub fn score(&self, settings: &Settings) -> f64 {
+        let parts = [
+            Some((settings.availability, self.availability_percent)),
+            self.p95_latency_ms.map(|value| (settings.latency, value)),
+            self.loss_percent.map(|value| (settings.loss, value)),
+            self.jitter_ms.map(|value| (settings.jitter, value)),
+        ];
+        let (total, weights) = parts
+            .into_iter()
+            .flatten()
+            .fold((0.0, 0.0), |(total, weights), (scale, value)| {
+                (total + scale.score(value) * scale.weight, weights + scale.weight)
+            });
+        if weights <= 0.0 {
+            return 100.0;
+        }
+        total / weights
+    }
+}
+
+fn local_midnight(day: NaiveDate) -> DateTime<Local> {
+    Local
+        .from_local_datetime(&day.and_hms_opt(0, 0, 0).expect("midnight exists"))
+        .earliest()
+        .unwrap_or_else(|| Local.from_utc_datetime(&day.and_hms_opt(0, 0, 0).expect("midnight exists")))
+}
+
+/// Share of failed probes that weren't part of an outage; periods rolled up
+/// while an outage was going on are left out as a whole.
+pub fn loss_percent(conn: &Connection, from: DateTime<Local>, to: DateTime<Local>) -> Result<Option<f64>> {
+    let outside_outages = |time: &str, end: &str| {
+        format!(
+            "NOT EXISTS (SELECT 1 FROM outages o WHERE o.site IS NULL AND o.derived_from IS NULL
+                AND julianday(o.start_time) <= julianday({end}) AND julianday(o.end_time) >= julianday({time}))"
+        )
+    };
+    let (probes, failures): (Option<i64>, Option<i64>) = conn.query_row(
+        &format!(
+            "SELECT SUM(probes), SUM(failures) FROM (
+                SELECT SUM(weight) AS probes, SUM(CASE WHEN success THEN 0 ELSE weight END) AS failures
+                FROM probes
+                WHERE site IS NULL AND julianday(time) >= julianday(?1) AND julianday(time) < julianday(?2) AND {}
+                UNION ALL
+                SELECT SUM(probes), SUM(failures) FROM probe_hourly
+                WHERE julianday(period) >= julianday(?1) AND julianday(period) < julianday(?2) AND {}
+                UNION ALL
+                SELECT SUM(probes), SUM(failures) FROM probe_minutely
+                WHERE julianday(period) >= julianday(?1) AND julianday(period) < julianday(?2) AND {}
+            )",
+            outside_outages("time", "time"),
+            outside_outages("period", "datetime(period, '+1 hour')"),
+            outside_outages("period", "datetime(period, '+1 minute')"),
+        ),
+        params![from.to_rfc3339(), to.to_rfc3339()],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    Ok(match (probes, failures) {
+        (Some(probes), Some(failures)) if probes > 0 => Some(failures as f64 / probes as f64 * 100.0),
+        _ => None,
+    })
+}
+
+/// Average absolute difference between the latencies of consecutive
+/// successful probes of the same target. Only raw probes have the order.
+fn jitter_ms(conn: &Connection, from: DateTime<Local>, to: DateTime<Local>) -> Result<Option<f64>> {
+    let mut stmt = conn.prepare(
+        "SELECT target, latency_ms FROM probes
+        WHERE site IS NULL AND success AND latency_ms IS NOT NULL
+            AND julianday(time) >= julianday(?1) AND julianday(time) < julianday(?2)
+        ORDER BY target, julianday(time)",
+    )?;
+    let rows = stmt
+        .query_map(params![from.to_rfc3339(), to.to_rfc3339()], |row| Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let differences: Vec<f64> = rows
+        .windows(2)
+        .filter(|pair| pair[0].0 == pair[1].0)
+        .map(|pair| (pair[1].1 - pair[0].1).abs())
+        .collect();
+    Ok((!differences.is_empty()).then(|| differences.iter().sum::<f64>() / differences.len() as f64))
+}
+
+/// Measures `day`, or `None` if nothing was recorded on it.
+pub fn measure(conn: &Connection, day: NaiveDate) -> Result<Option<Day>> {
+    let (from, to) = (local_midnight(day), local_midnight(day + Days::new(1)));
+    let period_seconds = (to - from).num_seconds();
+    let gap_seconds = coverage::gap_seconds(conn, from, to)?;
+    let loss_percent = loss_percent(conn, from, to)?;
+    if gap_seconds >= period_seconds && loss_percent.is_none() {
+        return Ok(None);
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT start_time, end_time FROM outages
+        WHERE site IS NULL AND derived_from IS NULL AND julianday(end_time) > julianday(?1) AND julianday(start_time) < julianday(?2)",
+    )?;
+    let downtime_seconds: i64 = stmt
+        .query_map(params![from.to_rfc3339(), to.to_rfc3339()], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?
+        .into_iter()
+        .filter_map(|(start, end)| Some((model::parse_timestamp(&start)?, model::parse_timestamp(&end)?)))
+        .map(|(start, end)| (end.min(to) - start.max(from)).num_seconds().max(0))
+        .sum();
+    // Watchers of older versions didn't record when they were monitoring
+    let gaps = if gap_seconds >= period_seconds { coverage::GapMode::Up } else { coverage::GapMode::Exclude };
+
+    Ok(Some(Day {
+        day,
+        availability_percent: gaps.uptime_percent(period_seconds, downtime_seconds, gap_seconds),
+        p95_latency_ms: latency::stats(conn, from, to)?.last().map(|stats| stats.p95_ms),
+        loss_percent,
+        jitter_ms: jitter_ms(conn, from, to)?,
+    }))
+}
+
+/// Measures and stores the finished days of the last `days` days before
+/// `now` that aren't stored yet, or all of them with `recompute`. Returns
+/// how many days were stored.
+pub fn store_days(conn: &Connection, settings: &Settings, days: u32, recompute: bool, now: DateTime<Local>) -> Result<usize> {
+    let today = now.date_naive();
+    let mut stored = 0;
+    for days_ago in 1..=days as u64 {
+        let day = today - Days::new(days_ago);
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM quality_daily WHERE day = ?)",
+            [day.to_string()],
+            |row| row.get(0),
+        )?;
+        if exists && !recompute {
+            continue;
+        }
+        if let Some(measured) = measure(conn, day)? {
+            conn.execute(
+                "INSERT OR REPLACE INTO quality_daily
+                    (day, availability_percent, p95_latency_ms, loss_percent, jitter_ms, score, computed_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    day.to_string(),
+                    measured.availability_percent,
+                    measured.p95_latency_ms,
+                    measured.loss_percent,
+                    measured.jitter_ms,
+                    measured.score(settings),
+                    now.to_rfc3339(),
+                ],
+            )?;
+            stored += 1;
+        }
+    }
+    Ok(stored)
+}
+
+/// Scores the stored days again with `settings`, which may have changed
+/// since they were stored.
+pub fn rescore(conn: &Connection, settings: &Settings) -> Result<()> {
+    for day in stored(conn, NaiveDate::MIN)? {
+        conn.execute(
+            "UPDATE quality_daily SET score = ?1 WHERE day = ?2",
+            params![day.score(settings), day.day.to_string()],
+        )?;
+    }
+    Ok(())
+}
+
+/// Stored days from `since` on, oldest first.
+pub fn stored(conn: &Connection, since: NaiveDate) -> Result<Vec<Day>> {
+    let mut stmt = conn.prepare(
+        "SELECT day, availability_percent, p95_latency_ms, loss_percent, jitter_ms FROM quality_daily
+        WHERE day >= ? ORDER BY day",
+    )?;
+    let rows = stmt
+        .query_map([since.to_string()], |row| {
+            Ok((row.get::<_, String>(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|(day, availability_percent, p95_latency_ms, loss_percent, jitter_ms)| {
+            Some(Day { day: day.parse().ok()?, availability_percent, p95_latency_ms, loss_percent, jitter_ms })
+        })
+        .collect())
+}
+
+/// Scores of one month, averaged over its stored days.
+pub struct Month {
+    /// `YYYY-MM`
+    pub month: String,
+    pub days: usize,
+    pub score: f64,
+    pub availability_percent: f64,
+    pub p95_latency_ms: Option<f64>,
+    pub loss_percent: Option<f64>,
+    pub jitter_ms: Option<f64>,
+}
+
+fn average(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), value| (sum + value, count + 1));
+    (count > 0).then(|| sum / count as f64)
+}
+
+/// Averages days per month, scoring them with `settings`, oldest month first.
+pub fn months(days: &[Day], settings: &Settings) -> Vec<Month> {
+    let mut by_month: BTreeMap<String, Vec<&Day>> = BTreeMap::new();
+    for day in days {
+        by_month.entry(day.day.format("%Y-%m").to_string()).or_default().push(day);
+    }
+    by_month
+        .into_iter()
+        .map(|(month, days)| Month {
+            month,
+            days: days.len(),
+            score: average(days.iter().map(|day| day.score(settings))).unwrap_or(100.0),
+            availability_percent: average(days.iter().map(|day| day.availability_percent)).unwrap_or(100.0),
+            p95_latency_ms: average(days.iter().filter_map(|day| day.p95_latency_ms)),
+            loss_percent: average(days.iter().filter_map(|day| day.loss_percent)),
+            jitter_ms: average(days.iter().filter_map(|day| day.jitter_ms)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(availability_percent: f64, p95_latency_ms: Option<f64>, loss_percent: Option<f64>, jitter_ms: Option<f64>) -> Day {
+        Day { day: NaiveDate::from_ymd_opt(2026, 3, 1).unwrap(), availability_percent, p95_latency_ms, loss_percent, jitter_ms }
+    }
+
+    #[test]
+    fn scales_between_bad_and_good() {
+        let latency = Scale { weight: 1.0, good: 30.0, bad: 200.0 };
+        let uptime = Scale { weight: 1.0, good: 99.99, bad: 99.0 };
+        let exact = Scale { weight: 1.0, good: 5.0, bad: 5.0 };
+        for (scale, value, expected) in [
+            (latency, 30.0, 100.0),
+            (latency, 10.0, 100.0),
+            (latency, 115.0, 50.0),
+            (latency, 200.0, 0.0),
+            (latency, 500.0, 0.0),
+            (uptime, 100.0, 100.0),
+            (uptime, 99.0, 0.0),
+            (uptime, 50.0, 0.0),
+            (exact, 5.0, 100.0),
+            (exact, 4.0, 0.0),
+        ] {
+            assert!((scale.score(value) - expected).abs() < 1e-9, "{} on {}..{}", value, scale.bad, scale.good);
+        }
+    }
+
+    #[test]
+    fn weighs_the_known_measures() {
+        let settings = Settings::default();
+        for (day, expected) in [
+            (day(100.0, Some(10.0), Some(0.0), Some(1.0)), 100.0),
+            (day(99.0, Some(500.0), Some(10.0), Some(100.0)), 0.0),
+            // Only availability is known
+            (day(99.0, None, None, None), 0.0),
+            (day(100.0, None, None, None), 100.0),
+            // Perfect availability (50) and latency (20) against the worst loss (20)
+            (day(100.0, Some(30.0), Some(5.0), None), 70.0 / 90.0 * 100.0),
+        ] {
+            assert!((day.score(&settings) - expected).abs() < 1e-9, "{:?}", (day.availability_percent, day.p95_latency_ms));
+        }
+
+        let unweighted = Settings { availability: Scale { weight: 0.0, good: 100.0, bad: 0.0 }, ..settings };
+        assert_eq!(day(0.0, None, None, None).score(&unweighted), 100.0);
+    }
+
+    #[test]
+    fn averages_days_per_month() {
+        let mut days = vec![day(100.0, Some(10.0), None, None), day(99.0, None, None, None)];
+        days.push(Day { day: NaiveDate::from_ymd_opt(2026, 2, 10).unwrap(), ..day(99.5, None, Some(1.0), None) });
+        let months = months(&days, &Settings::default());
+        assert_eq!(months.iter().map(|month| (month.month.as_str(), month.days)).collect::<Vec<_>>(), [("2026-02", 1), ("2026-03", 2)]);
+        assert_eq!(months[1].score, 50.0);
+        assert_eq!(months[1].availability_percent, 99.5);
+        assert_eq!(months[1].p95_latency_ms, Some(10.0));
+        assert_eq!(months[1].loss_percent, None);
+    }
+
+    #[test]
+    fn reads_the_quality_table() {
+        let settings: Settings = toml::from_str("[latency]\nweight = 5\ngood = 10\nbad = 100").unwrap();
+        assert_eq!((settings.latency.weight, settings.latency.good, settings.latency.bad), (5.0, 10.0, 100.0));
+        assert_eq!(settings.availability.weight, Settings::default().availability.weight);
+
+        for malformed in [
+            "[latency]\nweight = 5\ngood = 10",
+            "[latency]\nweight = 5\ngood = 10\nbad = 100\nworst = 500",
+            "[latency]\nweight = \"high\"\ngood = 10\nbad = 100",
+            "[bandwidth]\nweight = 5\ngood = 10\nbad = 100",
+            "latency = 5",
+        ] {
+            assert!(toml::from_str::<Settings>(malformed).is_err(), "{:?}", malformed);
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use chrono::{DateTime, Local};
+use rusqlite::types::Value;
+use rusqlite::Connection;
+use std::time::Duration;
+
+use crate::model::InternetOutage;
+
+/// Name `webgone` uses for the machine the database belongs to.
+const LOCAL_MONITOR: &str = "local";
+
+/// Where an outage was observed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Monitor {
+    /// The machine running `webgone watch` against the database
+    Local,
+    /// A site whose agent reports to this database through `webgone collect`
+    Site(String),
+}
+
+impl From<&str> for Monitor {
+    /// `"local"` stands for this machine, like `--site local` does.
+    fn from(name: &str) -> Self {
+        if name == LOCAL_MONITOR {
+            Monitor::Local
+        } else {
+            Monitor::Site(name.to_string())
+        }
+    }
+}
+
+impl From<String> for Monitor {
+    fn from(name: String) -> Self {
+        Monitor::from(name.as_str())
+    }
+}
+
+/// Selects outages from a webgone database. Every condition narrows the
+/// result down further; without any, all outages are returned.
+#[derive(Clone, Debug, Default)]
+pub struct OutageQuery {
+    since: Option<DateTime<Local>>,
+    until: Option<DateTime<Local>>,
+    min_duration: Option<Duration>,
+    max_duration: Option<Duration>,
+    monitor: Option<Monitor>,
+    tags: Vec<String>,
+    newest_first: bool,
+    limit: Option<usize>,
+}
+
+impl OutageQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only outages that started at or after `time`.
+    pub fn since(mut self, time: DateTime<Local>) -> Self {
+        self.since = Some(time);
+        self
+    }
+
+    /// Only outages that started before `time`.
+    pub fn until(mut self, time: DateTime<Local>) -> Self {
+        self.until = Some(time);
+        self
+    }
+
+    /// Only outages lasting at least `duration`.
+    pub fn min_duration(mut self, duration: Duration) -> Self {
+        self.min_duration = Some(duration);
+        self
+    }
+
+    /// Only outages lasting at most `duration`.
+    pub fn max_duration(mut self, duration: Duration) -> Self {
+        self.max_duration = Some(duration);
+        self
+    }
+
+    /// Only outages observed by `monitor`, e.g. `"local"` or the name of a site.
+    pub fn monitor(mut self, monitor: impl Into<Monitor>) -> Self {
+        self.monitor = Some(monitor.into());
+        self
+    }
+
+    /// Only outages with `tag`; call it repeatedly to require several tags.
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// Returns the newest outages first instead of the oldest.
+    pub fn newest_first(mut self) -> Self {
+        self.newest_first = true;
+        self
+    }
+
+    /// Returns at most `count` outages.
+    pub fn limit(mut self, count: usize) -> Self {
+        self.limit = Some(count);
+        self
+    }
+
+    /// Runs the query against a database written by webgone. Outages with
+    /// timestamps that can't be read are left out.
+    pub fn run(&self, conn: &Connection) -> rusqlite::Result<impl Iterator<Item = InternetOutage>> {
+        let mut conditions = Vec::new();
+        let mut params = Vec::new();
+
+        if let Some(since) = self.since {
+            conditions.push("julianday(start_time) >= julianday(?)");
+            params.push(Value::Text(since.to_rfc3339()));
+        }
+        if let Some(until) = self.until {
+            conditions.push("julianday(start_time) < julianday(?)");
+            params.push(Value::Text(until.to_rfc3339()));
+        }
+        if let Some(min) = self.min_duration {
+            conditions.push("duration_seconds >= ?");
+            params.push(Value::Integer(min.as_secs() as i64));
+        }
+        if let Some(max) = self.max_duration {
+            conditions.push("duration_seconds <= ?");
+            params.push(Value::Integer(max.as_secs() as i64));
+        }
+        match &self.monitor {
+            Some(Monitor::Local) => conditions.push("site IS NULL"),
+            Some(Monitor::Site(site)) => {
+                conditions.push("site = ?");
+                params.push(Value::Text(site.clone()));
+            }
+            None => {}
+        }
+        for tag in &self.tags {
+            conditions.push("id IN (SELECT outage_id FROM outage_tags WHERE tag = ?)");
+            params.push(Value::Text(tag.clone()));
+        }
+
+        let mut sql = String::from("SELECT * FROM outages");
+        if !conditions.is_empty() {
+            sql.push_str(&format!(" WHERE {}", conditions.join(" AND ")));
+        }
+        sql.push_str(if self.newest_first { " ORDER BY julianday(start_time) DESC" } else { " ORDER BY julianday(start_time)" });
+        if let Some(limit) = self.limit {
+            sql.push_str(&format!(" LIMIT {}", limit));
+        }
+
+        let mut stmt = conn.prepare(&sql)?;
+        let (mut outages, _) = InternetOutage::readable(stmt.query_map(rusqlite::params_from_iter(params), InternetOutage::from_row)?)?;
+
+        let mut tags = conn.prepare("SELECT tag FROM outage_tags WHERE outage_id = ? ORDER BY tag")?;
+        for outage in &mut outages {
+            outage.tags = tags
+                .query_map([outage.id], |row| row.get(0))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+        }
+        Ok(outages.into_iter())
+    }
+}
@@ -0,0 +1,94 @@
+use anyhow::Result;
+use rusqlite::Connection;
+use std::fmt;
+use std::time::Duration;
+
+/// Past outages needed before an ongoing outage is compared to them.
+const MIN_HISTORY: usize = 5;
+
+/// Default durations after which an ongoing outage is reported.
+pub const DEFAULT_NOTICES: &str = "1m,5m,15m,30m,1h,2h,4h";
+
+/// Durations of past local outages, used to put an ongoing outage into context.
+pub struct History {
+    /// Sorted ascending
+    durations: Vec<i64>,
+}
+
+/// How an ongoing outage compares to past ones.
+pub enum Estimate {
+    /// Too few past outages to say anything
+    NotEnoughHistory,
+    /// No past outage lasted this long
+    Longest { outages: usize },
+    Typical {
+        /// Share of past outages that were shorter, in percent
+        longer_than_percent: f64,
+        /// Median time until past outages that lasted this long recovered
+        median_remaining: Duration,
+        /// Number of past outages that lasted this long
+        sample: usize,
+    },
+}
+
+impl History {
+    /// Loads the durations of outages recorded on this machine; outages of
+    /// other sites are left out, since their connections behave differently.
+    pub fn load(conn: &Connection) -> Result<Self> {
+        let mut stmt = conn.prepare("SELECT duration_seconds FROM outages WHERE site IS NULL ORDER BY duration_seconds")?;
+        let durations = stmt.query_map([], |row| row.get(0))?.collect::<rusqlite::Result<Vec<i64>>>()?;
+        Ok(History { durations })
+    }
+
+    pub fn estimate(&self, elapsed: Duration) -> Estimate {
+        if self.durations.len() < MIN_HISTORY {
+            return Estimate::NotEnoughHistory;
+        }
+
+        let elapsed = elapsed.as_secs() as i64;
+        let shorter = self.durations.partition_point(|&duration| duration < elapsed);
+        let remaining = &self.durations[shorter..];
+        if remaining.is_empty() {
+            return Estimate::Longest { outages: self.durations.len() };
+        }
+
+        let middle = remaining.len() / 2;
+        let median = if remaining.len().is_multiple_of(2) {
+            (remaining[middle - 1] + remaining[middle]) / 2
+        } else {
+            remaining[middle]
+        };
+        Estimate::Typical {
+            longer_than_percent: 100.0 * shorter as f64 / self.durations.len() as f64,
+            median_remaining: Duration::from_secs((median - elapsed).max(0) as u64),
+            sample: remaining.len(),
+        }
+    }
+}
+
+/// Formats a duration as `45 s`, `18 min` or `2 h 5 min`.
+pub fn describe(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        0..60 => format!("{} s", seconds),
+        60..3600 => format!("{} min", seconds / 60),
+        _ if seconds % 3600 < 60 => format!("{} h", seconds / 3600),
+        _ => format!("{} h {} min", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+impl fmt::Display for Estimate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Estimate::NotEnoughHistory => write!(f, "not enough past outages for an estimate yet"),
+            Estimate::Longest { outages } => write!(f, "longer than all {} past outages", outages),
+            Estimate::Typical { longer_than_percent, median_remaining, sample } => write!(
+                f,
+                "longer than {:.0}% of past outages; median recovery from this point: {} (based on {} outage(s) that lasted this long)",
+                longer_than_percent,
+                describe(*median_remaining),
+                sample
+            ),
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use anyhow::Result;
+use chrono::DateTime;
+use rusqlite::Connection;
+use webgone::model::{self, UnreadableTimestamp};
+
+/// Columns of `outages` holding timestamps.
+const TIMESTAMP_COLUMNS: &[&str] =
+    &["start_time", "end_time", "detected_at", "confirmed_at", "recovered_at", "weather_observed_at"];
+
+pub struct TimestampRepair {
+    /// Timestamps that were (or, in a dry run, would be) rewritten
+    pub rewritten: usize,
+    /// Timestamps that aren't times at all and have to be fixed by hand
+    pub unreadable: Vec<UnreadableTimestamp>,
+}
+
+/// Rewrites outage timestamps that other tools or older versions wrote in
+/// another format as RFC 3339, the way webgone writes them. Values without
+/// an offset are taken as UTC. Nothing is changed with `dry_run`.
+pub fn timestamps(conn: &Connection, dry_run: bool) -> Result<TimestampRepair> {
+    let tx = conn.unchecked_transaction()?;
+    let mut repair = TimestampRepair { rewritten: 0, unreadable: Vec::new() };
+
+    for column in TIMESTAMP_COLUMNS {
+        let values = {
+            let mut stmt = tx.prepare(&format!(
+                "SELECT id, {column} FROM outages WHERE {column} IS NOT NULL ORDER BY id"
+            ))?;
+            let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+            rows.collect::<rusqlite::Result<Vec<_>>>()?
+        };
+
+        let mut update = tx.prepare(&format!("UPDATE outages SET {column} = ? WHERE id = ?"))?;
+        for (id, value) in values {
+            if DateTime::parse_from_rfc3339(&value).is_ok() {
+                continue;
+            }
+            match model::parse_timestamp(&value) {
+                Some(time) => {
+                    if !dry_run {
+                        update.execute(rusqlite::params![time.to_rfc3339(), id])?;
+                    }
+                    repair.rewritten += 1;
+                }
+                None => repair.unreadable.push(UnreadableTimestamp { id, column: column.to_string(), value }),
+            }
+        }
+    }
+
+    tx.commit()?;
+    repair.unreadable.sort_by_key(|row| row.id);
+    Ok(repair)
+}
@@ -0,0 +1,233 @@
+use anyhow::Result;
+use chrono::{DateTime, Days, Local, NaiveDate, TimeZone};
+use rusqlite::{params, Connection};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::epochs;
+use crate::error::WebgoneError;
+
+/// Flat tables of the replica, with times as Unix seconds in `time` columns
+/// the way Grafana's SQLite datasource expects them.
+const SCHEMA: &str = "
+    CREATE TABLE outages (
+        id INTEGER PRIMARY KEY,
+        time INTEGER NOT NULL,
+        end_time INTEGER NOT NULL,
+        start TEXT NOT NULL,
+        end TEXT NOT NULL,
+        site TEXT NOT NULL,
+        duration_seconds INTEGER NOT NULL,
+        duration_minutes REAL NOT NULL,
+        day TEXT NOT NULL,
+        month TEXT NOT NULL,
+        weekday INTEGER NOT NULL,
+        hour INTEGER NOT NULL,
+        epoch TEXT,
+        tags TEXT,
+        failed_components TEXT,
+        acknowledged INTEGER NOT NULL,
+        clock_anomaly INTEGER NOT NULL
+    );
+    CREATE TABLE daily_downtime (
+        time INTEGER NOT NULL,
+        day TEXT NOT NULL,
+        site TEXT NOT NULL,
+        outages INTEGER NOT NULL,
+        downtime_seconds INTEGER NOT NULL,
+        uptime_percent REAL NOT NULL,
+        PRIMARY KEY (site, day)
+    );
+    CREATE TABLE monthly_summary (
+        time INTEGER NOT NULL,
+        month TEXT NOT NULL,
+        site TEXT NOT NULL,
+        outages INTEGER NOT NULL,
+        downtime_seconds INTEGER NOT NULL,
+        longest_seconds INTEGER NOT NULL,
+        uptime_percent REAL NOT NULL,
+        PRIMARY KEY (site, month)
+    );
+    CREATE TABLE latency_hourly (
+        time INTEGER NOT NULL,
+        target TEXT NOT NULL,
+        probes INTEGER NOT NULL,
+        failures INTEGER NOT NULL,
+        loss_percent REAL NOT NULL,
+        avg_latency_ms REAL,
+        PRIMARY KEY (target, time)
+    );
+    CREATE TABLE epochs (
+        time INTEGER NOT NULL,
+        start_date TEXT NOT NULL,
+        label TEXT NOT NULL
+    );
+    CREATE TABLE replica_info (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+    CREATE INDEX outages_time ON outages (time);
+    CREATE INDEX daily_downtime_time ON daily_downtime (time);
+    CREATE INDEX latency_hourly_time ON latency_hourly (time);
+";
+
+/// What a replica was built from.
+pub struct ReplicaSummary {
+    pub outages: usize,
+    pub days: usize,
+    pub latency_hours: usize,
+}
+
+/// Writes a denormalized copy of the database at `db_path` to `path` for
+/// dashboards, e.g. Grafana's SQLite datasource. The copy is built next to
+/// `path` and moved over it when done, so readers never see a partial one.
+pub fn build(db_path: &Path, path: &Path, now: DateTime<Local>) -> Result<ReplicaSummary> {
+    let partial = partial_path(path);
+    let _ = fs::remove_file(&partial);
+    let summary = write(db_path, &partial, now);
+    if summary.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    let summary = summary?;
+    fs::rename(&partial, path)
+        .map_err(|e| WebgoneError::Io(format!("failed to replace {}: {}", path.display(), e)))?;
+    Ok(summary)
+}
+
+fn partial_path(path: &Path) -> PathBuf {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    PathBuf::from(partial)
+}
+
+fn write(db_path: &Path, path: &Path, now: DateTime<Local>) -> Result<ReplicaSummary> {
+    let replica = Connection::open(path)
+        .map_err(|e| WebgoneError::DatabaseOpen { path: path.to_path_buf(), reason: e.to_string() })?;
+    replica.execute_batch(SCHEMA)?;
+    replica.execute("ATTACH DATABASE ? AS source", [db_path.to_string_lossy()])?;
+
+    let tx = replica.unchecked_transaction()?;
+    let outages = tx.execute(
+        "INSERT INTO outages
+        SELECT o.id,
+            CAST(strftime('%s', o.start_time) AS INTEGER),
+            CAST(strftime('%s', o.end_time) AS INTEGER),
+            o.start_time,
+            o.end_time,
+            COALESCE(o.site, 'local'),
+            o.duration_seconds,
+            o.duration_seconds / 60.0,
+            date(o.start_time, 'localtime'),
+            strftime('%Y-%m', o.start_time, 'localtime'),
+            CAST(strftime('%w', o.start_time, 'localtime') AS INTEGER),
+            CAST(strftime('%H', o.start_time, 'localtime') AS INTEGER),
+            (SELECT e.label FROM source.epochs e WHERE e.start_date <= date(o.start_time, 'localtime')
+                ORDER BY e.start_date DESC LIMIT 1),
+            (SELECT group_concat(t.tag, ', ') FROM source.outage_tags t WHERE t.outage_id = o.id),
+            o.failed_components,
+            EXISTS (SELECT 1 FROM source.outage_acks a WHERE a.outage_id = o.id),
+            o.clock_anomaly
+        FROM source.outages o
+        WHERE strftime('%s', o.start_time) IS NOT NULL AND strftime('%s', o.end_time) IS NOT NULL",
+        [],
+    )?;
+    let days = daily_downtime(&tx, now)?;
+    tx.execute_batch(
+        "INSERT INTO monthly_summary
+        SELECT MIN(d.time), substr(d.day, 1, 7), d.site, SUM(d.outages), SUM(d.downtime_seconds),
+            COALESCE((SELECT MAX(o.duration_seconds) FROM outages o
+                WHERE o.site = d.site AND o.month = substr(d.day, 1, 7)), 0),
+            AVG(d.uptime_percent)
+        FROM daily_downtime d
+        GROUP BY d.site, substr(d.day, 1, 7);
+
+        INSERT INTO epochs
+        SELECT CAST(strftime('%s', start_date, 'utc') AS INTEGER), start_date, label
+        FROM source.epochs ORDER BY start_date;",
+    )?;
+    let latency_hours = tx.execute(
+        "INSERT INTO latency_hourly
+        SELECT CAST(strftime('%s', hour) AS INTEGER), target, SUM(probes), SUM(failures),
+            100.0 * SUM(failures) / SUM(probes),
+            SUM(latency_total) / NULLIF(SUM(latency_count), 0)
+        FROM (
+            SELECT strftime('%Y-%m-%dT%H:00:00Z', time) AS hour, target,
+                SUM(weight) AS probes, SUM(CASE WHEN success THEN 0 ELSE weight END) AS failures,
+                SUM(CASE WHEN success THEN latency_ms * weight END) AS latency_total,
+                SUM(CASE WHEN success AND latency_ms IS NOT NULL THEN weight END) AS latency_count
+            FROM source.probes WHERE site IS NULL GROUP BY 1, 2
+            UNION ALL
+            SELECT strftime('%Y-%m-%dT%H:00:00Z', period), target, SUM(probes), SUM(failures),
+                SUM(avg_latency_ms * (probes - failures)), SUM(CASE WHEN avg_latency_ms IS NOT NULL THEN probes - failures END)
+            FROM source.probe_minutely GROUP BY 1, 2
+            UNION ALL
+            SELECT period, target, probes, failures,
+                avg_latency_ms * (probes - failures), CASE WHEN avg_latency_ms IS NOT NULL THEN probes - failures END
+            FROM source.probe_hourly
+        )
+        WHERE hour IS NOT NULL
+        GROUP BY hour, target
+        HAVING SUM(probes) > 0",
+        [],
+    )?;
+    tx.execute(
+        "INSERT INTO replica_info (key, value) VALUES ('built_at', ?1), ('source', ?2)",
+        params![now.to_rfc3339(), db_path.to_string_lossy()],
+    )?;
+    tx.commit()?;
+    replica.execute("DETACH DATABASE source", [])?;
+
+    Ok(ReplicaSummary { outages, days, latency_hours })
+}
+
+/// Fills `daily_downtime` with one row per site and day from the first
+/// outage of the site until `now`, splitting outages at local midnight.
+fn daily_downtime(conn: &Connection, now: DateTime<Local>) -> Result<usize> {
+    let mut per_day: BTreeMap<(String, NaiveDate), (i64, i64)> = BTreeMap::new();
+    let mut first_days: BTreeMap<String, NaiveDate> = BTreeMap::new();
+    {
+        let mut stmt = conn.prepare("SELECT site, time, end_time FROM outages ORDER BY time")?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?, row.get::<_, i64>(2)?)))?;
+        for row in rows {
+            let (site, start, end) = row?;
+            let (Some(start), Some(end)) = (Local.timestamp_opt(start, 0).single(), Local.timestamp_opt(end, 0).single())
+            else {
+                continue;
+            };
+            first_days.entry(site.clone()).or_insert(start.date_naive());
+            per_day.entry((site.clone(), start.date_naive())).or_default().0 += 1;
+
+            let mut day = start.date_naive();
+            while epochs::start_of_day(day) < end {
+                let next = day + Days::new(1);
+                let from = start.max(epochs::start_of_day(day));
+                let to = end.min(epochs::start_of_day(next));
+                if to > from {
+                    per_day.entry((site.clone(), day)).or_default().1 += (to - from).num_seconds();
+                }
+                day = next;
+            }
+        }
+    }
+
+    let mut stmt = conn.prepare(
+        "INSERT INTO daily_downtime (time, day, site, outages, downtime_seconds, uptime_percent)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+    let mut rows = 0;
+    for (site, first_day) in first_days {
+        let mut day = first_day;
+        while day <= now.date_naive() {
+            let next = day + Days::new(1);
+            let (from, to) = (epochs::start_of_day(day), epochs::start_of_day(next));
+            let (outages, downtime) = per_day.get(&(site.clone(), day)).copied().unwrap_or_default();
+            let length = (to - from).num_seconds().max(1);
+            let uptime = 100.0 * (1.0 - downtime.min(length) as f64 / length as f64);
+            stmt.execute(params![from.timestamp(), day.to_string(), site, outages, downtime, uptime])?;
+            rows += 1;
+            day = next;
+        }
+    }
+    Ok(rows)
+}
@@ -0,0 +1,89 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use printpdf::{BuiltinFont, Mm, PdfDocument};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+use crate::error::WebgoneError;
+
+/// Output format of `webgone report`.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Format {
+    Text,
+    Markdown,
+    Html,
+    /// The text report laid out on A4 pages
+    Pdf,
+}
+
+impl Format {
+    fn builtin_template(self) -> &'static str {
+        match self {
+            Format::Text | Format::Pdf => include_str!("../templates/report.txt"),
+            Format::Markdown => include_str!("../templates/report.md"),
+            Format::Html => include_str!("../templates/report.html"),
+        }
+    }
+}
+
+/// Renders the report context with the built-in template for `format`, or
+/// with a user-supplied [Tera](https://keats.github.io/tera/) template.
+/// PDF reports lay out the rendered text, so custom PDF templates are plain text.
+pub fn render(format: Format, template: Option<&Path>, context: &Value) -> Result<Vec<u8>> {
+    let source = match template {
+        Some(path) => fs::read_to_string(path)
+            .with_context(|| format!("Failed to read report template {}", path.display()))?,
+        None => format.builtin_template().to_string(),
+    };
+
+    let context = tera::Context::from_value(context.clone()).context("Invalid report context")?;
+    // Escape outage data (SSIDs, tags, notes) only where it ends up in HTML
+    let autoescape = matches!(format, Format::Html);
+    let rendered = tera::Tera::one_off(&source, &context, autoescape).map_err(|e| {
+        WebgoneError::InvalidInput(format!("failed to render report template: {:#}", anyhow::Error::new(e)))
+    })?;
+
+    match format {
+        Format::Pdf => text_to_pdf(&rendered),
+        _ => Ok(rendered.into_bytes()),
+    }
+}
+
+const PAGE_WIDTH: Mm = Mm(210.0);
+const PAGE_HEIGHT: Mm = Mm(297.0);
+const MARGIN: Mm = Mm(15.0);
+const FONT_SIZE: f32 = 9.0;
+const LINE_HEIGHT: Mm = Mm(4.0);
+/// Characters of the monospaced font that fit between the margins
+const LINE_WIDTH: usize = 94;
+
+/// Lays out text in a monospaced font on as many A4 pages as needed,
+/// wrapping lines that don't fit between the margins.
+fn text_to_pdf(text: &str) -> Result<Vec<u8>> {
+    let (doc, page, layer) = PdfDocument::new("Internet Outage Report", PAGE_WIDTH, PAGE_HEIGHT, "Report");
+    let font = doc.add_builtin_font(BuiltinFont::Courier).context("Failed to load PDF font")?;
+
+    let lines = text.lines().flat_map(|line| {
+        let chars: Vec<char> = line.chars().collect();
+        if chars.is_empty() {
+            vec![String::new()]
+        } else {
+            chars.chunks(LINE_WIDTH).map(|chunk| chunk.iter().collect()).collect()
+        }
+    });
+
+    let mut layer = doc.get_page(page).get_layer(layer);
+    let mut y = PAGE_HEIGHT - MARGIN;
+    for line in lines {
+        if y < MARGIN {
+            let (page, new_layer) = doc.add_page(PAGE_WIDTH, PAGE_HEIGHT, "Report");
+            layer = doc.get_page(page).get_layer(new_layer);
+            y = PAGE_HEIGHT - MARGIN;
+        }
+        layer.use_text(line, FONT_SIZE, MARGIN, y, &font);
+        y -= LINE_HEIGHT;
+    }
+
+    doc.save_to_bytes().context("Failed to write PDF")
+}
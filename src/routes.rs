@@ -0,0 +1,286 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration as ChronoDuration, Local};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Anchors traced by default with `--trace-routes`, one per provider so a
+/// change in either peering shows up.
+pub const DEFAULT_ANCHORS: &str = "1.1.1.1,8.8.8.8";
+
+/// Tag added to outages close to a route change.
+pub const ROUTE_CHANGE_TAG: &str = "route change";
+
+/// How close to a route change outages are tagged with it and latency is
+/// compared before and after it.
+const CHANGE_WINDOW_MINUTES: i64 = 30;
+
+/// Share of responding hops two traces need to have in common to count as
+/// the same path; load balancers shuffling one or two hops stay below it.
+const MIN_SIMILARITY: f64 = 0.6;
+
+const MAX_HOPS: u32 = 30;
+
+/// Path to an anchor, one entry per hop; `None` for hops that didn't answer.
+pub struct Trace {
+    pub anchor: IpAddr,
+    pub hops: Vec<Option<IpAddr>>,
+}
+
+impl Trace {
+    /// Hops as stored in the database, e.g. `192.168.1.1 * 10.0.0.1`.
+    fn signature(&self) -> String {
+        self.hops
+            .iter()
+            .map(|hop| hop.map_or_else(|| "*".to_string(), |ip| ip.to_string()))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Whether the anchor itself answered, so the trace covers the whole path.
+    fn reached(&self) -> bool {
+        self.hops.last() == Some(&Some(self.anchor))
+    }
+
+    fn responding(&self) -> HashSet<IpAddr> {
+        self.hops.iter().flatten().copied().collect()
+    }
+}
+
+/// How a new trace differs from the previous one to the same anchor.
+pub struct RouteChange {
+    /// Hops that are only on one of the paths
+    pub changed_hops: usize,
+    /// Hops on either path
+    pub total_hops: usize,
+}
+
+/// A recorded route change with the median latency around it.
+pub struct RouteChangeSummary {
+    pub traced_at: DateTime<Local>,
+    pub anchor: String,
+    pub changed_hops: i64,
+    /// Length of the new path
+    pub hop_count: i64,
+    pub latency_before_ms: Option<f64>,
+    pub latency_after_ms: Option<f64>,
+    pub outages: i64,
+}
+
+pub fn init_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS route_traces (
+            id INTEGER PRIMARY KEY,
+            traced_at TEXT NOT NULL,
+            anchor TEXT NOT NULL,
+            hops TEXT NOT NULL,
+            changed_hops INTEGER,
+            hop_count INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Traces the route to each anchor in turn, one every `interval`, with the
+/// system's traceroute running in the background so probes aren't delayed.
+pub struct Tracer {
+    anchors: Vec<IpAddr>,
+    interval: Duration,
+    next: usize,
+    last_start: Option<Instant>,
+    running: Option<(IpAddr, Child)>,
+}
+
+impl Tracer {
+    pub fn new(anchors: Vec<IpAddr>, interval: Duration) -> Self {
+        Tracer { anchors, interval, next: 0, last_start: None, running: None }
+    }
+
+    /// Returns the trace that finished since the last call, and starts the
+    /// next one when it is due.
+    pub fn poll(&mut self) -> Result<Option<Trace>> {
+        if let Some((_, child)) = &mut self.running {
+            if child.try_wait()?.is_none() {
+                return Ok(None);
+            }
+            let (anchor, child) = self.running.take().expect("checked above");
+            let output = child.wait_with_output()?;
+            return Ok(Some(Trace { anchor, hops: parse_hops(&String::from_utf8_lossy(&output.stdout)) }));
+        }
+
+        // Anchors share the interval, so each one is traced once per `interval`
+        let spacing = self.interval / self.anchors.len().max(1) as u32;
+        if self.anchors.is_empty() || self.last_start.is_some_and(|at| at.elapsed() < spacing) {
+            return Ok(None);
+        }
+        self.last_start = Some(Instant::now());
+        let anchor = self.anchors[self.next % self.anchors.len()];
+        self.next += 1;
+        let child = traceroute(anchor).spawn().context("Failed to start traceroute")?;
+        self.running = Some((anchor, child));
+        Ok(None)
+    }
+
+    /// Stops a running trace, e.g. because the connection was lost halfway.
+    pub fn cancel(&mut self) {
+        if let Some((_, mut child)) = self.running.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+impl Drop for Tracer {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+fn traceroute(anchor: IpAddr) -> Command {
+    let mut command = if cfg!(windows) {
+        let mut tracert = Command::new("tracert");
+        tracert.args(["-d", "-h", &MAX_HOPS.to_string(), "-w", "2000"]);
+        tracert
+    } else {
+        // One query per hop keeps a full trace to a few seconds
+        let mut traceroute = Command::new("traceroute");
+        traceroute.args(["-n", "-q", "1", "-w", "2", "-m", &MAX_HOPS.to_string()]);
+        traceroute
+    };
+    command.arg(anchor.to_string()).stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::null());
+    command
+}
+
+/// Reads hops from traceroute or tracert output. Hop lines start with the
+/// hop number and end with the address that answered, e.g.
+/// ` 3  10.20.0.1  8.412 ms` or `  3    8 ms    7 ms    8 ms  10.20.0.1`.
+fn parse_hops(output: &str) -> Vec<Option<IpAddr>> {
+    output
+        .lines()
+        .filter(|line| line.split_whitespace().next().is_some_and(|hop| hop.parse::<u32>().is_ok()))
+        .map(|line| line.split_whitespace().skip(1).find_map(|field| field.parse().ok()))
+        .collect()
+}
+
+/// Compares two traces by the hops that answered in either, since hops
+/// that don't answer to every probe would otherwise look like changes.
+fn compare(previous: &Trace, current: &Trace) -> Option<RouteChange> {
+    let (before, after) = (previous.responding(), current.responding());
+    let total_hops = before.union(&after).count();
+    let common = before.intersection(&after).count();
+    if total_hops == 0 || common as f64 / total_hops as f64 >= MIN_SIMILARITY {
+        return None;
+    }
+    Some(RouteChange { changed_hops: total_hops - common, total_hops })
+}
+
+fn previous_trace(conn: &Connection, anchor: IpAddr) -> Result<Option<Trace>> {
+    let hops: Option<String> = conn
+        .query_row(
+            "SELECT hops FROM route_traces WHERE anchor = ? ORDER BY traced_at DESC, id DESC LIMIT 1",
+            [anchor.to_string()],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(hops.map(|hops| Trace { anchor, hops: hops.split(' ').map(|hop| hop.parse().ok()).collect() }))
+}
+
+/// Stores a trace and returns how it differs from the previous one to the
+/// same anchor, if the path changed significantly. Traces that didn't reach
+/// the anchor, e.g. because the network blocks traceroute, aren't stored, as
+/// a partial path would look like a change.
+pub fn record(conn: &Connection, traced_at: DateTime<Local>, trace: &Trace) -> Result<Option<RouteChange>> {
+    if !trace.reached() {
+        return Ok(None);
+    }
+    let change = previous_trace(conn, trace.anchor)?.and_then(|previous| compare(&previous, trace));
+    conn.execute(
+        "INSERT INTO route_traces (traced_at, anchor, hops, changed_hops, hop_count) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            traced_at.to_rfc3339(),
+            trace.anchor.to_string(),
+            trace.signature(),
+            change.as_ref().map(|change| change.changed_hops as i64),
+            trace.hops.len() as i64,
+        ],
+    )?;
+    Ok(change)
+}
+
+/// Tags outages that ended after `since` and lie within
+/// `CHANGE_WINDOW_MINUTES` of a route change. Returns how many outages were
+/// newly tagged.
+pub fn tag_outages(conn: &Connection, since: DateTime<Local>) -> Result<usize> {
+    let tagged = conn.execute(
+        "INSERT OR IGNORE INTO outage_tags (outage_id, tag)
+        SELECT DISTINCT o.id, ?1
+        FROM outages o
+        JOIN route_traces r ON r.changed_hops IS NOT NULL
+            AND julianday(r.traced_at) BETWEEN julianday(o.start_time) - ?2 AND julianday(o.end_time) + ?2
+        WHERE julianday(o.end_time) >= julianday(?3) - ?2",
+        params![ROUTE_CHANGE_TAG, CHANGE_WINDOW_MINUTES as f64 / (24.0 * 60.0), since.to_rfc3339()],
+    )?;
+    Ok(tagged)
+}
+
+fn median_latency(conn: &Connection, from: DateTime<Local>, to: DateTime<Local>) -> Result<Option<f64>> {
+    let mut stmt = conn.prepare(
+        "SELECT latency_ms FROM probes
+        WHERE success AND julianday(time) BETWEEN julianday(?1) AND julianday(?2)
+        ORDER BY latency_ms",
+    )?;
+    let latencies = stmt
+        .query_map([from.to_rfc3339(), to.to_rfc3339()], |row| row.get::<_, f64>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(match latencies.len() {
+        0 => None,
+        n if n % 2 == 0 => Some((latencies[n / 2 - 1] + latencies[n / 2]) / 2.0),
+        n => Some(latencies[n / 2]),
+    })
+}
+
+/// Route changes of the last `days` days, newest first, with the median
+/// latency in the half hour before and after each.
+pub fn changes(conn: &Connection, days: u32) -> Result<Vec<RouteChangeSummary>> {
+    let mut stmt = conn.prepare(
+        "SELECT r.traced_at, r.anchor, r.changed_hops, r.hop_count,
+            (SELECT COUNT(*) FROM outages o
+             WHERE julianday(r.traced_at) BETWEEN julianday(o.start_time) - ?2 AND julianday(o.end_time) + ?2)
+        FROM route_traces r
+        WHERE r.changed_hops IS NOT NULL AND julianday(r.traced_at) >= julianday('now', ?1)
+        ORDER BY r.traced_at DESC",
+    )?;
+    let rows = stmt
+        .query_map(
+            params![format!("-{} days", days), CHANGE_WINDOW_MINUTES as f64 / (24.0 * 60.0)],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, i64>(3)?,
+                    row.get::<_, i64>(4)?,
+                ))
+            },
+        )?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let window = ChronoDuration::minutes(CHANGE_WINDOW_MINUTES);
+    rows.into_iter()
+        .map(|(traced_at, anchor, changed_hops, hop_count, outages)| {
+            let traced_at = DateTime::parse_from_rfc3339(&traced_at)?.with_timezone(&Local);
+            Ok(RouteChangeSummary {
+                traced_at,
+                anchor,
+                changed_hops,
+                hop_count,
+                latency_before_ms: median_latency(conn, traced_at - window, traced_at)?,
+                latency_after_ms: median_latency(conn, traced_at, traced_at + window)?,
+                outages,
+            })
+        })
+        .collect()
+}
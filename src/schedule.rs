@@ -0,0 +1,399 @@
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Local, NaiveTime, TimeZone, Weekday};
+use clap::ValueEnum;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::email;
+use crate::error::WebgoneError;
+use crate::filter;
+use crate::quality;
+use crate::report;
+use crate::scrub;
+use crate::severity;
+use crate::storage::Granularity;
+
+/// When a scheduled task runs.
+#[derive(Clone, Copy)]
+pub enum When {
+    /// This long after the last run
+    Every(std::time::Duration),
+    Daily(NaiveTime),
+    Weekly(Weekday, NaiveTime),
+}
+
+impl FromStr for When {
+    type Err = String;
+
+    /// Parses `daily 04:00`, `weekly sun 04:00`, `hourly` or `every 6h`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let time = |value: &str| {
+            NaiveTime::parse_from_str(value, "%H:%M").map_err(|_| format!("invalid time of day: {} (use HH:MM)", value))
+        };
+        let words: Vec<&str> = value.split_whitespace().collect();
+        match words.as_slice() {
+            ["hourly"] => Ok(When::Every(std::time::Duration::from_secs(60 * 60))),
+            ["every", duration] => Ok(When::Every(filter::parse_duration(duration)?)),
+            ["daily", at] => Ok(When::Daily(time(at)?)),
+            ["weekly", day, at] => Ok(When::Weekly(
+                day.parse().map_err(|_| format!("invalid weekday: {}", day))?,
+                time(at)?,
+            )),
+            _ => Err(format!(
+                "invalid schedule: {} (use e.g. `daily 04:00`, `weekly sun 04:00`, `hourly` or `every 6h`)",
+                value
+            )),
+        }
+    }
+}
+
+impl When {
+    /// Whether the task is due at `now`, given when it last ran.
+    fn is_due(&self, now: DateTime<Local>, ran_at: DateTime<Local>) -> bool {
+        let (at, weekday) = match *self {
+            When::Every(interval) => return (now - ran_at).to_std().is_ok_and(|elapsed| elapsed >= interval),
+            When::Daily(at) => (at, None),
+            When::Weekly(weekday, at) => (at, Some(weekday)),
+        };
+        ran_at < Self::latest(now, at, weekday)
+    }
+
+    /// The most recent time at or before `now` that is at `at` on `weekday`, or on any day.
+    fn latest(now: DateTime<Local>, at: NaiveTime, weekday: Option<Weekday>) -> DateTime<Local> {
+        let mut day = now.date_naive();
+        loop {
+            let matches_day = weekday.is_none_or(|weekday| day.weekday() == weekday);
+            // A day on which a DST change skips the time has no run
+            if let Some(time) = Local.from_local_datetime(&day.and_time(at)).earliest() {
+                if matches_day && time <= now {
+                    return time;
+                }
+            }
+            day = day.pred_opt().expect("date in range");
+        }
+    }
+}
+
+/// Auxiliary tasks the watcher can run on a schedule.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Task {
+    /// Copy the database into the backup directory
+    Backup,
+    /// Write `webgone report` to a file
+    Report,
+    /// Roll old raw probes up and shrink the database, like `webgone db compact`
+    Compact,
+    /// Write the JSON export the static status page reads
+    StatusPage,
+    /// Remove metadata from old records, like `webgone db scrub`
+    Scrub,
+}
+
+impl Task {
+    const ALL: [Task; 5] = [Task::Backup, Task::Report, Task::Compact, Task::StatusPage, Task::Scrub];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Task::Backup => "backup",
+            Task::Report => "report",
+            Task::Compact => "compact",
+            Task::StatusPage => "statuspage",
+            Task::Scrub => "scrub",
+        }
+    }
+
+    /// Metadata key holding when the task last ran.
+    fn metadata_key(self) -> String {
+        format!("schedule_{}_ran_at", self.name())
+    }
+}
+
+impl fmt::Display for Task {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Task {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Task::ALL.into_iter().find(|task| task.name() == value).ok_or_else(|| {
+            let names: Vec<&str> = Task::ALL.iter().map(|task| task.name()).collect();
+            format!("unknown task in [schedule]: {} (available: {})", value, names.join(", "))
+        })
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct BackupConfig {
+    /// Directory for the copies [default: `backups` next to the database]
+    pub dir: Option<PathBuf>,
+    /// Number of copies to keep, 0 to keep all
+    pub keep: Option<usize>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReportConfig {
+    /// File to write; strftime placeholders like `%Y-%m` are replaced with the time of the run
+    pub output: Option<String>,
+    /// `text`, `markdown`, `html` or `pdf`
+    pub format: Option<String>,
+    pub template: Option<PathBuf>,
+    /// Only cover outages of this many days before the run [default: all]
+    pub days: Option<u32>,
+    /// Imported baseline to compare with [default: the most recently imported one]
+    pub baseline: Option<String>,
+    /// Also mail the report through the `[email]` server, as the text of the message
+    pub mail: bool,
+}
+
+impl ReportConfig {
+    pub fn format(&self) -> Result<report::Format> {
+        match &self.format {
+            Some(name) => Ok(report::Format::from_str(name, true)
+                .map_err(|_| WebgoneError::InvalidInput(format!("invalid [report] format: {}", name)))?),
+            None => Ok(report::Format::Text),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct CompactConfig {
+    /// Roll up raw probes older than this many days
+    pub older_than: Option<u32>,
+    /// `minute` or `hour`
+    pub granularity: Option<String>,
+}
+
+impl CompactConfig {
+    pub fn granularity(&self) -> Result<Granularity> {
+        match &self.granularity {
+            Some(name) => Ok(Granularity::from_str(name, true)
+                .map_err(|_| WebgoneError::InvalidInput(format!("invalid [compact] granularity: {}", name)))?),
+            None => Ok(Granularity::Hour),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatusPageConfig {
+    /// JSON file the status page loads, e.g. web/outages.json
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScrubConfig {
+    /// Scrub records older than this many days
+    pub older_than: Option<u32>,
+    pub fields: Vec<scrub::Field>,
+}
+
+/// Configuration file of `watch --config`: the `[schedule]` table maps tasks
+/// to when they run, and a table per task holds its settings. The `[quality]`
+/// table weighs the daily quality score, and `[severity]` sets how outages
+/// are classified and who is notified of them. `[email]` holds the SMTP
+/// server of `watch --notify email`.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub schedule: BTreeMap<String, String>,
+    pub backup: BackupConfig,
+    pub report: ReportConfig,
+    pub compact: CompactConfig,
+    pub statuspage: StatusPageConfig,
+    pub scrub: ScrubConfig,
+    pub quality: quality::Settings,
+    pub severity: severity::Settings,
+    pub email: email::Settings,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| WebgoneError::Io(format!("failed to read {}: {}", path.display(), e)))?;
+        let config: Config = toml::from_str(&text)
+            .map_err(|e| WebgoneError::InvalidInput(format!("invalid config {}: {}", path.display(), e)))?;
+        Ok(config)
+    }
+
+    /// The scheduled tasks, checked for the settings they need.
+    pub fn jobs(&self) -> Result<Vec<(Task, When)>> {
+        let mut jobs = Vec::new();
+        for (name, when) in &self.schedule {
+            let task: Task = name.parse().map_err(WebgoneError::InvalidInput)?;
+            let when: When = when
+                .parse()
+                .map_err(|e| WebgoneError::InvalidInput(format!("[schedule] {}: {}", name, e)))?;
+            let missing = match task {
+                Task::Report => {
+                    let format = self.report.format()?;
+                    if self.report.mail {
+                        if !matches!(format, report::Format::Text | report::Format::Markdown) {
+                            return Err(WebgoneError::InvalidInput(
+                                "[report] mail needs the text or markdown format".to_string(),
+                            )
+                            .into());
+                        }
+                        self.email.mailer(None)?;
+                    }
+                    (self.report.output.is_none() && !self.report.mail).then_some("[report] output or mail")
+                }
+                Task::Compact => {
+                    self.compact.granularity()?;
+                    None
+                }
+                Task::StatusPage => self.statuspage.output.is_none().then_some("[statuspage] output"),
+                Task::Scrub if self.scrub.fields.is_empty() => Some("[scrub] fields"),
+                Task::Scrub => self.scrub.older_than.is_none().then_some("[scrub] older_than"),
+                Task::Backup => None,
+            };
+            if let Some(setting) = missing {
+                return Err(WebgoneError::InvalidInput(format!("{} is scheduled but {} isn't set", task, setting)).into());
+            }
+            jobs.push((task, when));
+        }
+        Ok(jobs)
+    }
+}
+
+/// Whether a task is due at `now`.
+///
+/// Like digests, a task doesn't run when the watcher first starts with it,
+/// but at its next scheduled time. A run missed while the watcher was
+/// stopped is made up once it starts again.
+pub fn due(conn: &Connection, task: Task, when: When, now: DateTime<Local>) -> Result<bool> {
+    let ran_at: Option<String> = conn
+        .query_row("SELECT value FROM metadata WHERE key = ?", [task.metadata_key()], |row| row.get(0))
+        .optional()?;
+    match ran_at.and_then(|value| DateTime::parse_from_rfc3339(&value).ok()) {
+        Some(ran_at) => Ok(when.is_due(now, ran_at.with_timezone(&Local))),
+        None => {
+            mark_ran(conn, task, now)?;
+            Ok(false)
+        }
+    }
+}
+
+pub fn mark_ran(conn: &Connection, task: Task, at: DateTime<Local>) -> Result<()> {
+    conn.execute(
+        "INSERT INTO metadata (key, value) VALUES (?1, ?2)
+        ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+        params![task.metadata_key(), at.to_rfc3339()],
+    )?;
+    Ok(())
+}
+
+/// Copies the database into `dir` as `<name>-<time>.db` and deletes all but
+/// the newest `keep` copies. Returns the path of the copy.
+pub fn backup(conn: &Connection, db_path: &Path, dir: &Path, keep: usize, now: DateTime<Local>) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| WebgoneError::Io(format!("failed to create {}: {}", dir.display(), e)))?;
+    let stem = db_path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("webgone");
+    let path = dir.join(format!("{}-{}.db", stem, now.format("%Y%m%d-%H%M%S")));
+    // VACUUM INTO writes a consistent copy while the watcher keeps the database open
+    conn.execute("VACUUM INTO ?", [path.to_string_lossy()])?;
+
+    if keep > 0 {
+        let prefix = format!("{}-", stem);
+        let mut copies: Vec<PathBuf> = std::fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|file| {
+                file.extension().is_some_and(|extension| extension == "db")
+                    && file.file_name().and_then(|name| name.to_str()).is_some_and(|name| name.starts_with(&prefix))
+            })
+            .collect();
+        // The timestamps in the names sort oldest first
+        copies.sort();
+        for old in copies.iter().take(copies.len().saturating_sub(keep)) {
+            std::fs::remove_file(old)?;
+        }
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn local(value: &str) -> DateTime<Local> {
+        let time = chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap();
+        Local.from_local_datetime(&time).earliest().unwrap()
+    }
+
+    #[test]
+    fn parses_schedules() {
+        let at = |value| NaiveTime::parse_from_str(value, "%H:%M").unwrap();
+        for (input, expected) in [
+            ("hourly", "every 3600s"),
+            ("every 6h", "every 21600s"),
+            ("every 90", "every 90s"),
+            ("daily 04:00", "daily 04:00"),
+            ("  daily   23:59 ", "daily 23:59"),
+            ("weekly sun 04:00", "weekly Sun 04:00"),
+            ("weekly Monday 06:30", "weekly Mon 06:30"),
+        ] {
+            let parsed = match input.parse::<When>() {
+                Ok(When::Every(interval)) => format!("every {}s", interval.as_secs()),
+                Ok(When::Daily(time)) => format!("daily {}", time.format("%H:%M")),
+                Ok(When::Weekly(day, time)) => format!("weekly {} {}", day, time.format("%H:%M")),
+                Err(e) => panic!("{:?}: {}", input, e),
+            };
+            assert_eq!(parsed, expected, "{:?}", input);
+        }
+        assert!(matches!("daily 00:00".parse(), Ok(When::Daily(time)) if time == at("00:00")));
+    }
+
+    #[test]
+    fn rejects_malformed_schedules() {
+        for input in [
+            "",
+            "daily",
+            "daily 4am",
+            "daily 24:00",
+            "daily 04:00 05:00",
+            "weekly 04:00",
+            "weekly someday 04:00",
+            "every",
+            "every 6x",
+            "every 999999999999999999d",
+            "monthly 04:00",
+        ] {
+            assert!(input.parse::<When>().is_err(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn is_due_after_its_time() {
+        let daily: When = "daily 04:00".parse().unwrap();
+        let weekly: When = "weekly mon 04:00".parse().unwrap();
+        let every = When::Every(Duration::from_secs(6 * 60 * 60));
+        // 2026-03-16 is a Monday
+        for (when, ran_at, now, due) in [
+            (daily, "2026-03-16 04:00", "2026-03-16 23:00", false),
+            (daily, "2026-03-16 04:00", "2026-03-17 03:59", false),
+            (daily, "2026-03-16 04:00", "2026-03-17 04:00", true),
+            (daily, "2026-03-10 04:00", "2026-03-16 12:00", true),
+            (daily, "2026-03-16 03:00", "2026-03-16 05:00", true),
+            (weekly, "2026-03-16 04:00", "2026-03-22 23:59", false),
+            (weekly, "2026-03-16 04:00", "2026-03-23 04:00", true),
+            (weekly, "2026-03-15 12:00", "2026-03-16 04:01", true),
+            (every, "2026-03-16 04:00", "2026-03-16 09:59", false),
+            (every, "2026-03-16 04:00", "2026-03-16 10:00", true),
+            // A clock set back doesn't make it due
+            (every, "2026-03-16 04:00", "2026-03-16 03:00", false),
+        ] {
+            assert_eq!(when.is_due(local(now), local(ran_at)), due, "ran at {}, now {}", ran_at, now);
+        }
+    }
+}
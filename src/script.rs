@@ -0,0 +1,235 @@
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Local, Timelike};
+use rhai::{CallFnOptions, Dynamic, Engine, ImmutableString, Map, Scope, AST};
+use rusqlite::{params, Connection};
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::console::Console;
+use crate::error::WebgoneError;
+use crate::target::ProbeResult;
+
+/// Function a script defines to be called with every probe result.
+const HOOK_FUNCTION: &str = "on_probe";
+/// Operations a single call may run, so an endless loop in a script can't stall the watcher.
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// A metric value or event emitted by a script.
+enum Emitted {
+    Metric { name: String, value: f64 },
+    Event { name: String, message: String },
+}
+
+/// Summary of one custom metric over a period.
+pub struct MetricSummary {
+    pub name: String,
+    pub count: i64,
+    pub average: f64,
+    pub min: f64,
+    pub max: f64,
+    pub last_value: f64,
+    pub last_time: DateTime<Local>,
+}
+
+/// An event emitted by a script.
+pub struct CustomEvent {
+    pub time: DateTime<Local>,
+    pub name: String,
+    pub message: String,
+}
+
+pub fn init_tables(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS custom_metrics (
+            id INTEGER PRIMARY KEY,
+            time TEXT NOT NULL,
+            name TEXT NOT NULL,
+            value REAL NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS custom_metrics_name ON custom_metrics (name, time);
+        CREATE TABLE IF NOT EXISTS custom_events (
+            id INTEGER PRIMARY KEY,
+            time TEXT NOT NULL,
+            name TEXT NOT NULL,
+            message TEXT NOT NULL
+        );"
+    )?;
+    Ok(())
+}
+
+/// A rhai script whose `on_probe(probe)` function post-processes every probe
+/// result. It can call `metric(name, value)` and `event(name, message)`, and
+/// keep state between calls in `this`, a map that lives as long as the watcher.
+pub struct ProbeHook {
+    path: PathBuf,
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    state: Dynamic,
+    emitted: Rc<RefCell<Vec<Emitted>>>,
+}
+
+impl ProbeHook {
+    pub fn load(path: &Path) -> Result<Self> {
+        let emitted = Rc::new(RefCell::new(Vec::new()));
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+
+        let metrics = emitted.clone();
+        engine.register_fn("metric", move |name: ImmutableString, value: f64| {
+            metrics.borrow_mut().push(Emitted::Metric { name: name.to_string(), value });
+        });
+        let metrics = emitted.clone();
+        engine.register_fn("metric", move |name: ImmutableString, value: i64| {
+            metrics.borrow_mut().push(Emitted::Metric { name: name.to_string(), value: value as f64 });
+        });
+        let events = emitted.clone();
+        engine.register_fn("event", move |name: ImmutableString, message: ImmutableString| {
+            events.borrow_mut().push(Emitted::Event { name: name.to_string(), message: message.to_string() });
+        });
+
+        let ast = engine
+            .compile_file(path.to_path_buf())
+            .map_err(|e| WebgoneError::InvalidInput(format!("failed to load script {}: {}", path.display(), e)))?;
+        if !ast.iter_functions().any(|f| f.name == HOOK_FUNCTION && f.params.len() == 1) {
+            return Err(WebgoneError::InvalidInput(format!(
+                "script {} must define fn {}(probe)",
+                path.display(),
+                HOOK_FUNCTION
+            ))
+            .into());
+        }
+
+        // Top-level statements run once, e.g. to print a greeting or check settings
+        let mut scope = Scope::new();
+        engine
+            .run_ast_with_scope(&mut scope, &ast)
+            .map_err(|e| WebgoneError::InvalidInput(format!("script {} failed: {}", path.display(), e)))?;
+
+        Ok(ProbeHook {
+            path: path.to_path_buf(),
+            engine,
+            ast,
+            scope,
+            state: Dynamic::from_map(Map::new()),
+            emitted,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Calls the script with each probe result and stores what it emitted.
+    /// Script errors are printed and don't stop the watcher; only failing
+    /// to store the output is returned as an error.
+    pub fn run(
+        &mut self,
+        conn: &Connection,
+        console: &mut Console,
+        time: DateTime<Local>,
+        results: &[ProbeResult],
+    ) -> Result<()> {
+        for result in results {
+            let mut probe = Map::new();
+            probe.insert("time".into(), time.to_rfc3339().into());
+            probe.insert("hour".into(), Dynamic::from_int(time.hour().into()));
+            probe.insert("weekday".into(), Dynamic::from_int(time.weekday().number_from_monday().into()));
+            probe.insert("target".into(), result.target.to_string().into());
+            probe.insert("success".into(), result.is_success().into());
+            probe.insert(
+                "latency_ms".into(),
+                if result.is_success() {
+                    Dynamic::from_float(result.elapsed.as_secs_f64() * 1000.0)
+                } else {
+                    Dynamic::UNIT
+                },
+            );
+            probe.insert(
+                "error".into(),
+                result.error.clone().map_or(Dynamic::UNIT, Dynamic::from),
+            );
+
+            let options = CallFnOptions::new().eval_ast(false).bind_this_ptr(&mut self.state);
+            if let Err(e) = self
+                .engine
+                .call_fn_with_options::<Dynamic>(options, &mut self.scope, &self.ast, HOOK_FUNCTION, (probe,))
+            {
+                console.print(&format!("Script {} failed: {}", self.path.display(), e));
+            }
+        }
+
+        let emitted = std::mem::take(&mut *self.emitted.borrow_mut());
+        if emitted.is_empty() {
+            return Ok(());
+        }
+
+        let tx = conn.unchecked_transaction()?;
+        for item in emitted {
+            match item {
+                Emitted::Metric { name, value } => tx.execute(
+                    "INSERT INTO custom_metrics (time, name, value) VALUES (?1, ?2, ?3)",
+                    params![time.to_rfc3339(), name, value],
+                )?,
+                Emitted::Event { name, message } => {
+                    console.print(&format!("Script event {}: {}", name, message));
+                    tx.execute(
+                        "INSERT INTO custom_events (time, name, message) VALUES (?1, ?2, ?3)",
+                        params![time.to_rfc3339(), name, message],
+                    )?
+                }
+            };
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+fn parse_time(value: String) -> rusqlite::Result<DateTime<Local>> {
+    DateTime::parse_from_rfc3339(&value)
+        .map(|time| time.with_timezone(&Local))
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e)))
+}
+
+/// Count, average, range and newest value of every metric recorded since `since`.
+pub fn summaries(conn: &Connection, since: DateTime<Local>) -> Result<Vec<MetricSummary>> {
+    let mut stmt = conn.prepare(
+        "SELECT name, COUNT(*), AVG(value), MIN(value), MAX(value),
+            (SELECT value FROM custom_metrics AS newest WHERE newest.name = m.name ORDER BY id DESC LIMIT 1),
+            MAX(time)
+        FROM custom_metrics AS m
+        WHERE julianday(time) >= julianday(?)
+        GROUP BY name
+        ORDER BY name"
+    )?;
+    let summaries = stmt
+        .query_map([since.to_rfc3339()], |row| {
+            Ok(MetricSummary {
+                name: row.get(0)?,
+                count: row.get(1)?,
+                average: row.get(2)?,
+                min: row.get(3)?,
+                max: row.get(4)?,
+                last_value: row.get(5)?,
+                last_time: parse_time(row.get(6)?)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(summaries)
+}
+
+/// Newest events recorded since `since`, newest first.
+pub fn events(conn: &Connection, since: DateTime<Local>, limit: usize) -> Result<Vec<CustomEvent>> {
+    let mut stmt = conn.prepare(
+        "SELECT time, name, message FROM custom_events
+        WHERE julianday(time) >= julianday(?1)
+        ORDER BY id DESC LIMIT ?2"
+    )?;
+    let events = stmt
+        .query_map(params![since.to_rfc3339(), limit as i64], |row| {
+            Ok(CustomEvent { time: parse_time(row.get(0)?)?, name: row.get(1)?, message: row.get(2)? })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(events)
+}
@@ -0,0 +1,143 @@
+use anyhow::Result;
+use chrono::{Duration, Local};
+use clap::ValueEnum;
+use rusqlite::Connection;
+use serde::Deserialize;
+
+use crate::error::WebgoneError;
+
+/// Kinds of metadata `db scrub` removes. Times, durations and everything
+/// reports and aggregates are computed from stay.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Field {
+    /// Comments of acknowledgments, ticket and planned downtime notes, and script events
+    Notes,
+    /// System log lines attached to outages, which name interfaces, addresses and DHCP leases
+    Events,
+    /// Error messages of probes, route trace hops and DNS resolver addresses
+    Addresses,
+    /// Wi-Fi SSID and signal of outages, and hostname and options of the watcher
+    Network,
+    /// Packet captures of outages, including their files
+    Captures,
+}
+
+/// Columns of one table a field is stored in.
+struct Rule {
+    table: &'static str,
+    /// Time a row is old by
+    time_column: &'static str,
+    /// Assignments removing the data
+    set: &'static str,
+    /// Rows that still hold some of it
+    holds_data: &'static str,
+}
+
+impl Field {
+    pub fn name(self) -> &'static str {
+        match self {
+            Field::Notes => "notes",
+            Field::Events => "events",
+            Field::Addresses => "addresses",
+            Field::Network => "network",
+            Field::Captures => "captures",
+        }
+    }
+
+    fn rules(self) -> &'static [Rule] {
+        match self {
+            Field::Notes => &[
+                Rule { table: "outage_acks", time_column: "acked_at", set: "comment = NULL", holds_data: "comment IS NOT NULL" },
+                Rule { table: "tickets", time_column: "opened_at", set: "note = NULL", holds_data: "note IS NOT NULL" },
+                Rule { table: "planned_downtime", time_column: "end_time", set: "note = NULL", holds_data: "note IS NOT NULL" },
+                Rule { table: "custom_events", time_column: "time", set: "message = ''", holds_data: "message != ''" },
+                // The audit log repeats the comment of an acknowledgment
+                Rule { table: "audit_log", time_column: "time", set: "details = ''", holds_data: "command = 'ack' AND details != ''" },
+            ],
+            Field::Events => &[
+                Rule { table: "outage_events", time_column: "time", set: "message = ''", holds_data: "message != ''" },
+            ],
+            Field::Addresses => &[
+                Rule { table: "probes", time_column: "time", set: "error = NULL", holds_data: "error IS NOT NULL" },
+                Rule { table: "probe_history", time_column: "time", set: "error = NULL", holds_data: "error IS NOT NULL" },
+                // The number of hops and how many changed stay for `routes`
+                Rule { table: "route_traces", time_column: "traced_at", set: "hops = ''", holds_data: "hops != ''" },
+                Rule { table: "dns_benchmarks", time_column: "run_at", set: "address = ''", holds_data: "address != ''" },
+            ],
+            Field::Network => &[
+                Rule {
+                    table: "outages",
+                    time_column: "end_time",
+                    set: "wifi_ssid = NULL, wifi_signal_dbm = NULL",
+                    holds_data: "wifi_ssid IS NOT NULL OR wifi_signal_dbm IS NOT NULL",
+                },
+                Rule {
+                    table: "monitored_periods",
+                    time_column: "end_time",
+                    set: "hostname = NULL, config = NULL",
+                    holds_data: "hostname IS NOT NULL OR config IS NOT NULL",
+                },
+            ],
+            Field::Captures => &[
+                Rule { table: "outages", time_column: "end_time", set: "capture_path = NULL", holds_data: "capture_path IS NOT NULL" },
+            ],
+        }
+    }
+}
+
+/// Rows scrubbed of one field in one table.
+pub struct Scrubbed {
+    pub field: Field,
+    pub table: &'static str,
+    pub rows: usize,
+}
+
+/// Removes `fields` from the rows older than `days` days, all in one
+/// transaction. Signed outages are signed again through the connection's
+/// triggers. Capture files are deleted as well. Nothing is changed with
+/// `dry_run`; the counts are of the rows that would be scrubbed.
+pub fn scrub(conn: &Connection, fields: &[Field], days: u32, dry_run: bool) -> Result<Vec<Scrubbed>> {
+    let cutoff = (Local::now() - Duration::days(days.into())).to_rfc3339();
+    let tx = conn.unchecked_transaction()?;
+    let mut scrubbed = Vec::new();
+    let mut captures = Vec::new();
+
+    for &field in fields {
+        for rule in field.rules() {
+            let condition = format!("julianday({}) < julianday(?1) AND ({})", rule.time_column, rule.holds_data);
+            if field == Field::Captures && !dry_run {
+                let mut stmt = tx.prepare(&format!("SELECT capture_path FROM {} WHERE {condition}", rule.table))?;
+                let paths = stmt.query_map([&cutoff], |row| row.get::<_, String>(0))?;
+                captures.extend(paths.collect::<rusqlite::Result<Vec<_>>>()?);
+            }
+            let rows = if dry_run {
+                tx.query_row(&format!("SELECT COUNT(*) FROM {} WHERE {condition}", rule.table), [&cutoff], |row| {
+                    row.get(0)
+                })?
+            } else {
+                tx.execute(&format!("UPDATE {} SET {} WHERE {condition}", rule.table, rule.set), [&cutoff])?
+            };
+            scrubbed.push(Scrubbed { field, table: rule.table, rows });
+        }
+    }
+
+    tx.commit()?;
+    // Only once the outages no longer point to them
+    for path in captures {
+        if let Err(e) = std::fs::remove_file(&path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                return Err(WebgoneError::Io(format!("failed to delete the capture {}: {}", path, e)).into());
+            }
+        }
+    }
+    Ok(scrubbed)
+}
+
+/// Rewrites the database file without the free pages that may still hold
+/// scrubbed values, and empties the write-ahead log.
+pub fn purge_free_pages(conn: &Connection) -> Result<()> {
+    conn.execute_batch("VACUUM")?;
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+    Ok(())
+}
@@ -0,0 +1,90 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use rusqlite::Connection;
+use std::collections::BTreeMap;
+
+use crate::filter::OutageFilter;
+use crate::model;
+
+/// Everything written about an outage that a search looks at, as
+/// (outage id, where it was found, text). Causes are the failed components
+/// and probes and what a derived outage was caused by.
+const TEXTS: &str = "
+    SELECT id AS outage_id, 'cause' AS source, failed_components AS text FROM outages WHERE failed_components IS NOT NULL
+    UNION ALL SELECT id, 'cause', failed_probes FROM outages WHERE failed_probes IS NOT NULL
+    UNION ALL SELECT id, 'cause', 'derived from ' || derived_from FROM outages WHERE derived_from IS NOT NULL
+    UNION ALL SELECT outage_id, 'tag', tag FROM outage_tags
+    UNION ALL SELECT outage_id, 'ack', acked_by || COALESCE(': ' || comment, '') FROM outage_acks WHERE outage_id IS NOT NULL
+    UNION ALL SELECT l.outage_id, 'ticket', k.reference || COALESCE(': ' || k.note, '')
+        FROM ticket_outages l JOIN tickets k ON k.id = l.ticket_id
+    UNION ALL SELECT outage_id, 'event', source || ': ' || message FROM outage_events
+    UNION ALL SELECT outage_id, 'impact', description FROM outage_impacts";
+
+/// An outage a search found, with the texts that matched.
+pub struct Hit {
+    pub id: i64,
+    pub site: Option<String>,
+    /// `None` if the row's start time can't be read
+    pub start_time: Option<DateTime<Local>>,
+    pub duration_seconds: i64,
+    /// Where each matching text was found, e.g. `tag` or `ticket`, and the text
+    pub found: Vec<(String, String)>,
+}
+
+/// Outages matching `filter` where every one of `terms` appears, ignoring
+/// case, in at least one of their tags, acknowledgement comments, tickets,
+/// causes, recorded events or impact entries. Newest first, at most `limit`.
+pub fn search(conn: &Connection, terms: &[String], filter: &OutageFilter, limit: usize) -> Result<Vec<Hit>> {
+    let terms: Vec<String> = terms.iter().map(|term| term.to_lowercase()).filter(|term| !term.is_empty()).collect();
+    let (condition, params) = filter.where_clause();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT o.id, o.site, o.start_time, o.duration_seconds, t.source, t.text
+        FROM ({TEXTS}) t
+        JOIN outages o ON o.id = t.outage_id
+        WHERE t.outage_id IN (SELECT id FROM outages {condition})
+        ORDER BY julianday(o.start_time) DESC, o.id DESC"
+    ))?;
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(params), |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, i64>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, String>(5)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut hits: Vec<Hit> = Vec::new();
+    let mut seen: BTreeMap<i64, usize> = BTreeMap::new();
+    for (id, site, start_time, duration_seconds, source, text) in rows {
+        let index = *seen.entry(id).or_insert_with(|| {
+            hits.push(Hit {
+                id,
+                site,
+                start_time: model::parse_timestamp(&start_time),
+                duration_seconds,
+                found: Vec::new(),
+            });
+            hits.len() - 1
+        });
+        hits[index].found.push((source, text));
+    }
+
+    Ok(hits
+        .into_iter()
+        .filter_map(|mut hit| {
+            let lowered: Vec<String> = hit.found.iter().map(|(_, text)| text.to_lowercase()).collect();
+            if !terms.iter().all(|term| lowered.iter().any(|text| text.contains(term))) {
+                return None;
+            }
+            let mut matching = lowered.iter().map(|text| terms.iter().any(|term| text.contains(term)));
+            hit.found.retain(|_| matching.next().unwrap_or(false));
+            hit.found.dedup();
+            Some(hit)
+        })
+        .take(limit)
+        .collect())
+}
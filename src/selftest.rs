@@ -0,0 +1,266 @@
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OpenFlags};
+use std::env;
+use std::fs;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::path::{Path, PathBuf};
+use std::process::{self, Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::error::WebgoneError;
+
+/// Check interval of the watchers under test.
+const INTERVAL: &str = "0.2";
+
+/// How far a recorded duration may be off: checks are `INTERVAL` apart and
+/// durations are stored in whole seconds.
+const TOLERANCE: Duration = Duration::from_secs(1);
+
+/// How long to wait for a watcher to start before giving up.
+const START_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Whether the fake target answers, and for how long.
+#[derive(Clone, Copy)]
+enum Step {
+    Up(Duration),
+    Down(Duration),
+}
+
+struct Scenario {
+    name: &'static str,
+    /// Extra `watch` arguments; `{target}` is replaced by the fake target's address
+    args: &'static [&'static str],
+    steps: &'static [Step],
+    /// Durations of the outages that should be recorded, oldest first
+    expected: &'static [Duration],
+}
+
+const SCENARIOS: &[Scenario] = &[
+    Scenario {
+        name: "outage of the target is recorded",
+        args: &[],
+        steps: &[
+            Step::Up(Duration::from_millis(1000)),
+            Step::Down(Duration::from_millis(2000)),
+            Step::Up(Duration::from_millis(1500)),
+        ],
+        expected: &[Duration::from_millis(2000)],
+    },
+    Scenario {
+        name: "blip below --fail-threshold is ignored",
+        args: &["--fail-threshold", "5"],
+        steps: &[
+            Step::Up(Duration::from_millis(1000)),
+            Step::Down(Duration::from_millis(300)),
+            Step::Up(Duration::from_millis(1500)),
+        ],
+        expected: &[],
+    },
+    Scenario {
+        name: "outages within --merge-gap are merged",
+        args: &["--merge-gap", "2"],
+        steps: &[
+            Step::Up(Duration::from_millis(1000)),
+            Step::Down(Duration::from_millis(1000)),
+            Step::Up(Duration::from_millis(500)),
+            Step::Down(Duration::from_millis(1000)),
+            Step::Up(Duration::from_millis(1500)),
+        ],
+        expected: &[Duration::from_millis(2500)],
+    },
+    Scenario {
+        name: "failed HTTP component is an outage",
+        args: &["--up-when", "web", "--component", "web=http:http://{target}/"],
+        steps: &[
+            Step::Up(Duration::from_millis(1000)),
+            Step::Down(Duration::from_millis(2000)),
+            Step::Up(Duration::from_millis(1500)),
+        ],
+        expected: &[Duration::from_millis(2000)],
+    },
+];
+
+/// A local target answering TCP connections, and HTTP requests with
+/// `204 No Content`, until it is stopped.
+struct FakeTarget {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+impl FakeTarget {
+    fn start(addr: SocketAddr) -> Result<Self> {
+        let listener = TcpListener::bind(addr).with_context(|| format!("Failed to listen on {}", addr))?;
+        listener.set_nonblocking(true)?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let stop = stop.clone();
+            thread::spawn(move || {
+                while !stop.load(Ordering::SeqCst) {
+                    match listener.accept() {
+                        Ok((mut stream, _)) => {
+                            // Plain TCP probes close right away; HTTP checks send a request first
+                            let _ = stream.set_nonblocking(false);
+                            let _ = stream.set_read_timeout(Some(Duration::from_millis(200)));
+                            let mut request = [0u8; 1024];
+                            if stream.read(&mut request).is_ok_and(|read| read > 0) {
+                                let _ = stream.write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n");
+                            }
+                        }
+                        Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(Duration::from_millis(5)),
+                        Err(_) => {}
+                    }
+                }
+            })
+        };
+        Ok(FakeTarget { stop, thread })
+    }
+
+    /// Closes the listening socket, so connections are refused.
+    fn stop(self) {
+        self.stop.store(true, Ordering::SeqCst);
+        let _ = self.thread.join();
+    }
+}
+
+/// A `webgone watch` running in a directory of its own.
+struct Watcher(Child);
+
+impl Watcher {
+    fn start(dir: &Path, target: SocketAddr, args: &[String]) -> Result<Self> {
+        let exe = env::current_exe().context("Failed to locate the running executable")?;
+        let child = Command::new(exe)
+            .args(["--db", "internet_outages.db", "watch"])
+            .args(["--target", &target.to_string(), "--interval", INTERVAL, "--log-file", "watch.log"])
+            .args(args)
+            .current_dir(dir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .context("Failed to start the watcher")?;
+        let mut watcher = Watcher(child);
+
+        // The pid file is written right before the first check
+        let started = Instant::now();
+        while !dir.join("internet_outages.db.pid").exists() {
+            if let Some(status) = watcher.0.try_wait()? {
+                return Err(WebgoneError::Other(format!("the watcher exited early ({})", status)).into());
+            }
+            if started.elapsed() > START_TIMEOUT {
+                return Err(WebgoneError::Other("the watcher didn't start in time".to_string()).into());
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        Ok(watcher)
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        // Finished outages are written as soon as they end, so nothing is lost
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Outcome of one scenario, and the directory with its database and log.
+pub struct Outcome {
+    pub name: &'static str,
+    pub dir: PathBuf,
+    pub result: Result<String, String>,
+}
+
+impl Outcome {
+    pub fn passed(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+fn free_port() -> Result<SocketAddr> {
+    Ok(TcpListener::bind("127.0.0.1:0")?.local_addr()?)
+}
+
+fn recorded_durations(dir: &Path) -> Result<Vec<i64>> {
+    let conn = Connection::open_with_flags(dir.join("internet_outages.db"), OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut stmt = conn.prepare("SELECT duration_seconds FROM outages ORDER BY start_time")?;
+    let durations = stmt.query_map([], |row| row.get(0))?.collect::<rusqlite::Result<Vec<i64>>>()?;
+    Ok(durations)
+}
+
+fn compare(recorded: &[i64], expected: &[Duration]) -> Result<String, String> {
+    let describe = |durations: Vec<String>| {
+        if durations.is_empty() { "none".to_string() } else { durations.join(", ") }
+    };
+    let recorded_text = describe(recorded.iter().map(|seconds| format!("{} s", seconds)).collect());
+    let expected_text = describe(expected.iter().map(|duration| format!("{:.1} s", duration.as_secs_f64())).collect());
+
+    let matches = recorded.len() == expected.len()
+        && recorded
+            .iter()
+            .zip(expected)
+            .all(|(&seconds, duration)| (seconds as f64 - duration.as_secs_f64()).abs() <= TOLERANCE.as_secs_f64());
+    if matches {
+        Ok(format!("outages: {}", recorded_text))
+    } else {
+        Err(format!("expected outages of {}, recorded {}", expected_text, recorded_text))
+    }
+}
+
+fn run_scenario(scenario: &Scenario, dir: &Path) -> Result<Result<String, String>> {
+    fs::create_dir_all(dir).map_err(|e| WebgoneError::Io(format!("failed to create {}: {}", dir.display(), e)))?;
+    let addr = free_port()?;
+    let args: Vec<String> = scenario.args.iter().map(|arg| arg.replace("{target}", &addr.to_string())).collect();
+
+    let mut target = Some(FakeTarget::start(addr)?);
+    let watcher = Watcher::start(dir, addr, &args)?;
+    for step in scenario.steps {
+        match *step {
+            Step::Up(duration) => {
+                if target.is_none() {
+                    target = Some(FakeTarget::start(addr)?);
+                }
+                thread::sleep(duration);
+            }
+            Step::Down(duration) => {
+                if let Some(target) = target.take() {
+                    target.stop();
+                }
+                thread::sleep(duration);
+            }
+        }
+    }
+    drop(watcher);
+    if let Some(target) = target {
+        target.stop();
+    }
+
+    Ok(compare(&recorded_durations(dir)?, scenario.expected))
+}
+
+/// Runs every scenario in parallel, each with a fake target and a watcher of
+/// its own in a subdirectory of `base`.
+pub fn run(base: &Path) -> Vec<Outcome> {
+    thread::scope(|scope| {
+        let handles: Vec<_> = SCENARIOS
+            .iter()
+            .enumerate()
+            .map(|(index, scenario)| {
+                let dir = base.join(format!("scenario-{}", index + 1));
+                scope.spawn(move || {
+                    let result = run_scenario(scenario, &dir).unwrap_or_else(|e| Err(format!("{:#}", e)));
+                    Outcome { name: scenario.name, dir, result }
+                })
+            })
+            .collect();
+        handles.into_iter().map(|handle| handle.join().expect("self-test scenario panicked")).collect()
+    })
+}
+
+/// Directory the self-test works in, unique to this process.
+pub fn default_dir() -> PathBuf {
+    env::temp_dir().join(format!("webgone-selftest-{}", process::id()))
+}
@@ -0,0 +1,247 @@
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::Read;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tiny_http::{Header, Method, Request, Response, Server, SslConfig};
+
+use crate::error::WebgoneError;
+use crate::tls::Certificate;
+use crate::tokens::Scope;
+
+/// Window the rate limit is counted in.
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+/// Largest request body accepted by write endpoints.
+const MAX_BODY_BYTES: u64 = 1024 * 1024;
+
+pub struct ServeOptions {
+    pub listen: SocketAddr,
+    /// Origins allowed to call the API from a browser; `*` allows any origin
+    pub cors_origins: Vec<String>,
+    /// Requests per minute and client address, unlimited if `None`
+    pub rate_limit: Option<u32>,
+    /// How long responses are served from the cache
+    pub cache_ttl: Duration,
+    /// Require a read token for GET requests; writes always need a write token
+    pub require_token: bool,
+    /// Serve HTTPS with this certificate instead of plain HTTP
+    pub tls: Option<Certificate>,
+}
+
+/// The endpoints behind the server. Handlers return `None` if there is no such endpoint.
+pub trait Api {
+    /// Scope of a bearer token and who uses it, or `None` if it isn't valid.
+    fn authenticate(&mut self, token: &str) -> Result<Option<(Scope, String)>>;
+    fn get(&mut self, path: &str, query: &HashMap<String, String>) -> Result<Option<Value>>;
+    /// `actor` is who sent the request, as identified by its token.
+    fn post(&mut self, path: &str, body: Value, actor: Option<&str>) -> Result<Option<Value>>;
+}
+
+struct RateLimiter {
+    limit: u32,
+    clients: HashMap<IpAddr, (Instant, u32)>,
+}
+
+impl RateLimiter {
+    /// Counts a request and returns how long the client has to wait if it is over the limit.
+    fn check(&mut self, client: IpAddr) -> Option<Duration> {
+        let now = Instant::now();
+        self.clients.retain(|_, (start, _)| now.duration_since(*start) < RATE_LIMIT_WINDOW);
+
+        let (start, count) = self.clients.entry(client).or_insert((now, 0));
+        *count += 1;
+        (*count > self.limit).then(|| RATE_LIMIT_WINDOW.saturating_sub(now.duration_since(*start)))
+    }
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).expect("valid header")
+}
+
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) => (key.to_string(), value.to_string()),
+            None => (pair.to_string(), String::new()),
+        })
+        .collect()
+}
+
+/// The `Access-Control-Allow-Origin` value for a request, if its origin is allowed.
+fn allowed_origin(options: &ServeOptions, request: &Request) -> Option<String> {
+    let origin = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Origin"))
+        .map(|h| h.value.to_string())?;
+    if options.cors_origins.iter().any(|allowed| allowed == "*") {
+        Some("*".to_string())
+    } else {
+        options.cors_origins.contains(&origin).then_some(origin)
+    }
+}
+
+fn bearer_token(request: &Request) -> Option<String> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Authorization"))
+        .and_then(|h| h.value.as_str().strip_prefix("Bearer ").map(|token| token.trim().to_string()))
+}
+
+fn respond(request: Request, status: u16, body: &str, extra_headers: Vec<Header>) {
+    let mut response = Response::from_string(body)
+        .with_status_code(status)
+        .with_header(header("Content-Type", "application/json"));
+    for header in extra_headers {
+        response.add_header(header);
+    }
+    if let Err(e) = request.respond(response) {
+        println!("Failed to send response: {}", e);
+    }
+}
+
+fn error_body(message: &str) -> String {
+    json!({ "error": message }).to_string()
+}
+
+/// Answers with `status` if the handler succeeded, e.g. 201 for a created record.
+fn respond_result(request: Request, status: u16, result: Result<Option<Value>>, headers: Vec<Header>) {
+    match result {
+        Ok(Some(value)) => respond(request, status, &value.to_string(), headers),
+        Ok(None) => respond(request, 404, &error_body("not found"), headers),
+        Err(e) => match e.downcast_ref::<WebgoneError>() {
+            Some(WebgoneError::InvalidInput(message)) => respond(request, 400, &error_body(message), headers),
+            _ => {
+                println!("Failed to answer {}: {:#}", request.url(), e);
+                respond(request, 500, &error_body("internal error"), headers);
+            }
+        },
+    }
+}
+
+fn read_json_body(request: &mut Request) -> Result<Value, String> {
+    let mut body = String::new();
+    request
+        .as_reader()
+        .take(MAX_BODY_BYTES)
+        .read_to_string(&mut body)
+        .map_err(|e| format!("failed to read request body: {}", e))?;
+    serde_json::from_str(&body).map_err(|e| format!("invalid JSON body: {}", e))
+}
+
+/// Serves the JSON API until `running` is cleared.
+pub fn serve(options: &ServeOptions, running: &AtomicBool, api: &mut dyn Api) -> Result<()> {
+    let server = match &options.tls {
+        Some(tls) => Server::https(
+            options.listen,
+            SslConfig { certificate: tls.certificate.clone(), private_key: tls.private_key.clone() },
+        ),
+        None => Server::http(options.listen),
+    }
+    .map_err(|e| WebgoneError::Io(format!("failed to listen on {}: {}", options.listen, e)))?;
+
+    let mut rate_limiter = options.rate_limit.map(|limit| RateLimiter { limit, clients: HashMap::new() });
+    let mut cache: HashMap<String, (Instant, String)> = HashMap::new();
+
+    while running.load(Ordering::SeqCst) {
+        let Some(mut request) = server.recv_timeout(Duration::from_millis(500))? else {
+            continue;
+        };
+
+        let mut headers = Vec::new();
+        if let Some(origin) = allowed_origin(options, &request) {
+            headers.push(header("Access-Control-Allow-Origin", &origin));
+            headers.push(header("Vary", "Origin"));
+        }
+
+        if let (Some(limiter), Some(addr)) = (&mut rate_limiter, request.remote_addr()) {
+            if let Some(retry_after) = limiter.check(addr.ip()) {
+                headers.push(header("Retry-After", &retry_after.as_secs().max(1).to_string()));
+                respond(request, 429, &error_body("rate limit exceeded"), headers);
+                continue;
+            }
+        }
+
+        let required_scope = match request.method() {
+            Method::Get => options.require_token.then_some(Scope::Read),
+            Method::Post => Some(Scope::Write),
+            Method::Options => {
+                headers.push(header("Access-Control-Allow-Methods", "GET, POST, OPTIONS"));
+                headers.push(header("Access-Control-Allow-Headers", "Authorization, Content-Type"));
+                headers.push(header("Access-Control-Max-Age", "86400"));
+                respond(request, 204, "", headers);
+                continue;
+            }
+            _ => {
+                headers.push(header("Allow", "GET, POST, OPTIONS"));
+                respond(request, 405, &error_body("method not allowed"), headers);
+                continue;
+            }
+        };
+
+        let mut actor = None;
+        if let Some(required_scope) = required_scope {
+            let caller = match bearer_token(&request).map(|token| api.authenticate(&token)).transpose() {
+                Ok(caller) => caller.flatten(),
+                Err(e) => {
+                    respond_result(request, 200, Err(e), headers);
+                    continue;
+                }
+            };
+            match caller {
+                Some((scope, name)) if scope >= required_scope => actor = Some(name),
+                Some(_) => {
+                    let message = format!("this endpoint needs a {} token", required_scope);
+                    respond(request, 403, &error_body(&message), headers);
+                    continue;
+                }
+                None => {
+                    headers.push(header("WWW-Authenticate", "Bearer"));
+                    respond(request, 401, &error_body("missing or invalid API token"), headers);
+                    continue;
+                }
+            }
+        }
+
+        let url = request.url().to_string();
+        let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+
+        if *request.method() == Method::Post {
+            let result = match read_json_body(&mut request) {
+                Ok(body) => api.post(path, body, actor.as_deref()),
+                Err(message) => Err(WebgoneError::InvalidInput(message).into()),
+            };
+            // Writes change what the read endpoints return
+            if matches!(result, Ok(Some(_))) {
+                cache.clear();
+            }
+            respond_result(request, 201, result, headers);
+            continue;
+        }
+
+        if let Some((cached_at, body)) = cache.get(&url) {
+            if cached_at.elapsed() < options.cache_ttl {
+                headers.push(header("X-Cache", "HIT"));
+                respond(request, 200, &body.clone(), headers);
+                continue;
+            }
+        }
+
+        let result = api.get(path, &parse_query(query));
+        if let Ok(Some(value)) = &result {
+            if !options.cache_ttl.is_zero() {
+                cache.retain(|_, (cached_at, _)| cached_at.elapsed() < options.cache_ttl);
+                cache.insert(url.clone(), (Instant::now(), value.to_string()));
+            }
+            headers.push(header("Cache-Control", &format!("max-age={}", options.cache_ttl.as_secs())));
+        }
+        respond_result(request, 200, result, headers);
+    }
+
+    Ok(())
+}
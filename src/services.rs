@@ -0,0 +1,367 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use rusqlite::{params, Connection};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::error::WebgoneError;
+use crate::sites;
+use crate::verdict::{self, Check, Component};
+use webgone::debounce::{Debounce, Transition};
+
+/// How often the state of a service is saved while it doesn't change.
+const STATUS_SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Something other than the internet connection to watch, e.g. a NAS or a
+/// VPN endpoint, given as `NAME=tcp:IP:PORT`, `NAME=icmp:IP`, `NAME=http:URL`,
+/// `NAME=dns[:IP]` or `NAME=gateway` like a component of `--up-when`.
+#[derive(Clone)]
+pub struct Service {
+    pub name: String,
+    pub spec: String,
+    check: Check,
+}
+
+impl FromStr for Service {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let component: Component = s.parse()?;
+        if component.name == sites::LOCAL_SITE {
+            return Err(format!("`{}` stands for the internet connection and can't name a service", sites::LOCAL_SITE));
+        }
+        let spec = s.split_once('=').map_or(s, |(_, spec)| spec).trim().to_string();
+        Ok(Service { name: component.name, spec, check: component.check })
+    }
+}
+
+/// A service that can't be reached while another one, or the internet
+/// connection (`local`), is down, given as `CHILD=PARENT`.
+#[derive(Clone)]
+pub struct Dependency {
+    pub child: String,
+    pub parent: String,
+}
+
+impl FromStr for Dependency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((child, parent)) if !child.trim().is_empty() && !parent.trim().is_empty() => {
+                Ok(Dependency { child: child.trim().to_string(), parent: parent.trim().to_string() })
+            }
+            _ => Err(format!("expected CHILD=PARENT, e.g. nas=vpn or vpn=local, got `{}`", s)),
+        }
+    }
+}
+
+/// Checks that dependencies are between watched services or on the internet
+/// connection, with at most one parent per service and without cycles.
+pub fn check_dependencies(services: &[Service], dependencies: &[Dependency]) -> Result<(), WebgoneError> {
+    let is_service = |name: &str| services.iter().any(|service| service.name == name);
+    for (index, dependency) in dependencies.iter().enumerate() {
+        if !is_service(&dependency.child) {
+            return Err(WebgoneError::InvalidInput(format!(
+                "--depends-on {}={}: {} isn't a --service",
+                dependency.child, dependency.parent, dependency.child
+            )));
+        }
+        if dependency.parent != sites::LOCAL_SITE && !is_service(&dependency.parent) {
+            return Err(WebgoneError::InvalidInput(format!(
+                "--depends-on {}={}: {} is neither a --service nor `{}`",
+                dependency.child, dependency.parent, dependency.parent, sites::LOCAL_SITE
+            )));
+        }
+        if dependencies[..index].iter().any(|other| other.child == dependency.child) {
+            return Err(WebgoneError::InvalidInput(format!("--depends-on gives {} more than one parent", dependency.child)));
+        }
+    }
+    for dependency in dependencies {
+        let mut name = dependency.parent.as_str();
+        let mut steps = 0;
+        while let Some(parent) = dependencies.iter().find(|other| other.child == name) {
+            if parent.parent == dependency.child || steps > dependencies.len() {
+                return Err(WebgoneError::InvalidInput(format!("{} depends on itself through --depends-on", dependency.child)));
+            }
+            name = &parent.parent;
+            steps += 1;
+        }
+    }
+    Ok(())
+}
+
+pub fn init_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS service_status (
+            name TEXT PRIMARY KEY,
+            check_spec TEXT NOT NULL,
+            up INTEGER NOT NULL,
+            since TEXT NOT NULL,
+            checked_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// A change of a service to print, and to pass on to notifications unless
+/// the outage is derived from one of what it depends on.
+pub struct Event {
+    pub subject: String,
+    pub message: String,
+    pub notify: bool,
+}
+
+/// What a service depends on.
+#[derive(Clone, Copy)]
+enum Parent {
+    Internet,
+    Service(usize),
+}
+
+/// An outage of a service that is still going on.
+struct Outage {
+    start: DateTime<Local>,
+    detected: DateTime<Local>,
+    confirmed: DateTime<Local>,
+    /// What was down when the outage began, which explains it
+    derived_from: Option<String>,
+}
+
+/// One check of the service at `index`.
+struct Checked {
+    index: usize,
+    started: DateTime<Local>,
+    finished: DateTime<Local>,
+    up: bool,
+}
+
+struct State {
+    service: Service,
+    parent: Option<Parent>,
+    /// Number of services between this one and the internet connection or a
+    /// service without a parent
+    depth: usize,
+    debounce: Debounce,
+    up: bool,
+    /// When `up` last changed, or the watcher started
+    since: DateTime<Local>,
+    outage: Option<Outage>,
+    last_check: Option<DateTime<Local>>,
+    last_save: Option<Instant>,
+}
+
+/// Watches services alongside the internet connection. Their checks run on
+/// a thread of their own, so a slow service doesn't delay the watcher's
+/// probes; results are applied and written by the watcher's loop. Outages
+/// are recorded with the service name as their site; ones that began while
+/// what the service depends on was down are recorded as derived from it.
+pub struct Services {
+    states: Vec<State>,
+    results: Receiver<Checked>,
+    fail_threshold: u32,
+    recover_threshold: u32,
+}
+
+impl Services {
+    /// Starts checking every service each `interval` until `running` is cleared.
+    pub fn start(
+        services: Vec<Service>,
+        dependencies: &[Dependency],
+        interval: Duration,
+        fail_threshold: u32,
+        recover_threshold: u32,
+        running: Arc<AtomicBool>,
+    ) -> Self {
+        let (sender, results) = mpsc::channel();
+        let checks: Vec<Check> = services.iter().map(|service| service.check.clone()).collect();
+        thread::spawn(move || {
+            while running.load(Ordering::SeqCst) {
+                let started_at = Instant::now();
+                let outcomes: Vec<Checked> = thread::scope(|scope| {
+                    let handles: Vec<_> = checks
+                        .iter()
+                        .enumerate()
+                        .map(|(index, check)| {
+                            scope.spawn(move || {
+                                let started = Local::now();
+                                let up = verdict::check(check);
+                                Checked { index, started, finished: Local::now(), up }
+                            })
+                        })
+                        .collect();
+                    handles.into_iter().filter_map(|handle| handle.join().ok()).collect()
+                });
+                for outcome in outcomes {
+                    if sender.send(outcome).is_err() {
+                        return;
+                    }
+                }
+                thread::sleep(interval.saturating_sub(started_at.elapsed()));
+            }
+        });
+
+        let parent_of = |name: &str| {
+            dependencies.iter().find(|dependency| dependency.child == name).map(|dependency| {
+                match services.iter().position(|service| service.name == dependency.parent) {
+                    Some(index) => Parent::Service(index),
+                    None => Parent::Internet,
+                }
+            })
+        };
+        let depth_of = |name: &str| {
+            let mut depth = 0;
+            let mut name = name;
+            while let Some(dependency) = dependencies.iter().find(|dependency| dependency.child == name) {
+                depth += 1;
+                name = &dependency.parent;
+            }
+            depth
+        };
+        let now = Local::now();
+        let states = services
+            .iter()
+            .map(|service| State {
+                service: service.clone(),
+                parent: parent_of(&service.name),
+                depth: depth_of(&service.name),
+                debounce: Debounce::new(fail_threshold, recover_threshold),
+                up: true,
+                since: now,
+                outage: None,
+                last_check: None,
+                last_save: None,
+            })
+            .collect();
+        Services { states, results, fail_threshold, recover_threshold }
+    }
+
+    /// Applies the checks that finished since the last call, records ended
+    /// outages and saves the state of each service. `internet_up` is the
+    /// state of the internet connection for services depending on it.
+    pub fn update(&mut self, conn: &Connection, internet_up: bool) -> Result<Vec<Event>> {
+        let mut events = Vec::new();
+        let mut results: Vec<Checked> = self.results.try_iter().collect();
+        // Parents first, so a child going down with its parent sees it down
+        results.sort_by_key(|checked| self.states[checked.index].depth);
+        for checked in results {
+            let derived_from = match self.states[checked.index].parent {
+                Some(Parent::Internet) if !internet_up => Some(sites::LOCAL_SITE.to_string()),
+                Some(Parent::Service(parent)) if !self.states[parent].up => Some(self.states[parent].service.name.clone()),
+                _ => None,
+            };
+            let state = &mut self.states[checked.index];
+            state.last_check = Some(checked.finished);
+            match state.debounce.record(checked.started, checked.finished, checked.up) {
+                Some(Transition::Lost { since, seen }) => {
+                    state.up = false;
+                    state.since = since;
+                    state.last_save = None;
+                    let mut message = format!(
+                        "Service {} ({}) is down since {}",
+                        state.service.name,
+                        state.service.spec,
+                        since.format("%H:%M:%S")
+                    );
+                    if let Some(parent) = &derived_from {
+                        message.push_str(&format!(", derived from {} being down", parent));
+                    }
+                    events.push(Event {
+                        subject: format!("webgone: {} is down", state.service.name),
+                        message,
+                        notify: derived_from.is_none(),
+                    });
+                    state.outage = Some(Outage { start: since, detected: seen, confirmed: checked.finished, derived_from });
+                }
+                Some(Transition::Restored { since }) => {
+                    state.up = true;
+                    state.since = since;
+                    state.last_save = None;
+                    if let Some(outage) = state.outage.take() {
+                        record_outage(conn, &state.service.name, &outage, since, checked.finished)?;
+                        events.push(Event {
+                            subject: format!("webgone: {} is back up", state.service.name),
+                            message: format!(
+                                "Service {} is back up after {} seconds",
+                                state.service.name,
+                                (since - outage.start).num_seconds().max(0)
+                            ),
+                            notify: outage.derived_from.is_none(),
+                        });
+                    }
+                }
+                None => {}
+            }
+        }
+
+        for state in &mut self.states {
+            if let Some(checked_at) = state.last_check {
+                if state.last_save.is_none_or(|at| at.elapsed() >= STATUS_SAVE_INTERVAL) {
+                    state.last_save = Some(Instant::now());
+                    save_status(conn, state, checked_at)?;
+                }
+            }
+        }
+        Ok(events)
+    }
+
+    /// Drops the checks that came in while monitoring is paused.
+    pub fn discard(&mut self) {
+        while self.results.try_recv().is_ok() {}
+    }
+
+    /// Records the outages still going on as ending at `now`, e.g. when
+    /// monitoring is paused or stopped, since nobody watches them afterwards.
+    pub fn interrupt(&mut self, conn: &Connection, now: DateTime<Local>) -> Result<()> {
+        for state in &mut self.states {
+            if let Some(outage) = state.outage.take() {
+                record_outage(conn, &state.service.name, &outage, now, now)?;
+            }
+            state.debounce = Debounce::new(self.fail_threshold, self.recover_threshold);
+            state.up = true;
+            state.since = now;
+            state.last_save = None;
+        }
+        Ok(())
+    }
+}
+
+fn record_outage(
+    conn: &Connection,
+    name: &str,
+    outage: &Outage,
+    end: DateTime<Local>,
+    recovered: DateTime<Local>,
+) -> Result<()> {
+    let end = end.max(outage.start);
+    conn.execute(
+        "INSERT INTO outages (start_time, end_time, duration_seconds, site, detected_at, confirmed_at, recovered_at, derived_from)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            outage.start.to_rfc3339(),
+            end.to_rfc3339(),
+            (end - outage.start).num_seconds(),
+            name,
+            outage.detected.to_rfc3339(),
+            outage.confirmed.to_rfc3339(),
+            recovered.to_rfc3339(),
+            outage.derived_from,
+        ],
+    )?;
+    Ok(())
+}
+
+fn save_status(conn: &Connection, state: &State, checked_at: DateTime<Local>) -> Result<()> {
+    conn.execute(
+        "INSERT INTO service_status (name, check_spec, up, since, checked_at) VALUES (?1, ?2, ?3, ?4, ?5)
+        ON CONFLICT (name) DO UPDATE SET
+            check_spec = excluded.check_spec, up = excluded.up, since = excluded.since, checked_at = excluded.checked_at",
+        params![state.service.name, state.service.spec, state.up, state.since.to_rfc3339(), checked_at.to_rfc3339()],
+    )?;
+    Ok(())
+}
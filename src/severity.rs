@@ -0,0 +1,311 @@
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Days, Local, NaiveTime, TimeZone, Weekday};
+use rusqlite::{params, Connection};
+use serde::Deserialize;
+use std::time::Duration;
+
+use crate::error::WebgoneError;
+use crate::filter;
+use crate::notify::Channels;
+use webgone::model::{self, Severity};
+
+/// Days from the start of an outage after which every weekday has come up.
+const WEEK: u64 = 7;
+
+/// The `[severity]` table of the configuration file.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// Shortest minor outage [default: 1m]
+    pub minor: Option<String>,
+    /// Shortest major outage [default: 15m]
+    pub major: Option<String>,
+    /// Shortest critical outage [default: 1h]
+    pub critical: Option<String>,
+    /// Outages during these hours are one level more severe, e.g. `mon-fri 09:00-17:00` (the default), or `none`
+    pub business_hours: Option<String>,
+    /// Lowest severity each notification channel is told about
+    pub notify: Routing,
+}
+
+impl Settings {
+    /// The thresholds, checked.
+    pub fn classifier(&self) -> Result<Classifier> {
+        let defaults = Classifier::default();
+        let duration = |value: &Option<String>, name: &str, default: Duration| match value {
+            Some(value) => filter::parse_duration(value)
+                .map_err(|e| WebgoneError::InvalidInput(format!("invalid [severity] {}: {}", name, e))),
+            None => Ok(default),
+        };
+        let minor = duration(&self.minor, "minor", defaults.minor)?;
+        let major = duration(&self.major, "major", defaults.major)?;
+        let critical = duration(&self.critical, "critical", defaults.critical)?;
+        if minor > major || major > critical {
+            return Err(WebgoneError::InvalidInput(
+                "[severity] thresholds must grow from minor to major to critical".to_string(),
+            )
+            .into());
+        }
+        let business_hours = match self.business_hours.as_deref() {
+            Some("none") => None,
+            Some(value) => Some(
+                BusinessHours::parse(value)
+                    .map_err(|e| WebgoneError::InvalidInput(format!("invalid [severity] business_hours: {}", e)))?,
+            ),
+            None => defaults.business_hours,
+        };
+        Ok(Classifier { minor, major, critical, business_hours })
+    }
+}
+
+/// Channels `watch` notifies, each only of outages at least as severe as
+/// set. All are told about every outage by default.
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(default, deny_unknown_fields)]
+pub struct Routing {
+    pub command: Option<Severity>,
+    pub desktop: Option<Severity>,
+    pub webhook: Option<Severity>,
+    pub discord: Option<Severity>,
+    pub email: Option<Severity>,
+}
+
+impl Routing {
+    /// The channels to notify of an outage of `severity`.
+    pub fn channels(&self, severity: Severity) -> Channels {
+        let wants = |minimum: Option<Severity>| minimum.is_none_or(|minimum| severity >= minimum);
+        Channels {
+            command: wants(self.command),
+            desktop: wants(self.desktop),
+            webhook: wants(self.webhook),
+            discord: wants(self.discord),
+            email: wants(self.email),
+        }
+    }
+}
+
+/// Recurring hours on some weekdays.
+#[derive(Clone, Copy)]
+pub struct BusinessHours {
+    /// Indexed by days from Monday
+    days: [bool; 7],
+    from: NaiveTime,
+    to: NaiveTime,
+}
+
+impl BusinessHours {
+    /// Parses `mon-fri 09:00-17:00`; days are a range or a list like `mon,wed,fri`.
+    fn parse(value: &str) -> Result<Self, String> {
+        let (days_part, hours_part) = value
+            .trim()
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| format!("{} (use e.g. `mon-fri 09:00-17:00`)", value))?;
+        let weekday = |name: &str| name.trim().parse::<Weekday>().map_err(|_| format!("invalid weekday: {}", name));
+        let mut days = [false; 7];
+        for part in days_part.split(',') {
+            match part.split_once('-') {
+                Some((first, last)) => {
+                    let (first, last) = (weekday(first)?, weekday(last)?);
+                    let mut day = first;
+                    loop {
+                        days[day.num_days_from_monday() as usize] = true;
+                        if day == last {
+                            break;
+                        }
+                        day = day.succ();
+                    }
+                }
+                None => days[weekday(part)?.num_days_from_monday() as usize] = true,
+            }
+        }
+        let time = |value: &str| {
+            NaiveTime::parse_from_str(value.trim(), "%H:%M").map_err(|_| format!("invalid time of day: {} (use HH:MM)", value))
+        };
+        let (from, to) = hours_part.split_once('-').ok_or_else(|| format!("invalid hours: {} (use HH:MM-HH:MM)", hours_part))?;
+        let (from, to) = (time(from)?, time(to)?);
+        if from >= to {
+            return Err(format!("business hours end before they start: {}", hours_part));
+        }
+        Ok(BusinessHours { days, from, to })
+    }
+
+    /// Whether any part of `start` to `end` falls within business hours.
+    fn overlaps(&self, start: DateTime<Local>, end: DateTime<Local>) -> bool {
+        let mut day = start.date_naive();
+        let last = end.date_naive().min(day + Days::new(WEEK));
+        while day <= last {
+            if self.days[day.weekday().num_days_from_monday() as usize] {
+                let at = |time: NaiveTime| Local.from_local_datetime(&day.and_time(time)).earliest();
+                if let (Some(opens), Some(closes)) = (at(self.from), at(self.to)) {
+                    if start < closes && end >= opens {
+                        return true;
+                    }
+                }
+            }
+            day = day + Days::new(1);
+        }
+        false
+    }
+}
+
+/// Classifies outages by how long they lasted and whether they hit
+/// business hours.
+#[derive(Clone, Copy)]
+pub struct Classifier {
+    minor: Duration,
+    major: Duration,
+    critical: Duration,
+    business_hours: Option<BusinessHours>,
+}
+
+impl Default for Classifier {
+    fn default() -> Self {
+        Classifier {
+            minor: Duration::from_secs(60),
+            major: Duration::from_secs(15 * 60),
+            critical: Duration::from_secs(60 * 60),
+            business_hours: Some(BusinessHours::parse("mon-fri 09:00-17:00").expect("valid default")),
+        }
+    }
+}
+
+impl Classifier {
+    /// Severity of an outage from `start` to `end`. For an ongoing outage,
+    /// `end` is now, and the outage can only get more severe.
+    pub fn classify(&self, start: DateTime<Local>, end: DateTime<Local>) -> Severity {
+        let duration = (end - start).to_std().unwrap_or_default();
+        let severity = if duration >= self.critical {
+            Severity::Critical
+        } else if duration >= self.major {
+            Severity::Major
+        } else if duration >= self.minor {
+            Severity::Minor
+        } else {
+            Severity::Info
+        };
+        match &self.business_hours {
+            Some(hours) if hours.overlaps(start, end) => severity.raised(),
+            _ => severity,
+        }
+    }
+}
+
+/// Classifies outages recorded before severities were, with the default
+/// thresholds.
+pub fn backfill(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare("SELECT id, start_time, end_time FROM outages WHERE severity IS NULL")?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let classifier = Classifier::default();
+    for (id, start, end) in rows {
+        // Unreadable rows are left to `db repair`
+        if let (Some(start), Some(end)) = (model::parse_timestamp(&start), model::parse_timestamp(&end)) {
+            conn.execute(
+                "UPDATE outages SET severity = ?1 WHERE id = ?2",
+                params![classifier.classify(start, end).name(), id],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// ANSI color of a severity in terminal tables.
+pub fn color(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Info => "\x1b[36m",
+        Severity::Minor => "\x1b[33m",
+        Severity::Major => "\x1b[31m",
+        Severity::Critical => "\x1b[1;97;41m",
+    }
+}
+
+pub const RESET_COLOR: &str = "\x1b[0m";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(value: &str) -> DateTime<Local> {
+        let time = chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap();
+        Local.from_local_datetime(&time).earliest().unwrap()
+    }
+
+    #[test]
+    fn parses_business_hours() {
+        for (input, days, hours) in [
+            ("mon-fri 09:00-17:00", "MTWTF..", "09:00-17:00"),
+            ("sat,sun 10:00-14:00", ".....SS", "10:00-14:00"),
+            ("mon,wed-thu 08:30-12:00", "M.WT...", "08:30-12:00"),
+            ("fri-mon 00:00-23:59", "M...FSS", "00:00-23:59"),
+            ("Tuesday 09:00 - 17:00", ".T.....", "09:00-17:00"),
+            ("  sun 06:00-07:00  ", "......S", "06:00-07:00"),
+        ] {
+            let parsed = BusinessHours::parse(input).unwrap_or_else(|e| panic!("{:?}: {}", input, e));
+            let letters: String =
+                parsed.days.iter().zip("MTWTFSS".chars()).map(|(on, letter)| if *on { letter } else { '.' }).collect();
+            assert_eq!(letters, days, "{:?}", input);
+            assert_eq!(format!("{}-{}", parsed.from.format("%H:%M"), parsed.to.format("%H:%M")), hours, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_business_hours() {
+        for input in [
+            "",
+            "mon-fri",
+            "09:00-17:00",
+            "funday 09:00-17:00",
+            "mon-funday 09:00-17:00",
+            "mon,,fri 09:00-17:00",
+            "mon-fri 9-17",
+            "mon-fri 09:00",
+            "mon-fri 09:00-25:00",
+            "mon-fri 17:00-09:00",
+            "mon-fri 09:00-09:00",
+        ] {
+            assert!(BusinessHours::parse(input).is_err(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn classifies_by_duration_and_business_hours() {
+        let classifier = Classifier::default();
+        // 2026-03-14 is a Saturday, 2026-03-16 a Monday
+        for (start, end, severity) in [
+            ("2026-03-14 12:00", "2026-03-14 12:00", Severity::Info),
+            ("2026-03-14 12:00", "2026-03-14 12:01", Severity::Minor),
+            ("2026-03-14 12:00", "2026-03-14 12:15", Severity::Major),
+            ("2026-03-14 12:00", "2026-03-14 13:00", Severity::Critical),
+            ("2026-03-16 08:00", "2026-03-16 08:59", Severity::Major),
+            ("2026-03-16 08:40", "2026-03-16 08:50", Severity::Minor),
+            ("2026-03-16 12:00", "2026-03-16 12:00", Severity::Minor),
+            ("2026-03-16 12:00", "2026-03-16 12:15", Severity::Critical),
+            ("2026-03-16 17:00", "2026-03-16 17:05", Severity::Minor),
+            // Over the weekend into Monday morning
+            ("2026-03-14 20:00", "2026-03-16 09:30", Severity::Critical),
+            ("2026-03-14 20:00", "2026-03-14 20:10", Severity::Minor),
+        ] {
+            assert_eq!(classifier.classify(local(start), local(end)), severity, "{} to {}", start, end);
+        }
+    }
+
+    #[test]
+    fn checks_the_configured_thresholds() {
+        let settings = |minor: &str, major: &str, business_hours: Option<&str>| Settings {
+            minor: Some(minor.to_string()),
+            major: Some(major.to_string()),
+            business_hours: business_hours.map(str::to_string),
+            ..Default::default()
+        };
+        let classifier = settings("30s", "5m", Some("none")).classifier().unwrap();
+        let start = local("2026-03-16 12:00");
+        assert_eq!(classifier.classify(start, start + Duration::from_secs(30)), Severity::Minor);
+        assert_eq!(classifier.classify(start, start + Duration::from_secs(300)), Severity::Major);
+        for (minor, major, business_hours) in
+            [("5m", "1m", None), ("soon", "5m", None), ("1m", "5m", Some("weekdays")), ("1m", "2h", None)]
+        {
+            assert!(settings(minor, major, business_hours).classifier().is_err(), "{} {} {:?}", minor, major, business_hours);
+        }
+    }
+}
@@ -0,0 +1,377 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
+use rusqlite::functions::FunctionFlags;
+use rusqlite::types::ValueRef;
+use rusqlite::{params, Connection, OptionalExtension};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::error::WebgoneError;
+
+/// File name of the signing key, stored next to the database.
+const KEY_FILE: &str = "webgone-signing.key";
+
+/// Columns of `outages` covered by a row's signature. Tags, tickets and
+/// acknowledgements live in tables of their own and aren't signed. Columns
+/// are only ever appended, together with a new `SIGNATURE_VERSION`.
+const SIGNED_COLUMNS: &[&str] = &[
+    "id",
+    "site",
+    "remote_id",
+    "start_time",
+    "end_time",
+    "duration_seconds",
+    "detected_at",
+    "confirmed_at",
+    "recovered_at",
+    "wifi_ssid",
+    "wifi_signal_dbm",
+    "weather_observed_at",
+    "weather_code",
+    "temperature_c",
+    "precipitation_mm",
+    "wind_speed_kmh",
+    "capture_path",
+    "failed_components",
+    "actor",
+    "failed_probes",
+    "clock_anomaly",
+    "derived_from",
+    "severity",
+];
+
+/// Version of the signatures written by this binary, stored in `metadata`.
+/// Version 1 covered the columns up to `actor`; databases without the key
+/// stay at that version until it is enabled again.
+const SIGNATURE_VERSION: i64 = 2;
+
+const SIGNATURE_VERSION_KEY: &str = "signature_version";
+
+/// Number of `SIGNED_COLUMNS` each signature version covers, starting at version 1.
+const VERSION_COLUMNS: &[usize] = &[19, SIGNED_COLUMNS.len()];
+
+/// SQL function computing a row's signature from the signed columns.
+const FUNCTION: &str = "webgone_signature";
+
+const BLOCK_SIZE: usize = 64;
+
+/// Secret key the outage rows are signed with.
+pub struct SigningKey([u8; 32]);
+
+/// Why a row failed verification.
+pub enum Problem {
+    /// The row was written without the key, e.g. by another tool or an older webgone
+    Unsigned,
+    /// The row changed after it was signed
+    Modified,
+}
+
+pub struct FailedRow {
+    pub id: i64,
+    pub start_time: Option<DateTime<Local>>,
+    pub problem: Problem,
+}
+
+pub fn key_path(db_path: &Path) -> PathBuf {
+    db_path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new(".")).join(KEY_FILE)
+}
+
+/// Reads a key file; `None` if it doesn't exist, i.e. signing isn't enabled.
+pub fn load(path: &Path) -> Result<Option<SigningKey>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(WebgoneError::Io(format!("failed to read {}: {}", path.display(), e)).into()),
+    };
+    let key = hex::decode(contents.trim())
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .ok_or_else(|| WebgoneError::InvalidInput(format!("{} is not a webgone signing key", path.display())))?;
+    Ok(Some(SigningKey(key)))
+}
+
+/// Loads the key stored next to the database, or generates one. Returns
+/// whether it was generated.
+pub fn load_or_create(db_path: &Path) -> Result<(SigningKey, bool)> {
+    let path = key_path(db_path);
+    if let Some(key) = load(&path)? {
+        return Ok((key, false));
+    }
+
+    let mut key = [0u8; 32];
+    getrandom::getrandom(&mut key).map_err(|e| anyhow!("Failed to generate a signing key: {}", e))?;
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(&path)
+        .and_then(|mut file| file.write_all(hex::encode(key).as_bytes()))
+        .map_err(|e| WebgoneError::Io(format!("failed to write {}: {}", path.display(), e)))?;
+    Ok((SigningKey(key), true))
+}
+
+fn hmac_sha256(key: &[u8; 32], message: &[u8]) -> [u8; 32] {
+    let mut inner_pad = [0x36u8; BLOCK_SIZE];
+    let mut outer_pad = [0x5cu8; BLOCK_SIZE];
+    for (i, byte) in key.iter().enumerate() {
+        inner_pad[i] ^= byte;
+        outer_pad[i] ^= byte;
+    }
+    let inner = Sha256::new().chain_update(inner_pad).chain_update(message).finalize();
+    Sha256::new().chain_update(outer_pad).chain_update(inner).finalize().into()
+}
+
+/// Appends a value with its type and length, so no two rows encode alike.
+fn encode(value: ValueRef, out: &mut Vec<u8>) {
+    match value {
+        ValueRef::Null => out.extend_from_slice(b"N;"),
+        ValueRef::Integer(i) => out.extend_from_slice(format!("I{};", i).as_bytes()),
+        ValueRef::Real(f) => out.extend_from_slice(format!("R{:016x};", f.to_bits()).as_bytes()),
+        ValueRef::Text(bytes) | ValueRef::Blob(bytes) => {
+            out.extend_from_slice(format!("T{}:", bytes.len()).as_bytes());
+            out.extend_from_slice(bytes);
+            out.push(b';');
+        }
+    }
+}
+
+/// Makes `webgone_signature(columns...)` available on the connection. It
+/// takes any number of columns, so signatures of older versions can be checked.
+fn register(conn: &Connection, key: SigningKey) -> Result<()> {
+    conn.create_scalar_function(
+        FUNCTION,
+        -1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        move |ctx| {
+            let mut message = Vec::new();
+            for i in 0..ctx.len() {
+                encode(ctx.get_raw(i), &mut message);
+            }
+            Ok(hex::encode(hmac_sha256(&key.0, &message)))
+        },
+    )?;
+    Ok(())
+}
+
+/// SQL computing the signature of `version` from the row's columns.
+fn versioned_signature_sql(version: i64, prefix: &str) -> String {
+    let count = VERSION_COLUMNS[(version - 1) as usize];
+    let columns: Vec<String> = SIGNED_COLUMNS[..count].iter().map(|column| format!("{}{}", prefix, column)).collect();
+    format!("{}({})", FUNCTION, columns.join(", "))
+}
+
+fn signature_sql(prefix: &str) -> String {
+    versioned_signature_sql(SIGNATURE_VERSION, prefix)
+}
+
+/// Signs every outage this connection writes from now on, if signing is
+/// enabled for the database. The triggers are temporary, so changes made
+/// with other tools keep their old signature and show up in `db verify`.
+pub fn install(conn: &Connection, db_path: &Path) -> Result<bool> {
+    let Some(key) = load(&key_path(db_path))? else {
+        return Ok(false);
+    };
+    enable(conn, key)?;
+    Ok(true)
+}
+
+/// Signs rows written through `conn` with `key`.
+pub fn enable(conn: &Connection, key: SigningKey) -> Result<()> {
+    register(conn, key)?;
+    let sign = format!("UPDATE outages SET signature = {} WHERE id = NEW.id;", signature_sql("NEW."));
+    conn.execute_batch(&format!(
+        "CREATE TEMP TRIGGER IF NOT EXISTS outages_insert_sign AFTER INSERT ON outages
+        BEGIN {sign} END;
+        CREATE TEMP TRIGGER IF NOT EXISTS outages_update_sign AFTER UPDATE ON outages
+        BEGIN {sign} END;"
+    ))?;
+    upgrade(conn)?;
+    Ok(())
+}
+
+/// Re-signs outages signed by an older version of webgone, so their
+/// signatures cover the columns added since. Only rows whose old signature
+/// still matches are re-signed; changed rows keep failing verification.
+fn upgrade(conn: &Connection) -> Result<()> {
+    let version = stored_version(conn)?;
+    if version >= SIGNATURE_VERSION {
+        return Ok(());
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        &format!(
+            "UPDATE outages SET signature = {} WHERE signature = {}",
+            signature_sql(""),
+            versioned_signature_sql(version, "")
+        ),
+        [],
+    )?;
+    tx.execute(
+        "INSERT OR REPLACE INTO metadata (key, value) VALUES (?1, ?2)",
+        params![SIGNATURE_VERSION_KEY, SIGNATURE_VERSION.to_string()],
+    )?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Version of the signatures in the database. Without the key next to it,
+/// e.g. when it is only kept elsewhere, they stay at the version they were
+/// written with.
+fn stored_version(conn: &Connection) -> Result<i64> {
+    let version = conn
+        .query_row("SELECT value FROM metadata WHERE key = ?", [SIGNATURE_VERSION_KEY], |row| row.get::<_, String>(0))
+        .optional()?
+        .and_then(|value| value.parse::<i64>().ok())
+        .unwrap_or(1);
+    Ok(version.clamp(1, SIGNATURE_VERSION))
+}
+
+/// Signs the outages that have no signature yet, e.g. those recorded before
+/// signing was enabled. Returns how many were signed.
+pub fn sign_unsigned(conn: &Connection) -> Result<usize> {
+    Ok(conn.execute(&format!("UPDATE outages SET signature = {} WHERE signature IS NULL", signature_sql("")), [])?)
+}
+
+/// Outages whose signature is missing or doesn't match their fields.
+pub fn verify(conn: &Connection, key: SigningKey) -> Result<Vec<FailedRow>> {
+    register(conn, key)?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, start_time, signature, {} FROM outages ORDER BY start_time, id",
+        versioned_signature_sql(stored_version(conn)?, "")
+    ))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|(id, start_time, signature, expected)| {
+            let problem = match signature {
+                None => Problem::Unsigned,
+                Some(signature) if signature != expected => Problem::Modified,
+                Some(_) => return None,
+            };
+            // A tampered start time may not even parse
+            let start_time =
+                DateTime::parse_from_rfc3339(&start_time).ok().map(|time| time.with_timezone(&Local));
+            Some(FailedRow { id, start_time, problem })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key() -> SigningKey {
+        SigningKey([7; 32])
+    }
+
+    /// Three outages signed the way version 1 did, of which the second was
+    /// changed afterwards and the third never signed.
+    fn version_1_database() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(&format!(
+            "CREATE TABLE outages ({}, signature TEXT);
+            CREATE TABLE metadata (key TEXT PRIMARY KEY, value TEXT);
+            INSERT INTO outages (id, start_time, end_time, duration_seconds, actor, severity) VALUES
+                (1, '2026-03-01T10:00:00+00:00', '2026-03-01T10:05:00+00:00', 300, NULL, 'minor'),
+                (2, '2026-03-02T10:00:00+00:00', '2026-03-02T10:05:00+00:00', 300, 'token 1', 'minor'),
+                (3, '2026-03-03T10:00:00+00:00', '2026-03-03T10:05:00+00:00', 300, NULL, 'minor');",
+            SIGNED_COLUMNS.join(", ")
+        ))
+        .unwrap();
+        register(&conn, key()).unwrap();
+        conn.execute(&format!("UPDATE outages SET signature = {} WHERE id < 3", versioned_signature_sql(1, "")), [])
+            .unwrap();
+        conn.execute("UPDATE outages SET duration_seconds = 1 WHERE id = 2", []).unwrap();
+        conn
+    }
+
+    fn failures(conn: &Connection) -> Vec<(i64, &'static str)> {
+        verify(conn, key())
+            .unwrap()
+            .into_iter()
+            .map(|row| (row.id, if matches!(row.problem, Problem::Unsigned) { "unsigned" } else { "modified" }))
+            .collect()
+    }
+
+    fn set_version(conn: &Connection, value: Option<&str>) {
+        conn.execute("DELETE FROM metadata", []).unwrap();
+        if let Some(value) = value {
+            conn.execute("INSERT INTO metadata (key, value) VALUES (?1, ?2)", [SIGNATURE_VERSION_KEY, value]).unwrap();
+        }
+    }
+
+    #[test]
+    fn reads_the_stored_version() {
+        let conn = version_1_database();
+        for (stored, version) in
+            [(None, 1), (Some("1"), 1), (Some("2"), 2), (Some("0"), 1), (Some("-5"), 1), (Some("99"), 2), (Some("two"), 1), (Some(""), 1)]
+        {
+            set_version(&conn, stored);
+            assert_eq!(stored_version(&conn).unwrap(), version, "{:?}", stored);
+        }
+    }
+
+    #[test]
+    fn verifies_rows_at_the_version_they_were_signed_with() {
+        let conn = version_1_database();
+        // Until the key is enabled again the rows are checked as version 1
+        assert_eq!(failures(&conn), [(2, "modified"), (3, "unsigned")]);
+        set_version(&conn, Some("2"));
+        assert_eq!(failures(&conn), [(1, "modified"), (2, "modified"), (3, "unsigned")]);
+    }
+
+    #[test]
+    fn upgrades_only_rows_that_still_match() {
+        for stored in [None, Some("1"), Some("garbage")] {
+            let conn = version_1_database();
+            set_version(&conn, stored);
+            enable(&conn, key()).unwrap();
+            assert_eq!(stored_version(&conn).unwrap(), SIGNATURE_VERSION, "{:?}", stored);
+            let signature = |id: i64| {
+                conn.query_row("SELECT signature FROM outages WHERE id = ?", [id], |row| row.get::<_, Option<String>>(0))
+                    .unwrap()
+            };
+            let expected: String =
+                conn.query_row(&format!("SELECT {} FROM outages WHERE id = 1", signature_sql("")), [], |row| row.get(0)).unwrap();
+            assert_eq!(signature(1), Some(expected), "{:?}", stored);
+            assert_eq!(failures(&conn), [(2, "modified"), (3, "unsigned")], "{:?}", stored);
+
+            // Enabling it again changes nothing
+            let before = (signature(1), signature(2));
+            enable(&conn, key()).unwrap();
+            assert_eq!((signature(1), signature(2)), before);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_keys() {
+        let dir = std::env::temp_dir().join(format!("webgone-signing-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(KEY_FILE);
+        for contents in ["", "not hex", "0707", &"07".repeat(33), &"zz".repeat(32)] {
+            fs::write(&path, contents).unwrap();
+            assert!(load(&path).is_err(), "{:?}", contents);
+        }
+        fs::write(&path, format!("{}\n", "07".repeat(32))).unwrap();
+        assert!(load(&path).unwrap().is_some_and(|key| key.0 == [7; 32]));
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(load(&path).unwrap().is_none());
+    }
+}
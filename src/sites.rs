@@ -0,0 +1,174 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Local};
+use rusqlite::types::Value;
+use rusqlite::Connection;
+
+use crate::coverage::{self, GapMode};
+
+/// Name under which `--site` and reports refer to data recorded on this
+/// machine; agents can't use it.
+pub const LOCAL_SITE: &str = "local";
+
+/// A site whose newest probe is older than this is reported as having no recent data.
+const STALE_AFTER: Duration = Duration::minutes(10);
+
+/// Value of the `site` column for a site name, NULL for local data. Compare with `site IS ?`.
+pub fn column_value(site: &str) -> Value {
+    if site == LOCAL_SITE {
+        Value::Null
+    } else {
+        Value::Text(site.to_string())
+    }
+}
+
+pub enum SiteState {
+    Up,
+    Down,
+    /// No probe came in recently, e.g. because the agent lost its connection
+    Stale,
+}
+
+pub struct SiteSummary {
+    pub site: String,
+    pub outages: i64,
+    /// Downtime within the period
+    pub downtime_seconds: i64,
+    /// Time without monitoring within the period; only known for local data
+    pub gap_seconds: Option<i64>,
+    pub uptime_percent: f64,
+    pub last_seen: Option<DateTime<Local>>,
+    pub state: Option<SiteState>,
+}
+
+/// Outages and downtime of every site in one month.
+pub struct MonthComparison {
+    /// `YYYY-MM`
+    pub month: String,
+    /// Number of outages and downtime in seconds, in the order of the sites
+    pub sites: Vec<(i64, i64)>,
+}
+
+fn parse_time(value: &str) -> Option<DateTime<Local>> {
+    DateTime::parse_from_rfc3339(value).ok().map(|time| time.with_timezone(&Local))
+}
+
+/// Sites with any outages or probes and services watched with `--service`,
+/// local data first.
+pub fn names(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT site FROM (SELECT site FROM outages UNION SELECT site FROM probes UNION SELECT name FROM service_status)
+        ORDER BY site IS NOT NULL, site"
+    )?;
+    let sites = stmt
+        .query_map([], |row| row.get::<_, Option<String>>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(sites.into_iter().map(|site| site.unwrap_or_else(|| LOCAL_SITE.to_string())).collect())
+}
+
+/// Outages, downtime and uptime of every site over the last `days` days,
+/// and whether it is up according to its newest probe. Monitoring gaps count
+/// as `gaps` says for local data and services; agents don't report theirs.
+/// Outages derived from what a service depends on are left out.
+pub fn summaries(conn: &Connection, days: u32, gaps: GapMode) -> Result<Vec<SiteSummary>> {
+    let now = Local::now();
+    let period_start = now - Duration::days(days.into());
+    let period_seconds = (now - period_start).num_seconds().max(1);
+
+    let mut outages = conn.prepare(
+        "SELECT start_time, end_time FROM outages
+        WHERE site IS ?1 AND derived_from IS NULL AND julianday(end_time) >= julianday(?2)"
+    )?;
+    // Services only save their state, not every check
+    let mut last_probe = conn.prepare(
+        "SELECT time, success FROM (
+            SELECT * FROM (SELECT time, success FROM probes WHERE site IS ?1 ORDER BY id DESC LIMIT 1)
+            UNION ALL
+            SELECT checked_at, up FROM service_status WHERE name IS ?1
+        )
+        ORDER BY julianday(time) DESC LIMIT 1"
+    )?;
+
+    names(conn)?
+        .into_iter()
+        .map(|site| {
+            let value = column_value(&site);
+            let periods = outages
+                .query_map(rusqlite::params![value, period_start.to_rfc3339()], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+
+            // Only count the part of outages that falls into the period
+            let downtime_seconds: i64 = periods
+                .iter()
+                .filter_map(|(start, end)| Some((parse_time(start)?, parse_time(end)?)))
+                .map(|(start, end)| (end.min(now) - start.max(period_start)).num_seconds().max(0))
+                .sum();
+
+            let last = last_probe
+                .query_row([&value], |row| Ok((row.get::<_, String>(0)?, row.get::<_, bool>(1)?)))
+                .ok()
+                .and_then(|(time, success)| Some((parse_time(&time)?, success)));
+            let state = last.map(|(time, success)| {
+                if now - time > STALE_AFTER {
+                    SiteState::Stale
+                } else if success {
+                    SiteState::Up
+                } else {
+                    SiteState::Down
+                }
+            });
+
+            // Services are watched by the local watcher, so they share its gaps
+            let watched_here = site == LOCAL_SITE
+                || conn.query_row("SELECT EXISTS (SELECT 1 FROM service_status WHERE name = ?)", [&site], |row| row.get(0))?;
+            let gap_seconds = if watched_here { Some(coverage::gap_seconds(conn, period_start, now)?) } else { None };
+
+            Ok(SiteSummary {
+                site,
+                outages: periods.len() as i64,
+                downtime_seconds,
+                gap_seconds,
+                uptime_percent: gaps.uptime_percent(period_seconds, downtime_seconds, gap_seconds.unwrap_or(0)),
+                last_seen: last.map(|(time, _)| time),
+                state,
+            })
+        })
+        .collect()
+}
+
+/// Number of outages and downtime per site in each of the last `months`
+/// months (newest first), with the sites in the order of [`names`].
+pub fn monthly_downtime(conn: &Connection, months: u32) -> Result<(Vec<String>, Vec<MonthComparison>)> {
+    let sites = names(conn)?;
+    let mut stmt = conn.prepare(
+        "SELECT strftime('%Y-%m', start_time, 'localtime') AS month, COUNT(*), SUM(duration_seconds)
+        FROM outages
+        WHERE site IS ?1 AND derived_from IS NULL AND month >= strftime('%Y-%m', 'now', 'localtime', 'start of month', ?2)
+        GROUP BY month"
+    )?;
+
+    let mut rows: Vec<MonthComparison> = Vec::new();
+    for (index, site) in sites.iter().enumerate() {
+        let offset = format!("-{} months", months.saturating_sub(1));
+        let per_month = stmt
+            .query_map(rusqlite::params![column_value(site), offset], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?, row.get::<_, i64>(2)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        for (month, count, seconds) in per_month {
+            let position = match rows.iter().position(|row| row.month == month) {
+                Some(position) => position,
+                None => {
+                    rows.push(MonthComparison { month, sites: vec![(0, 0); sites.len()] });
+                    rows.len() - 1
+                }
+            };
+            rows[position].sites[index] = (count, seconds);
+        }
+    }
+
+    rows.sort_by(|a, b| b.month.cmp(&a.month));
+    Ok((sites, rows))
+}
@@ -0,0 +1,278 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration as ChronoDuration, Timelike, Utc};
+use clap::ValueEnum;
+use rusqlite::{params, Connection};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Preset storage settings for the watcher.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum StorageProfile {
+    /// Write probes promptly with full durability
+    Default,
+    /// Minimize writes for SD cards and other flash media
+    Flash,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Synchronous {
+    Off,
+    Normal,
+    Full,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum JournalMode {
+    Delete,
+    Truncate,
+    Wal,
+}
+
+/// Resolved storage settings, after applying overrides on top of a profile.
+pub struct StorageSettings {
+    pub synchronous: Option<Synchronous>,
+    pub journal_mode: Option<JournalMode>,
+    pub batch_size: usize,
+    pub flush_interval: Duration,
+    /// Record only every Nth successful probe (failures are always recorded)
+    pub sample_every: u32,
+    /// Roll up and delete raw probes older than this many days
+    pub raw_retention_days: Option<u32>,
+    pub rollup_granularity: Granularity,
+}
+
+impl StorageProfile {
+    pub fn settings(self) -> StorageSettings {
+        match self {
+            StorageProfile::Default => StorageSettings {
+                synchronous: None,
+                // WAL lets report commands read while the watcher writes
+                journal_mode: Some(JournalMode::Wal),
+                batch_size: 20,
+                flush_interval: Duration::from_secs(30),
+                sample_every: 1,
+                raw_retention_days: None,
+                rollup_granularity: Granularity::Hour,
+            },
+            StorageProfile::Flash => StorageSettings {
+                synchronous: Some(Synchronous::Normal),
+                journal_mode: Some(JournalMode::Wal),
+                batch_size: 500,
+                flush_interval: Duration::from_secs(300),
+                sample_every: 10,
+                raw_retention_days: Some(7),
+                rollup_granularity: Granularity::Hour,
+            },
+        }
+    }
+}
+
+impl StorageSettings {
+    pub fn apply_pragmas(&self, conn: &Connection) -> Result<()> {
+        if let Some(journal_mode) = self.journal_mode {
+            let mode = match journal_mode {
+                JournalMode::Delete => "DELETE",
+                JournalMode::Truncate => "TRUNCATE",
+                JournalMode::Wal => "WAL",
+            };
+            // journal_mode returns the resulting mode as a row
+            conn.query_row(&format!("PRAGMA journal_mode = {mode}"), [], |_| Ok(()))?;
+        }
+        if let Some(synchronous) = self.synchronous {
+            let level = match synchronous {
+                Synchronous::Off => "OFF",
+                Synchronous::Normal => "NORMAL",
+                Synchronous::Full => "FULL",
+            };
+            conn.execute_batch(&format!("PRAGMA synchronous = {level}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Period length of rolled up probe aggregates.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Granularity {
+    Minute,
+    Hour,
+}
+
+impl Granularity {
+    fn table(self) -> &'static str {
+        match self {
+            Granularity::Minute => "probe_minutely",
+            Granularity::Hour => "probe_hourly",
+        }
+    }
+
+    /// strftime format truncating a timestamp to the start of its period
+    fn period_format(self) -> &'static str {
+        match self {
+            Granularity::Minute => "%Y-%m-%dT%H:%M:00Z",
+            Granularity::Hour => "%Y-%m-%dT%H:00:00Z",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Granularity::Minute => "per-minute",
+            Granularity::Hour => "hourly",
+        }
+    }
+}
+
+struct Rollup {
+    probes: i64,
+    failures: i64,
+    latencies: Vec<f64>,
+}
+
+fn percentile(sorted: &[f64], p: f64) -> Option<f64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = ((p / 100.0) * (sorted.len() - 1) as f64).round() as usize;
+    Some(sorted[rank])
+}
+
+/// Rolls raw probes older than `days` days up into per-minute or hourly
+/// aggregates and deletes them, so aggregates are kept forever while raw data
+/// stays bounded. Percentiles are computed from the raw samples of each period.
+///
+/// The cutoff is aligned to the start of an hour, so every period is rolled up
+/// exactly once. Returns the number of raw probes removed.
+pub fn roll_up_probes(conn: &Connection, days: u32, granularity: Granularity) -> Result<usize> {
+    roll_up_probes_before(conn, Utc::now() - ChronoDuration::days(days.into()), granularity)
+}
+
+/// Start of the hour `time` falls into.
+pub fn start_of_hour(time: DateTime<Utc>) -> DateTime<Utc> {
+    time.with_minute(0)
+        .and_then(|t| t.with_second(0))
+        .and_then(|t| t.with_nanosecond(0))
+        .expect("start of hour is always valid")
+}
+
+/// Rolls up and deletes the raw probes before the start of the hour `cutoff`
+/// falls into, like `roll_up_probes`.
+pub fn roll_up_probes_before(conn: &Connection, cutoff: DateTime<Utc>, granularity: Granularity) -> Result<usize> {
+    let cutoff = start_of_hour(cutoff).to_rfc3339();
+
+    let mut rollups: BTreeMap<(String, String), Rollup> = BTreeMap::new();
+    {
+        let mut stmt = conn.prepare(&format!("
+            SELECT target, strftime('{}', time), success, latency_ms, weight
+            FROM probes
+            WHERE julianday(time) < julianday(?)
+        ", granularity.period_format()))?;
+        let rows = stmt.query_map([&cutoff], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, bool>(2)?,
+                row.get::<_, Option<f64>>(3)?,
+                row.get::<_, i64>(4)?,
+            ))
+        })?;
+
+        for row in rows {
+            let (target, period, success, latency, weight) = row?;
+            let rollup = rollups.entry((target, period)).or_insert(Rollup {
+                probes: 0,
+                failures: 0,
+                latencies: Vec::new(),
+            });
+            rollup.probes += weight;
+            if !success {
+                rollup.failures += weight;
+            }
+            rollup.latencies.extend(latency);
+        }
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    {
+        let mut stmt = tx.prepare(&format!("
+            INSERT OR REPLACE INTO {} (
+                target, period, probes, failures,
+                min_latency_ms, avg_latency_ms, max_latency_ms, p50_latency_ms, p95_latency_ms, p99_latency_ms
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+        ", granularity.table()))?;
+        for ((target, period), mut rollup) in rollups {
+            rollup.latencies.sort_by(f64::total_cmp);
+            let latencies = &rollup.latencies;
+            let avg = (!latencies.is_empty()).then(|| latencies.iter().sum::<f64>() / latencies.len() as f64);
+            stmt.execute(params![
+                target,
+                period,
+                rollup.probes,
+                rollup.failures,
+                latencies.first(),
+                avg,
+                latencies.last(),
+                percentile(latencies, 50.0),
+                percentile(latencies, 95.0),
+                percentile(latencies, 99.0),
+            ])?;
+        }
+    }
+    let removed = tx.execute("DELETE FROM probes WHERE julianday(time) < julianday(?)", [&cutoff])?;
+    tx.commit()?;
+
+    Ok(removed)
+}
+
+/// A short description of the effective settings.
+pub fn describe(settings: &StorageSettings) -> String {
+    let retention = match settings.raw_retention_days {
+        Some(days) => format!("{} days, then {} aggregates", days, settings.rollup_granularity.name()),
+        None => "forever".to_string(),
+    };
+    format!(
+        "Storage: writing probes every {} results or {} seconds, recording every {} successful probe(s), keeping raw probes {}",
+        settings.batch_size,
+        settings.flush_interval.as_secs(),
+        settings.sample_every,
+        retention,
+    )
+}
+
+/// Creates the aggregate tables raw probes are rolled up into.
+pub fn init_aggregate_tables(conn: &Connection) -> Result<()> {
+    // Before there were several granularities, hourly aggregates were keyed by `hour`
+    let table = Granularity::Hour.table();
+    let keyed_by_hour: bool = conn.query_row(
+        &format!("SELECT EXISTS (SELECT 1 FROM pragma_table_info('{table}') WHERE name = 'hour')"),
+        [],
+        |row| row.get(0),
+    )?;
+    if keyed_by_hour {
+        conn.execute(&format!("ALTER TABLE {table} RENAME COLUMN hour TO period"), [])?;
+    }
+
+    for granularity in [Granularity::Minute, Granularity::Hour] {
+        conn.execute(
+            &format!(
+                "CREATE TABLE IF NOT EXISTS {} (
+                    target TEXT NOT NULL,
+                    period TEXT NOT NULL,
+                    probes INTEGER NOT NULL,
+                    failures INTEGER NOT NULL,
+                    min_latency_ms REAL,
+                    avg_latency_ms REAL,
+                    max_latency_ms REAL,
+                    p50_latency_ms REAL,
+                    p95_latency_ms REAL,
+                    p99_latency_ms REAL,
+                    PRIMARY KEY (target, period)
+                )",
+                granularity.table()
+            ),
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+/// Time between retention runs while watching.
+pub const RETENTION_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
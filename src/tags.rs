@@ -0,0 +1,89 @@
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::error::WebgoneError;
+
+/// Number of outages and their total duration for one tag.
+pub struct TagSummary {
+    pub tag: String,
+    pub outages: i64,
+    pub total_seconds: i64,
+}
+
+pub fn init_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS outage_tags (
+            outage_id INTEGER NOT NULL REFERENCES outages(id),
+            tag TEXT NOT NULL,
+            PRIMARY KEY (outage_id, tag)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn ensure_outage_exists(conn: &Connection, outage_id: i64) -> Result<()> {
+    let exists = conn
+        .query_row("SELECT 1 FROM outages WHERE id = ?", [outage_id], |_| Ok(()))
+        .optional()?
+        .is_some();
+    if !exists {
+        return Err(WebgoneError::InvalidInput(format!("no outage with id {}", outage_id)).into());
+    }
+    Ok(())
+}
+
+/// Adds tags to an outage; tags it already has are kept once.
+pub fn add(conn: &Connection, outage_id: i64, tags: &[String]) -> Result<()> {
+    ensure_outage_exists(conn, outage_id)?;
+    let tx = conn.unchecked_transaction()?;
+    {
+        let mut stmt = tx.prepare("INSERT OR IGNORE INTO outage_tags (outage_id, tag) VALUES (?1, ?2)")?;
+        for tag in tags {
+            stmt.execute(params![outage_id, tag])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+pub fn remove(conn: &Connection, outage_id: i64, tags: &[String]) -> Result<()> {
+    ensure_outage_exists(conn, outage_id)?;
+    let tx = conn.unchecked_transaction()?;
+    {
+        let mut stmt = tx.prepare("DELETE FROM outage_tags WHERE outage_id = ?1 AND tag = ?2")?;
+        for tag in tags {
+            stmt.execute(params![outage_id, tag])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+pub fn for_outage(conn: &Connection, outage_id: i64) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT tag FROM outage_tags WHERE outage_id = ? ORDER BY tag")?;
+    let tags = stmt
+        .query_map([outage_id], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(tags)
+}
+
+pub fn summary(conn: &Connection) -> Result<Vec<TagSummary>> {
+    let mut stmt = conn.prepare(
+        "SELECT t.tag, COUNT(*), SUM(o.duration_seconds)
+        FROM outage_tags t
+        JOIN outages o ON o.id = t.outage_id
+        GROUP BY t.tag
+        ORDER BY COUNT(*) DESC, t.tag"
+    )?;
+    let summary = stmt
+        .query_map([], |row| {
+            Ok(TagSummary {
+                tag: row.get(0)?,
+                outages: row.get(1)?,
+                total_seconds: row.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(summary)
+}
@@ -0,0 +1,441 @@
+use clap::ValueEnum;
+use std::collections::VecDeque;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::dnsbench;
+use crate::icmp;
+
+/// Highly available anycast resolvers used by `--target auto`, spread over
+/// several providers so a single provider's outage never looks like ours.
+const ANYCAST_ENDPOINTS: &[[u8; 4]] = &[
+    [1, 1, 1, 1],         // Cloudflare
+    [1, 0, 0, 1],         // Cloudflare
+    [8, 8, 8, 8],         // Google
+    [8, 8, 4, 4],         // Google
+    [9, 9, 9, 9],         // Quad9
+    [149, 112, 112, 112], // Quad9
+    [208, 67, 222, 222],  // OpenDNS
+    [208, 67, 220, 220],  // OpenDNS
+];
+
+/// How many different endpoints are tried before a check counts as failed.
+const MAX_ATTEMPTS: usize = 3;
+
+/// Lower bound for an endpoint's selection weight, so an endpoint that failed
+/// a lot still gets probed occasionally and can recover.
+const MIN_WEIGHT: f64 = 0.05;
+
+/// Successful probes the adaptive timeout is derived from; older ones are dropped.
+const BASELINE_SIZE: usize = 500;
+
+/// Successful probes needed before the timeout adapts.
+const MIN_BASELINE: usize = 20;
+
+/// The adaptive timeout is this multiple of the baseline's 99th percentile latency.
+pub const TIMEOUT_FACTOR: u32 = 3;
+
+/// How a target is probed.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ProbeMode {
+    /// Open a TCP connection
+    Tcp,
+    /// Send an ICMP echo request, like ping
+    Icmp,
+    /// Fetch --url and check the status code
+    Http,
+    /// Resolve --domain at the resolver
+    Dns,
+}
+
+/// Range of HTTP status codes that count as success, given as `200`,
+/// `200-399` or `2xx`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct StatusRange {
+    pub min: u16,
+    pub max: u16,
+}
+
+impl StatusRange {
+    pub fn contains(self, status: u16) -> bool {
+        (self.min..=self.max).contains(&status)
+    }
+}
+
+impl FromStr for StatusRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let usage = || format!("expected a status code like 200, a range like 200-399 or a class like 2xx, got `{}`", s);
+        let s = s.trim();
+        let (min, max) = match (s.split_once('-'), s.strip_suffix("xx")) {
+            (Some((min, max)), _) => (min.trim().parse().map_err(|_| usage())?, max.trim().parse().map_err(|_| usage())?),
+            (None, Some(class)) => {
+                let class: u16 = class.parse().map_err(|_| usage())?;
+                (class * 100, class * 100 + 99)
+            }
+            (None, None) => {
+                let status = s.parse().map_err(|_| usage())?;
+                (status, status)
+            }
+        };
+        if !(100..=599).contains(&min) || !(100..=599).contains(&max) || min > max {
+            return Err(usage());
+        }
+        Ok(StatusRange { min, max })
+    }
+}
+
+impl fmt::Display for StatusRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.min == self.max {
+            write!(f, "{}", self.min)
+        } else {
+            write!(f, "{}-{}", self.min, self.max)
+        }
+    }
+}
+
+/// A single endpoint and how to probe it.
+#[derive(Clone, PartialEq, Eq)]
+pub enum Target {
+    Tcp(SocketAddr),
+    Icmp(IpAddr),
+    /// A URL that has to answer with a status in `expect`
+    Http { url: String, expect: StatusRange },
+    /// A resolver that has to answer a query for `domain`
+    Dns { resolver: SocketAddr, domain: String },
+}
+
+impl Target {
+    /// The target at `ip` for `mode`, with `port` for TCP and DNS and the
+    /// domain to resolve for DNS; `None` for HTTP, which fetches URLs rather
+    /// than addresses.
+    pub fn new(mode: ProbeMode, ip: IpAddr, port: u16, domain: &str) -> Option<Self> {
+        match mode {
+            ProbeMode::Tcp => Some(Target::Tcp(SocketAddr::new(ip, port))),
+            ProbeMode::Icmp => Some(Target::Icmp(ip)),
+            ProbeMode::Http => None,
+            ProbeMode::Dns => Some(Target::Dns { resolver: SocketAddr::new(ip, port), domain: domain.to_string() }),
+        }
+    }
+
+    /// Name of the probe type, as recorded with an outage in `failed_probes`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Target::Tcp(_) => "tcp",
+            Target::Icmp(_) => "icmp",
+            Target::Http { .. } => "http",
+            Target::Dns { .. } => "dns",
+        }
+    }
+}
+
+/// TCP targets are shown as IP:PORT, as before there were other probes.
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Tcp(addr) => write!(f, "{}", addr),
+            Target::Icmp(ip) => write!(f, "icmp:{}", ip),
+            Target::Http { url, .. } => write!(f, "{}", url),
+            Target::Dns { resolver, domain } => write!(f, "dns:{}/{}", resolver, domain),
+        }
+    }
+}
+
+/// Where the watcher sends its probes.
+#[derive(Clone)]
+pub enum TargetSpec {
+    /// Rotate over the built-in anycast endpoints
+    Auto,
+    Target(Target),
+    /// An IP address probed the way `--probe` says, on `--port` for TCP
+    Ip(IpAddr),
+    /// A URL fetched over HTTP(S)
+    Url(String),
+    /// A resolver queried for `domain`, or for `--domain` if not given
+    Dns { resolver: SocketAddr, domain: Option<String> },
+}
+
+impl TargetSpec {
+    /// The target to probe, with `mode` and `port` for a bare IP, the status
+    /// codes in `expect` for a URL and `domain` for resolvers given without one.
+    pub fn resolve(&self, mode: ProbeMode, port: u16, expect: StatusRange, domain: &str) -> Result<Target, String> {
+        match self {
+            TargetSpec::Auto => Err("--target auto can't be combined with other targets".to_string()),
+            TargetSpec::Target(target) => Ok(target.clone()),
+            TargetSpec::Ip(ip) => Target::new(mode, *ip, port, domain)
+                .ok_or_else(|| format!("--probe http fetches URLs, so give {} as IP:PORT or icmp:{}", ip, ip)),
+            TargetSpec::Url(url) => Ok(Target::Http { url: url.clone(), expect }),
+            TargetSpec::Dns { resolver, domain: own } => {
+                Ok(Target::Dns { resolver: *resolver, domain: own.as_deref().unwrap_or(domain).to_string() })
+            }
+        }
+    }
+}
+
+impl FromStr for TargetSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let usage = || {
+            format!("expected `auto`, IP, IP:PORT, tcp:IP:PORT, icmp:IP, dns:IP[:PORT][/DOMAIN] or an http(s):// URL, got `{}`", s)
+        };
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(TargetSpec::Auto);
+        }
+        if s.starts_with("http://") || s.starts_with("https://") {
+            return Ok(TargetSpec::Url(s.to_string()));
+        }
+        if let Some(addr) = s.strip_prefix("tcp:") {
+            return addr.parse().map(|addr| TargetSpec::Target(Target::Tcp(addr))).map_err(|_| usage());
+        }
+        if let Some(ip) = s.strip_prefix("icmp:") {
+            return ip.parse().map(|ip| TargetSpec::Target(Target::Icmp(ip))).map_err(|_| usage());
+        }
+        if let Some(spec) = s.strip_prefix("dns:") {
+            let (address, domain) = match spec.split_once('/') {
+                Some((address, domain)) if !domain.is_empty() => (address, Some(domain.to_string())),
+                Some(_) => return Err(usage()),
+                None => (spec, None),
+            };
+            let resolver = address
+                .parse::<SocketAddr>()
+                .or_else(|_| address.parse::<IpAddr>().map(|ip| SocketAddr::new(ip, 53)))
+                .map_err(|_| usage())?;
+            return Ok(TargetSpec::Dns { resolver, domain });
+        }
+        s.parse()
+            .map(|addr| TargetSpec::Target(Target::Tcp(addr)))
+            .or_else(|_| s.parse().map(TargetSpec::Ip))
+            .map_err(|_| usage())
+    }
+}
+
+/// How many of several targets have to fail before a check counts as failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Quorum {
+    All,
+    Failures(usize),
+}
+
+impl Quorum {
+    /// Failed targets out of `targets` needed for a failed check.
+    pub fn required(self, targets: usize) -> usize {
+        match self {
+            Quorum::All => targets,
+            Quorum::Failures(failures) => failures,
+        }
+    }
+}
+
+impl FromStr for Quorum {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("all") {
+            return Ok(Quorum::All);
+        }
+        match s.parse() {
+            Ok(failures) if failures > 0 => Ok(Quorum::Failures(failures)),
+            _ => Err(format!("expected `all` or a number of targets of at least 1, got `{}`", s)),
+        }
+    }
+}
+
+impl fmt::Display for Quorum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Quorum::All => write!(f, "all"),
+            Quorum::Failures(failures) => write!(f, "{}", failures),
+        }
+    }
+}
+
+/// Outcome of a single probe.
+#[derive(Clone)]
+pub struct ProbeResult {
+    pub target: Target,
+    pub elapsed: Duration,
+    pub error: Option<String>,
+}
+
+impl ProbeResult {
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+pub fn probe(target: &Target, timeout: Duration) -> ProbeResult {
+    let start = Instant::now();
+    let error = match target {
+        Target::Tcp(addr) => TcpStream::connect_timeout(addr, timeout).err().map(|e| e.to_string()),
+        Target::Icmp(ip) => icmp::ping(*ip, timeout).err().map(|e| e.to_string()),
+        Target::Http { url, expect } => fetch(url, *expect, timeout).err(),
+        // Each query has a socket of its own, so the id doesn't have to be unique
+        Target::Dns { resolver, domain } => dnsbench::query(*resolver, domain, 1, timeout).err(),
+    };
+
+    ProbeResult {
+        target: target.clone(),
+        elapsed: start.elapsed(),
+        error,
+    }
+}
+
+/// Fetches `url`, following redirects, and checks that the final status is
+/// in `expect`. A fresh connection each time, so a broken resolver or route
+/// isn't hidden by a connection kept alive from before.
+fn fetch(url: &str, expect: StatusRange, timeout: Duration) -> Result<(), String> {
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(timeout))
+        .http_status_as_error(false)
+        .build()
+        .into();
+    let status = agent.get(url).call().map_err(|e| e.to_string())?.status().as_u16();
+    if expect.contains(status) {
+        Ok(())
+    } else {
+        Err(format!("HTTP status {}, expected {}", status, expect))
+    }
+}
+
+/// Probes every target at once, so a check takes as long as the slowest one.
+pub fn probe_all(targets: &[Target], timeout: Duration) -> Vec<ProbeResult> {
+    thread::scope(|scope| {
+        let handles: Vec<_> = targets.iter().map(|target| scope.spawn(move || probe(target, timeout))).collect();
+        handles
+            .into_iter()
+            .zip(targets)
+            .map(|(handle, target)| {
+                handle.join().unwrap_or_else(|_| ProbeResult {
+                    target: target.clone(),
+                    elapsed: Duration::ZERO,
+                    error: Some("probe panicked".to_string()),
+                })
+            })
+            .collect()
+    })
+}
+
+struct Endpoint {
+    target: Target,
+    /// Exponentially weighted success rate, between 0 and 1
+    health: f64,
+    current_weight: f64,
+}
+
+/// A set of interchangeable endpoints probed in health-weighted rotation.
+pub struct TargetPool {
+    endpoints: Vec<Endpoint>,
+}
+
+impl TargetPool {
+    /// The built-in anycast endpoints, probed with `mode`, on `port` for TCP
+    /// and DNS, resolving `domain` for DNS.
+    pub fn anycast(mode: ProbeMode, port: u16, domain: &str) -> Self {
+        TargetPool {
+            endpoints: ANYCAST_ENDPOINTS
+                .iter()
+                .filter_map(|ip| {
+                    Some(Endpoint { target: Target::new(mode, IpAddr::from(*ip), port, domain)?, health: 1.0, current_weight: 0.0 })
+                })
+                .collect(),
+        }
+    }
+
+    pub fn endpoint_count(&self) -> usize {
+        self.endpoints.len()
+    }
+
+    /// Picks the next endpoint using smooth weighted round-robin, so healthy
+    /// endpoints are probed more often without starving the others.
+    fn pick(&mut self, tried: &[usize]) -> Option<usize> {
+        let candidates: Vec<usize> = (0..self.endpoints.len()).filter(|i| !tried.contains(i)).collect();
+        let total: f64 = candidates.iter().map(|&i| self.endpoints[i].health.max(MIN_WEIGHT)).sum();
+
+        for &i in &candidates {
+            let endpoint = &mut self.endpoints[i];
+            endpoint.current_weight += endpoint.health.max(MIN_WEIGHT);
+        }
+
+        let best = candidates.into_iter().max_by(|&a, &b| {
+            self.endpoints[a].current_weight.total_cmp(&self.endpoints[b].current_weight)
+        })?;
+        self.endpoints[best].current_weight -= total;
+        Some(best)
+    }
+
+    /// Probes endpoints until one answers, trying up to `MAX_ATTEMPTS`
+    /// different ones. Returns every attempt; the check succeeded if the last
+    /// one did.
+    pub fn check(&mut self, timeout: Duration) -> Vec<ProbeResult> {
+        let mut tried = Vec::new();
+        let mut results = Vec::new();
+
+        while tried.len() < MAX_ATTEMPTS {
+            let Some(index) = self.pick(&tried) else { break };
+            tried.push(index);
+
+            let result = probe(&self.endpoints[index].target, timeout);
+            let success = result.is_success();
+            let endpoint = &mut self.endpoints[index];
+            endpoint.health = endpoint.health * 0.8 + if success { 0.2 } else { 0.0 };
+
+            results.push(result);
+            if success {
+                break;
+            }
+        }
+
+        results
+    }
+}
+
+/// Probe timeout derived from a rolling baseline of recent latencies, so slow
+/// links (satellite, congested) aren't cut off and fast ones fail quickly.
+pub struct AdaptiveTimeout {
+    latencies: VecDeque<Duration>,
+    initial: Duration,
+    min: Duration,
+    max: Duration,
+}
+
+impl AdaptiveTimeout {
+    /// Uses `initial` until the baseline is established, and keeps the
+    /// timeout between `min` and `max` afterwards.
+    pub fn new(initial: Duration, min: Duration, max: Duration) -> Self {
+        AdaptiveTimeout { latencies: VecDeque::with_capacity(BASELINE_SIZE), initial, min, max }
+    }
+
+    /// Adds a probe to the baseline. Failed probes are left out, so an outage
+    /// doesn't stretch the timeout.
+    pub fn record(&mut self, result: &ProbeResult) {
+        if !result.is_success() {
+            return;
+        }
+        if self.latencies.len() == BASELINE_SIZE {
+            self.latencies.pop_front();
+        }
+        self.latencies.push_back(result.elapsed);
+    }
+
+    /// 99th percentile latency of the baseline, once there are enough probes.
+    pub fn p99(&self) -> Option<Duration> {
+        if self.latencies.len() < MIN_BASELINE {
+            return None;
+        }
+        let mut sorted: Vec<Duration> = self.latencies.iter().copied().collect();
+        sorted.sort();
+        let index = (sorted.len() as f64 * 0.99).ceil() as usize - 1;
+        Some(sorted[index])
+    }
+
+    pub fn current(&self) -> Duration {
+        let timeout = self.p99().map_or(self.initial, |p99| p99 * TIMEOUT_FACTOR);
+        timeout.clamp(self.min, self.max)
+    }
+}
@@ -0,0 +1,115 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::error::WebgoneError;
+
+/// An ISP support ticket with the outages linked to it.
+pub struct Ticket {
+    pub reference: String,
+    pub note: Option<String>,
+    pub opened_at: DateTime<Local>,
+    pub closed_at: Option<DateTime<Local>>,
+    pub outages: i64,
+    pub downtime_seconds: i64,
+}
+
+pub fn init_tables(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS tickets (
+            id INTEGER PRIMARY KEY,
+            reference TEXT NOT NULL UNIQUE,
+            note TEXT,
+            opened_at TEXT NOT NULL,
+            closed_at TEXT
+        );
+        CREATE TABLE IF NOT EXISTS ticket_outages (
+            ticket_id INTEGER NOT NULL REFERENCES tickets(id),
+            outage_id INTEGER NOT NULL REFERENCES outages(id),
+            PRIMARY KEY (ticket_id, outage_id)
+        );"
+    )?;
+    Ok(())
+}
+
+fn ticket_id(conn: &Connection, reference: &str) -> Result<i64> {
+    conn.query_row("SELECT id FROM tickets WHERE reference = ?", [reference], |row| row.get(0))
+        .optional()?
+        .ok_or_else(|| WebgoneError::InvalidInput(format!("no ticket {}", reference)).into())
+}
+
+pub fn open(conn: &Connection, reference: &str, note: Option<&str>) -> Result<()> {
+    let exists = conn
+        .query_row("SELECT 1 FROM tickets WHERE reference = ?", [reference], |_| Ok(()))
+        .optional()?
+        .is_some();
+    if exists {
+        return Err(WebgoneError::InvalidInput(format!("ticket {} already exists", reference)).into());
+    }
+
+    conn.execute(
+        "INSERT INTO tickets (reference, note, opened_at) VALUES (?1, ?2, ?3)",
+        params![reference, note, Local::now().to_rfc3339()],
+    )?;
+    Ok(())
+}
+
+pub fn close(conn: &Connection, reference: &str) -> Result<()> {
+    let id = ticket_id(conn, reference)?;
+    conn.execute(
+        "UPDATE tickets SET closed_at = ?1 WHERE id = ?2 AND closed_at IS NULL",
+        params![Local::now().to_rfc3339(), id],
+    )?;
+    Ok(())
+}
+
+pub fn link(conn: &Connection, reference: &str, outage_ids: &[i64]) -> Result<()> {
+    let id = ticket_id(conn, reference)?;
+    let tx = conn.unchecked_transaction()?;
+    {
+        let mut exists = tx.prepare("SELECT 1 FROM outages WHERE id = ?")?;
+        let mut insert = tx.prepare("INSERT OR IGNORE INTO ticket_outages (ticket_id, outage_id) VALUES (?1, ?2)")?;
+        for outage_id in outage_ids {
+            if !exists.exists([outage_id])? {
+                return Err(WebgoneError::InvalidInput(format!("no outage with id {}", outage_id)).into());
+            }
+            insert.execute(params![id, outage_id])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// All tickets, unresolved ones first, with their linked outages summed up.
+pub fn list(conn: &Connection, only_open: bool) -> Result<Vec<Ticket>> {
+    let parse_time = |value: String| {
+        DateTime::parse_from_rfc3339(&value)
+            .map(|time| time.with_timezone(&Local))
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e)))
+    };
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT t.reference, t.note, t.opened_at, t.closed_at,
+            COUNT(o.id), COALESCE(SUM(o.duration_seconds), 0)
+        FROM tickets t
+        LEFT JOIN ticket_outages l ON l.ticket_id = t.id
+        LEFT JOIN outages o ON o.id = l.outage_id
+        {}
+        GROUP BY t.id
+        ORDER BY t.closed_at IS NOT NULL, t.opened_at DESC",
+        if only_open { "WHERE t.closed_at IS NULL" } else { "" }
+    ))?;
+    let tickets = stmt
+        .query_map([], |row| {
+            Ok(Ticket {
+                reference: row.get(0)?,
+                note: row.get(1)?,
+                opened_at: parse_time(row.get(2)?)?,
+                closed_at: row.get::<_, Option<String>>(3)?.map(parse_time).transpose()?,
+                outages: row.get(4)?,
+                downtime_seconds: row.get(5)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(tickets)
+}
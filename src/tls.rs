@@ -0,0 +1,76 @@
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use crate::error::WebgoneError;
+
+/// File names of the generated certificate, stored next to the database so
+/// clients that trusted it once keep working after a restart.
+const SELF_SIGNED_CERT: &str = "webgone-tls-cert.pem";
+const SELF_SIGNED_KEY: &str = "webgone-tls-key.pem";
+
+pub fn read_pem(path: &Path) -> Result<Vec<u8>> {
+    fs::read(path).map_err(|e| WebgoneError::Io(format!("failed to read {}: {}", path.display(), e)).into())
+}
+
+/// A PEM encoded certificate chain and private key.
+pub struct Certificate {
+    pub certificate: Vec<u8>,
+    pub private_key: Vec<u8>,
+}
+
+impl Certificate {
+    pub fn load(certificate: &Path, private_key: &Path) -> Result<Self> {
+        Ok(Certificate { certificate: read_pem(certificate)?, private_key: read_pem(private_key)? })
+    }
+
+    /// Loads the self-signed certificate stored in `dir`, or generates one
+    /// valid for `hosts` (names or IP addresses). Returns whether it was generated.
+    pub fn self_signed(dir: &Path, hosts: Vec<String>) -> Result<(Self, bool)> {
+        let cert_path = dir.join(SELF_SIGNED_CERT);
+        let key_path = dir.join(SELF_SIGNED_KEY);
+        if cert_path.exists() && key_path.exists() {
+            return Ok((Self::load(&cert_path, &key_path)?, false));
+        }
+
+        let generated = rcgen::generate_simple_self_signed(hosts).context("Failed to generate a certificate")?;
+        let certificate = Certificate {
+            certificate: generated.cert.pem().into_bytes(),
+            private_key: generated.key_pair.serialize_pem().into_bytes(),
+        };
+
+        let write = |path: &Path, contents: &[u8], private: bool| {
+            let mut options = fs::OpenOptions::new();
+            options.write(true).create(true).truncate(true);
+            #[cfg(unix)]
+            if private {
+                use std::os::unix::fs::OpenOptionsExt;
+                options.mode(0o600);
+            }
+            #[cfg(not(unix))]
+            let _ = private;
+            options
+                .open(path)
+                .and_then(|mut file| file.write_all(contents))
+                .map_err(|e| WebgoneError::Io(format!("failed to write {}: {}", path.display(), e)))
+        };
+        write(&cert_path, &certificate.certificate, false)?;
+        write(&key_path, &certificate.private_key, true)?;
+
+        Ok((certificate, true))
+    }
+
+    /// SHA-256 fingerprint of the leaf certificate, as shown by browsers and
+    /// `openssl x509 -fingerprint -sha256`.
+    pub fn fingerprint(&self) -> Result<String> {
+        let pem = pem::parse(&self.certificate)
+            .map_err(|e| WebgoneError::InvalidInput(format!("invalid certificate: {}", e)))?;
+        Ok(Sha256::digest(pem.contents())
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect::<Vec<_>>()
+            .join(":"))
+    }
+}
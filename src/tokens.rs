@@ -0,0 +1,138 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use rusqlite::{params, Connection, OptionalExtension};
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+use crate::error::WebgoneError;
+
+/// Prefix of generated tokens, so they are easy to recognize in configs and secret scanners.
+const TOKEN_PREFIX: &str = "wg_";
+
+/// What an API token may do. Write tokens can also read.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Scope {
+    Read,
+    Write,
+}
+
+impl Scope {
+    fn as_str(self) -> &'static str {
+        match self {
+            Scope::Read => "read",
+            Scope::Write => "write",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "read" => Some(Scope::Read),
+            "write" => Some(Scope::Write),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A stored API token. The token itself is only shown when it is created.
+pub struct Token {
+    pub id: i64,
+    pub name: Option<String>,
+    pub scope: Scope,
+    pub created_at: DateTime<Local>,
+    pub last_used_at: Option<DateTime<Local>>,
+}
+
+pub fn init_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS api_tokens (
+            id INTEGER PRIMARY KEY,
+            name TEXT,
+            scope TEXT NOT NULL,
+            token_hash BLOB NOT NULL UNIQUE,
+            created_at TEXT NOT NULL,
+            last_used_at TEXT
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn hash(token: &str) -> Vec<u8> {
+    Sha256::digest(token.as_bytes()).to_vec()
+}
+
+/// Generates a random token and stores its hash. Returns the token id and the token.
+pub fn create(conn: &Connection, name: Option<&str>, scope: Scope) -> Result<(i64, String)> {
+    let mut secret = [0u8; 32];
+    getrandom::getrandom(&mut secret).map_err(|e| anyhow!("Failed to generate a random token: {}", e))?;
+    let token = format!("{}{}", TOKEN_PREFIX, hex::encode(secret));
+
+    conn.execute(
+        "INSERT INTO api_tokens (name, scope, token_hash, created_at) VALUES (?1, ?2, ?3, ?4)",
+        params![name, scope.as_str(), hash(&token), Local::now().to_rfc3339()],
+    )?;
+    Ok((conn.last_insert_rowid(), token))
+}
+
+/// Returns the scope of a token and who uses it, or `None` if it is unknown
+/// or revoked, and records that it was used.
+pub fn authenticate(conn: &Connection, token: &str) -> Result<Option<(Scope, String)>> {
+    let hash = hash(token);
+    let row: Option<(i64, Option<String>, String)> = conn
+        .query_row("SELECT id, name, scope FROM api_tokens WHERE token_hash = ?", [&hash], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })
+        .optional()?;
+    let Some((id, name, scope)) = row else {
+        return Ok(None);
+    };
+
+    conn.execute(
+        "UPDATE api_tokens SET last_used_at = ?1 WHERE token_hash = ?2",
+        params![Local::now().to_rfc3339(), hash],
+    )?;
+    Ok(Scope::parse(&scope).map(|scope| (scope, actor(id, name.as_deref()))))
+}
+
+/// How records created with a token are attributed: its name, or its id for
+/// tokens created without one.
+pub fn actor(id: i64, name: Option<&str>) -> String {
+    name.map_or_else(|| format!("token {}", id), str::to_string)
+}
+
+pub fn revoke(conn: &Connection, id: i64) -> Result<()> {
+    if conn.execute("DELETE FROM api_tokens WHERE id = ?", [id])? == 0 {
+        return Err(WebgoneError::InvalidInput(format!("no token with id {}", id)).into());
+    }
+    Ok(())
+}
+
+pub fn list(conn: &Connection) -> Result<Vec<Token>> {
+    let parse_time = |value: String| {
+        DateTime::parse_from_rfc3339(&value)
+            .map(|time| time.with_timezone(&Local))
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e)))
+    };
+
+    let mut stmt = conn.prepare("SELECT id, name, scope, created_at, last_used_at FROM api_tokens ORDER BY id")?;
+    let tokens = stmt
+        .query_map([], |row| {
+            let scope: String = row.get(2)?;
+            Ok(Token {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                scope: Scope::parse(&scope).unwrap_or(Scope::Read),
+                created_at: parse_time(row.get(3)?)?,
+                last_used_at: row.get::<_, Option<String>>(4)?.map(parse_time).transpose()?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(tokens)
+}
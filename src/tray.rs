@@ -0,0 +1,222 @@
+use anyhow::Result;
+use chrono::{DateTime, Local, TimeZone};
+use rusqlite::{params, Connection};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tao::event::{Event, StartCause};
+use tao::event_loop::{ControlFlow, EventLoopBuilder};
+use tray_icon::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+use tray_icon::{Icon, TrayIcon, TrayIconBuilder};
+
+use crate::error::WebgoneError;
+use crate::{acks, lock, pause, recovery};
+
+/// How often the tray re-reads the state the watcher recorded.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+const ICON_SIZE: u32 = 32;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    Online,
+    /// In an outage since the given time
+    Offline(DateTime<Local>),
+    Paused,
+    /// No watcher is running against the database
+    Stopped,
+}
+
+impl State {
+    fn load(conn: &Connection, db_path: &Path) -> Result<Self> {
+        if lock::watcher_pid(db_path).is_none() {
+            return Ok(State::Stopped);
+        }
+        if pause::is_paused(conn)? {
+            return Ok(State::Paused);
+        }
+        Ok(match acks::current(conn)? {
+            Some(start) => State::Offline(start),
+            None => State::Online,
+        })
+    }
+
+    fn describe(self) -> String {
+        match self {
+            State::Online => "online".to_string(),
+            State::Offline(start) => format!(
+                "offline for {}",
+                recovery::describe((Local::now() - start).to_std().unwrap_or_default())
+            ),
+            State::Paused => "monitoring paused".to_string(),
+            State::Stopped => "watcher not running".to_string(),
+        }
+    }
+
+    fn color(self) -> [u8; 3] {
+        match self {
+            State::Online => [46, 160, 67],
+            State::Offline(_) => [207, 34, 46],
+            State::Paused | State::Stopped => [140, 140, 140],
+        }
+    }
+}
+
+/// A filled circle in the color of the state.
+fn icon(state: State) -> Result<Icon> {
+    let [red, green, blue] = state.color();
+    let center = (ICON_SIZE as f64 - 1.0) / 2.0;
+    let radius = ICON_SIZE as f64 / 2.0 - 1.0;
+    let mut rgba = Vec::with_capacity((ICON_SIZE * ICON_SIZE * 4) as usize);
+    for y in 0..ICON_SIZE {
+        for x in 0..ICON_SIZE {
+            let distance = ((x as f64 - center).powi(2) + (y as f64 - center).powi(2)).sqrt();
+            // Fade the outermost pixel for a smoother edge
+            let alpha = ((radius + 0.5 - distance).clamp(0.0, 1.0) * 255.0) as u8;
+            rgba.extend([red, green, blue, alpha]);
+        }
+    }
+    Icon::from_rgba(rgba, ICON_SIZE, ICON_SIZE)
+        .map_err(|e| WebgoneError::Other(format!("failed to create tray icon: {}", e)).into())
+}
+
+/// Seconds of local downtime since midnight, including an ongoing outage.
+fn downtime_today(conn: &Connection, state: State) -> Result<i64> {
+    let now = Local::now();
+    let midnight = now.date_naive().and_hms_opt(0, 0, 0).expect("midnight is valid");
+    let start = Local.from_local_datetime(&midnight).earliest().unwrap_or(now);
+
+    let mut stmt = conn.prepare(
+        "SELECT start_time, end_time FROM outages
+        WHERE site IS NULL AND julianday(end_time) >= julianday(?1)"
+    )?;
+    let parse = |value: String| DateTime::parse_from_rfc3339(&value).ok().map(|time| time.with_timezone(&Local));
+    let mut seconds: i64 = stmt
+        .query_map(params![start.to_rfc3339()], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?
+        .into_iter()
+        .filter_map(|(from, to)| Some((parse(from)?, parse(to)?)))
+        .map(|(from, to)| (to.min(now) - from.max(start)).num_seconds().max(0))
+        .sum();
+    if let State::Offline(since) = state {
+        seconds += (now - since.max(start)).num_seconds().max(0);
+    }
+    Ok(seconds)
+}
+
+struct Tray {
+    conn: Connection,
+    db_path: PathBuf,
+    dashboard_url: String,
+    icon: TrayIcon,
+    pause_item: MenuItem,
+    state: Option<State>,
+}
+
+impl Tray {
+    fn refresh(&mut self) -> Result<()> {
+        let state = State::load(&self.conn, &self.db_path)?;
+        let downtime = downtime_today(&self.conn, state)?;
+        self.icon
+            .set_tooltip(Some(format!(
+                "webgone: {}\nDowntime today: {:02}:{:02}:{:02}",
+                state.describe(),
+                downtime / 3600,
+                (downtime % 3600) / 60,
+                downtime % 60
+            )))
+            .map_err(|e| WebgoneError::Other(e.to_string()))?;
+
+        // Only swap the icon on changes, some desktops flicker otherwise
+        let color_changed = self.state.is_none_or(|previous| previous.color() != state.color());
+        if color_changed {
+            self.icon.set_icon(Some(icon(state)?)).map_err(|e| WebgoneError::Other(e.to_string()))?;
+        }
+        self.pause_item.set_text(if state == State::Paused { "Resume monitoring" } else { "Pause monitoring" });
+        self.pause_item.set_enabled(state != State::Stopped);
+        self.state = Some(state);
+        Ok(())
+    }
+}
+
+/// Shows the state of the watcher running against the database as a tray
+/// icon until it is quit from the menu. Only returns on errors during startup.
+pub fn run(conn: Connection, db_path: &Path, dashboard_url: String) -> Result<()> {
+    let event_loop = EventLoopBuilder::<MenuEvent>::with_user_event().build();
+    let proxy = event_loop.create_proxy();
+    MenuEvent::set_event_handler(Some(move |event| {
+        let _ = proxy.send_event(event);
+    }));
+
+    let open_item = MenuItem::new("Open dashboard", true, None);
+    let pause_item = MenuItem::new("Pause monitoring", true, None);
+    let quit_item = MenuItem::new("Quit", true, None);
+    let menu = Menu::new();
+    menu.append_items(&[&open_item, &pause_item, &PredefinedMenuItem::separator(), &quit_item])
+        .map_err(|e| WebgoneError::Other(format!("failed to create tray menu: {}", e)))?;
+
+    let db_path = db_path.to_path_buf();
+    let mut conn = Some(conn);
+    let mut tray: Option<Tray> = None;
+    let mut next_refresh = Instant::now();
+
+    event_loop.run(move |event, _, control_flow| {
+        *control_flow = ControlFlow::WaitUntil(next_refresh);
+
+        match event {
+            // macOS only allows creating the icon once the event loop runs
+            Event::NewEvents(StartCause::Init) => {
+                let built = TrayIconBuilder::new()
+                    .with_menu(Box::new(menu.clone()))
+                    .with_tooltip("webgone")
+                    .with_icon(icon(State::Stopped).expect("valid icon"))
+                    .build();
+                match (built, conn.take()) {
+                    (Ok(icon), Some(conn)) => {
+                        tray = Some(Tray {
+                            conn,
+                            db_path: db_path.clone(),
+                            dashboard_url: dashboard_url.clone(),
+                            icon,
+                            pause_item: pause_item.clone(),
+                            state: None,
+                        })
+                    }
+                    (Err(e), _) => {
+                        eprintln!("Error: failed to create tray icon: {}", e);
+                        *control_flow = ControlFlow::ExitWithCode(1);
+                        return;
+                    }
+                    (_, None) => unreachable!("the event loop starts once"),
+                }
+            }
+            Event::UserEvent(menu_event) => {
+                let Some(tray) = &mut tray else { return };
+                if menu_event.id == *quit_item.id() {
+                    *control_flow = ControlFlow::Exit;
+                    return;
+                }
+                let result = if menu_event.id == *open_item.id() {
+                    open::that(&tray.dashboard_url).map_err(Into::into)
+                } else if menu_event.id == *pause_item.id() {
+                    pause::set_paused(&tray.conn, tray.state != Some(State::Paused))
+                } else {
+                    Ok(())
+                };
+                if let Err(e) = result.and_then(|()| tray.refresh()) {
+                    eprintln!("Error: {:#}", e);
+                }
+            }
+            _ => {}
+        }
+
+        if Instant::now() >= next_refresh {
+            next_refresh = Instant::now() + REFRESH_INTERVAL;
+            *control_flow = ControlFlow::WaitUntil(next_refresh);
+            if let Some(tray) = &mut tray {
+                if let Err(e) = tray.refresh() {
+                    eprintln!("Error: {:#}", e);
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,179 @@
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate, TimeZone};
+use rusqlite::{params, Connection, OptionalExtension};
+
+/// Metadata key holding the last week the trend alert was evaluated for.
+const CHECKED_WEEK_KEY: &str = "trend_checked_week";
+
+/// Loss rates below this percentage count as this percentage, so a week
+/// without any loss doesn't make the first lost probe an infinite increase.
+const MIN_LOSS_PERCENT: f64 = 0.1;
+
+/// Probe latency and loss within one week, Monday to Sunday.
+pub struct WeekSummary {
+    pub start: NaiveDate,
+    pub probes: i64,
+    pub failures: i64,
+    /// Average latency of successful probes, including rolled up ones
+    pub average_latency_ms: Option<f64>,
+}
+
+impl WeekSummary {
+    pub fn loss_percent(&self) -> Option<f64> {
+        (self.probes > 0).then(|| self.failures as f64 / self.probes as f64 * 100.0)
+    }
+}
+
+/// What got worse week over week.
+#[derive(Clone, Copy)]
+pub enum Metric {
+    Latency,
+    Loss,
+}
+
+impl Metric {
+    fn value(self, week: &WeekSummary) -> Option<f64> {
+        match self {
+            Metric::Latency => week.average_latency_ms,
+            Metric::Loss => week.loss_percent().map(|loss| loss.max(MIN_LOSS_PERCENT)),
+        }
+    }
+
+    fn describe(self, week: &WeekSummary) -> String {
+        match self {
+            Metric::Latency => week.average_latency_ms.map_or("-".to_string(), |ms| format!("{:.1} ms", ms)),
+            Metric::Loss => week.loss_percent().map_or("-".to_string(), |loss| format!("{:.2}%", loss)),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Metric::Latency => "Average latency",
+            Metric::Loss => "Probe loss",
+        }
+    }
+}
+
+fn start_of_week(day: NaiveDate) -> NaiveDate {
+    day - Days::new(day.weekday().num_days_from_monday() as u64)
+}
+
+fn local_midnight(day: NaiveDate) -> DateTime<Local> {
+    Local
+        .from_local_datetime(&day.and_hms_opt(0, 0, 0).expect("midnight exists"))
+        .earliest()
+        .unwrap_or_else(|| Local.from_utc_datetime(&day.and_hms_opt(0, 0, 0).expect("midnight exists")))
+}
+
+fn summarize(conn: &Connection, start: NaiveDate) -> Result<WeekSummary> {
+    let (from, to) = (local_midnight(start), local_midnight(start + Days::new(7)));
+    // Raw probes stand for `weight` probes each; aggregates for all of theirs
+    let (probes, failures, latency_sum, latency_count): (Option<i64>, Option<i64>, Option<f64>, Option<f64>) =
+        conn.query_row(
+            "SELECT SUM(probes), SUM(failures), SUM(total), SUM(count) FROM (
+                SELECT SUM(weight) AS probes, SUM(CASE WHEN success THEN 0 ELSE weight END) AS failures,
+                    SUM(CASE WHEN success THEN latency_ms * weight END) AS total,
+                    SUM(CASE WHEN success THEN weight END) AS count
+                FROM probes
+                WHERE site IS NULL AND julianday(time) >= julianday(?1) AND julianday(time) < julianday(?2)
+                UNION ALL
+                SELECT SUM(probes), SUM(failures), SUM(avg_latency_ms * (probes - failures)),
+                    SUM(CASE WHEN avg_latency_ms IS NOT NULL THEN probes - failures END)
+                FROM probe_hourly
+                WHERE julianday(period) >= julianday(?1) AND julianday(period) < julianday(?2)
+                UNION ALL
+                SELECT SUM(probes), SUM(failures), SUM(avg_latency_ms * (probes - failures)),
+                    SUM(CASE WHEN avg_latency_ms IS NOT NULL THEN probes - failures END)
+                FROM probe_minutely
+                WHERE julianday(period) >= julianday(?1) AND julianday(period) < julianday(?2)
+            )",
+            params![from.to_rfc3339(), to.to_rfc3339()],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )?;
+
+    Ok(WeekSummary {
+        start,
+        probes: probes.unwrap_or(0),
+        failures: failures.unwrap_or(0),
+        average_latency_ms: match (latency_sum, latency_count) {
+            (Some(sum), Some(count)) if count > 0.0 => Some(sum / count),
+            _ => None,
+        },
+    })
+}
+
+/// The last `count` complete weeks before `now`, oldest first. Weeks
+/// without probes are included, so gaps stay visible.
+pub fn weeks(conn: &Connection, count: u32, now: DateTime<Local>) -> Result<Vec<WeekSummary>> {
+    let current = start_of_week(now.date_naive());
+    (1..=count as u64)
+        .rev()
+        .map(|weeks_ago| summarize(conn, current - Days::new(7 * weeks_ago)))
+        .collect()
+}
+
+/// Change of `metric` from one week to the next in percent, if both weeks
+/// have a value.
+pub fn change_percent(metric: Metric, previous: &WeekSummary, current: &WeekSummary) -> Option<f64> {
+    let (before, after) = (metric.value(previous)?, metric.value(current)?);
+    (before > 0.0).then(|| (after - before) / before * 100.0)
+}
+
+/// Messages for each metric that got worse by more than `percent` in each
+/// of the last `consecutive` week-over-week comparisons of `weeks`.
+pub fn degradations(weeks: &[WeekSummary], percent: f64, consecutive: u32) -> Vec<String> {
+    let needed = consecutive.max(1) as usize + 1;
+    if weeks.len() < needed {
+        return Vec::new();
+    }
+    let recent = &weeks[weeks.len() - needed..];
+
+    [Metric::Latency, Metric::Loss]
+        .into_iter()
+        .filter(|metric| {
+            recent
+                .windows(2)
+                .all(|pair| change_percent(*metric, &pair[0], &pair[1]).is_some_and(|change| change > percent))
+        })
+        .map(|metric| {
+            let values: Vec<String> = recent
+                .iter()
+                .map(|week| format!("{} (week of {})", metric.describe(week), week.start.format("%Y-%m-%d")))
+                .collect();
+            format!(
+                "{} got worse by more than {}% for {} week(s) in a row: {}",
+                metric.name(),
+                percent,
+                needed - 1,
+                values.join(" -> ")
+            )
+        })
+        .collect()
+}
+
+/// Monday of the last complete week if the trend alert hasn't been evaluated
+/// for it yet. When first run, the current state is taken as evaluated, so
+/// only weeks completed while watching can raise an alert.
+pub fn due(conn: &Connection, now: DateTime<Local>) -> Result<Option<NaiveDate>> {
+    let last_week = start_of_week(now.date_naive()) - Days::new(7);
+    let checked: Option<String> = conn
+        .query_row("SELECT value FROM metadata WHERE key = ?", [CHECKED_WEEK_KEY], |row| row.get(0))
+        .optional()?;
+    match checked.and_then(|value| value.parse::<NaiveDate>().ok()) {
+        Some(checked) if checked >= last_week => Ok(None),
+        Some(_) => Ok(Some(last_week)),
+        None => {
+            mark_checked(conn, last_week)?;
+            Ok(None)
+        }
+    }
+}
+
+pub fn mark_checked(conn: &Connection, week: NaiveDate) -> Result<()> {
+    conn.execute(
+        "INSERT INTO metadata (key, value) VALUES (?1, ?2)
+        ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+        params![CHECKED_WEEK_KEY, week.to_string()],
+    )?;
+    Ok(())
+}
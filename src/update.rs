@@ -0,0 +1,137 @@
+use anyhow::{bail, Context, Result};
+use semver::Version;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::error::WebgoneError;
+
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/cozyGalvinism/webgone/releases/latest";
+/// Upper bound for the downloaded binary, well above the size of a release build
+const MAX_BINARY_SIZE: u64 = 100 * 1024 * 1024;
+
+/// A published release with the binary asset for the running platform.
+pub struct Release {
+    pub version: Version,
+    binary_url: String,
+    checksum_url: String,
+}
+
+/// Name of the release asset built for the running platform, e.g. `webgone-x86_64-linux`.
+fn asset_name() -> String {
+    let extension = if cfg!(windows) { ".exe" } else { "" };
+    format!("webgone-{}-{}{}", env::consts::ARCH, env::consts::OS, extension)
+}
+
+fn agent(timeout: Duration) -> ureq::Agent {
+    ureq::Agent::config_builder()
+        .timeout_global(Some(timeout))
+        .build()
+        .into()
+}
+
+pub fn current_version() -> Version {
+    Version::parse(env!("CARGO_PKG_VERSION")).expect("package version is valid semver")
+}
+
+/// Looks up the latest GitHub release and the assets for this platform.
+pub fn latest_release() -> Result<Release> {
+    let body = agent(Duration::from_secs(10))
+        .get(LATEST_RELEASE_URL)
+        .header("User-Agent", concat!("webgone/", env!("CARGO_PKG_VERSION")))
+        .header("Accept", "application/vnd.github+json")
+        .call()
+        .context("Release lookup failed")?
+        .body_mut()
+        .read_to_string()?;
+
+    let json: Value = serde_json::from_str(&body).context("GitHub returned invalid JSON")?;
+    let tag = json.get("tag_name").and_then(Value::as_str).context("Release has no tag")?;
+    let version = Version::parse(tag.trim_start_matches('v'))
+        .with_context(|| format!("Release tag {} is not a version", tag))?;
+
+    let asset = asset_name();
+    let checksum_asset = format!("{}.sha256", asset);
+    let assets = json.get("assets").and_then(Value::as_array).cloned().unwrap_or_default();
+    let download_url = |name: &str| {
+        assets
+            .iter()
+            .find(|a| a.get("name").and_then(Value::as_str) == Some(name))
+            .and_then(|a| a.get("browser_download_url"))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    };
+
+    let Some(binary_url) = download_url(&asset) else {
+        bail!(WebgoneError::Other(format!("release {} has no binary for this platform ({})", version, asset)));
+    };
+    let Some(checksum_url) = download_url(&checksum_asset) else {
+        bail!(WebgoneError::Other(format!("release {} has no checksum for {}", version, asset)));
+    };
+
+    Ok(Release { version, binary_url, checksum_url })
+}
+
+/// Downloads the release binary, verifies its SHA-256 checksum and replaces
+/// the running executable with it.
+pub fn install(release: &Release) -> Result<PathBuf> {
+    let agent = agent(Duration::from_secs(120));
+
+    let checksum = agent
+        .get(&release.checksum_url)
+        .call()
+        .context("Checksum download failed")?
+        .body_mut()
+        .read_to_string()?;
+    // sha256sum format: "<hex digest>  <file name>"
+    let expected = checksum.split_whitespace().next().context("Checksum file is empty")?.to_lowercase();
+
+    let binary = agent
+        .get(&release.binary_url)
+        .call()
+        .context("Binary download failed")?
+        .body_mut()
+        .with_config()
+        .limit(MAX_BINARY_SIZE)
+        .read_to_vec()?;
+
+    let actual = hex::encode(Sha256::digest(&binary));
+    if actual != expected {
+        bail!(WebgoneError::Other(format!(
+            "checksum mismatch for downloaded binary (expected {}, got {}), not installing it",
+            expected, actual
+        )));
+    }
+
+    let exe = env::current_exe().context("Failed to locate the running executable")?;
+    replace_executable(&exe, &binary)?;
+    Ok(exe)
+}
+
+/// Writes the new binary next to the old one and renames it into place, so
+/// an interrupted update never leaves a half-written executable behind.
+fn replace_executable(exe: &Path, binary: &[u8]) -> Result<()> {
+    let staged = exe.with_extension("new");
+    fs::write(&staged, binary).with_context(|| format!("Failed to write {}", staged.display()))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(exe)?.permissions().mode();
+        fs::set_permissions(&staged, fs::Permissions::from_mode(mode))?;
+    }
+
+    // A running executable can't be overwritten on Windows, but it can be renamed
+    #[cfg(windows)]
+    {
+        let old = exe.with_extension("old");
+        let _ = fs::remove_file(&old);
+        fs::rename(exe, &old)?;
+    }
+
+    fs::rename(&staged, exe).with_context(|| format!("Failed to replace {}", exe.display()))?;
+    Ok(())
+}
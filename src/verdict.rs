@@ -0,0 +1,424 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::ErrorKind;
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
+
+use crate::dnsbench;
+use crate::error::WebgoneError;
+use crate::icmp;
+use crate::locations;
+use crate::target::ProbeResult;
+
+/// Page fetched by the built-in `http` component, which answers with an empty 204.
+pub const DEFAULT_HTTP_URL: &str = "http://connectivitycheck.gstatic.com/generate_204";
+
+/// Domain the `dns` components resolve.
+const DNS_DOMAIN: &str = "example.com";
+
+/// Ports tried on the default gateway; most routers run a DNS forwarder or
+/// a web interface.
+const GATEWAY_PORTS: &[u16] = &[53, 80];
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// What a component checks.
+#[derive(Clone)]
+pub enum Check {
+    /// The watcher's own probe of `--target`/`--ip`
+    Target,
+    /// The default gateway answers, even if it refuses the connection
+    Gateway,
+    /// A resolver answers a query; the system's upstream resolver if `None`
+    Dns(Option<SocketAddr>),
+    /// A URL returns a success status
+    Http(String),
+    /// A TCP connection can be opened
+    Tcp(SocketAddr),
+    /// The host answers an ICMP echo request
+    Icmp(IpAddr),
+}
+
+/// A named check the `--up-when` expression refers to, given as
+/// `NAME=gateway`, `NAME=dns[:IP]`, `NAME=http:URL`, `NAME=tcp:IP:PORT` or
+/// `NAME=icmp:IP`.
+#[derive(Clone)]
+pub struct Component {
+    pub name: String,
+    pub check: Check,
+}
+
+impl FromStr for Component {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let usage = || format!("expected NAME=gateway, NAME=dns[:IP], NAME=http:URL, NAME=tcp:IP:PORT or NAME=icmp:IP, got `{}`", s);
+        let (name, spec) = s.split_once('=').ok_or_else(usage)?;
+        let (kind, arg) = match spec.split_once(':') {
+            Some((kind, arg)) => (kind, Some(arg)),
+            None => (spec, None),
+        };
+        let check = match (kind, arg) {
+            ("gateway", None) => Check::Gateway,
+            ("dns", None) => Check::Dns(None),
+            ("dns", Some(arg)) => Check::Dns(Some(
+                arg.parse::<SocketAddr>()
+                    .or_else(|_| arg.parse::<IpAddr>().map(|ip| SocketAddr::new(ip, 53)))
+                    .map_err(|_| usage())?,
+            )),
+            ("http", Some(url)) => Check::Http(url.to_string()),
+            ("tcp", Some(addr)) => Check::Tcp(addr.parse().map_err(|_| usage())?),
+            ("icmp", Some(ip)) => Check::Icmp(ip.parse().map_err(|_| usage())?),
+            _ => return Err(usage()),
+        };
+        let name = name.trim();
+        if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-') {
+            return Err(format!("component names may only contain letters, digits, `_` and `-`, got `{}`", name));
+        }
+        Ok(Component { name: name.to_string(), check })
+    }
+}
+
+/// Components available without `--component`.
+fn built_in() -> Vec<Component> {
+    vec![
+        Component { name: "target".to_string(), check: Check::Target },
+        Component { name: "gateway".to_string(), check: Check::Gateway },
+        Component { name: "dns".to_string(), check: Check::Dns(None) },
+        Component { name: "http".to_string(), check: Check::Http(DEFAULT_HTTP_URL.to_string()) },
+    ]
+}
+
+/// Boolean expression over components, e.g. `gateway AND (dns OR http)`.
+#[derive(Clone)]
+pub enum Expr {
+    Component(String),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+impl FromStr for Expr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let spaced = s.replace('(', " ( ").replace(')', " ) ");
+        let tokens: Vec<&str> = spaced.split_whitespace().collect();
+        let mut parser = Parser { tokens: &tokens, position: 0 };
+        let expr = parser.or()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(format!("unexpected `{}` in `{}`", token, s)),
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Component(name) => write!(f, "{}", name),
+            Expr::Not(inner) => match **inner {
+                Expr::Component(_) | Expr::Not(_) => write!(f, "NOT {}", inner),
+                _ => write!(f, "NOT ({})", inner),
+            },
+            Expr::And(left, right) => write!(f, "{} AND {}", left.operand(), right.operand()),
+            Expr::Or(left, right) => write!(f, "{} OR {}", left, right),
+        }
+    }
+}
+
+/// Recursive descent parser; NOT binds tighter than AND, AND tighter than OR.
+struct Parser<'a> {
+    tokens: &'a [&'a str],
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        let token = self.peek();
+        self.position += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.peek().is_some_and(|token| token.eq_ignore_ascii_case("or")) {
+            self.position += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.not()?;
+        while self.peek().is_some_and(|token| token.eq_ignore_ascii_case("and")) {
+            self.position += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(token) if token.eq_ignore_ascii_case("not") => Ok(Expr::Not(Box::new(self.not()?))),
+            Some("(") => {
+                let expr = self.or()?;
+                match self.next() {
+                    Some(")") => Ok(expr),
+                    _ => Err("missing `)`".to_string()),
+                }
+            }
+            Some(token) if ["and", "or", ")"].iter().any(|keyword| token.eq_ignore_ascii_case(keyword)) => {
+                Err(format!("expected a component name, got `{}`", token))
+            }
+            Some(token) => Ok(Expr::Component(token.to_string())),
+            None => Err("expected a component name, got the end of the expression".to_string()),
+        }
+    }
+}
+
+impl Expr {
+    /// Formats the expression as a side of AND, which binds tighter than OR.
+    fn operand(&self) -> String {
+        match self {
+            Expr::Or(..) => format!("({})", self),
+            _ => self.to_string(),
+        }
+    }
+
+    fn names<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match self {
+            Expr::Component(name) => {
+                if !names.contains(&name.as_str()) {
+                    names.push(name);
+                }
+            }
+            Expr::Not(inner) => inner.names(names),
+            Expr::And(left, right) | Expr::Or(left, right) => {
+                left.names(names);
+                right.names(names);
+            }
+        }
+    }
+
+    fn evaluate(&self, results: &BTreeMap<&str, bool>) -> bool {
+        match self {
+            Expr::Component(name) => results[name.as_str()],
+            Expr::Not(inner) => !inner.evaluate(results),
+            Expr::And(left, right) => left.evaluate(results) && right.evaluate(results),
+            Expr::Or(left, right) => left.evaluate(results) || right.evaluate(results),
+        }
+    }
+}
+
+/// Outcome of one evaluation of the `--up-when` expression.
+pub struct Verdict {
+    pub up: bool,
+    /// Components that failed, in the order they appear in the expression
+    pub failed: Vec<String>,
+}
+
+/// Decides whether the internet is up from several components instead of
+/// the target probe alone.
+pub struct UpWhen {
+    expr: Expr,
+    components: Vec<Component>,
+}
+
+impl UpWhen {
+    /// Checks that every name in `expr` is a built-in or custom component;
+    /// custom ones replace built-ins of the same name.
+    pub fn new(expr: Expr, custom: Vec<Component>) -> Result<Self, WebgoneError> {
+        let mut available = custom;
+        for component in built_in() {
+            if !available.iter().any(|custom| custom.name == component.name) {
+                available.push(component);
+            }
+        }
+
+        let mut names = Vec::new();
+        expr.names(&mut names);
+        let mut components = Vec::new();
+        for name in names {
+            let component = available.iter().find(|component| component.name == name).ok_or_else(|| {
+                WebgoneError::InvalidInput(format!(
+                    "unknown component `{}` in --up-when; define it with --component {}=...",
+                    name, name
+                ))
+            })?;
+            components.push(component.clone());
+        }
+        Ok(UpWhen { expr, components })
+    }
+
+    /// Names of the components the expression uses.
+    pub fn component_names(&self) -> Vec<&str> {
+        self.components.iter().map(|component| component.name.as_str()).collect()
+    }
+
+    /// Runs every component of the expression in parallel, so a check takes
+    /// as long as the slowest component, and evaluates the expression.
+    pub fn evaluate(&self, target: &ProbeResult) -> Verdict {
+        let outcomes: Vec<bool> = thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .components
+                .iter()
+                .map(|component| scope.spawn(|| run(&component.check, Some(target))))
+                .collect();
+            handles.into_iter().map(|handle| handle.join().unwrap_or(false)).collect()
+        });
+
+        let results: BTreeMap<&str, bool> = self
+            .components
+            .iter()
+            .zip(&outcomes)
+            .map(|(component, up)| (component.name.as_str(), *up))
+            .collect();
+        Verdict {
+            up: self.expr.evaluate(&results),
+            failed: self
+                .components
+                .iter()
+                .zip(&outcomes)
+                .filter(|(_, up)| !**up)
+                .map(|(component, _)| component.name.clone())
+                .collect(),
+        }
+    }
+}
+
+/// Runs a check outside of an `--up-when` expression, where there is no
+/// target probe to refer to.
+pub fn check(check: &Check) -> bool {
+    run(check, None)
+}
+
+fn run(check: &Check, target: Option<&ProbeResult>) -> bool {
+    match check {
+        Check::Target => target.is_some_and(ProbeResult::is_success),
+        Check::Gateway => locations::gateway_ip().is_some_and(|gateway| {
+            GATEWAY_PORTS.iter().any(|port| reachable(SocketAddr::new(gateway, *port)))
+        }),
+        Check::Dns(resolver) => {
+            let resolver = resolver.or_else(|| {
+                // Loopback stubs like systemd-resolved answer from their cache even while offline
+                dnsbench::system_resolvers()
+                    .into_iter()
+                    .find(|ip| !ip.is_loopback())
+                    .or_else(locations::gateway_ip)
+                    .map(|ip| SocketAddr::new(ip, 53))
+            });
+            // Each query has a socket of its own, so the id doesn't have to be unique
+            resolver.is_some_and(|resolver| dnsbench::query(resolver, DNS_DOMAIN, 1, TIMEOUT).is_ok())
+        }
+        Check::Http(url) => {
+            let agent: ureq::Agent = ureq::Agent::config_builder().timeout_global(Some(TIMEOUT)).build().into();
+            agent.get(url).call().is_ok_and(|response| response.status().is_success())
+        }
+        Check::Tcp(addr) => TcpStream::connect_timeout(addr, TIMEOUT).is_ok(),
+        Check::Icmp(ip) => icmp::ping(*ip, TIMEOUT).is_ok(),
+    }
+}
+
+/// Whether a host answers at all; a refused connection means it is there.
+fn reachable(addr: SocketAddr) -> bool {
+    match TcpStream::connect_timeout(&addr, TIMEOUT) {
+        Ok(_) => true,
+        Err(e) => e.kind() == ErrorKind::ConnectionRefused,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_expressions_with_precedence() {
+        // Printed back with only the parentheses the precedence needs
+        for (input, printed) in [
+            ("gateway", "gateway"),
+            ("gateway AND dns", "gateway AND dns"),
+            ("gateway and dns or http", "gateway AND dns OR http"),
+            ("gateway AND (dns OR http)", "gateway AND (dns OR http)"),
+            ("(gateway AND dns) OR http", "gateway AND dns OR http"),
+            ("gateway OR dns AND http", "gateway OR dns AND http"),
+            ("NOT gateway AND dns", "NOT gateway AND dns"),
+            ("NOT (gateway AND dns)", "NOT (gateway AND dns)"),
+            ("not not gateway", "NOT NOT gateway"),
+            ("((gateway))", "gateway"),
+            ("gateway AND(dns OR http)", "gateway AND (dns OR http)"),
+        ] {
+            let expr: Expr = input.parse().unwrap_or_else(|e| panic!("{:?}: {}", input, e));
+            assert_eq!(expr.to_string(), printed, "{:?}", input);
+            // What is printed parses to the same expression
+            assert_eq!(printed.parse::<Expr>().unwrap().to_string(), printed);
+        }
+    }
+
+    #[test]
+    fn evaluates_expressions() {
+        let results = BTreeMap::from([("gateway", true), ("dns", false), ("http", true)]);
+        for (input, up) in [
+            ("gateway", true),
+            ("dns", false),
+            ("gateway AND dns", false),
+            ("gateway AND dns OR http", true),
+            ("gateway AND (dns OR http)", true),
+            ("NOT dns AND http", true),
+            ("NOT (dns OR http)", false),
+        ] {
+            assert_eq!(input.parse::<Expr>().unwrap().evaluate(&results), up, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_expressions() {
+        for input in [
+            "",
+            "   ",
+            "gateway AND",
+            "OR dns",
+            "gateway dns",
+            "(gateway",
+            "gateway)",
+            "()",
+            "NOT",
+            "gateway AND AND dns",
+            "gateway AND (dns OR)",
+        ] {
+            assert!(input.parse::<Expr>().is_err(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn parses_components() {
+        for (input, name) in [
+            ("lan=gateway", "lan"),
+            ("resolver=dns", "resolver"),
+            ("quad9=dns:9.9.9.9", "quad9"),
+            ("v6=dns:[2620:fe::fe]:53", "v6"),
+            ("portal=http:http://example.com/ok", "portal"),
+            ("ssh=tcp:192.168.1.1:22", "ssh"),
+            ("modem_1=icmp:192.168.100.1", "modem_1"),
+        ] {
+            assert_eq!(input.parse::<Component>().map(|component| component.name), Ok(name.to_string()), "{:?}", input);
+        }
+        for input in ["gateway", "=gateway", "lan=router", "x=dns:nonsense", "x=tcp:1.2.3.4", "x=icmp:", "x=http", "a b=gateway"] {
+            assert!(input.parse::<Component>().is_err(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn refuses_unknown_components() {
+        let expr: Expr = "gateway AND modem".parse().unwrap();
+        assert!(UpWhen::new(expr.clone(), Vec::new()).is_err());
+        let modem = "modem=icmp:192.168.100.1".parse().unwrap();
+        assert_eq!(UpWhen::new(expr, vec![modem]).unwrap().component_names(), ["gateway", "modem"]);
+    }
+}
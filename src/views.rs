@@ -0,0 +1,84 @@
+use anyhow::Result;
+use rusqlite::{Connection, OptionalExtension};
+
+/// Views for common questions, with stable names for `sqlite3`, dashboards
+/// and scripts. Days and months are local; sites recorded here are `local`.
+const VIEWS: &[(&str, &str)] = &[
+    (
+        "v_incidents",
+        "CREATE VIEW v_incidents AS
+        SELECT o.id,
+            COALESCE(o.site, 'local') AS site,
+            o.start_time,
+            o.end_time,
+            o.duration_seconds,
+            o.duration_seconds / 60.0 AS duration_minutes,
+            date(o.start_time, 'localtime') AS day,
+            strftime('%Y-%m', o.start_time, 'localtime') AS month,
+            (SELECT group_concat(t.tag, ', ') FROM outage_tags t WHERE t.outage_id = o.id) AS tags,
+            (SELECT group_concat(k.reference, ', ') FROM ticket_outages l JOIN tickets k ON k.id = l.ticket_id
+                WHERE l.outage_id = o.id) AS tickets,
+            a.acked_by,
+            a.comment AS ack_comment,
+            o.failed_components,
+            o.actor,
+            o.clock_anomaly,
+            o.derived_from,
+            o.failed_probes,
+            o.severity
+        FROM outages o
+        LEFT JOIN outage_acks a ON a.outage_id = o.id",
+    ),
+    (
+        "v_daily_downtime",
+        "CREATE VIEW v_daily_downtime AS
+        WITH RECURSIVE spans (site, day, first, start, end) AS (
+            SELECT COALESCE(site, 'local'), date(start_time, 'localtime'), 1, julianday(start_time), julianday(end_time)
+            FROM outages
+            WHERE julianday(start_time) IS NOT NULL AND julianday(end_time) IS NOT NULL
+            UNION ALL
+            SELECT site, date(day, '+1 day'), 0, start, end
+            FROM spans
+            WHERE julianday(day, '+1 day', 'utc') < end
+        )
+        SELECT day,
+            site,
+            SUM(first) AS outages,
+            CAST(ROUND(SUM(MAX(0, MIN(end, julianday(day, '+1 day', 'utc')) - MAX(start, julianday(day, 'utc')))) * 86400)
+                AS INTEGER) AS downtime_seconds,
+            100.0 * (1 - MIN(1.0, SUM(MAX(0, MIN(end, julianday(day, '+1 day', 'utc')) - MAX(start, julianday(day, 'utc'))))
+                / (julianday(day, '+1 day', 'utc') - julianday(day, 'utc')))) AS uptime_percent
+        FROM spans
+        GROUP BY day, site",
+    ),
+    (
+        "v_monthly_summary",
+        "CREATE VIEW v_monthly_summary AS
+        SELECT substr(d.day, 1, 7) AS month,
+            d.site,
+            SUM(d.outages) AS outages,
+            SUM(d.downtime_seconds) AS downtime_seconds,
+            (SELECT MAX(i.duration_seconds) FROM v_incidents i WHERE i.site = d.site AND i.month = substr(d.day, 1, 7))
+                AS longest_seconds,
+            100.0 * (1 - MIN(1.0, SUM(d.downtime_seconds) / (86400 * (
+                MIN(julianday('now'), julianday(substr(d.day, 1, 7) || '-01', '+1 month', 'utc'))
+                - julianday(substr(d.day, 1, 7) || '-01', 'utc'))))) AS uptime_percent
+        FROM v_daily_downtime d
+        GROUP BY substr(d.day, 1, 7), d.site",
+    ),
+];
+
+/// Creates the views, and replaces ones an older version created with
+/// another definition. Must run after all tables they read exist.
+pub fn init(conn: &Connection) -> Result<()> {
+    for (name, sql) in VIEWS {
+        let existing: Option<String> = conn
+            .query_row("SELECT sql FROM sqlite_master WHERE type = 'view' AND name = ?", [name], |row| row.get(0))
+            .optional()?;
+        // Replacing a view changes the schema, which makes a running watcher re-check its tables
+        if existing.as_deref() != Some(*sql) {
+            conn.execute_batch(&format!("DROP VIEW IF EXISTS {name}; {sql};"))?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,81 @@
+//! WebAssembly entry point for the static status page in `web/`, which
+//! computes everything locally from a file written by
+//! `webgone export --format json`.
+
+use chrono::Duration;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::analysis::{self, HistogramBucket};
+use crate::model::{MonthlyOutage, OutageExport, OutageStats, PlannedDowntime};
+
+#[derive(Serialize)]
+struct MonthCost {
+    #[serde(flatten)]
+    month: MonthlyOutage,
+    downtime_percent: f64,
+    /// Only with a monthly rate
+    cost: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct Planned {
+    #[serde(flatten)]
+    downtime: PlannedDowntime,
+    /// `planned`, `in progress` or `over` at the time of the export
+    state: &'static str,
+}
+
+#[derive(Serialize)]
+struct Analysis {
+    stats: OutageStats,
+    /// Over the `days` before the export was written
+    uptime_percent: f64,
+    days: u32,
+    histogram: Vec<HistogramBucket>,
+    months: Vec<MonthCost>,
+    /// Planned downtime that isn't over or ended within the `days` before the export
+    planned: Vec<Planned>,
+}
+
+/// Analyzes an export and returns the results as JSON: overall stats, the
+/// uptime over the last `days` before the export, a histogram of outage
+/// durations, the downtime per month, with its cost if `monthly_rate` is
+/// given, and planned downtime.
+#[wasm_bindgen]
+pub fn analyze(export: &str, days: u32, monthly_rate: Option<f64>) -> Result<String, JsError> {
+    let export: OutageExport = serde_json::from_str(export)?;
+    let outages = &export.outages;
+    let from = export.exported_at - Duration::days(days.into());
+    let planned = export
+        .planned
+        .into_iter()
+        .filter(|downtime| downtime.end > from)
+        .map(|downtime| Planned {
+            state: if downtime.start > export.exported_at {
+                "planned"
+            } else if downtime.end > export.exported_at {
+                "in progress"
+            } else {
+                "over"
+            },
+            downtime,
+        })
+        .collect();
+    let analysis = Analysis {
+        stats: analysis::stats(outages),
+        uptime_percent: analysis::uptime_percent(outages, from, export.exported_at),
+        days,
+        histogram: analysis::histogram(outages, analysis::DEFAULT_BUCKETS),
+        months: analysis::monthly(outages)
+            .into_iter()
+            .map(|month| MonthCost {
+                downtime_percent: month.downtime_percent(),
+                cost: monthly_rate.map(|rate| month.cost(rate)),
+                month,
+            })
+            .collect(),
+        planned,
+    };
+    Ok(serde_json::to_string(&analysis)?)
+}
@@ -0,0 +1,94 @@
+use anyhow::{Context, Result};
+use chrono::Local;
+use serde_json::Value;
+use std::time::{Duration, Instant};
+
+/// Default endpoint, any Open-Meteo compatible (e.g. self-hosted) API works.
+pub const DEFAULT_API_URL: &str = "https://api.open-meteo.com/v1/forecast";
+
+pub use webgone::model::WeatherReport;
+
+/// Keeps the most recent weather report around.
+///
+/// The weather can't be fetched while the connection is down, so the watcher
+/// refreshes this cache while online and records the cached conditions when
+/// an outage starts.
+pub struct WeatherCache {
+    api_url: String,
+    latitude: f64,
+    longitude: f64,
+    max_age: Duration,
+    last_refresh: Option<Instant>,
+    report: Option<WeatherReport>,
+    failures: u64,
+}
+
+impl WeatherCache {
+    pub fn new(api_url: String, latitude: f64, longitude: f64, max_age: Duration) -> Self {
+        WeatherCache {
+            api_url,
+            latitude,
+            longitude,
+            max_age,
+            last_refresh: None,
+            report: None,
+            failures: 0,
+        }
+    }
+
+    /// Fetches fresh conditions if the cached ones are older than `max_age`.
+    pub fn refresh_if_stale(&mut self) -> Result<()> {
+        if self.last_refresh.is_some_and(|at| at.elapsed() < self.max_age) {
+            return Ok(());
+        }
+
+        // Count failed attempts as a refresh too, so an unreachable API isn't hammered every probe
+        self.last_refresh = Some(Instant::now());
+        match fetch(&self.api_url, self.latitude, self.longitude) {
+            Ok(report) => self.report = Some(report),
+            Err(e) => {
+                self.failures += 1;
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn report(&self) -> Option<&WeatherReport> {
+        self.report.as_ref()
+    }
+
+    /// Number of failed refreshes since the cache was created.
+    pub fn failures(&self) -> u64 {
+        self.failures
+    }
+}
+
+fn fetch(api_url: &str, latitude: f64, longitude: f64) -> Result<WeatherReport> {
+    let url = format!(
+        "{}?latitude={}&longitude={}&current=weather_code,temperature_2m,precipitation,wind_speed_10m",
+        api_url, latitude, longitude
+    );
+
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(Duration::from_secs(5)))
+        .build()
+        .into();
+    let body = agent
+        .get(&url)
+        .call()
+        .context("Weather API request failed")?
+        .body_mut()
+        .read_to_string()?;
+
+    let json: Value = serde_json::from_str(&body).context("Weather API returned invalid JSON")?;
+    let current = json.get("current").context("Weather API response has no current conditions")?;
+
+    Ok(WeatherReport {
+        observed_at: Local::now(),
+        weather_code: current.get("weather_code").and_then(Value::as_i64),
+        temperature_c: current.get("temperature_2m").and_then(Value::as_f64),
+        precipitation_mm: current.get("precipitation").and_then(Value::as_f64),
+        wind_speed_kmh: current.get("wind_speed_10m").and_then(Value::as_f64),
+    })
+}
@@ -0,0 +1,115 @@
+use chrono::{DateTime, Local};
+use serde_json::{json, Value};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+use webgone::model::Severity;
+
+/// How long one request may take.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest wait between two attempts; waits double from one second.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(5 * 60);
+
+/// Payload sent when an outage starts, or when it got severe enough for a
+/// webhook that only wants more severe ones. `target` names what stopped
+/// responding.
+pub fn outage_started(started_at: DateTime<Local>, severity: Severity, target: &str, host: Option<&str>) -> Value {
+    json!({
+        "event": "outage_started",
+        "started_at": started_at.to_rfc3339(),
+        "severity": severity,
+        "target": target,
+        "host": host,
+    })
+}
+
+/// Payload sent when the outage recorded as `outage_id` is over.
+pub fn outage_ended(
+    outage_id: i64,
+    started_at: DateTime<Local>,
+    ended_at: DateTime<Local>,
+    severity: Severity,
+    target: &str,
+    host: Option<&str>,
+) -> Value {
+    json!({
+        "event": "outage_ended",
+        "outage_id": outage_id,
+        "started_at": started_at.to_rfc3339(),
+        "ended_at": ended_at.to_rfc3339(),
+        "duration_seconds": (ended_at - started_at).num_seconds(),
+        "severity": severity,
+        "target": target,
+        "host": host,
+    })
+}
+
+/// POSTs payloads to webhook URLs in the background, one thread per URL so
+/// a slow or unreachable endpoint doesn't hold up the watcher or the other
+/// URLs. Each URL receives the payloads in order; a failed request is
+/// retried with backoff before moving on to the next payload.
+pub struct Webhooks {
+    queues: Vec<Sender<Value>>,
+    /// Failures reported by the threads, for the watcher to print
+    failures: Receiver<String>,
+}
+
+impl Webhooks {
+    /// Starts a thread for each of `urls`, trying each payload up to
+    /// `attempts` times.
+    pub fn start(urls: &[String], attempts: u32) -> Self {
+        let (report, failures) = mpsc::channel();
+        let queues = urls
+            .iter()
+            .map(|url| {
+                let (queue, payloads) = mpsc::channel();
+                let (url, report) = (url.clone(), report.clone());
+                thread::spawn(move || deliver_all(&url, attempts, payloads, report));
+                queue
+            })
+            .collect();
+        Webhooks { queues, failures }
+    }
+
+    pub fn send(&self, payload: &Value) {
+        for queue in &self.queues {
+            // The thread only stops when the watcher does
+            let _ = queue.send(payload.clone());
+        }
+    }
+
+    /// Failures since the last call.
+    pub fn failures(&self) -> Vec<String> {
+        self.failures.try_iter().collect()
+    }
+}
+
+fn deliver_all(url: &str, attempts: u32, payloads: Receiver<Value>, report: Sender<String>) {
+    let agent: ureq::Agent = ureq::Agent::config_builder().timeout_global(Some(TIMEOUT)).build().into();
+    for payload in payloads {
+        let event = payload["event"].as_str().unwrap_or("event").to_string();
+        let mut delay = Duration::from_secs(1);
+        for attempt in 1..=attempts.max(1) {
+            let result = agent
+                .post(url)
+                .header("Content-Type", "application/json")
+                .header("User-Agent", &format!("webgone/{}", env!("CARGO_PKG_VERSION")))
+                .send(payload.to_string());
+            let Err(e) = result else {
+                break;
+            };
+            if attempt == attempts.max(1) {
+                let _ = report.send(format!("Webhook {} gave up on {} after {} attempt(s): {}", url, event, attempt, e));
+                break;
+            }
+            // Only the first failure is reported; the connection is often just down
+            if attempt == 1 {
+                let _ = report.send(format!("Webhook {} failed for {} ({}), retrying", url, event, e));
+            }
+            thread::sleep(delay);
+            delay = (delay * 2).min(MAX_RETRY_DELAY);
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use std::process::Command;
+
+pub use webgone::model::WifiInfo;
+
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Reads the current SSID and signal level of the wireless interface.
+///
+/// Returns `None` if there is no wireless link (or the platform isn't
+/// supported), so callers can treat a wired machine the same as a failed read.
+#[cfg(target_os = "linux")]
+pub fn current(interface: Option<&str>) -> Option<WifiInfo> {
+    // Entries look like "wlan0: 0000   54.  -56.  -256 ...", after two header lines
+    let wireless = std::fs::read_to_string("/proc/net/wireless").ok()?;
+    let (name, signal_dbm) = wireless.lines().skip(2).find_map(|line| {
+        let (name, rest) = line.split_once(':')?;
+        let name = name.trim();
+        if interface.is_some_and(|wanted| wanted != name) {
+            return None;
+        }
+        let level: f64 = rest.split_whitespace().nth(2)?.trim_end_matches('.').parse().ok()?;
+        Some((name.to_string(), level as i32))
+    })?;
+
+    let ssid = command_output("iwgetid", &[&name, "--raw"])
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty());
+
+    Some(WifiInfo { ssid, signal_dbm: Some(signal_dbm) })
+}
+
+#[cfg(target_os = "macos")]
+pub fn current(_interface: Option<&str>) -> Option<WifiInfo> {
+    let airport = "/System/Library/PrivateFrameworks/Apple80211.framework/Versions/Current/Resources/airport";
+    let output = command_output(airport, &["-I"])?;
+
+    let mut info = WifiInfo { ssid: None, signal_dbm: None };
+    for line in output.lines() {
+        match line.trim().split_once(':') {
+            Some(("SSID", value)) => info.ssid = Some(value.trim().to_string()),
+            Some(("agrCtlRSSI", value)) => info.signal_dbm = value.trim().parse().ok(),
+            _ => {}
+        }
+    }
+
+    (info.ssid.is_some() || info.signal_dbm.is_some()).then_some(info)
+}
+
+#[cfg(target_os = "windows")]
+pub fn current(interface: Option<&str>) -> Option<WifiInfo> {
+    let output = command_output("netsh", &["wlan", "show", "interfaces"])?;
+
+    let mut info = WifiInfo { ssid: None, signal_dbm: None };
+    let mut selected = interface.is_none();
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once(':') else { continue };
+        let value = value.trim();
+        match key.trim() {
+            "Name" => selected = interface.map_or(true, |wanted| wanted == value),
+            "SSID" if selected => info.ssid = Some(value.to_string()),
+            // netsh reports signal quality as a percentage; map it onto the usual -100..-50 dBm range
+            "Signal" if selected => {
+                info.signal_dbm = value.trim_end_matches('%').parse::<i32>().ok().map(|q| q / 2 - 100)
+            }
+            _ => {}
+        }
+    }
+
+    (info.ssid.is_some() || info.signal_dbm.is_some()).then_some(info)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub fn current(_interface: Option<&str>) -> Option<WifiInfo> {
+    None
+}
@@ -0,0 +1,50 @@
+//! Reports piped into a reader that quits early, like `webgone recent | head`.
+
+use std::fs;
+use std::io::Read;
+use std::process::{Command, Stdio};
+
+#[test]
+fn reports_end_quietly_when_the_reader_quits() {
+    let dir = std::env::temp_dir().join(format!("webgone-broken-pipe-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let db = dir.join("webgone.db");
+    let webgone = || {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_webgone"));
+        command.arg("--db").arg(&db);
+        command
+    };
+
+    // Creates the schema, then enough outages to fill the pipe
+    assert!(webgone().arg("init").output().unwrap().status.success());
+    let conn = rusqlite::Connection::open(&db).unwrap();
+    conn.execute_batch(
+        "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 3000)
+        INSERT INTO outages (start_time, end_time, duration_seconds)
+        SELECT strftime('%Y-%m-%dT%H:%M:%S+00:00', '2026-01-01', '+' || i || ' hours'),
+            strftime('%Y-%m-%dT%H:%M:%S+00:00', '2026-01-01', '+' || i || ' hours', '+5 minutes'), 300
+        FROM n",
+    )
+    .unwrap();
+    drop(conn);
+
+    for format in ["table", "json", "csv"] {
+        let mut child = webgone()
+            .args(["recent", "--limit", "3000", "--format", format])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut start = [0u8; 16];
+        child.stdout.take().unwrap().read_exact(&mut start).unwrap();
+        let output = child.wait_with_output().unwrap();
+        assert!(output.status.success(), "{}: {}", format, String::from_utf8_lossy(&output.stderr));
+        assert!(output.stderr.is_empty(), "{}: {}", format, String::from_utf8_lossy(&output.stderr));
+    }
+    let crash_reports = fs::read_dir(&dir)
+        .unwrap()
+        .filter(|entry| entry.as_ref().unwrap().file_name().to_string_lossy().starts_with("webgone-crash-"))
+        .count();
+    assert_eq!(crash_reports, 0);
+    fs::remove_dir_all(&dir).unwrap();
+}
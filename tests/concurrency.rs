@@ -0,0 +1,213 @@
+//! Runs reports while a watcher and another connection write to the same
+//! database, and changes the schema under a running watcher.
+
+use rusqlite::{Connection, OptionalExtension};
+use std::fs::File;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process::{Child, Command, ExitStatus, Output};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Longer than any report takes on a small database, but shorter than the
+/// five seconds a blocked connection waits for a lock before failing.
+const REPORT_DEADLINE: Duration = Duration::from_secs(4);
+
+/// A database in a directory of its own, removed when the test is done.
+struct TestDb {
+    dir: PathBuf,
+}
+
+impl TestDb {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("webgone-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("failed to create the test directory");
+        let db = TestDb { dir };
+        let output = db.command(&["init"]).output().expect("failed to run webgone init");
+        assert!(output.status.success(), "{}", describe(&output));
+        db
+    }
+
+    fn path(&self) -> PathBuf {
+        self.dir.join("outages.db")
+    }
+
+    fn command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_webgone"));
+        command.args(args).env("WEBGONE_DB", self.path()).env("LANG", "C").current_dir(&self.dir);
+        command
+    }
+
+    /// Starts a watcher probing `target`, e.g. a closed port so it keeps writing probes and outages.
+    /// Its output goes to `watch.log`, a pipe nobody reads would fill up and stall it.
+    fn watch(&self, target: &str) -> Child {
+        let log = File::create(self.log()).expect("failed to create the watcher log");
+        let stderr = log.try_clone().expect("failed to share the watcher log");
+        self.command(&["watch", "--target", target, "--interval", "0.25"])
+            .stdout(log)
+            .stderr(stderr)
+            .spawn()
+            .expect("failed to start the watcher")
+    }
+
+    fn log(&self) -> PathBuf {
+        self.dir.join("watch.log")
+    }
+
+    fn watch_output(&self) -> String {
+        std::fs::read_to_string(self.log()).unwrap_or_default()
+    }
+
+    fn connect(&self) -> Connection {
+        let conn = Connection::open(self.path()).expect("failed to open the test database");
+        conn.busy_timeout(Duration::from_secs(5)).expect("failed to set the busy timeout");
+        conn
+    }
+}
+
+impl Drop for TestDb {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn describe(output: &Output) -> String {
+    format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr))
+}
+
+/// Waits for `child` to exit, killing it after `deadline`. Returns `None` if it had to be killed.
+fn wait_for(child: &mut Child, deadline: Duration) -> Option<ExitStatus> {
+    let started = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait().expect("failed to wait for webgone") {
+            return Some(status);
+        }
+        if started.elapsed() > deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return None;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// Polls `condition` every 100 ms until it holds or `deadline` passes.
+fn eventually(deadline: Duration, mut condition: impl FnMut() -> bool) -> bool {
+    let started = Instant::now();
+    while started.elapsed() < deadline {
+        if condition() {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    false
+}
+
+fn add_outages(conn: &Connection) {
+    for day in 1..=3 {
+        conn.execute(
+            "INSERT INTO outages (start_time, end_time, duration_seconds) VALUES (?1, ?2, 120)",
+            [format!("2026-0{}-01T10:00:00+00:00", day), format!("2026-0{}-01T10:02:00+00:00", day)],
+        )
+        .expect("failed to add an outage");
+    }
+}
+
+#[test]
+fn reports_neither_block_nor_fail_while_others_write() {
+    let db = TestDb::new("concurrency");
+    add_outages(&db.connect());
+    let mut watcher = db.watch("127.0.0.1:9");
+    thread::sleep(Duration::from_secs(1));
+
+    // Another writer holds the write lock for as long as the reports run
+    let writer = db.connect();
+    writer.execute_batch("BEGIN IMMEDIATE").expect("failed to take the write lock");
+    add_outages(&writer);
+
+    let reports: [&[&str]; 3] = [&["stats"], &["recent"], &["cost", "40"]];
+    let (sender, results) = mpsc::channel();
+    for args in reports {
+        let mut command = db.command(args);
+        let sender = sender.clone();
+        thread::spawn(move || {
+            let _ = sender.send((args.join(" "), command.output()));
+        });
+    }
+    let deadline = Instant::now() + REPORT_DEADLINE;
+    for _ in reports {
+        let (report, output) = results
+            .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            .unwrap_or_else(|_| panic!("a report blocked for more than {:?}", REPORT_DEADLINE));
+        let output = output.expect("failed to run a report");
+        assert!(output.status.success(), "webgone {} failed: {}", report, describe(&output));
+    }
+
+    writer.execute_batch("COMMIT").expect("failed to release the write lock");
+    // The watcher waited for the lock and kept going
+    thread::sleep(Duration::from_secs(1));
+    let stopped = watcher.try_wait().expect("failed to check the watcher");
+    let _ = watcher.kill();
+    let _ = watcher.wait();
+    assert!(stopped.is_none(), "the watcher stopped: {}", db.watch_output());
+
+    let output = db.command(&["stats"]).output().expect("failed to run webgone stats");
+    assert!(output.status.success(), "{}", describe(&output));
+}
+
+#[test]
+fn watcher_stops_when_a_newer_version_changes_the_schema() {
+    let db = TestDb::new("schema-change");
+    let mut watcher = db.watch("127.0.0.1:9");
+    thread::sleep(Duration::from_secs(1));
+
+    db.connect()
+        .execute_batch("CREATE TABLE future_feature (id INTEGER PRIMARY KEY); PRAGMA user_version = 1000;")
+        .expect("failed to change the schema");
+
+    let Some(status) = wait_for(&mut watcher, Duration::from_secs(10)) else {
+        panic!("the watcher kept running on a database of a newer schema");
+    };
+    assert!(!status.success());
+    assert!(db.watch_output().contains("is newer than the version"), "{}", db.watch_output());
+
+    // Reports refuse the database too, instead of failing on unknown tables later
+    let output = db.command(&["stats"]).output().expect("failed to run webgone stats");
+    assert!(!output.status.success());
+    assert!(describe(&output).contains("is newer than the version"), "{}", describe(&output));
+}
+
+#[test]
+fn outage_survives_a_restart_after_a_busy_database() {
+    let db = TestDb::new("restart");
+    // A port nothing listens on until the connection should come back
+    let address = TcpListener::bind("127.0.0.1:0").and_then(|listener| listener.local_addr()).expect("failed to find a free port");
+    let mut watcher = db.watch(&address.to_string());
+    let conn = db.connect();
+    let current_outage = || {
+        conn.query_row("SELECT value FROM metadata WHERE key = 'current_outage_start'", [], |row| row.get::<_, String>(0))
+            .optional()
+            .expect("failed to read the current outage")
+    };
+    assert!(eventually(Duration::from_secs(10), || current_outage().is_some()), "no outage began: {}", db.watch_output());
+
+    // Longer than the watcher waits for the lock, so its next write fails and it restarts
+    let writer = db.connect();
+    writer.execute_batch("BEGIN IMMEDIATE").expect("failed to take the write lock");
+    thread::sleep(Duration::from_secs(1));
+    let _listener = TcpListener::bind(address).expect("failed to listen on the target port");
+    thread::sleep(Duration::from_secs(6));
+    writer.execute_batch("COMMIT").expect("failed to release the write lock");
+
+    let recorded = eventually(Duration::from_secs(15), || {
+        conn.query_row("SELECT COUNT(*) FROM outages", [], |row| row.get::<_, i64>(0)).expect("failed to count outages") > 0
+    });
+    let _ = watcher.kill();
+    let _ = watcher.wait();
+    let output = db.watch_output();
+    assert!(output.contains("Watcher failed"), "the watcher didn't fail on the busy database: {}", output);
+    assert!(recorded, "the outage was lost by the restart: {}", output);
+    assert!(current_outage().is_none(), "the current outage wasn't cleared: {}", output);
+}
@@ -0,0 +1,17 @@
+//! Drives the watcher end to end against a fake target, see `webgone selftest`.
+
+use std::process::Command;
+
+#[test]
+fn watcher_records_outages_of_a_fake_target() {
+    let output = Command::new(env!("CARGO_BIN_EXE_webgone"))
+        .arg("selftest")
+        .output()
+        .expect("failed to run webgone selftest");
+    assert!(
+        output.status.success(),
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}